@@ -0,0 +1,218 @@
+//! Compares the same workloads on native limbo_core and on the component under wasmtime and
+//! under [wasm_component_layer], to put numbers on the cost of crossing the component boundary.
+//!
+//! Build the component first (`just build`), then run `cargo bench --bench runtimes`.
+//!
+//! The `scan` group also compares lifting rows as WIT values (`statement.all`) against the
+//! protobuf encoding (`statement.all-proto`), which crosses the boundary as a single `list<u8>`.
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use wasmtime::component::Component;
+use wit_limbo_host::layer_host::{LayerDatabase, LimboInstance};
+use wit_limbo_host::{Database, Value};
+
+const CREATE: &str =
+    "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INTEGER);";
+const INSERT: &str = "INSERT INTO users (name, age) VALUES (?, ?);";
+const LOOKUP: &str = "SELECT * FROM users WHERE id = ?;";
+const SCAN: &str = "SELECT * FROM users;";
+
+/// Rows inserted before the lookup and scan benchmarks
+const ROWS: i64 = 1_000;
+
+/// Utility function to get the workspace dir
+fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+fn wasm_path() -> PathBuf {
+    workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm")
+}
+
+fn user(i: i64) -> [Value; 2] {
+    [Value::Text(format!("user {}", i)), Value::Integer(i % 100)]
+}
+
+/// limbo_core linked directly into the host, the baseline.
+struct Native {
+    io: Arc<dyn limbo_core::IO>,
+    conn: std::rc::Rc<limbo_core::Connection>,
+}
+
+impl Native {
+    fn open() -> Self {
+        let io: Arc<dyn limbo_core::IO> = Arc::new(limbo_core::MemoryIO::new().unwrap());
+        let db = limbo_core::Database::open_file(io.clone(), ":memory:").unwrap();
+        let conn = db.connect();
+        Self { io, conn }
+    }
+
+    fn query(&self, sql: &str, params: &[Value]) -> usize {
+        let mut stmt = self.conn.prepare(sql).unwrap();
+        for (i, param) in params.iter().enumerate() {
+            // as the guest binds them
+            let value = match param {
+                Value::Null => limbo_core::OwnedValue::Null,
+                Value::Integer(i) => limbo_core::OwnedValue::Integer(*i),
+                Value::Float(f) => limbo_core::OwnedValue::Float(*f),
+                Value::Text(s) | Value::TextDecimal(s) => {
+                    limbo_core::OwnedValue::build_text(std::rc::Rc::new(s.clone()))
+                }
+                Value::Blob(b) => limbo_core::OwnedValue::Blob(std::rc::Rc::new(b.clone())),
+            };
+            stmt.bind_at(std::num::NonZeroUsize::new(i + 1).unwrap(), value);
+        }
+        let mut rows = 0;
+        loop {
+            match stmt.step().unwrap() {
+                limbo_core::StepResult::Row => {
+                    // convert like the guest does, so the comparison is fair
+                    let row = stmt.row().unwrap();
+                    let values: Vec<_> = row.get_values().map(|value| value.to_value()).collect();
+                    criterion::black_box(values);
+                    rows += 1;
+                }
+                limbo_core::StepResult::IO => self.io.run_once().unwrap(),
+                _ => return rows,
+            }
+        }
+    }
+}
+
+fn wasmtime_db(engine: &wasmtime::Engine, component: &Component) -> Database {
+    let mut db = Database::open(engine, component, ":memory:").unwrap();
+    db.exec(CREATE).unwrap();
+    db
+}
+
+type LayerEngine = wasm_component_layer::Engine<wasmi_runtime_layer::Engine>;
+
+fn layer_db(
+    engine: &LayerEngine,
+    bytes: &[u8],
+) -> (LimboInstance<wasmi_runtime_layer::Engine>, LayerDatabase) {
+    let mut instance = LimboInstance::new(engine, bytes).unwrap();
+    let db = instance.open(":memory:").unwrap();
+    instance.exec(&db, CREATE).unwrap();
+    (instance, db)
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let engine = wit_limbo_host::engine().unwrap();
+    let component = Component::from_file(&engine, wasm_path()).unwrap();
+    let layer_engine = LayerEngine::new(wasmi_runtime_layer::Engine::default());
+    let bytes = std::fs::read(wasm_path()).unwrap();
+
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(1));
+
+    group.bench_function("native", |b| {
+        let native = Native::open();
+        native.query(CREATE, &[]);
+        let mut i = 0;
+        b.iter(|| {
+            i += 1;
+            native.query(INSERT, &user(i))
+        });
+    });
+
+    group.bench_function("wasmtime", |b| {
+        let mut db = wasmtime_db(&engine, &component);
+        let mut i = 0;
+        b.iter(|| {
+            i += 1;
+            db.query(INSERT, &user(i)).unwrap()
+        });
+    });
+
+    group.bench_function("wasm_component_layer", |b| {
+        let (mut instance, db) = layer_db(&layer_engine, &bytes);
+        let mut i = 0;
+        b.iter(|| {
+            i += 1;
+            instance.query(&db, INSERT, &user(i)).unwrap()
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let engine = wit_limbo_host::engine().unwrap();
+    let component = Component::from_file(&engine, wasm_path()).unwrap();
+    let layer_engine = LayerEngine::new(wasmi_runtime_layer::Engine::default());
+    let bytes = std::fs::read(wasm_path()).unwrap();
+
+    let mut group = c.benchmark_group("lookup");
+
+    let native = Native::open();
+    native.query(CREATE, &[]);
+    let mut db = wasmtime_db(&engine, &component);
+    let (mut instance, layer) = layer_db(&layer_engine, &bytes);
+    for i in 1..=ROWS {
+        native.query(INSERT, &user(i));
+        db.query(INSERT, &user(i)).unwrap();
+        instance.query(&layer, INSERT, &user(i)).unwrap();
+    }
+
+    let id = [Value::Integer(ROWS / 2)];
+    group.bench_function("native", |b| b.iter(|| native.query(LOOKUP, &id)));
+    group.bench_function("wasmtime", |b| b.iter(|| db.query(LOOKUP, &id).unwrap()));
+    group.bench_function("wasm_component_layer", |b| {
+        b.iter(|| instance.query(&layer, LOOKUP, &id).unwrap())
+    });
+
+    group.finish();
+}
+
+fn bench_scan(c: &mut Criterion) {
+    let engine = wit_limbo_host::engine().unwrap();
+    let component = Component::from_file(&engine, wasm_path()).unwrap();
+    let layer_engine = LayerEngine::new(wasmi_runtime_layer::Engine::default());
+    let bytes = std::fs::read(wasm_path()).unwrap();
+
+    let mut group = c.benchmark_group("scan");
+    group.throughput(Throughput::Elements(ROWS as u64));
+
+    let native = Native::open();
+    native.query(CREATE, &[]);
+    let mut db = wasmtime_db(&engine, &component);
+    let (mut instance, layer) = layer_db(&layer_engine, &bytes);
+    for i in 1..=ROWS {
+        native.query(INSERT, &user(i));
+        db.query(INSERT, &user(i)).unwrap();
+        instance.query(&layer, INSERT, &user(i)).unwrap();
+    }
+
+    group.bench_function("native", |b| b.iter(|| native.query(SCAN, &[])));
+    for encoding in ["wit", "proto"] {
+        group.bench_with_input(
+            BenchmarkId::new("wasmtime", encoding),
+            &encoding,
+            |b, &encoding| {
+                b.iter(|| match encoding {
+                    "wit" => db.query(SCAN, &[]).unwrap().len(),
+                    _ => db.query_proto(SCAN, &[]).unwrap().len(),
+                })
+            },
+        );
+    }
+    group.bench_function("wasm_component_layer", |b| {
+        b.iter(|| instance.query(&layer, SCAN, &[]).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert, bench_lookup, bench_scan);
+criterion_main!(benches);
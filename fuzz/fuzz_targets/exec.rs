@@ -0,0 +1,9 @@
+//! Arbitrary SQL through `database.exec`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wit_limbo_fuzz::with_db;
+
+fuzz_target!(|sql: &str| {
+    with_db(|db| db.exec(sql));
+});
@@ -0,0 +1,42 @@
+//! Arbitrary SQL and parameters through `database.prepare`, `statement.bind` and
+//! `statement.all-with-names`.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use wit_limbo_fuzz::with_db;
+use wit_limbo_host::Value;
+
+#[derive(Debug, Arbitrary)]
+enum Param {
+    Null,
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    Blob(Vec<u8>),
+    TextDecimal(String),
+}
+
+impl From<Param> for Value {
+    fn from(param: Param) -> Self {
+        match param {
+            Param::Null => Value::Null,
+            Param::Integer(i) => Value::Integer(i),
+            Param::Float(f) => Value::Float(f),
+            Param::Text(s) => Value::Text(s),
+            Param::Blob(b) => Value::Blob(b),
+            Param::TextDecimal(s) => Value::TextDecimal(s),
+        }
+    }
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    sql: String,
+    params: Vec<Param>,
+}
+
+fuzz_target!(|input: Input| {
+    let params: Vec<Value> = input.params.into_iter().map(Value::from).collect();
+    with_db(|db| db.query(&input.sql, &params).map(drop));
+});
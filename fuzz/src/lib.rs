@@ -0,0 +1,43 @@
+//! Shared setup for the fuzz targets: one component instance per fuzzing thread, with a
+//! small schema so that arbitrary SQL has something to hit.
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use wasmtime::component::Component;
+use wit_limbo_host::{Database, Error};
+
+const SCHEMA: &str = "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, data BLOB);";
+
+thread_local! {
+    static DB: RefCell<Option<Database>> = const { RefCell::new(None) };
+}
+
+/// The release component, override with `WIT_LIMBO_WASM`.
+fn wasm_path() -> PathBuf {
+    std::env::var_os("WIT_LIMBO_WASM")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../target/wasm32-unknown-unknown/release/wit_limbo.wasm")
+        })
+}
+
+fn open() -> Database {
+    let engine = wit_limbo_host::engine().unwrap();
+    let component = Component::from_file(&engine, wasm_path()).unwrap();
+    let mut db = Database::open(&engine, &component, ":memory:").unwrap();
+    db.exec(SCHEMA).unwrap();
+    db
+}
+
+/// Run `f` against the thread's database. The component may return a `db-error` for bad
+/// input, but it must never trap: a trap is a guest panic, and fails the fuzz run.
+pub fn with_db(f: impl FnOnce(&mut Database) -> Result<(), Error>) {
+    DB.with_borrow_mut(|db| {
+        let db = db.get_or_insert_with(open);
+        match f(db) {
+            Ok(()) | Err(Error::Db(_)) => {}
+            Err(e) => panic!("component trapped instead of returning db-error: {}", e),
+        }
+    })
+}
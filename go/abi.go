@@ -0,0 +1,222 @@
+package limbo
+
+import (
+	"encoding/binary"
+	"fmt"
+	"math"
+
+	"github.com/bytecodealliance/wasmtime-go/v25"
+)
+
+// abi reads and writes canonical ABI values in the guest's linear memory.
+//
+// wasmtime-go runs core modules, not components, so this does the lifting and lowering
+// a component runtime would otherwise do for us. The layouts follow wit/world.wit.
+type abi struct {
+	store   wasmtime.Storelike
+	memory  *wasmtime.Memory
+	realloc *wasmtime.Func
+}
+
+// data must be fetched again after anything that can grow memory, like alloc.
+func (a *abi) data() []byte {
+	return a.memory.UnsafeData(a.store)
+}
+
+func (a *abi) u32(ptr uint32) uint32 {
+	return binary.LittleEndian.Uint32(a.data()[ptr:])
+}
+
+func (a *abi) putU32(ptr, v uint32) {
+	binary.LittleEndian.PutUint32(a.data()[ptr:], v)
+}
+
+func (a *abi) alloc(size, align uint32) (uint32, error) {
+	ptr, err := a.realloc.Call(a.store, int32(0), int32(0), int32(align), int32(size))
+	if err != nil {
+		return 0, err
+	}
+	return uint32(ptr.(int32)), nil
+}
+
+// lowerBytes copies b into newly allocated guest memory, which the guest takes ownership of.
+func (a *abi) lowerBytes(b []byte) (uint32, uint32, error) {
+	if len(b) == 0 {
+		return 1, 0, nil
+	}
+	ptr, err := a.alloc(uint32(len(b)), 1)
+	if err != nil {
+		return 0, 0, err
+	}
+	copy(a.data()[ptr:], b)
+	return ptr, uint32(len(b)), nil
+}
+
+func (a *abi) liftBytes(ptr, length uint32) []byte {
+	out := make([]byte, length)
+	copy(out, a.data()[ptr:ptr+length])
+	return out
+}
+
+func (a *abi) liftString(ptr, length uint32) string {
+	return string(a.data()[ptr : ptr+length])
+}
+
+// Decimal is an exact decimal number, the `text-decimal` case of record-value.
+type Decimal string
+
+// record-value is 16 bytes: the case at 0, and its payload at 8.
+const recordValueSize = 16
+
+func (a *abi) liftRecordValue(ptr uint32) (any, error) {
+	data := a.data()
+	payload := ptr + 8
+	switch data[ptr] {
+	case 0:
+		return nil, nil
+	case 1:
+		return int64(binary.LittleEndian.Uint64(data[payload:])), nil
+	case 2:
+		return math.Float64frombits(binary.LittleEndian.Uint64(data[payload:])), nil
+	case 3:
+		return a.liftString(a.u32(payload), a.u32(payload+4)), nil
+	case 4:
+		return a.liftBytes(a.u32(payload), a.u32(payload+4)), nil
+	case 5:
+		return Decimal(a.liftString(a.u32(payload), a.u32(payload+4))), nil
+	default:
+		return nil, fmt.Errorf("unknown record-value case %d", data[ptr])
+	}
+}
+
+// lowerRecordValue flattens v into the (case, i64, i32) core parameters of a record-value.
+func (a *abi) lowerRecordValue(v any) (int32, int64, int32, error) {
+	switch v := v.(type) {
+	case nil:
+		return 0, 0, 0, nil
+	case int:
+		return 1, int64(v), 0, nil
+	case int32:
+		return 1, int64(v), 0, nil
+	case int64:
+		return 1, v, 0, nil
+	case bool:
+		if v {
+			return 1, 1, 0, nil
+		}
+		return 1, 0, 0, nil
+	case float64:
+		return 2, int64(math.Float64bits(v)), 0, nil
+	case string:
+		ptr, length, err := a.lowerBytes([]byte(v))
+		return 3, int64(ptr), int32(length), err
+	case []byte:
+		ptr, length, err := a.lowerBytes(v)
+		return 4, int64(ptr), int32(length), err
+	case Decimal:
+		ptr, length, err := a.lowerBytes([]byte(v))
+		return 5, int64(ptr), int32(length), err
+	default:
+		return 0, 0, 0, fmt.Errorf("unsupported parameter type %T", v)
+	}
+}
+
+// liftRows lifts a list<list<record-value>> whose (ptr, len) is at ptr.
+func (a *abi) liftRows(ptr uint32) ([][]any, error) {
+	rowsPtr, rowsLen := a.u32(ptr), a.u32(ptr+4)
+	rows := make([][]any, rowsLen)
+	for i := range rows {
+		row := rowsPtr + uint32(i)*8
+		valuesPtr, valuesLen := a.u32(row), a.u32(row+4)
+		rows[i] = make([]any, valuesLen)
+		for j := range rows[i] {
+			value, err := a.liftRecordValue(valuesPtr + uint32(j)*recordValueSize)
+			if err != nil {
+				return nil, err
+			}
+			rows[i][j] = value
+		}
+	}
+	return rows, nil
+}
+
+// liftStrings lifts a list<string> whose (ptr, len) is at ptr.
+func (a *abi) liftStrings(ptr uint32) []string {
+	listPtr, listLen := a.u32(ptr), a.u32(ptr+4)
+	out := make([]string, listLen)
+	for i := range out {
+		s := listPtr + uint32(i)*8
+		out[i] = a.liftString(a.u32(s), a.u32(s+4))
+	}
+	return out
+}
+
+// writeErr writes the err case of a result<T, string> at retptr, with the message at
+// offset payload.
+func (a *abi) writeErr(retptr, payload uint32, err error) {
+	ptr, length, allocErr := a.lowerBytes([]byte(err.Error()))
+	if allocErr != nil {
+		panic(allocErr)
+	}
+	a.data()[retptr] = 1
+	a.putU32(retptr+payload, ptr)
+	a.putU32(retptr+payload+4, length)
+}
+
+// writeOk writes the ok case of a result at retptr, the caller writes any payload.
+func (a *abi) writeOk(retptr uint32) {
+	a.data()[retptr] = 0
+}
+
+// DbError is a db-error returned by the database, see wit/world.wit.
+type DbError struct {
+	// Kind is the db-error case: parse, corrupt, io, misuse, busy, closed, interrupted,
+	// out-of-memory, limit-exceeded, cannot-open or other.
+	Kind    string
+	Message string
+	// Position is where the SQL failed to parse, for parse errors that know it.
+	Position *SqlPosition
+}
+
+// SqlPosition is where in some SQL it failed to parse, see sql-position in wit/world.wit.
+type SqlPosition struct {
+	// Line and Column count from 1, Column in characters.
+	Line, Column uint32
+	// Offset is in bytes from the start of the SQL.
+	Offset uint32
+	// Token is the token there, empty at the end of the SQL.
+	Token string
+}
+
+func (e *DbError) Error() string {
+	return e.Kind + ": " + e.Message
+}
+
+// dbErrorKinds are the db-error cases, in declaration order.
+var dbErrorKinds = []string{"parse", "corrupt", "io", "misuse", "busy", "closed", "interrupted", "out-of-memory", "limit-exceeded", "cannot-open", "other"}
+
+// liftResult reads the result<T, db-error> at ptr, returning where the ok payload is.
+//
+// The case is at 0 and the payload at 4. A db-error has its case at 4 and message at 8, and
+// a parse error its optional position at 16: the case, then line, column, offset and token
+// from 20.
+func (a *abi) liftResult(ptr uint32) (uint32, error) {
+	data := a.data()
+	if data[ptr] == 0 {
+		return ptr + 4, nil
+	}
+	kind := "other"
+	if i := int(data[ptr+4]); i < len(dbErrorKinds) {
+		kind = dbErrorKinds[i]
+	}
+	dbErr := &DbError{Kind: kind, Message: a.liftString(a.u32(ptr+8), a.u32(ptr+12))}
+	if kind == "parse" && data[ptr+16] == 1 {
+		dbErr.Position = &SqlPosition{
+			Line:   a.u32(ptr + 20),
+			Column: a.u32(ptr + 24),
+			Offset: a.u32(ptr + 28),
+			Token:  a.liftString(a.u32(ptr+32), a.u32(ptr+36)),
+		}
+	}
+	return 0, dbErr
+}
@@ -0,0 +1,5 @@
+module github.com/DougAnderson444/wit-limbo/go
+
+go 1.22
+
+require github.com/bytecodealliance/wasmtime-go/v25 v25.0.0
@@ -0,0 +1,279 @@
+package limbo
+
+import (
+	"crypto/rand"
+	"encoding/binary"
+	"fmt"
+	"log"
+	"os"
+	"path/filepath"
+	"time"
+
+	"github.com/bytecodealliance/wasmtime-go/v25"
+)
+
+// host holds the state behind the component's imports, and the resource handle tables
+// that a component runtime would otherwise keep for us.
+type host struct {
+	abi *abi
+
+	// root is the directory database files are relative to.
+	root  string
+	files []*os.File
+	kv    map[string][]byte
+
+	// reads holds the results of reads submitted through host-io and not yet polled.
+	reads    map[int64]read
+	lastRead int64
+
+	handles    map[string]map[int32]int32
+	nextHandle int32
+}
+
+func newHost(root string) *host {
+	return &host{
+		root:    root,
+		kv:      map[string][]byte{},
+		reads:   map[int64]read{},
+		handles: map[string]map[int32]int32{},
+	}
+}
+
+func (h *host) newHandle(resource string, rep int32) int32 {
+	if h.handles[resource] == nil {
+		h.handles[resource] = map[int32]int32{}
+	}
+	h.nextHandle++
+	h.handles[resource][h.nextHandle] = rep
+	return h.nextHandle
+}
+
+// rep is what the guest expects for a borrowed handle of its own resource.
+func (h *host) rep(resource string, handle int32) (int32, error) {
+	rep, ok := h.handles[resource][handle]
+	if !ok {
+		return 0, fmt.Errorf("unknown %s handle %d", resource, handle)
+	}
+	return rep, nil
+}
+
+func (h *host) file(fd int32) (*os.File, error) {
+	if int(fd) >= len(h.files) || fd < 0 {
+		return nil, fmt.Errorf("bad file descriptor %d", fd)
+	}
+	return h.files[fd], nil
+}
+
+// read is the result of a host-fs read.
+type read struct {
+	data []byte
+	err  error
+}
+
+// readAt reads up to length bytes at offset of the file fd, fewer at the end of the file.
+func (h *host) readAt(fd int32, offset int64, length int32) read {
+	f, err := h.file(fd)
+	if err != nil {
+		return read{err: err}
+	}
+	buf := make([]byte, length)
+	n, err := f.ReadAt(buf, offset)
+	if n < int(length) {
+		// a short read at the end of the file is fine
+		err = nil
+	}
+	return read{data: buf[:n], err: err}
+}
+
+const (
+	hostRandomModule        = "component:wit-limbo/host-random@0.2.0"
+	hostLogModule           = "component:wit-limbo/host-log@0.2.0"
+	hostClockModule         = "component:wit-limbo/host-clock@0.2.0"
+	hostTraceModule         = "component:wit-limbo/host-trace@0.2.0"
+	hostSubscriptionsModule = "component:wit-limbo/host-subscriptions@0.2.0"
+	hostProgressModule      = "component:wit-limbo/host-progress@0.2.0"
+	hostFsModule            = "component:wit-limbo/host-fs@0.2.0"
+	hostIoModule            = "component:wit-limbo/host-io@0.2.0"
+	hostKvModule            = "component:wit-limbo/host-kv@0.2.0"
+	exportModule            = "[export]component:wit-limbo/limbo@0.2.0"
+)
+
+type importDef struct {
+	module, name string
+	f            any
+}
+
+func (h *host) define(linker *wasmtime.Linker) error {
+	defs := []importDef{
+		{hostRandomModule, "random-byte", func() int32 {
+			var b [1]byte
+			if _, err := rand.Read(b[:]); err != nil {
+				panic(err)
+			}
+			return int32(b[0])
+		}},
+		{hostLogModule, "log", func(ptr, length int32) {
+			log.Println(h.abi.liftString(uint32(ptr), uint32(length)))
+		}},
+		{hostClockModule, "sleep", func(ms int32) {
+			time.Sleep(time.Duration(uint32(ms)) * time.Millisecond)
+		}},
+		{hostClockModule, "now", func() int64 {
+			return time.Now().UnixMicro()
+		}},
+		// spans aren't traced
+		{hostTraceModule, "span-start", func(ptr, length int32) int64 {
+			return 0
+		}},
+		{hostTraceModule, "span-end", func(id int64) {}},
+		// nothing subscribes through database/sql
+		{hostSubscriptionsModule, "on-query-changed", func(id int64) {}},
+		// no long call is run through database/sql
+		{hostProgressModule, "on-progress", func(ptr, length int32, done, total int64) {}},
+
+		{hostFsModule, "open", func(pathPtr, pathLen, create, retptr int32) {
+			path := filepath.Join(h.root, h.abi.liftString(uint32(pathPtr), uint32(pathLen)))
+			flags := os.O_RDWR
+			if create != 0 {
+				flags |= os.O_CREATE
+			}
+			f, err := os.OpenFile(path, flags, 0o644)
+			if err != nil {
+				h.abi.writeErr(uint32(retptr), 4, err)
+				return
+			}
+			h.files = append(h.files, f)
+			h.abi.writeOk(uint32(retptr))
+			h.abi.putU32(uint32(retptr)+4, uint32(len(h.files)-1))
+		}},
+		{hostFsModule, "read", func(fd int32, offset int64, length, retptr int32) {
+			h.writeRead(uint32(retptr), h.readAt(fd, offset, length))
+		}},
+		{hostFsModule, "write", func(fd int32, offset int64, dataPtr, dataLen, retptr int32) {
+			f, err := h.file(fd)
+			if err == nil {
+				_, err = f.WriteAt(h.abi.liftBytes(uint32(dataPtr), uint32(dataLen)), offset)
+			}
+			if err != nil {
+				h.abi.writeErr(uint32(retptr), 4, err)
+				return
+			}
+			h.abi.writeOk(uint32(retptr))
+		}},
+		{hostFsModule, "sync", func(fd, retptr int32) {
+			f, err := h.file(fd)
+			if err == nil {
+				err = f.Sync()
+			}
+			if err != nil {
+				h.abi.writeErr(uint32(retptr), 4, err)
+				return
+			}
+			h.abi.writeOk(uint32(retptr))
+		}},
+		{hostFsModule, "size", func(fd, retptr int32) {
+			f, err := h.file(fd)
+			var info os.FileInfo
+			if err == nil {
+				info, err = f.Stat()
+			}
+			// u64 is 8 byte aligned, so the payload starts at 8
+			if err != nil {
+				h.abi.writeErr(uint32(retptr), 8, err)
+				return
+			}
+			h.abi.writeOk(uint32(retptr))
+			binary.LittleEndian.PutUint64(h.abi.data()[retptr+8:], uint64(info.Size()))
+		}},
+
+		// files are read as the reads are submitted, and kept until polled
+		{hostIoModule, "submit-io", func(fd int32, offset int64, length int32) int64 {
+			h.lastRead++
+			h.reads[h.lastRead] = h.readAt(fd, offset, length)
+			return h.lastRead
+		}},
+		{hostIoModule, "poll-io", func(id int64, retptr int32) {
+			r, ok := h.reads[id]
+			if !ok {
+				h.abi.data()[retptr] = 0
+				return
+			}
+			delete(h.reads, id)
+			h.abi.data()[retptr] = 1
+			h.writeRead(uint32(retptr)+4, r)
+		}},
+
+		{hostKvModule, "get", func(keyPtr, keyLen, retptr int32) {
+			value, ok := h.kv[h.abi.liftString(uint32(keyPtr), uint32(keyLen))]
+			h.abi.writeOk(uint32(retptr))
+			if !ok {
+				h.abi.data()[retptr+4] = 0
+				return
+			}
+			ptr, length, err := h.abi.lowerBytes(value)
+			if err != nil {
+				panic(err)
+			}
+			h.abi.data()[retptr+4] = 1
+			h.abi.putU32(uint32(retptr)+8, ptr)
+			h.abi.putU32(uint32(retptr)+12, length)
+		}},
+		{hostKvModule, "set", func(keyPtr, keyLen, valuePtr, valueLen, retptr int32) {
+			key := h.abi.liftString(uint32(keyPtr), uint32(keyLen))
+			h.kv[key] = h.abi.liftBytes(uint32(valuePtr), uint32(valueLen))
+			h.abi.writeOk(uint32(retptr))
+		}},
+		{hostKvModule, "flush", func(retptr int32) {
+			h.abi.writeOk(uint32(retptr))
+		}},
+	}
+
+	for _, resource := range []string{"database", "statement", "blob-writer"} {
+		resource := resource
+		defs = append(defs,
+			importDef{exportModule, "[resource-new]" + resource, func(rep int32) int32 {
+				return h.newHandle(resource, rep)
+			}},
+			importDef{exportModule, "[resource-rep]" + resource, func(handle int32) int32 {
+				rep, err := h.rep(resource, handle)
+				if err != nil {
+					panic(err)
+				}
+				return rep
+			}},
+			importDef{exportModule, "[resource-drop]" + resource, func(caller *wasmtime.Caller, handle int32) {
+				rep, err := h.rep(resource, handle)
+				if err != nil {
+					panic(err)
+				}
+				delete(h.handles[resource], handle)
+				dtor := caller.GetExport("component:wit-limbo/limbo@0.2.0#[dtor]" + resource).Func()
+				if _, err := dtor.Call(caller, rep); err != nil {
+					panic(err)
+				}
+			}},
+		)
+	}
+
+	for _, def := range defs {
+		if err := linker.FuncWrap(def.module, def.name, def.f); err != nil {
+			return fmt.Errorf("defining %s#%s: %w", def.module, def.name, err)
+		}
+	}
+	return nil
+}
+
+// writeRead writes r as the result<list<u8>, string> of a read at retptr.
+func (h *host) writeRead(retptr uint32, r read) {
+	if r.err != nil {
+		h.abi.writeErr(retptr, 4, r.err)
+		return
+	}
+	ptr, length, err := h.abi.lowerBytes(r.data)
+	if err != nil {
+		panic(err)
+	}
+	h.abi.writeOk(retptr)
+	h.abi.putU32(retptr+4, ptr)
+	h.abi.putU32(retptr+8, length)
+}
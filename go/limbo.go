@@ -0,0 +1,241 @@
+// Package limbo embeds the wit-limbo component in Go, with an Open/Exec/Query API.
+//
+// wasmtime-go runs core wasm modules rather than components, so this loads the core module
+// (built with plain `cargo build`, before `cargo component` wraps it) and does the
+// canonical ABI lifting and lowering itself, following wit/world.wit.
+//
+//	db, err := limbo.Open("wit_limbo.wasm", ":memory:")
+//	err = db.Exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+//	rows, err := db.Query("SELECT * FROM users WHERE id = ?", 1)
+package limbo
+
+import (
+	"fmt"
+	"time"
+
+	"github.com/bytecodealliance/wasmtime-go/v25"
+)
+
+const exportPrefix = "component:wit-limbo/limbo@0.2.0#"
+
+// DB is a database running inside its own instance of the component.
+type DB struct {
+	store    *wasmtime.Store
+	instance *wasmtime.Instance
+	host     *host
+	handle   int32
+}
+
+// Rows are the results of a query, with the column names.
+type Rows struct {
+	Columns []string
+	Values  [][]any
+}
+
+// Options configure Open.
+type Options struct {
+	// Dir is the directory database files are relative to, the working directory if empty.
+	Dir string
+	// Flags say how the database is opened, see OpenFlags. Nil opens it as Open does,
+	// creating it if it doesn't exist.
+	Flags *OpenFlags
+}
+
+// OpenFlags are the open-flags of the WIT, for Options.Flags.
+type OpenFlags uint8
+
+const (
+	// OpenCreate creates the database if it doesn't exist, rather than failing with a
+	// cannot-open DbError.
+	OpenCreate OpenFlags = 1 << iota
+	// OpenReadOnly refuses SQL that writes. It can't be combined with OpenCreate.
+	OpenReadOnly
+	// OpenExclusive fails with a cannot-open DbError if the database already exists.
+	OpenExclusive
+)
+
+// Open instantiates the core module at wasmPath and opens the database at path.
+//
+// ":memory:" is an in-memory database, "kv:<name>" is kept in memory by the host,
+// and any other path is a file.
+func Open(wasmPath, path string) (*DB, error) {
+	return OpenWith(wasmPath, path, Options{})
+}
+
+// OpenWith is Open with options.
+func OpenWith(wasmPath, path string, opts Options) (*DB, error) {
+	engine := wasmtime.NewEngine()
+	module, err := wasmtime.NewModuleFromFile(engine, wasmPath)
+	if err != nil {
+		return nil, err
+	}
+
+	h := newHost(opts.Dir)
+	linker := wasmtime.NewLinker(engine)
+	if err := h.define(linker); err != nil {
+		return nil, err
+	}
+
+	store := wasmtime.NewStore(engine)
+	instance, err := linker.Instantiate(store, module)
+	if err != nil {
+		return nil, err
+	}
+	h.abi = &abi{
+		store:   store,
+		memory:  instance.GetExport(store, "memory").Memory(),
+		realloc: instance.GetFunc(store, "cabi_realloc"),
+	}
+
+	db := &DB{store: store, instance: instance, host: h}
+
+	pathPtr, pathLen, err := h.abi.lowerBytes([]byte(path))
+	if err != nil {
+		return nil, err
+	}
+	// open-with rather than the constructor, which returns a database that failed to open
+	// closed
+	flags := OpenCreate
+	if opts.Flags != nil {
+		flags = *opts.Flags
+	}
+	err = db.callResult("[static]database.open-with", func(ptr uint32) error {
+		db.handle = int32(h.abi.u32(ptr))
+		return nil
+	}, int32(pathPtr), int32(pathLen), int32(flags))
+	if err != nil {
+		return nil, err
+	}
+	return db, nil
+}
+
+func (db *DB) call(name string, args ...any) (any, error) {
+	f := db.instance.GetFunc(db.store, exportPrefix+name)
+	if f == nil {
+		return nil, fmt.Errorf("missing export %s", name)
+	}
+	return f.Call(db.store, args...)
+}
+
+// postReturn lets the guest free what it returned from name.
+func (db *DB) postReturn(name string, retptr any) error {
+	f := db.instance.GetFunc(db.store, "cabi_post_"+exportPrefix+name)
+	if f == nil {
+		return nil
+	}
+	_, err := f.Call(db.store, retptr)
+	return err
+}
+
+func (db *DB) drop(resource string, handle int32) error {
+	rep, err := db.host.rep(resource, handle)
+	if err != nil {
+		return err
+	}
+	delete(db.host.handles[resource], handle)
+	_, err = db.call("[dtor]"+resource, rep)
+	return err
+}
+
+// Exec executes a SQL statement, discarding any rows it produces.
+func (db *DB) Exec(sql string) error {
+	rep, err := db.host.rep("database", db.handle)
+	if err != nil {
+		return err
+	}
+	sqlPtr, sqlLen, err := db.host.abi.lowerBytes([]byte(sql))
+	if err != nil {
+		return err
+	}
+	return db.callResult("[method]database.exec", nil, rep, int32(sqlPtr), int32(sqlLen))
+}
+
+// callResult calls name, which returns a result<T, db-error>, and hands the ok payload
+// to lift before letting the guest free the return area.
+func (db *DB) callResult(name string, lift func(ptr uint32) error, args ...any) error {
+	retptr, err := db.call(name, args...)
+	if err != nil {
+		return err
+	}
+	payload, err := db.host.abi.liftResult(uint32(retptr.(int32)))
+	if err == nil && lift != nil {
+		err = lift(payload)
+	}
+	if postErr := db.postReturn(name, retptr); postErr != nil {
+		return postErr
+	}
+	return err
+}
+
+// Query runs sql with args bound in order, and returns the resulting rows.
+//
+// Args may be nil, int, int32, int64, bool, float64, string, []byte or Decimal.
+func (db *DB) Query(sql string, args ...any) (*Rows, error) {
+	abi := db.host.abi
+	rep, err := db.host.rep("database", db.handle)
+	if err != nil {
+		return nil, err
+	}
+	sqlPtr, sqlLen, err := abi.lowerBytes([]byte(sql))
+	if err != nil {
+		return nil, err
+	}
+	var stmtHandle int32
+	err = db.callResult("[method]database.prepare", func(ptr uint32) error {
+		stmtHandle = int32(abi.u32(ptr))
+		return nil
+	}, rep, int32(sqlPtr), int32(sqlLen))
+	if err != nil {
+		return nil, err
+	}
+	defer db.drop("statement", stmtHandle)
+
+	stmtRep, err := db.host.rep("statement", stmtHandle)
+	if err != nil {
+		return nil, err
+	}
+	for i, arg := range args {
+		tag, payload, payloadLen, err := abi.lowerRecordValue(arg)
+		if err != nil {
+			return nil, err
+		}
+		if err := db.callResult("[method]statement.bind", nil, stmtRep, int32(i+1), tag, payload, payloadLen); err != nil {
+			return nil, err
+		}
+	}
+
+	rows := &Rows{}
+	err = db.callResult("[method]statement.all-with-names", func(ptr uint32) error {
+		// result-set is { columns: list<string>, rows: list<list<record-value>>, affinities: list<affinity> }
+		values, err := abi.liftRows(ptr + 8)
+		rows.Columns, rows.Values = abi.liftStrings(ptr), values
+		return err
+	}, stmtRep)
+	if err != nil {
+		return nil, err
+	}
+	return rows, nil
+}
+
+// SetBusyTimeout makes statements wait up to d for another connection's lock before they
+// fail with a busy error. Zero, the default, fails straight away.
+func (db *DB) SetBusyTimeout(d time.Duration) error {
+	rep, err := db.host.rep("database", db.handle)
+	if err != nil {
+		return err
+	}
+	_, err = db.call("[method]database.set-busy-timeout", rep, int32(d.Milliseconds()))
+	return err
+}
+
+// Close checkpoints the WAL, releases the connection and drops the database resource.
+func (db *DB) Close() error {
+	rep, err := db.host.rep("database", db.handle)
+	if err != nil {
+		return err
+	}
+	if err := db.callResult("[method]database.close", nil, rep); err != nil {
+		return err
+	}
+	return db.drop("database", db.handle)
+}
@@ -0,0 +1,91 @@
+package limbo
+
+import (
+	"os"
+	"path/filepath"
+	"reflect"
+	"testing"
+)
+
+// The core module, built with `cargo build --target wasm32-unknown-unknown --release --target-dir target/core`.
+func wasmPath(t *testing.T) string {
+	path := filepath.Join("..", "target", "core", "wasm32-unknown-unknown", "release", "wit_limbo.wasm")
+	if _, err := os.Stat(path); err != nil {
+		t.Skipf("core module not built: %v", err)
+	}
+	return path
+}
+
+func TestExecAndQuery(t *testing.T) {
+	db, err := Open(wasmPath(t), ":memory:")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer db.Close()
+
+	for _, sql := range []string{
+		"CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+		"INSERT INTO users (name) VALUES ('Alice');",
+	} {
+		if err := db.Exec(sql); err != nil {
+			t.Fatal(err)
+		}
+	}
+
+	rows, err := db.Query("SELECT id, name FROM users WHERE id = ?", 1)
+	if err != nil {
+		t.Fatal(err)
+	}
+
+	want := &Rows{
+		Columns: []string{"id", "name"},
+		Values:  [][]any{{int64(1), "Alice"}},
+	}
+	if !reflect.DeepEqual(rows, want) {
+		t.Fatalf("got %+v, want %+v", rows, want)
+	}
+}
+
+func TestFileDatabase(t *testing.T) {
+	dir := t.TempDir()
+	db, err := OpenWith(wasmPath(t), "test.db", Options{Dir: dir})
+	if err != nil {
+		t.Fatal(err)
+	}
+	if err := db.Exec("CREATE TABLE blobs (data BLOB);"); err != nil {
+		t.Fatal(err)
+	}
+	if _, err := db.Query("INSERT INTO blobs VALUES (?)", []byte{0, 1, 2}); err != nil {
+		t.Fatal(err)
+	}
+
+	rows, err := db.Query("SELECT data FROM blobs")
+	if err != nil {
+		t.Fatal(err)
+	}
+	if got := rows.Values[0][0]; !reflect.DeepEqual(got, []byte{0, 1, 2}) {
+		t.Fatalf("got %v", got)
+	}
+}
+
+func TestMalformedSQLReturnsDbError(t *testing.T) {
+	db, err := Open(wasmPath(t), ":memory:")
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer db.Close()
+
+	err = db.Exec("SELEKT 1")
+	dbErr, ok := err.(*DbError)
+	if !ok || dbErr.Kind != "parse" {
+		t.Fatalf("got %v, want a parse DbError", err)
+	}
+	if dbErr.Position == nil || dbErr.Position.Offset != 0 || dbErr.Position.Token != "SELEKT" {
+		t.Fatalf("got position %+v, want SELEKT at the start", dbErr.Position)
+	}
+
+	// the instance is still usable
+	if _, err := db.Query("SELECT 1"); err != nil {
+		t.Fatal(err)
+	}
+}
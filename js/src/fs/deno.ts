@@ -0,0 +1,69 @@
+// `FsBackend` on real files through Deno's synchronous `FsFile` API.
+import type { FsBackend } from "../host-fs.js";
+
+// only the parts of the Deno namespace used here, so the package builds without Deno's types
+interface DenoFsFile {
+  seekSync(offset: number | bigint, whence: number): number;
+  readSync(p: Uint8Array): number | null;
+  writeSync(p: Uint8Array): number;
+  syncSync(): void;
+  statSync(): { size: number };
+}
+
+interface DenoNamespace {
+  openSync(
+    path: string,
+    options: { read: boolean; write: boolean; create: boolean },
+  ): DenoFsFile;
+  SeekMode: { Start: number };
+}
+
+/** A backend opening database files relative to `root`, the working directory by default. */
+export function denoFs(root?: string): FsBackend {
+  const deno = (globalThis as any).Deno as DenoNamespace;
+
+  const files: DenoFsFile[] = [];
+  const fileOf = (fd: number) => {
+    const file = files[fd];
+    if (file === undefined) {
+      throw new Error(`bad file descriptor ${fd}`);
+    }
+    return file;
+  };
+
+  return {
+    open(path, create) {
+      const resolved = root === undefined ? path : `${root}/${path}`;
+      files.push(deno.openSync(resolved, { read: true, write: true, create }));
+      return files.length - 1;
+    },
+    read(fd, offset, len) {
+      const file = fileOf(fd);
+      file.seekSync(offset, deno.SeekMode.Start);
+      const buf = new Uint8Array(len);
+      let n = 0;
+      while (n < len) {
+        const read = file.readSync(buf.subarray(n));
+        if (read === null) {
+          break;
+        }
+        n += read;
+      }
+      return buf.subarray(0, n);
+    },
+    write(fd, offset, data) {
+      const file = fileOf(fd);
+      file.seekSync(offset, deno.SeekMode.Start);
+      let written = 0;
+      while (written < data.length) {
+        written += file.writeSync(data.subarray(written));
+      }
+    },
+    sync(fd) {
+      fileOf(fd).syncSync();
+    },
+    size(fd) {
+      return BigInt(fileOf(fd).statSync().size);
+    },
+  };
+}
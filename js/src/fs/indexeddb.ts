@@ -0,0 +1,110 @@
+// `KvBackend` on IndexedDB, for browsers and contexts without OPFS.
+//
+// IndexedDB is asynchronous but the component's imports are not, so every
+// page of the database is loaded into memory when it is opened, and reads are
+// served from there. Writes update memory straight away and are persisted in
+// batches: once `maxBatch` pages are dirty, on `flush()` (every commit), or
+// after `maxDelayMs`, whichever comes first. `persisted()` resolves once every
+// write so far has reached IndexedDB.
+import type { KvBackend } from "../host-kv.js";
+
+const STORE = "pages";
+
+export interface IndexedDbOptions {
+  /** Name of the IndexedDB database holding the pages. Defaults to `"wit-limbo"`. */
+  dbName?: string;
+  /** Persist once this many pages are dirty. Defaults to 64. */
+  maxBatch?: number;
+  /** Persist dirty pages at least this often. Defaults to 100ms. */
+  maxDelayMs?: number;
+}
+
+export interface IndexedDbBackend extends KvBackend {
+  /** Resolves once every write so far has been persisted. */
+  persisted(): Promise<void>;
+  /** Persist outstanding writes and close the IndexedDB connection. */
+  close(): Promise<void>;
+}
+
+function request<T>(req: IDBRequest<T>): Promise<T> {
+  return new Promise((resolve, reject) => {
+    req.onsuccess = () => resolve(req.result);
+    req.onerror = () => reject(req.error);
+  });
+}
+
+/** Load the pages of the database `name` from IndexedDB and return a backend for them. */
+export async function indexedDb(name: string, options: IndexedDbOptions = {}): Promise<IndexedDbBackend> {
+  const { dbName = "wit-limbo", maxBatch = 64, maxDelayMs = 100 } = options;
+
+  const open = indexedDB.open(dbName, 1);
+  open.onupgradeneeded = () => open.result.createObjectStore(STORE);
+  const idb = await request(open);
+
+  // keys are `<file>/<block>`, so everything for `name` (and its `-wal`) shares the prefix
+  const range = IDBKeyRange.bound(name, `${name}￿`);
+  const tx = idb.transaction(STORE, "readonly");
+  const [keys, values] = await Promise.all([
+    request(tx.objectStore(STORE).getAllKeys(range)),
+    request(tx.objectStore(STORE).getAll(range)),
+  ]);
+
+  const pages = new Map<string, Uint8Array>();
+  keys.forEach((key, i) => pages.set(String(key), values[i]));
+
+  const dirty = new Map<string, Uint8Array>();
+  let timer: ReturnType<typeof setTimeout> | undefined;
+  let pending: Promise<void> = Promise.resolve();
+
+  const persist = () => {
+    if (timer !== undefined) {
+      clearTimeout(timer);
+      timer = undefined;
+    }
+    if (dirty.size === 0) {
+      return;
+    }
+    const batch = [...dirty];
+    dirty.clear();
+    pending = pending.then(
+      () =>
+        new Promise<void>((resolve, reject) => {
+          const tx = idb.transaction(STORE, "readwrite");
+          for (const [key, value] of batch) {
+            tx.objectStore(STORE).put(value, key);
+          }
+          tx.oncomplete = () => resolve();
+          tx.onerror = () => reject(tx.error);
+        }),
+    );
+  };
+
+  return {
+    get(key) {
+      return pages.get(key);
+    },
+    set(key, value) {
+      // the component reuses its buffers, so keep a copy
+      const copy = value.slice();
+      pages.set(key, copy);
+      dirty.set(key, copy);
+      if (dirty.size >= maxBatch) {
+        persist();
+      } else if (timer === undefined) {
+        timer = setTimeout(persist, maxDelayMs);
+      }
+    },
+    flush() {
+      persist();
+    },
+    persisted() {
+      persist();
+      return pending;
+    },
+    async close() {
+      persist();
+      await pending;
+      idb.close();
+    },
+  };
+}
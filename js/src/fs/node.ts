@@ -0,0 +1,50 @@
+// `FsBackend` on real files through Node's synchronous `fs` API, so the same
+// component serves server-side JS as well as the browser.
+import type { FsBackend } from "../host-fs.js";
+
+/**
+ * A backend opening database files relative to `root`, the working directory by default.
+ *
+ * `node:fs` is imported dynamically so browser bundles never see it.
+ */
+export async function nodeFs(root?: string): Promise<FsBackend> {
+  const fs = await import("node:fs");
+  const path = await import("node:path");
+
+  const fds: number[] = [];
+  const fdOf = (fd: number) => {
+    const nodeFd = fds[fd];
+    if (nodeFd === undefined) {
+      throw new Error(`bad file descriptor ${fd}`);
+    }
+    return nodeFd;
+  };
+
+  return {
+    open(file, create) {
+      const resolved = root === undefined ? file : path.join(root, file);
+      if (create && !fs.existsSync(resolved)) {
+        fs.closeSync(fs.openSync(resolved, "w"));
+      }
+      fds.push(fs.openSync(resolved, "r+"));
+      return fds.length - 1;
+    },
+    read(fd, offset, len) {
+      const buf = new Uint8Array(len);
+      const n = fs.readSync(fdOf(fd), buf, 0, len, offset);
+      return buf.subarray(0, n);
+    },
+    write(fd, offset, data) {
+      let written = 0;
+      while (written < data.length) {
+        written += fs.writeSync(fdOf(fd), data, written, data.length - written, offset + BigInt(written));
+      }
+    },
+    sync(fd) {
+      fs.fsyncSync(fdOf(fd));
+    },
+    size(fd) {
+      return BigInt(fs.fstatSync(fdOf(fd)).size);
+    },
+  };
+}
@@ -0,0 +1,58 @@
+// `FsBackend` on the Origin Private File System, so databases survive page reloads.
+//
+// OPFS only offers synchronous access through `FileSystemSyncAccessHandle`,
+// which is only available inside a dedicated worker, and creating the handles
+// is asynchronous. So every file the database will use is opened up front.
+import type { FsBackend } from "../host-fs.js";
+
+/**
+ * Open `paths` in OPFS (creating them if needed) and return a backend for them.
+ *
+ * Must be called from a dedicated worker. `dir` defaults to the OPFS root.
+ */
+export async function opfs(
+  paths: string[],
+  dir?: FileSystemDirectoryHandle,
+): Promise<FsBackend> {
+  const root = dir ?? (await navigator.storage.getDirectory());
+
+  const handles = new Map<string, FileSystemSyncAccessHandle>();
+  for (const path of paths) {
+    const file = await root.getFileHandle(path, { create: true });
+    handles.set(path, await file.createSyncAccessHandle());
+  }
+
+  const fds: FileSystemSyncAccessHandle[] = [];
+  const handle = (fd: number) => {
+    const h = fds[fd];
+    if (h === undefined) {
+      throw new Error(`bad file descriptor ${fd}`);
+    }
+    return h;
+  };
+
+  return {
+    open(path) {
+      const h = handles.get(path);
+      if (h === undefined) {
+        throw new Error(`${path} was not opened in OPFS up front`);
+      }
+      fds.push(h);
+      return fds.length - 1;
+    },
+    read(fd, offset, len) {
+      const buf = new Uint8Array(len);
+      const n = handle(fd).read(buf, { at: Number(offset) });
+      return buf.subarray(0, n);
+    },
+    write(fd, offset, data) {
+      handle(fd).write(data, { at: Number(offset) });
+    },
+    sync(fd) {
+      handle(fd).flush();
+    },
+    size(fd) {
+      return BigInt(handle(fd).getSize());
+    },
+  };
+}
@@ -0,0 +1,61 @@
+// Implementation of the `component:wit-limbo/host-fs` imports.
+//
+// The component calls these synchronously, so they delegate to whichever
+// `FsBackend` was selected with `setFsBackend` when the database was opened.
+
+/** Synchronous file storage, as required by the `host-fs` interface. */
+export interface FsBackend {
+  /** Open the file at `path`, creating it if `create` is set. Returns a file descriptor. */
+  open(path: string, create: boolean): number;
+  /** Read up to `len` bytes at `offset`. Returns fewer bytes at the end of the file. */
+  read(fd: number, offset: bigint, len: number): Uint8Array;
+  /** Write `data` at `offset`, extending the file if needed. */
+  write(fd: number, offset: bigint, data: Uint8Array): void;
+  /** Flush the file to durable storage. */
+  sync(fd: number): void;
+  /** The size of the file in bytes. */
+  size(fd: number): bigint;
+}
+
+let backend: FsBackend | undefined;
+
+/** Select the backend used by databases opened from now on. */
+export function setFsBackend(fs: FsBackend): void {
+  backend = fs;
+}
+
+/**
+ * Run `f` against the current backend, turning exceptions into the `err`
+ * case of the WIT `result` (jco reads it from the `payload` property).
+ */
+function withBackend<T>(f: (fs: FsBackend) => T): T {
+  try {
+    if (backend === undefined) {
+      throw new Error("no file system backend selected, use ':memory:' or open with one");
+    }
+    return f(backend);
+  } catch (e) {
+    const message = e instanceof Error ? e.message : String(e);
+    throw Object.assign(new Error(message), { payload: message });
+  }
+}
+
+export function open(path: string, create: boolean): number {
+  return withBackend((fs) => fs.open(path, create));
+}
+
+export function read(fd: number, offset: bigint, len: number): Uint8Array {
+  return withBackend((fs) => fs.read(fd, offset, len));
+}
+
+export function write(fd: number, offset: bigint, data: Uint8Array): void {
+  withBackend((fs) => fs.write(fd, offset, data));
+}
+
+export function sync(fd: number): void {
+  withBackend((fs) => fs.sync(fd));
+}
+
+export function size(fd: number): bigint {
+  return withBackend((fs) => fs.size(fd));
+}
@@ -0,0 +1,35 @@
+// Implementation of the `component:wit-limbo/host-io` imports.
+//
+// The `FsBackend`s are synchronous, so each read is done as it is submitted, through
+// `host-fs`, and kept until the component polls for it.
+import { read } from "./host-fs.js";
+
+/** A read of a `host-fs` file, as the WIT `io-request` record. */
+export interface IoRequest {
+  fd: number;
+  offset: bigint;
+  len: number;
+}
+
+type ReadResult = { tag: "ok"; val: Uint8Array } | { tag: "err"; val: string };
+
+const reads = new Map<bigint, ReadResult>();
+let lastId = 0n;
+
+export function submitIo(request: IoRequest): bigint {
+  let result: ReadResult;
+  try {
+    result = { tag: "ok", val: read(request.fd, request.offset, request.len) };
+  } catch (e) {
+    result = { tag: "err", val: e instanceof Error ? e.message : String(e) };
+  }
+  lastId += 1n;
+  reads.set(lastId, result);
+  return lastId;
+}
+
+export function pollIo(id: bigint): ReadResult | undefined {
+  const result = reads.get(id);
+  reads.delete(id);
+  return result;
+}
@@ -0,0 +1,46 @@
+// Implementation of the `component:wit-limbo/host-kv` imports.
+//
+// Like `host-fs`, the component calls these synchronously, so they delegate to
+// whichever `KvBackend` was selected with `setKvBackend`.
+
+/** Synchronous key-value storage, as required by the `host-kv` interface. */
+export interface KvBackend {
+  /** Get the value stored under `key`, if any. */
+  get(key: string): Uint8Array | undefined;
+  /** Store `value` under `key`. May be buffered until `flush`. */
+  set(key: string, value: Uint8Array): void;
+  /** Make every `set` so far durable. */
+  flush(): void;
+}
+
+let backend: KvBackend | undefined;
+
+/** Select the backend used by `kv:` databases opened from now on. */
+export function setKvBackend(kv: KvBackend): void {
+  backend = kv;
+}
+
+/** See `withBackend` in host-fs.ts. */
+function withBackend<T>(f: (kv: KvBackend) => T): T {
+  try {
+    if (backend === undefined) {
+      throw new Error("no key-value backend selected for this 'kv:' database");
+    }
+    return f(backend);
+  } catch (e) {
+    const message = e instanceof Error ? e.message : String(e);
+    throw Object.assign(new Error(message), { payload: message });
+  }
+}
+
+export function get(key: string): Uint8Array | undefined {
+  return withBackend((kv) => kv.get(key));
+}
+
+export function set(key: string, value: Uint8Array): void {
+  withBackend((kv) => kv.set(key, value));
+}
+
+export function flush(): void {
+  withBackend((kv) => kv.flush());
+}
@@ -0,0 +1,79 @@
+// Implementation of the `component:wit-limbo/host-random`, `host-log`, `host-clock`,
+// `host-trace`, `host-subscriptions`, `host-progress` and `host-locale` imports.
+//
+// `jco transpile --map` points the generated bindings at this module, so users
+// never have to wire the imports up themselves.
+
+/** Get a random byte, from the platform CSPRNG. */
+export function randomByte(): number {
+  return crypto.getRandomValues(new Uint8Array(1))[0];
+}
+
+/** Logs a message to the console. */
+export function log(message: string): void {
+  console.log(message);
+}
+
+const sleepCell = new Int32Array(new SharedArrayBuffer(4));
+
+/** Block for `ms` milliseconds, while a busy statement waits for a lock. */
+export function sleep(ms: number): void {
+  try {
+    Atomics.wait(sleepCell, 0, 0, ms);
+  } catch {
+    // Atomics.wait is not allowed on the main thread of a browser.
+    const until = Date.now() + ms;
+    while (Date.now() < until);
+  }
+}
+
+/** Microseconds since the Unix epoch. */
+export function now(): bigint {
+  return BigInt(Math.round((performance.timeOrigin + performance.now()) * 1000));
+}
+
+const spans = new Map<bigint, { name: string; start: number }>();
+let nextSpan = 0n;
+
+/** Start a span, recorded as a `performance.measure` entry when it ends. */
+export function spanStart(name: string): bigint {
+  const id = nextSpan++;
+  spans.set(id, { name, start: performance.now() });
+  return id;
+}
+
+/** End the span `id`. */
+export function spanEnd(id: bigint): void {
+  const span = spans.get(id);
+  if (span) {
+    spans.delete(id);
+    performance.measure(span.name, { start: span.start });
+  }
+}
+
+/** A subscribed query's rows changed. The wrapper doesn't subscribe, so nothing is told. */
+export function onQueryChanged(_subscriptionId: bigint): void {}
+
+let progressHandler: ((operation: string, done: bigint, total: bigint) => void) | undefined;
+
+/** Call `handler` with the progress of long calls, e.g. to update a progress bar. */
+export function setOnProgress(
+  handler: ((operation: string, done: bigint, total: bigint) => void) | undefined,
+): void {
+  progressHandler = handler;
+}
+
+/** `done` of the `total` steps of a long call have finished, told to `setOnProgress`'s handler. */
+export function onProgress(operation: string, done: bigint, total: bigint): void {
+  progressHandler?.(operation, done, total);
+}
+
+/** Lowercase `text` by the platform's default locale. */
+export function toLower(text: string): string {
+  return text.toLocaleLowerCase();
+}
+
+/** Compare `a` and `b` as `locale` sorts them, by `Intl.Collator`. */
+export function collate(locale: string, a: string, b: string): number {
+  return Math.sign(new Intl.Collator(locale).compare(a, b));
+}
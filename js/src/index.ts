@@ -0,0 +1,328 @@
+// Typed wrapper around the jco-transpiled component.
+//
+// The generated bindings speak WIT: variants are `{ tag, val }` objects and
+// `s64` values are bigints. This wrapper turns rows into plain JS objects.
+import { limbo } from "./generated/wit_limbo.js";
+import { type FsBackend, setFsBackend } from "./host-fs.js";
+import { type KvBackend, setKvBackend } from "./host-kv.js";
+import { opfs } from "./fs/opfs.js";
+import { type IndexedDbOptions, indexedDb } from "./fs/indexeddb.js";
+import { nodeFs } from "./fs/node.js";
+import { denoFs } from "./fs/deno.js";
+import { detectRuntime } from "./runtime.js";
+
+export type { FsBackend } from "./host-fs.js";
+export type { KvBackend } from "./host-kv.js";
+export { opfs } from "./fs/opfs.js";
+export { nodeFs } from "./fs/node.js";
+export { denoFs } from "./fs/deno.js";
+export { type Runtime, detectRuntime } from "./runtime.js";
+export { setOnProgress } from "./host.js";
+export { type IndexedDbBackend, type IndexedDbOptions, indexedDb } from "./fs/indexeddb.js";
+
+/** Where a database that isn't `:memory:` is stored. */
+export interface StorageOptions {
+  /** File storage, for plain paths. */
+  fs?: FsBackend;
+  /** Key-value storage, for `kv:` paths. */
+  kv?: KvBackend;
+}
+
+type RecordValue =
+  | { tag: "null" }
+  | { tag: "integer"; val: bigint }
+  | { tag: "float"; val: number }
+  | { tag: "text"; val: string }
+  | { tag: "blob"; val: Uint8Array }
+  | { tag: "text-decimal"; val: string };
+
+/** What the last run of a statement did, see `Statement.lastRunStats`. */
+export type RunStats = {
+  vmSteps: bigint;
+  rowsProduced: bigint;
+  ioOperations: bigint;
+  elapsedMicros: bigint;
+};
+
+/** What `Database.tick` did. */
+export type TickWork = {
+  /** Writes committed from a batch that had waited long enough. */
+  flushed: number;
+  /** Whether the WAL was checkpointed into the database file. */
+  checkpointed: boolean;
+  /** Whether cached query rows were dropped to relieve the heap. */
+  trimmed: boolean;
+};
+
+/** Where some SQL failed to parse: line and column from 1, offset in bytes. */
+export type SqlPosition = {
+  line: number;
+  column: number;
+  offset: number;
+  token: string;
+};
+
+type DbError =
+  | { tag: "parse"; val: { message: string; position?: SqlPosition } }
+  | {
+      tag: "corrupt" | "io" | "misuse" | "busy" | "closed" | "interrupted" | "out-of-memory" | "limit-exceeded" | "cannot-open" | "other";
+      val: string;
+    };
+
+/**
+ * An error returned by the database, with the `db-error` case as its `kind`, and for `parse`
+ * errors the `position` in the SQL where that is known.
+ */
+export class DatabaseError extends Error {
+  readonly kind: DbError["tag"];
+  readonly position?: SqlPosition;
+
+  constructor(error: DbError) {
+    super(error.tag === "parse" ? error.val.message : error.val);
+    this.name = "DatabaseError";
+    this.kind = error.tag;
+    if (error.tag === "parse") {
+      this.position = error.val.position;
+    }
+  }
+}
+
+/**
+ * Call into the component, rethrowing a returned `db-error` as a `DatabaseError`.
+ *
+ * jco throws a `ComponentError` with the `db-error` as its `payload` for `result` errors.
+ */
+function guard<T>(f: () => T): T {
+  try {
+    return f();
+  } catch (e) {
+    const payload = (e as { payload?: DbError }).payload;
+    if (payload !== undefined && typeof payload.tag === "string") {
+      throw new DatabaseError(payload);
+    }
+    throw e;
+  }
+}
+
+/** A SQL value as seen from JavaScript. */
+export type Value = null | number | bigint | string | Uint8Array;
+
+/** A value that `Database.queryJson` binds or returns. */
+export type JsonValue = null | boolean | number | string | number[];
+
+/** A row, keyed by column name. */
+export type Row = Record<string, Value>;
+
+/**
+ * Convert a record-value into a JS value.
+ *
+ * Integers outside `Number.MAX_SAFE_INTEGER` stay bigints so no precision is lost.
+ */
+function fromRecordValue(value: RecordValue): Value {
+  switch (value.tag) {
+    case "null":
+      return null;
+    case "integer":
+      return value.val >= BigInt(Number.MIN_SAFE_INTEGER) &&
+        value.val <= BigInt(Number.MAX_SAFE_INTEGER)
+        ? Number(value.val)
+        : value.val;
+    case "float":
+    case "text":
+    case "text-decimal":
+    case "blob":
+      return value.val;
+  }
+}
+
+/** Convert a JS value into a record-value, for binding parameters. */
+function toRecordValue(value: Value): RecordValue {
+  if (value === null) {
+    return { tag: "null" };
+  } else if (typeof value === "bigint") {
+    return { tag: "integer", val: value };
+  } else if (typeof value === "number") {
+    return Number.isInteger(value)
+      ? { tag: "integer", val: BigInt(value) }
+      : { tag: "float", val: value };
+  } else if (typeof value === "string") {
+    return { tag: "text", val: value };
+  } else {
+    return { tag: "blob", val: value };
+  }
+}
+
+/** A prepared statement. */
+export class Statement {
+  #inner: limbo.Statement;
+
+  constructor(inner: limbo.Statement) {
+    this.#inner = inner;
+  }
+
+  /** Bind `params` to the statement's parameters, in order. */
+  bind(...params: Value[]): this {
+    params.forEach((param, i) => guard(() => this.#inner.bind(i + 1, toRecordValue(param))));
+    return this;
+  }
+
+  /** Get all rows, as objects keyed by column name. */
+  all(): Row[] {
+    const { columns, rows } = guard(() => this.#inner.allWithNames());
+    return rows.map((row: RecordValue[]) =>
+      Object.fromEntries(columns.map((column: string, i: number) => [column, fromRecordValue(row[i])])),
+    );
+  }
+
+  /** Get all rows, as arrays of values in column order. */
+  raw(): Value[][] {
+    return guard(() => this.#inner.all()).map((row: RecordValue[]) => row.map(fromRecordValue));
+  }
+
+  /**
+   * Iterate over the rows, as objects keyed by column name, fetching `chunkSize` rows
+   * from the component at a time rather than all of them at once.
+   */
+  *iterate(chunkSize = 64): Generator<Row> {
+    const columns = this.#inner.columnNames();
+    const rows = guard(() => this.#inner.run());
+    for (;;) {
+      const chunk = guard(() => rows.nextChunk(chunkSize));
+      for (const row of chunk) {
+        yield Object.fromEntries(columns.map((column: string, i: number) => [column, fromRecordValue(row[i])]));
+      }
+      if (chunk.length < chunkSize) {
+        return;
+      }
+    }
+  }
+
+  /** What the last run of the statement did: VM steps, rows, IO operations and time taken. */
+  lastRunStats(): RunStats {
+    return this.#inner.lastRunStats();
+  }
+
+  /** Release the statement. Using it afterwards throws a `closed` DatabaseError. */
+  finalize(): void {
+    guard(() => this.#inner.finalize());
+  }
+}
+
+/** A database running inside the wit-limbo component. */
+export class Database {
+  #inner: limbo.Database;
+
+  /**
+   * Open the database at `path`, e.g. `":memory:"`.
+   *
+   * `kv:<name>` paths are stored through `storage.kv`, and any other path is
+   * opened through `storage.fs`. Without them, the most recently used backend is used.
+   */
+  constructor(path: string, storage: StorageOptions = {}) {
+    if (storage.fs !== undefined) {
+      setFsBackend(storage.fs);
+    }
+    if (storage.kv !== undefined) {
+      setKvBackend(storage.kv);
+    }
+    this.#inner = new limbo.Database(path);
+  }
+
+  /**
+   * Open the database at `path` with the storage that suits the current runtime:
+   * real files on Node, Bun and Deno, OPFS in a browser worker, IndexedDB for `kv:` paths.
+   */
+  static async open(path: string): Promise<Database> {
+    if (path === ":memory:") {
+      return new Database(path);
+    }
+    if (path.startsWith("kv:")) {
+      return Database.openIndexedDb(path.slice("kv:".length));
+    }
+    switch (detectRuntime()) {
+      case "deno":
+        return new Database(path, { fs: denoFs() });
+      // Bun implements node:fs
+      case "bun":
+      case "node":
+        return new Database(path, { fs: await nodeFs() });
+      case "browser":
+        return Database.openOpfs(path);
+      default:
+        throw new Error(`no file system available for ${path}, pass one with new Database(path, { fs })`);
+    }
+  }
+
+  /**
+   * Open the database at `path` in the Origin Private File System, so it
+   * survives page reloads. Must be called from a dedicated worker.
+   */
+  static async openOpfs(path: string, dir?: FileSystemDirectoryHandle): Promise<Database> {
+    return new Database(path, { fs: await opfs([path, `${path}-wal`], dir) });
+  }
+
+  /**
+   * Open the database `name` stored in IndexedDB, for browsers without OPFS.
+   * Writes are persisted in the background, see `indexedDb`.
+   */
+  static async openIndexedDb(name: string, options?: IndexedDbOptions): Promise<Database> {
+    return new Database(`kv:${name}`, { kv: await indexedDb(name, options) });
+  }
+
+  /** Execute a SQL statement, returning the rows of any RETURNING clause. */
+  exec(sql: string): Value[][] {
+    return guard(() => this.#inner.exec(sql)).map((row: RecordValue[]) => row.map(fromRecordValue));
+  }
+
+  /** Prepare a SQL statement. */
+  prepare(sql: string): Statement {
+    return new Statement(guard(() => this.#inner.prepare(sql)));
+  }
+
+  /**
+   * Run `sql` with `params`, bound by position from an array or by name from an object,
+   * and return its rows, all in one call into the component. Values go through JSON, so
+   * integers beyond `Number.MAX_SAFE_INTEGER` lose precision and blobs come back as arrays
+   * of bytes; use `prepare` for those.
+   */
+  queryJson(
+    sql: string,
+    params: JsonValue[] | Record<string, JsonValue> = [],
+  ): { columns: string[]; rows: JsonValue[][] } {
+    return JSON.parse(guard(() => this.#inner.queryJson(sql, JSON.stringify(params))));
+  }
+
+  /**
+   * Wait up to `ms` milliseconds for another connection's lock before a statement throws
+   * a `busy` DatabaseError. 0, the default, throws straight away.
+   */
+  setBusyTimeout(ms: number): void {
+    this.#inner.setBusyTimeout(ms);
+  }
+
+  /**
+   * Do the upkeep the component has no timers for: commit a batch that has waited long
+   * enough, checkpoint the WAL, and trim caches under heap pressure. Call it periodically,
+   * e.g. `requestIdleCallback(() => db.tick())`, to keep that work out of your own calls.
+   */
+  tick(): TickWork {
+    return guard(() => this.#inner.tick());
+  }
+
+  /**
+   * Read every page of every table and return what is wrong with the database, empty if
+   * nothing is, stopping at `maxErrors` problems (0 for no limit). Progress goes to the
+   * handler given to `setOnProgress`.
+   */
+  integrityCheck(maxErrors = 0): string[] {
+    return guard(() => this.#inner.integrityCheck(maxErrors));
+  }
+
+  /**
+   * Checkpoint the WAL and release the connection, rather than waiting for garbage
+   * collection. Using the database afterwards throws a `closed` DatabaseError.
+   */
+  close(): void {
+    guard(() => this.#inner.close());
+  }
+}
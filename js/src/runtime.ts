@@ -0,0 +1,21 @@
+// Detection of the JavaScript runtime we're running on, to pick a storage backend.
+
+export type Runtime = "deno" | "bun" | "node" | "browser" | "unknown";
+
+export function detectRuntime(): Runtime {
+  const g = globalThis as any;
+  // Deno and Bun both provide some Node compatibility, so check for them first
+  if (g.Deno?.version?.deno !== undefined) {
+    return "deno";
+  }
+  if (g.process?.versions?.bun !== undefined) {
+    return "bun";
+  }
+  if (g.process?.versions?.node !== undefined) {
+    return "node";
+  }
+  if (g.navigator?.storage?.getDirectory !== undefined) {
+    return "browser";
+  }
+  return "unknown";
+}
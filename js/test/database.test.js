@@ -0,0 +1,61 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+
+import { Database, DatabaseError } from "../dist/index.js";
+
+test("exec and prepare().all() return rows as objects", () => {
+  const db = new Database(":memory:");
+  db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);");
+  db.exec("INSERT INTO users (name) VALUES ('Alice');");
+
+  const rows = db.prepare("SELECT * FROM users WHERE id = ?").bind(1).all();
+
+  assert.deepEqual(rows, [{ id: 1, name: "Alice" }]);
+});
+
+test("malformed SQL throws a DatabaseError", () => {
+  const db = new Database(":memory:");
+
+  assert.throws(() => db.exec("SELEKT 1"), (e) => e instanceof DatabaseError && e.kind === "parse");
+  assert.throws(
+    () => db.exec("SELECT 1;\n  SELEKT 2"),
+    (e) => e.position.line === 2 && e.position.column === 3 && e.position.token === "SELEKT",
+  );
+  // the instance is still usable
+  assert.deepEqual(db.exec("SELECT 1"), [[1]]);
+});
+
+test("close and finalize release resources", () => {
+  const db = new Database(":memory:");
+  const statement = db.prepare("SELECT 1");
+  statement.finalize();
+  assert.throws(() => statement.raw(), (e) => e instanceof DatabaseError && e.kind === "closed");
+
+  db.close();
+  assert.throws(() => db.exec("SELECT 1"), (e) => e instanceof DatabaseError && e.kind === "closed");
+});
+
+test("iterate() fetches rows in chunks", () => {
+  const db = new Database(":memory:");
+  db.exec("CREATE TABLE t (n INTEGER)");
+  db.exec("INSERT INTO t VALUES (1), (2), (3), (4), (5)");
+  const statement = db.prepare("SELECT n FROM t WHERE n > ?");
+  assert.deepEqual([...statement.bind(1).iterate(2)].map((row) => row.n), [2, 3, 4, 5]);
+  assert.deepEqual([...statement.bind(3).iterate(2)].map((row) => row.n), [4, 5]);
+});
+
+test("queryJson() binds and returns rows in one call", () => {
+  const db = new Database(":memory:");
+  db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);");
+  db.exec("INSERT INTO users (name) VALUES ('Alice'), ('Bob');");
+
+  assert.deepEqual(db.queryJson("SELECT name FROM users WHERE id > ?", [1]), {
+    columns: ["name"],
+    rows: [["Bob"]],
+  });
+  assert.deepEqual(db.queryJson("SELECT id FROM users WHERE name = :name", { name: "Alice" }).rows, [[1]]);
+  assert.throws(
+    () => db.queryJson("SELECT ?", [{}]),
+    (e) => e instanceof DatabaseError && e.kind === "misuse",
+  );
+});
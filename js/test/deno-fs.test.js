@@ -0,0 +1,20 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+
+import { Database, denoFs, detectRuntime } from "../dist/index.js";
+
+test("a file database persists across opens on Deno", { skip: detectRuntime() !== "deno" }, () => {
+  const dir = globalThis.Deno.makeTempDirSync({ prefix: "wit-limbo-" });
+
+  const db = new Database("test.db", { fs: denoFs(dir) });
+  db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);");
+  db.exec("INSERT INTO users (name) VALUES ('Alice');");
+
+  const reopened = new Database("test.db", { fs: denoFs(dir) });
+  assert.deepEqual(reopened.prepare("SELECT name FROM users").all(), [{ name: "Alice" }]);
+});
+
+test("Database.open picks the runtime's file system", async () => {
+  const db = await Database.open(":memory:");
+  assert.deepEqual(db.exec("SELECT 1 AS one"), [[1]]);
+});
@@ -0,0 +1,18 @@
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { mkdtempSync } from "node:fs";
+import { tmpdir } from "node:os";
+import { join } from "node:path";
+
+import { Database, nodeFs } from "../dist/index.js";
+
+test("a file database persists across opens on Node", async () => {
+  const dir = mkdtempSync(join(tmpdir(), "wit-limbo-"));
+
+  const db = new Database("test.db", { fs: await nodeFs(dir) });
+  db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);");
+  db.exec("INSERT INTO users (name) VALUES ('Alice');");
+
+  const reopened = new Database("test.db", { fs: await nodeFs(dir) });
+  assert.deepEqual(reopened.prepare("SELECT name FROM users").all(), [{ name: "Alice" }]);
+});
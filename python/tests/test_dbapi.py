@@ -0,0 +1,50 @@
+import wit_limbo
+
+
+def test_execute_and_fetch():
+    conn = wit_limbo.connect(":memory:")
+    cur = conn.cursor()
+    cur.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+    cur.execute("INSERT INTO users (name) VALUES (?)", ("Alice",))
+
+    cur.execute("SELECT id, name FROM users WHERE id = ?", (1,))
+
+    assert [d[0] for d in cur.description] == ["id", "name"]
+    assert cur.fetchall() == [(1, "Alice")]
+
+
+def test_file_database_persists(tmp_path):
+    with wit_limbo.connect("test.db", root=str(tmp_path)) as conn:
+        conn.execute("CREATE TABLE blobs (data BLOB)")
+        conn.execute("INSERT INTO blobs VALUES (?)", (b"\x00\x01\x02",))
+
+    with wit_limbo.connect("test.db", root=str(tmp_path)) as conn:
+        assert conn.execute("SELECT data FROM blobs").fetchone() == (b"\x00\x01\x02",)
+
+
+def test_malformed_sql_raises():
+    conn = wit_limbo.connect(":memory:")
+
+    try:
+        conn.execute("SELEKT 1")
+    except wit_limbo.ProgrammingError:
+        pass
+    else:
+        raise AssertionError("expected ProgrammingError")
+
+    # the connection is still usable
+    assert conn.execute("SELECT 1").fetchall() == [(1,)]
+
+
+def test_closed_connection_raises():
+    conn = wit_limbo.connect(":memory:")
+    cur = conn.cursor()
+    conn.close()
+    conn.close()
+
+    try:
+        cur.execute("SELECT 1")
+    except wit_limbo.ProgrammingError:
+        pass
+    else:
+        raise AssertionError("expected ProgrammingError")
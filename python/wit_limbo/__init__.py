@@ -0,0 +1,235 @@
+"""Limbo (SQLite) running in a WebAssembly component, with a DB-API 2.0 (PEP 249) interface.
+
+>>> import wit_limbo
+>>> conn = wit_limbo.connect(":memory:")
+>>> cur = conn.cursor()
+>>> cur.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+>>> cur.execute("INSERT INTO users (name) VALUES (?)", ("Alice",))
+>>> cur.execute("SELECT * FROM users").fetchall()
+[(1, 'Alice')]
+"""
+from decimal import Decimal
+from typing import Any, List, Optional, Sequence, Tuple
+
+from wasmtime import Store
+
+from ._generated import Root, RootImports
+from ._generated.exports import limbo
+from ._generated.types import Err
+from ._host import (
+    HostClock,
+    HostFs,
+    HostIo,
+    HostKv,
+    HostLog,
+    HostProgress,
+    HostRandom,
+    HostSubscriptions,
+    HostTrace,
+)
+
+apilevel = "2.0"
+# one component instance per connection, which must not be shared between threads
+threadsafety = 1
+paramstyle = "qmark"
+
+
+class Error(Exception):
+    pass
+
+
+class InterfaceError(Error):
+    pass
+
+
+class DatabaseError(Error):
+    pass
+
+
+class ProgrammingError(DatabaseError):
+    pass
+
+
+class NotSupportedError(DatabaseError):
+    pass
+
+
+class OperationalError(DatabaseError):
+    pass
+
+
+class IntegrityError(DatabaseError):
+    pass
+
+
+class DataError(DatabaseError):
+    pass
+
+
+def _check(result: Any) -> Any:
+    """Unwrap a ``result<T, db-error>``, raising the matching DB-API exception for errors."""
+    if not isinstance(result, Err):
+        return result.value
+    error = result.value
+    if isinstance(error, limbo.DbErrorParse):
+        raise ProgrammingError(error.value.message)
+    if isinstance(error, limbo.DbErrorMisuse):
+        raise InterfaceError(error.value)
+    if isinstance(error, limbo.DbErrorClosed):
+        raise ProgrammingError(error.value)
+    if isinstance(error, limbo.DbErrorOutOfMemory):
+        raise MemoryError(error.value)
+    if isinstance(error, limbo.DbErrorLimitExceeded):
+        raise DataError(error.value)
+    if isinstance(error, limbo.DbErrorCorrupt):
+        raise IntegrityError(error.value)
+    raise OperationalError(error.value)
+
+
+def _from_record_value(value: Any) -> Any:
+    """Convert a record-value into the matching Python type."""
+    if isinstance(value, limbo.RecordValueNull):
+        return None
+    if isinstance(value, limbo.RecordValueBlob):
+        return bytes(value.value)
+    if isinstance(value, limbo.RecordValueTextDecimal):
+        return Decimal(value.value)
+    # integer, float and text map directly
+    return value.value
+
+
+def _to_record_value(value: Any) -> Any:
+    """Convert a Python value into a record-value, for binding parameters."""
+    if value is None:
+        return limbo.RecordValueNull()
+    if isinstance(value, bool):
+        return limbo.RecordValueInteger(int(value))
+    if isinstance(value, int):
+        if -(2**63) <= value < 2**63:
+            return limbo.RecordValueInteger(value)
+        return limbo.RecordValueTextDecimal(str(value))
+    if isinstance(value, float):
+        return limbo.RecordValueFloat(value)
+    if isinstance(value, Decimal):
+        return limbo.RecordValueTextDecimal(str(value))
+    if isinstance(value, str):
+        return limbo.RecordValueText(value)
+    if isinstance(value, (bytes, bytearray, memoryview)):
+        return limbo.RecordValueBlob(bytes(value))
+    raise InterfaceError(f"unsupported parameter type {type(value).__name__}")
+
+
+class Cursor:
+    def __init__(self, connection: "Connection"):
+        self.connection = connection
+        self.description: Optional[List[Tuple]] = None
+        self.rowcount = -1
+        self.arraysize = 1
+        self._rows: List[Tuple] = []
+
+    def execute(self, sql: str, parameters: Sequence[Any] = ()) -> "Cursor":
+        store, db = self.connection._store, self.connection._db
+        if db is None:
+            raise ProgrammingError("Cannot operate on a closed database.")
+        statement = _check(db.prepare(store, sql))
+        try:
+            for i, param in enumerate(parameters):
+                _check(statement.bind(store, i + 1, _to_record_value(param)))
+            result_set = _check(statement.all_with_names(store))
+        finally:
+            _check(statement.finalize(store))
+
+        self.description = [
+            (name, None, None, None, None, None, None) for name in result_set.columns
+        ] or None
+        self._rows = [
+            tuple(_from_record_value(value) for value in row) for row in result_set.rows
+        ]
+        self.rowcount = -1
+        return self
+
+    def executemany(self, sql: str, seq_of_parameters: Sequence[Sequence[Any]]) -> "Cursor":
+        for parameters in seq_of_parameters:
+            self.execute(sql, parameters)
+        return self
+
+    def fetchone(self) -> Optional[Tuple]:
+        return self._rows.pop(0) if self._rows else None
+
+    def fetchmany(self, size: Optional[int] = None) -> List[Tuple]:
+        size = self.arraysize if size is None else size
+        rows, self._rows = self._rows[:size], self._rows[size:]
+        return rows
+
+    def fetchall(self) -> List[Tuple]:
+        rows, self._rows = self._rows, []
+        return rows
+
+    def close(self) -> None:
+        self._rows = []
+
+    def setinputsizes(self, sizes: Any) -> None:
+        pass
+
+    def setoutputsize(self, size: Any, column: Any = None) -> None:
+        pass
+
+    def __iter__(self):
+        return iter(self.fetchone, None)
+
+
+class Connection:
+    def __init__(self, path: str, root: Optional[str], timeout: float):
+        self._store = Store()
+        # the generated bindings load the core modules they were generated from
+        fs = HostFs(root)
+        imports = RootImports(
+            host_random=HostRandom(),
+            host_log=HostLog(),
+            host_clock=HostClock(),
+            host_trace=HostTrace(),
+            host_subscriptions=HostSubscriptions(),
+            host_progress=HostProgress(),
+            host_fs=fs,
+            host_io=HostIo(fs),
+            host_kv=HostKv(),
+        )
+        self._root = Root(self._store, imports)
+        self._db = self._root.limbo().Database(self._store, path)
+        self._db.set_busy_timeout(self._store, int(timeout * 1000))
+
+    def cursor(self) -> Cursor:
+        return Cursor(self)
+
+    def execute(self, sql: str, parameters: Sequence[Any] = ()) -> Cursor:
+        return self.cursor().execute(sql, parameters)
+
+    def commit(self) -> None:
+        # every statement autocommits
+        pass
+
+    def rollback(self) -> None:
+        raise NotSupportedError("transactions are not supported yet")
+
+    def close(self) -> None:
+        """Checkpoint the WAL and release the connection. Closing twice is a no-op."""
+        if self._db is not None:
+            _check(self._db.close(self._store))
+            self._db = None
+
+    def __enter__(self) -> "Connection":
+        return self
+
+    def __exit__(self, *exc) -> None:
+        self.close()
+
+
+def connect(path: str, root: Optional[str] = None, timeout: float = 0.0) -> Connection:
+    """Open the database at ``path``.
+
+    ``:memory:`` is an in-memory database, ``kv:<name>`` is kept in memory by the host,
+    and any other path is a file relative to ``root`` (the working directory by default).
+    ``timeout`` is how many seconds a statement waits for another connection's lock
+    before raising OperationalError, as in the ``sqlite3`` module.
+    """
+    return Connection(path, root, timeout)
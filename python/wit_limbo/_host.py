@@ -0,0 +1,134 @@
+"""Implementations of the component's imports: host-random, host-log, host-clock,
+host-trace, host-subscriptions, host-progress, host-fs, host-io and host-kv."""
+import os
+import secrets
+import sys
+import time
+from typing import Dict, List, Optional
+
+from ._generated import imports
+from ._generated.types import Err, Ok, Result
+
+
+class HostRandom(imports.host_random.HostRandom):
+    def random_byte(self) -> int:
+        return secrets.randbits(8)
+
+
+class HostLog(imports.host_log.HostLog):
+    def log(self, message: str) -> None:
+        print(message, file=sys.stderr)
+
+
+class HostClock(imports.host_clock.HostClock):
+    def sleep(self, ms: int) -> None:
+        time.sleep(ms / 1000)
+
+    def now(self) -> int:
+        return time.time_ns() // 1000
+
+
+class HostTrace(imports.host_trace.HostTrace):
+    # spans aren't traced
+    def span_start(self, name: str) -> int:
+        return 0
+
+    def span_end(self, id: int) -> None:
+        pass
+
+
+class HostSubscriptions(imports.host_subscriptions.HostSubscriptions):
+    # nothing subscribes through the DB-API
+    def on_query_changed(self, subscription_id: int) -> None:
+        pass
+
+
+class HostProgress(imports.host_progress.HostProgress):
+    # no long call is run through the DB-API
+    def on_progress(self, operation: str, done: int, total: int) -> None:
+        pass
+
+
+class HostFs(imports.host_fs.HostFs):
+    """Database files on the local file system, relative to ``root``."""
+
+    def __init__(self, root: Optional[str] = None):
+        self.root = root
+        self.fds: List[int] = []
+
+    def _fd(self, fd: int) -> int:
+        if fd >= len(self.fds):
+            raise OSError(f"bad file descriptor {fd}")
+        return self.fds[fd]
+
+    def open(self, path: str, create: bool) -> Result[int, str]:
+        if self.root is not None:
+            path = os.path.join(self.root, path)
+        flags = os.O_RDWR | (os.O_CREAT if create else 0) | getattr(os, "O_BINARY", 0)
+        try:
+            self.fds.append(os.open(path, flags, 0o644))
+        except OSError as e:
+            return Err(str(e))
+        return Ok(len(self.fds) - 1)
+
+    def read(self, fd: int, offset: int, len: int) -> Result[bytes, str]:
+        try:
+            return Ok(os.pread(self._fd(fd), len, offset))
+        except OSError as e:
+            return Err(str(e))
+
+    def write(self, fd: int, offset: int, data: bytes) -> Result[None, str]:
+        try:
+            written = 0
+            while written < len(data):
+                written += os.pwrite(self._fd(fd), data[written:], offset + written)
+        except OSError as e:
+            return Err(str(e))
+        return Ok(None)
+
+    def sync(self, fd: int) -> Result[None, str]:
+        try:
+            os.fsync(self._fd(fd))
+        except OSError as e:
+            return Err(str(e))
+        return Ok(None)
+
+    def size(self, fd: int) -> Result[int, str]:
+        try:
+            return Ok(os.fstat(self._fd(fd)).st_size)
+        except OSError as e:
+            return Err(str(e))
+
+
+class HostIo(imports.host_io.HostIo):
+    """Reads of ``fs``'s files, done as they are submitted and kept until polled."""
+
+    def __init__(self, fs: HostFs):
+        self.fs = fs
+        self.reads: Dict[int, Result[bytes, str]] = {}
+        self.last_id = 0
+
+    def submit_io(self, request: imports.host_io.IoRequest) -> int:
+        self.last_id += 1
+        self.reads[self.last_id] = self.fs.read(request.fd, request.offset, request.len)
+        return self.last_id
+
+    def poll_io(self, id: int) -> Optional[Result[bytes, str]]:
+        return self.reads.pop(id, None)
+
+
+class HostKv(imports.host_kv.HostKv):
+    """Key-value storage for ``kv:`` databases, kept in memory."""
+
+    def __init__(self):
+        self.entries: Dict[str, bytes] = {}
+
+    def get(self, key: str) -> Result[Optional[bytes], str]:
+        return Ok(self.entries.get(key))
+
+    def set(self, key: str, value: bytes) -> Result[None, str]:
+        self.entries[key] = bytes(value)
+        return Ok(None)
+
+    def flush(self) -> Result[None, str]:
+        return Ok(None)
@@ -0,0 +1,173 @@
+//! Recording the changes to rows of audited tables, see `database.enable-audit` in the WIT.
+//! Limbo doesn't run triggers, so the guest reads an audited table before and after each
+//! write to it and records the rows that differ.
+use std::{cell::RefCell, collections::BTreeMap, num::NonZeroUsize, rc::Rc};
+
+use serde_json::Value as Json;
+
+use crate::bindings::component::wit_limbo::host_clock::now;
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    BlobEncoding, DbError, JsonOptions, RecordValue,
+};
+use crate::sql::Changes;
+use crate::{collect_rows, json, quote_identifier, run_to_completion, zero_stats, Component};
+
+/// The changes recorded to rows of the audited tables, oldest first.
+pub(crate) const LOG: &str = "wit_limbo_audit";
+
+/// The names of the audited tables.
+pub(crate) const AUDITED: &str = "wit_limbo_audited";
+
+/// The audited tables of one database, shared by its connections like its subscriptions, and
+/// loaded from [AUDITED] when first needed.
+#[derive(Default)]
+pub(crate) struct Audited {
+    known: RefCell<Option<Rc<[String]>>>,
+}
+
+impl Audited {
+    /// Load the tables again when next needed, after [AUDITED] or the schema changed.
+    pub(crate) fn forget(&self) {
+        self.known.replace(None);
+    }
+
+    fn get(&self, db: &Component) -> Result<Rc<[String]>, DbError> {
+        if let Some(known) = &*self.known.borrow() {
+            return Ok(known.clone());
+        }
+        let tables: Rc<[String]> = load(db)?.into();
+        self.known.replace(Some(tables.clone()));
+        Ok(tables)
+    }
+
+    /// The name `table` was audited under, if it is.
+    pub(crate) fn name(&self, db: &Component, table: &str) -> Result<Option<String>, DbError> {
+        Ok(self
+            .get(db)?
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(table))
+            .cloned())
+    }
+}
+
+/// The tables named in [AUDITED], none if it doesn't exist yet.
+fn load(db: &Component) -> Result<Vec<String>, DbError> {
+    let exists = db.query_with(
+        "SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = ?",
+        vec![RecordValue::Text(AUDITED.to_string())],
+    )?;
+    if exists.is_empty() {
+        return Ok(vec![]);
+    }
+    db.query_with(&format!("SELECT name FROM {}", AUDITED), vec![])?
+        .into_iter()
+        .map(|row| match &row[..] {
+            [RecordValue::Text(name)] => Ok(name.clone()),
+            _ => Err(DbError::Corrupt(format!(
+                "{} has an unexpected row: {:?}",
+                AUDITED, row
+            ))),
+        })
+        .collect()
+}
+
+/// The rows of the audited tables a statement can write, read before it runs.
+pub(crate) struct Before(Vec<(String, BTreeMap<i64, Json>)>);
+
+/// The rows of `table` by rowid, as JSON objects with blobs in base64, read without the
+/// connection's filters.
+fn rows(db: &Component, table: &str) -> Result<BTreeMap<i64, Json>, DbError> {
+    let mut stmt = db
+        .conn()?
+        .prepare(format!("SELECT rowid, * FROM {}", quote_identifier(table)))?;
+    let columns: Vec<String> = (1..stmt.num_columns())
+        .map(|i| stmt.get_column_name(i).to_string())
+        .collect();
+    let options = JsonOptions {
+        blob_encoding: BlobEncoding::Base64,
+        ..json::default_options()
+    };
+    collect_rows(&mut stmt, &db.io, &db.state, &mut zero_stats())?
+        .into_iter()
+        .map(|mut row| match row.remove(0) {
+            RecordValue::Integer(rowid) => Ok((rowid, json::row_object(&columns, row, &options))),
+            other => Err(DbError::Corrupt(format!(
+                "{} has a rowid of {:?}",
+                table, other
+            ))),
+        })
+        .collect()
+}
+
+/// What recording a statement making `changes` needs: the audited tables it can write, as
+/// they are before it runs.
+pub(crate) fn before(db: &Component, changes: &Changes) -> Result<Option<Before>, DbError> {
+    let tables: Vec<String> = match changes {
+        Changes::Table(table) => db.state.audited.name(db, table)?.into_iter().collect(),
+        Changes::Everything => db.state.audited.get(db)?.to_vec(),
+        _ => return Ok(None),
+    };
+    if tables.is_empty() {
+        return Ok(None);
+    }
+    let mut before = vec![];
+    for table in tables {
+        let rows = rows(db, &table)?;
+        before.push((table, rows));
+    }
+    Ok(Some(Before(before)))
+}
+
+/// Record the rows that changed since `before`, at `host-clock.now`, in [LOG].
+pub(crate) fn record(db: &Component, before: Option<Before>) -> Result<(), DbError> {
+    let Some(Before(before)) = before else {
+        return Ok(());
+    };
+    let at = now() as i64;
+    let conn = db.conn()?;
+    let mut insert = conn.prepare(format!(
+        "INSERT INTO {} (table_name, row_id, op, old, new, at) VALUES (?, ?, ?, ?, ?, ?)",
+        LOG
+    ))?;
+    let mut recorded = false;
+    for (table, old_rows) in before {
+        // a table dropped by the statement has no rows left to compare
+        let mut new_rows = rows(db, &table).unwrap_or_default();
+        let mut changes = vec![];
+        for (rowid, old) in old_rows {
+            match new_rows.remove(&rowid) {
+                Some(new) if new == old => {}
+                Some(new) => changes.push((rowid, "update", Some(old), Some(new))),
+                None => changes.push((rowid, "delete", Some(old), None)),
+            }
+        }
+        for (rowid, new) in new_rows {
+            changes.push((rowid, "insert", None, Some(new)));
+        }
+        for (rowid, op, old, new) in changes {
+            let text = |row: Option<Json>| match row {
+                Some(row) => RecordValue::Text(row.to_string()),
+                None => RecordValue::Null,
+            };
+            let values = [
+                RecordValue::Text(table.clone()),
+                RecordValue::Integer(rowid),
+                RecordValue::Text(op.to_string()),
+                text(old),
+                text(new),
+                RecordValue::Integer(at),
+            ];
+            for (i, value) in values.into_iter().enumerate() {
+                insert.bind_at(NonZeroUsize::new(i + 1).unwrap(), value.into());
+            }
+            run_to_completion(&mut insert, &db.io, &db.state)?;
+            insert.reset();
+            recorded = true;
+        }
+    }
+    drop(insert);
+    if recorded {
+        db.changed(&Changes::Table(LOG.to_string()));
+    }
+    Ok(())
+}
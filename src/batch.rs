@@ -0,0 +1,46 @@
+//! Queueing writes to commit together, see `database.begin-batched` in the WIT.
+
+/// The writes queued on a connection in batched mode.
+pub(crate) struct Batch {
+    /// Milliseconds the first queued write may wait before the batch is committed, 0 for no
+    /// limit.
+    max_delay_ms: u32,
+    /// Writes that make a full batch, 0 for no limit.
+    max_ops: u32,
+    queued: Vec<String>,
+    /// When the first of `queued` was queued, in milliseconds, see `host-clock.now`.
+    first_queued_ms: u64,
+}
+
+impl Batch {
+    pub(crate) fn new(max_delay_ms: u32, max_ops: u32) -> Self {
+        Self {
+            max_delay_ms,
+            max_ops,
+            queued: vec![],
+            first_queued_ms: 0,
+        }
+    }
+
+    /// Queue `sql` at `now_ms`, returning whether the batch is now due to be committed.
+    pub(crate) fn push(&mut self, sql: String, now_ms: u64) -> bool {
+        if self.queued.is_empty() {
+            self.first_queued_ms = now_ms;
+        }
+        self.queued.push(sql);
+        let full = self.max_ops != 0 && self.queued.len() >= self.max_ops as usize;
+        full || self.is_late(now_ms)
+    }
+
+    /// Whether the first queued write has waited `max_delay_ms` at `now_ms`.
+    pub(crate) fn is_late(&self, now_ms: u64) -> bool {
+        self.max_delay_ms != 0
+            && !self.queued.is_empty()
+            && now_ms.saturating_sub(self.first_queued_ms) >= self.max_delay_ms as u64
+    }
+
+    /// The writes queued so far, leaving the batch empty.
+    pub(crate) fn take(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.queued)
+    }
+}
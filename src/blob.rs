@@ -0,0 +1,90 @@
+//! Streaming blob writes, see the `blob-writer` resource in the WIT.
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    DbError, GuestBlobWriter, RecordValue,
+};
+use crate::{error, quote_identifier, Component, ConnState};
+
+pub struct BlobWriter {
+    io: Arc<dyn limbo_core::IO>,
+    state: Rc<ConnState>,
+    /// The UPDATE that writes the blob into the row once it is finished.
+    sql: String,
+    rowid: i64,
+    len: usize,
+    /// The chunks written so far, `None` once the blob has been finished.
+    buf: RefCell<Option<Vec<u8>>>,
+}
+
+impl BlobWriter {
+    /// A writer for `len` bytes, or [DbError::OutOfMemory] if there isn't room for them.
+    pub fn new(
+        db: &Component,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        len: u64,
+    ) -> Result<Self, DbError> {
+        let len = usize::try_from(len).map_err(|_| error::out_of_memory())?;
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(len)
+            .map_err(|_| error::out_of_memory())?;
+        Ok(Self {
+            io: db.io.clone(),
+            state: db.state.clone(),
+            sql: format!(
+                "UPDATE {} SET {} = ? WHERE rowid = ?",
+                quote_identifier(table),
+                quote_identifier(column)
+            ),
+            rowid,
+            len,
+            buf: RefCell::new(Some(buf)),
+        })
+    }
+
+    /// The UPDATE that writes the blob, for checking before any of it is written.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+}
+
+impl GuestBlobWriter for BlobWriter {
+    fn write(&self, chunk: Vec<u8>) -> Result<(), DbError> {
+        let mut buf = self.buf.borrow_mut();
+        let buf = buf.as_mut().ok_or_else(finished)?;
+        if buf.len() + chunk.len() > self.len {
+            return Err(DbError::Misuse(format!(
+                "blob-writer: wrote more than {} bytes",
+                self.len
+            )));
+        }
+        buf.extend_from_slice(&chunk);
+        Ok(())
+    }
+
+    /// Write the blob with the UPDATE run as any other statement on the connection is, so
+    /// it is refused on a read-only connection, kept to the table's filter, and followed by
+    /// the query caches, subscriptions and audit log.
+    fn finish(&self) -> Result<(), DbError> {
+        let buf = self.buf.borrow_mut().take().ok_or_else(finished)?;
+        if buf.len() != self.len {
+            return Err(DbError::Misuse(format!(
+                "blob-writer: expected {} bytes, got {}",
+                self.len,
+                buf.len()
+            )));
+        }
+        let db = Component::of(&self.state, &self.io);
+        db.query_with(
+            &self.sql,
+            vec![RecordValue::Blob(buf), RecordValue::Integer(self.rowid)],
+        )?;
+        Ok(())
+    }
+}
+
+fn finished() -> DbError {
+    DbError::Misuse("blob-writer already finished".to_string())
+}
@@ -0,0 +1,116 @@
+//! Keeping each run of a statement that only reads consistent while other connections write,
+//! see `rows` in the WIT.
+//!
+//! `statement.all` reads every row in one call, which nothing else can run in the middle of,
+//! but a run stepped by `rows.next` or `statement.all-chunked` is read across many calls, and
+//! limbo would show it whatever the database's other connections commit in between. So before
+//! a connection runs a statement that writes, the runs part way through on the database's
+//! other connections read the rest of their rows into a buffer, and return them from it.
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue};
+use crate::{next_row, BusyWait, ConnState, SharedStatement};
+
+/// The runs part way through on the connections to one database.
+#[derive(Default)]
+pub(crate) struct Cursors {
+    live: RefCell<Vec<Weak<Cursor>>>,
+}
+
+impl Cursors {
+    pub(crate) fn add(&self, cursor: &Rc<Cursor>) {
+        let mut live = self.live.borrow_mut();
+        live.retain(|cursor| cursor.strong_count() > 0);
+        live.push(Rc::downgrade(cursor));
+    }
+
+    /// Buffer the rest of the rows of the runs on connections other than `writer`, which is
+    /// about to write.
+    pub(crate) fn before_write(&self, writer: &ConnState) {
+        let live: Vec<_> = self
+            .live
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for cursor in live {
+            if !std::ptr::eq(Rc::as_ptr(&cursor.state), writer) {
+                cursor.buffer_rest();
+            }
+        }
+    }
+}
+
+/// A run of a statement that only reads.
+pub(crate) struct Cursor {
+    shared: Rc<SharedStatement>,
+    io: Arc<dyn limbo_core::IO>,
+    state: Rc<ConnState>,
+    /// Which run of the statement this is.
+    run: u64,
+    /// Whether the run has returned its last row.
+    finished: Cell<bool>,
+    /// The rest of the rows, once they have been read ahead of a write. An error reading
+    /// them is returned after the rows read before it.
+    buffered: RefCell<Option<VecDeque<Result<Vec<RecordValue>, DbError>>>>,
+}
+
+impl Cursor {
+    pub(crate) fn new(
+        shared: Rc<SharedStatement>,
+        io: Arc<dyn limbo_core::IO>,
+        state: Rc<ConnState>,
+        run: u64,
+    ) -> Rc<Self> {
+        Rc::new(Self {
+            shared,
+            io,
+            state,
+            run,
+            finished: Cell::new(false),
+            buffered: RefCell::new(None),
+        })
+    }
+
+    /// The next of the buffered rows, `Some(Ok(None))` once they have all been returned, or
+    /// `None` if the rows aren't buffered and the statement steps to the next itself.
+    pub(crate) fn next_buffered(&self) -> Option<Result<Option<Vec<RecordValue>>, DbError>> {
+        let mut buffered = self.buffered.borrow_mut();
+        Some(buffered.as_mut()?.pop_front().transpose())
+    }
+
+    /// Mark the run as having returned its last row, so nothing is read ahead for it.
+    pub(crate) fn finish(&self) {
+        self.finished.set(true);
+        self.buffered.take();
+    }
+
+    /// Step the statement to its end, keeping the rows it produces.
+    fn buffer_rest(&self) {
+        if self.finished.get()
+            || self.buffered.borrow().is_some()
+            || self.shared.run.get() != self.run
+        {
+            return;
+        }
+        let mut busy = BusyWait::new(&self.state);
+        let mut rows = VecDeque::new();
+        loop {
+            let row = self
+                .shared
+                .timed(|stats| next_row(&mut self.shared.stmt()?, &self.io, &mut busy, stats));
+            match row {
+                Ok(Some(row)) => rows.push_back(Ok(row)),
+                Ok(None) => break,
+                Err(e) => {
+                    rows.push_back(Err(e));
+                    break;
+                }
+            }
+        }
+        self.buffered.replace(Some(rows));
+    }
+}
@@ -0,0 +1,215 @@
+//! JSON documents kept in tables, see the `docs` interface in the WIT.
+use std::cell::Cell;
+
+use serde_json::{Map, Value as Json};
+
+use crate::bindings::exports::component::wit_limbo::docs::{Document, Guest, GuestStore};
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, GuestDatabase, RecordValue};
+use crate::{json, Component};
+
+/// The documents of every collection.
+const DOCS: &str = "wit_limbo_docs";
+
+/// The index of the documents' top-level fields: one row per field with a null, boolean,
+/// number or string value, kept in step with [DOCS] by `put`.
+const FIELDS: &str = "wit_limbo_doc_fields";
+
+pub struct DocStore {
+    db: Component,
+    /// Whether [DOCS] and [FIELDS] are known to exist.
+    created: Cell<bool>,
+}
+
+impl Guest for Component {
+    type Store = DocStore;
+}
+
+impl DocStore {
+    /// Run `sql` with `params` bound in order, creating the tables first if needed.
+    fn query(&self, sql: &str, params: Vec<RecordValue>) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        if !self.created.get() {
+            for sql in [
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {} \
+                     (collection TEXT, id TEXT, body TEXT NOT NULL, PRIMARY KEY (collection, id))",
+                    DOCS
+                ),
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {} (collection TEXT, id TEXT, field TEXT, value)",
+                    FIELDS
+                ),
+                format!(
+                    "CREATE INDEX IF NOT EXISTS {}_value ON {} (collection, field, value)",
+                    FIELDS, FIELDS
+                ),
+                format!(
+                    "CREATE INDEX IF NOT EXISTS {}_id ON {} (collection, id)",
+                    FIELDS, FIELDS
+                ),
+            ] {
+                self.db.exec(sql)?;
+            }
+            self.created.set(true);
+        }
+        self.db.query_with(sql, params)
+    }
+}
+
+/// `text` parsed as a JSON object, or [DbError::Misuse] naming `what` it is.
+fn object(text: &str, what: &str) -> Result<Map<String, Json>, DbError> {
+    match serde_json::from_str(text) {
+        Ok(Json::Object(object)) => Ok(object),
+        Ok(_) => Err(DbError::Misuse(format!("{} must be a JSON object", what))),
+        Err(e) => Err(DbError::Misuse(format!(
+            "{} is not valid JSON: {}",
+            what, e
+        ))),
+    }
+}
+
+/// The document in a row of `id, body`.
+fn document(row: Vec<RecordValue>) -> Result<Document, DbError> {
+    let mut row = row.into_iter();
+    match (row.next(), row.next()) {
+        (Some(RecordValue::Text(id)), Some(RecordValue::Text(json))) => Ok(Document { id, json }),
+        other => Err(DbError::Corrupt(format!(
+            "{} has an unexpected row: {:?}",
+            DOCS, other
+        ))),
+    }
+}
+
+/// Whether `a` and `b` are the same value, as SQL's IS compares them.
+fn same(a: &RecordValue, b: &RecordValue) -> bool {
+    use RecordValue as V;
+    match (a, b) {
+        (V::Null, V::Null) => true,
+        (V::Integer(a), V::Integer(b)) => a == b,
+        (V::Integer(i), V::Float(f)) | (V::Float(f), V::Integer(i)) => *i as f64 == *f,
+        (V::Float(a), V::Float(b)) => a == b,
+        (V::Text(a), V::Text(b)) => a == b,
+        _ => false,
+    }
+}
+
+impl GuestStore for DocStore {
+    fn new(path: String) -> Self {
+        Self {
+            db: Component::new(path),
+            created: Cell::new(false),
+        }
+    }
+
+    fn put(&self, collection: String, id: String, json: String) -> Result<(), DbError> {
+        let fields = object(&json, "a document")?;
+        let key = || {
+            vec![
+                RecordValue::Text(collection.clone()),
+                RecordValue::Text(id.clone()),
+            ]
+        };
+        self.db.transaction(&format!("put of {:?}", id), || {
+            let mut params = key();
+            params.push(RecordValue::Text(json.clone()));
+            self.query(
+                &format!(
+                    "INSERT INTO {} (collection, id, body) VALUES (?, ?, ?) \
+                     ON CONFLICT (collection, id) DO UPDATE SET body = excluded.body",
+                    DOCS
+                ),
+                params,
+            )?;
+            self.query(
+                &format!("DELETE FROM {} WHERE collection = ? AND id = ?", FIELDS),
+                key(),
+            )?;
+            let insert = format!(
+                "INSERT INTO {} (collection, id, field, value) VALUES (?, ?, ?, ?)",
+                FIELDS
+            );
+            for (field, value) in &fields {
+                // arrays and objects aren't indexed, so `find` can't match them
+                let Ok(value) = json::to_record_value(value) else {
+                    continue;
+                };
+                let mut params = key();
+                params.extend([RecordValue::Text(field.clone()), value]);
+                self.query(&insert, params)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn get(&self, collection: String, id: String) -> Result<Option<String>, DbError> {
+        let rows = self.query(
+            &format!(
+                "SELECT id, body FROM {} WHERE collection = ? AND id = ?",
+                DOCS
+            ),
+            vec![RecordValue::Text(collection), RecordValue::Text(id)],
+        )?;
+        Ok(match rows.into_iter().next() {
+            Some(row) => Some(document(row)?.json),
+            None => None,
+        })
+    }
+
+    fn find(
+        &self,
+        collection: String,
+        filter: String,
+        limit: u32,
+    ) -> Result<Vec<Document>, DbError> {
+        let filter = object(&filter, "a filter")?;
+        let mut conditions = vec![];
+        for (field, value) in &filter {
+            let value = json::to_record_value(value).map_err(|e| {
+                DbError::Misuse(format!("filter on {:?} can't match: {}", field, e))
+            })?;
+            conditions.push((field.as_str(), value));
+        }
+
+        // the first condition is looked up in the field index, the rest checked here
+        let rows = match conditions.first() {
+            None => self.query(
+                &format!(
+                    "SELECT id, body FROM {} WHERE collection = ? ORDER BY id",
+                    DOCS
+                ),
+                vec![RecordValue::Text(collection)],
+            )?,
+            Some((field, value)) => self.query(
+                &format!(
+                    "SELECT d.id, d.body FROM {} f JOIN {} d \
+                     ON d.collection = f.collection AND d.id = f.id \
+                     WHERE f.collection = ? AND f.field = ? AND f.value IS ? ORDER BY d.id",
+                    FIELDS, DOCS
+                ),
+                vec![
+                    RecordValue::Text(collection),
+                    RecordValue::Text(field.to_string()),
+                    value.clone(),
+                ],
+            )?,
+        };
+
+        let mut found = vec![];
+        for row in rows {
+            if found.len() >= limit as usize {
+                break;
+            }
+            let document = document(row)?;
+            let fields = object(&document.json, "a stored document")?;
+            let matches = conditions.iter().skip(1).all(|(field, value)| {
+                fields
+                    .get(*field)
+                    .and_then(|stored| json::to_record_value(stored).ok())
+                    .is_some_and(|stored| same(&stored, value))
+            });
+            if matches {
+                found.push(document);
+            }
+        }
+        Ok(found)
+    }
+}
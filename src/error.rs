@@ -0,0 +1,136 @@
+//! Mapping limbo errors onto the WIT `db-error` variant.
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, ParseError};
+use crate::sql;
+
+impl From<limbo_core::LimboError> for DbError {
+    fn from(e: limbo_core::LimboError) -> Self {
+        use limbo_core::LimboError;
+        let message = e.to_string();
+        match e {
+            LimboError::ParseError(_) | LimboError::LexerError(_) => parse(message),
+            LimboError::Corrupt(_) | LimboError::NotADB => DbError::Corrupt(message),
+            LimboError::IOError(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::NotFound
+                        | std::io::ErrorKind::AlreadyExists
+                        | std::io::ErrorKind::InvalidInput
+                ) =>
+            {
+                DbError::CannotOpen(message)
+            }
+            LimboError::IOError(_) => DbError::Io(message),
+            _ => DbError::Other(message),
+        }
+    }
+}
+
+/// The error for SQL that doesn't parse, where in the SQL left for [located] to find.
+pub(crate) fn parse(message: String) -> DbError {
+    DbError::Parse(ParseError {
+        message,
+        position: None,
+    })
+}
+
+/// `e` with the position of a parse error filled in from `sql`, the SQL that failed.
+pub(crate) fn located(e: DbError, sql: &str) -> DbError {
+    match e {
+        DbError::Parse(ParseError {
+            message,
+            position: None,
+        }) => {
+            let position = sql::locate(sql, &message);
+            DbError::Parse(ParseError { message, position })
+        }
+        e => e,
+    }
+}
+
+/// `e` from the statement `at` bytes into `script`, with its position moved into the script.
+pub(crate) fn shifted(e: DbError, script: &str, at: usize) -> DbError {
+    match e {
+        DbError::Parse(ParseError {
+            message,
+            position: Some(position),
+        }) => DbError::Parse(ParseError {
+            message,
+            position: Some(sql::position(script, at + position.offset as usize)),
+        }),
+        e => e,
+    }
+}
+
+/// The error for a statement that stepped to [limbo_core::StepResult::Busy].
+pub(crate) fn busy() -> DbError {
+    DbError::Busy("database is locked".to_string())
+}
+
+/// The error for a run ended by `database.cancel-running`.
+pub(crate) fn interrupted() -> DbError {
+    DbError::Interrupted("interrupted".to_string())
+}
+
+/// The error for a result too large to allocate.
+pub(crate) fn out_of_memory() -> DbError {
+    DbError::OutOfMemory("out of memory".to_string())
+}
+
+/// The error for SQL that limbo_core would otherwise accept without doing what it says.
+pub(crate) fn unsupported(what: &str) -> DbError {
+    DbError::Other(format!("{} is not supported yet", what))
+}
+
+/// The error for using a `what` after it has been closed or finalized.
+pub(crate) fn closed(what: &str) -> DbError {
+    DbError::Closed(format!("{} is closed", what))
+}
+
+/// The SQLite extended result code for `e`, see `database.last-error-code` in the WIT.
+/// Limbo has no result codes of its own, so constraint failures are told apart by the
+/// message SQLite and limbo both give them, and other errors get their kind's primary code.
+pub(crate) fn code(e: &DbError) -> u32 {
+    match e {
+        DbError::Parse(_) => 1,
+        DbError::Busy(_) => 5,
+        DbError::OutOfMemory(_) => 7,
+        DbError::Interrupted(_) => 9,
+        DbError::Io(_) => 10,
+        DbError::Corrupt(_) => 11,
+        DbError::CannotOpen(_) => 14,
+        DbError::LimitExceeded(_) => 18,
+        DbError::Misuse(_) | DbError::Closed(_) => 21,
+        DbError::Other(message) => {
+            const CONSTRAINTS: [(&str, u32); 5] = [
+                ("CHECK constraint failed", 275),
+                ("FOREIGN KEY constraint failed", 787),
+                ("NOT NULL constraint failed", 1299),
+                ("PRIMARY KEY constraint failed", 1555),
+                ("UNIQUE constraint failed", 2067),
+            ];
+            CONSTRAINTS
+                .iter()
+                .find(|(prefix, _)| message.contains(prefix))
+                .map_or(1, |(_, code)| *code)
+        }
+    }
+}
+
+impl DbError {
+    /// The error message, whatever the kind of error.
+    pub fn message(&self) -> &str {
+        match self {
+            DbError::Parse(ParseError { message, .. })
+            | DbError::Corrupt(message)
+            | DbError::Io(message)
+            | DbError::Misuse(message)
+            | DbError::Busy(message)
+            | DbError::Closed(message)
+            | DbError::Interrupted(message)
+            | DbError::OutOfMemory(message)
+            | DbError::LimitExceeded(message)
+            | DbError::CannotOpen(message)
+            | DbError::Other(message) => message,
+        }
+    }
+}
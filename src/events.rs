@@ -0,0 +1,121 @@
+//! An append-only log of events by stream, see the `events` interface in the WIT.
+use std::cell::Cell;
+
+use crate::bindings::exports::component::wit_limbo::events::{Event, Guest, GuestStore};
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, GuestDatabase, RecordValue};
+use crate::Component;
+
+/// The table the events are kept in, created on first use. Its primary key of
+/// `(stream, seq)` is the index `read` goes through.
+const TABLE: &str = "wit_limbo_events";
+
+pub struct EventStore {
+    db: Component,
+    /// Whether [TABLE] is known to exist.
+    created: Cell<bool>,
+}
+
+impl Guest for Component {
+    type Store = EventStore;
+}
+
+impl EventStore {
+    /// Run `sql` with `params` bound in order, creating the table first if needed.
+    fn query(&self, sql: &str, params: Vec<RecordValue>) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        if !self.created.get() {
+            self.db.exec(format!(
+                "CREATE TABLE IF NOT EXISTS {} (stream TEXT, seq INTEGER, type TEXT NOT NULL, \
+                 payload BLOB NOT NULL, PRIMARY KEY (stream, seq))",
+                TABLE
+            ))?;
+            self.created.set(true);
+        }
+        self.db.query_with(sql, params)
+    }
+}
+
+/// The event in a row of `seq, type, payload`.
+fn event(row: Vec<RecordValue>) -> Result<Event, DbError> {
+    let mut row = row.into_iter();
+    match (row.next(), row.next(), row.next()) {
+        (
+            Some(RecordValue::Integer(seq)),
+            Some(RecordValue::Text(type_)),
+            Some(RecordValue::Blob(payload)),
+        ) if seq > 0 => Ok(Event {
+            seq: seq as u64,
+            type_,
+            payload,
+        }),
+        other => Err(DbError::Corrupt(format!(
+            "{} has an unexpected row: {:?}",
+            TABLE, other
+        ))),
+    }
+}
+
+impl GuestStore for EventStore {
+    fn new(path: String) -> Self {
+        Self {
+            db: Component::new(path),
+            created: Cell::new(false),
+        }
+    }
+
+    fn append(&self, stream: String, type_: String, payload: Vec<u8>) -> Result<u64, DbError> {
+        // reading the last seq and inserting after it in one transaction keeps two
+        // connections appending to a stream from taking the same seq
+        self.db
+            .transaction(&format!("append to {:?}", stream), move || {
+                let rows = self.query(
+                    &format!(
+                        "SELECT coalesce(max(seq), 0) + 1 FROM {} WHERE stream = ?",
+                        TABLE
+                    ),
+                    vec![RecordValue::Text(stream.clone())],
+                )?;
+                let seq = match rows
+                    .into_iter()
+                    .next()
+                    .and_then(|row| row.into_iter().next())
+                {
+                    Some(RecordValue::Integer(seq)) => seq,
+                    other => {
+                        return Err(DbError::Corrupt(format!(
+                            "{} has an unexpected seq: {:?}",
+                            TABLE, other
+                        )))
+                    }
+                };
+                self.query(
+                    &format!(
+                        "INSERT INTO {} (stream, seq, type, payload) VALUES (?, ?, ?, ?)",
+                        TABLE
+                    ),
+                    vec![
+                        RecordValue::Text(stream),
+                        RecordValue::Integer(seq),
+                        RecordValue::Text(type_),
+                        RecordValue::Blob(payload),
+                    ],
+                )?;
+                Ok(seq as u64)
+            })
+    }
+
+    fn read(&self, stream: String, from_seq: u64, limit: u32) -> Result<Vec<Event>, DbError> {
+        let rows = self.query(
+            &format!(
+                "SELECT seq, type, payload FROM {} WHERE stream = ? AND seq >= ? \
+                 ORDER BY seq LIMIT ?",
+                TABLE
+            ),
+            vec![
+                RecordValue::Text(stream),
+                RecordValue::Integer(from_seq.min(i64::MAX as u64) as i64),
+                RecordValue::Integer(limit as i64),
+            ],
+        )?;
+        rows.into_iter().map(event).collect()
+    }
+}
@@ -0,0 +1,100 @@
+//! Storage faults the host injects through `host-faults`, when built with the
+//! `fault-injection` feature, for testing that databases recover from failed and torn writes.
+//! Without the feature every operation runs as usual.
+use limbo_core::Result;
+
+/// A storage operation, the cases of `host-faults.storage-op`, in order.
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    not(all(feature = "fault-injection", not(feature = "native"))),
+    allow(dead_code)
+)]
+pub(crate) enum StorageOp {
+    Read,
+    Write,
+    Sync,
+}
+
+#[cfg(not(all(feature = "fault-injection", not(feature = "native"))))]
+pub(crate) fn check(_path: &str, _op: StorageOp) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(all(feature = "fault-injection", not(feature = "native"))))]
+pub(crate) fn write(
+    _path: &str,
+    data: &[u8],
+    write: impl FnOnce(&[u8]) -> Result<()>,
+) -> Result<()> {
+    write(data)
+}
+
+/// What to do with an operation, the cases of `host-faults.fault`, in order.
+#[cfg(all(feature = "fault-injection", not(feature = "native")))]
+enum Fault {
+    None,
+    Fail,
+    ShortWrite,
+}
+
+#[cfg(all(feature = "fault-injection", not(feature = "native")))]
+thread_local! {
+    /// The storage operations asked about so far.
+    static OPS: std::cell::Cell<u64> = const { std::cell::Cell::new(0) };
+}
+
+/// Asks through `host-faults`, which the `example-fault-injection` world imports.
+/// The bindings are only generated for the `example` world, so the import is declared
+/// by hand, lowered the way the generated bindings would, as [crate::log] does.
+#[cfg(all(feature = "fault-injection", not(feature = "native")))]
+fn inject(path: &str, op: StorageOp) -> Fault {
+    #[link(wasm_import_module = "component:wit-limbo/host-faults@0.2.0")]
+    extern "C" {
+        #[link_name = "inject"]
+        fn host_inject(path: *const u8, path_len: usize, op: i32, n: i64) -> i32;
+    }
+    let n = OPS.with(|ops| {
+        ops.set(ops.get() + 1);
+        ops.get()
+    });
+    match unsafe { host_inject(path.as_ptr(), path.len(), op as i32, n as i64) } {
+        0 => Fault::None,
+        1 => Fault::Fail,
+        _ => Fault::ShortWrite,
+    }
+}
+
+#[cfg(all(feature = "fault-injection", not(feature = "native")))]
+fn injected(path: &str) -> limbo_core::LimboError {
+    limbo_core::LimboError::IOError(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        format!("fault injected into {}", path),
+    ))
+}
+
+/// Fail `op` on the file at `path` if the host injects a fault into it.
+#[cfg(all(feature = "fault-injection", not(feature = "native")))]
+pub(crate) fn check(path: &str, op: StorageOp) -> Result<()> {
+    match inject(path, op) {
+        Fault::None => Ok(()),
+        Fault::Fail | Fault::ShortWrite => Err(injected(path)),
+    }
+}
+
+/// Write `data` to the file at `path` with `write`, unless the host injects a fault into it:
+/// failing it, or writing the first half of `data` and then failing it.
+#[cfg(all(feature = "fault-injection", not(feature = "native")))]
+pub(crate) fn write(
+    path: &str,
+    data: &[u8],
+    write: impl FnOnce(&[u8]) -> Result<()>,
+) -> Result<()> {
+    match inject(path, StorageOp::Write) {
+        Fault::None => write(data),
+        Fault::Fail => Err(injected(path)),
+        Fault::ShortWrite => {
+            write(&data[..data.len() / 2])?;
+            Err(injected(path))
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Checking rows against their tables' foreign keys, see `database.foreign-key-check` in the
+//! WIT.
+//!
+//! Limbo parses REFERENCES clauses but does nothing with them: it neither enforces foreign
+//! keys nor has `PRAGMA foreign_key_check`. So the keys are read from each table's CREATE
+//! TABLE statement instead, and a LEFT JOIN to the parent finds the rows whose key, with none
+//! of its columns NULL, matches no row there, as SQLite's check does.
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, FkViolation, RecordValue};
+use crate::{quote_identifier, sql, Component};
+
+/// The rows of `table`, or of every table, whose foreign keys refer to no row.
+pub(crate) fn check(db: &Component, table: Option<&str>) -> Result<Vec<FkViolation>, DbError> {
+    let schema = db.schema()?;
+    let tables: Vec<_> = schema
+        .iter()
+        .filter(|row| {
+            row.kind == "table" && table.map_or(true, |name| row.name.eq_ignore_ascii_case(name))
+        })
+        .collect();
+    if let (Some(name), []) = (table, &tables[..]) {
+        return Err(DbError::Misuse(format!("no such table: {}", name)));
+    }
+    let mut violations = vec![];
+    for row in tables {
+        let keys = row.sql.as_deref().map_or(vec![], sql::foreign_keys);
+        for (fkid, key) in keys.into_iter().enumerate() {
+            let parent = schema.iter().find(|parent| {
+                parent.kind == "table" && parent.name.eq_ignore_ascii_case(&key.parent)
+            });
+            let sql = match parent {
+                Some(_) => {
+                    let parent_columns = if key.parent_columns.is_empty() {
+                        primary_key(db, &key.parent)?
+                    } else {
+                        key.parent_columns
+                    };
+                    if parent_columns.len() != key.columns.len() {
+                        return Err(DbError::Other(format!(
+                            "foreign key mismatch - \"{}\" referencing \"{}\"",
+                            row.name, key.parent
+                        )));
+                    }
+                    orphans(&row.name, &key.columns, &key.parent, &parent_columns)
+                }
+                // every row with a key refers to a table that isn't there
+                None => format!(
+                    "SELECT rowid FROM {} WHERE {}",
+                    quote_identifier(&row.name),
+                    not_null(&key.columns, None)
+                ),
+            };
+            for found in db.query_all(sql)? {
+                violations.push(FkViolation {
+                    table: row.name.clone(),
+                    rowid: match found[..] {
+                        [RecordValue::Integer(rowid)] => Some(rowid),
+                        _ => None,
+                    },
+                    parent: key.parent.clone(),
+                    fkid: fkid as i64,
+                });
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// The columns of `table`'s primary key in order, or its rowid if it declares none.
+fn primary_key(db: &Component, table: &str) -> Result<Vec<String>, DbError> {
+    let sql = format!("PRAGMA table_info({})", quote_identifier(table));
+    // each row is: cid, name, type, notnull, dflt_value, pk
+    let mut key: Vec<_> = db
+        .query_all(sql)?
+        .into_iter()
+        .filter_map(|row| match &row[..] {
+            [_, RecordValue::Text(name), _, _, _, RecordValue::Integer(pk)] if *pk > 0 => {
+                Some((*pk, name.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    if key.is_empty() {
+        return Ok(vec!["rowid".to_string()]);
+    }
+    key.sort();
+    Ok(key.into_iter().map(|(_, name)| name).collect())
+}
+
+/// The SELECT of the rowids of the rows of `table` whose `columns` match no row of `parent`.
+fn orphans(table: &str, columns: &[String], parent: &str, parent_columns: &[String]) -> String {
+    let on: Vec<_> = columns
+        .iter()
+        .zip(parent_columns)
+        .map(|(column, parent_column)| {
+            format!("p.{} = c.{}", quoted(parent_column), quoted(column))
+        })
+        .collect();
+    format!(
+        "SELECT c.rowid FROM {} AS c LEFT JOIN {} AS p ON {} WHERE {} AND p.{} IS NULL",
+        quote_identifier(table),
+        quote_identifier(parent),
+        on.join(" AND "),
+        not_null(columns, Some("c")),
+        quoted(&parent_columns[0])
+    )
+}
+
+/// `column` quoted, other than a parent's `rowid`, which quoting would make a column name.
+fn quoted(column: &str) -> String {
+    match column {
+        "rowid" => column.to_string(),
+        _ => quote_identifier(column),
+    }
+}
+
+/// The condition that none of `columns`, of the table aliased `alias`, is NULL.
+fn not_null(columns: &[String], alias: Option<&str>) -> String {
+    let conditions: Vec<_> = columns
+        .iter()
+        .map(|column| {
+            let prefix = alias.map_or(String::new(), |alias| format!("{}.", alias));
+            format!("{}{} IS NOT NULL", prefix, quoted(column))
+        })
+        .collect();
+    conditions.join(" AND ")
+}
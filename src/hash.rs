@@ -0,0 +1,298 @@
+//! `sha256(x)`, `blake3(x)` and `base64(x)`, with the `hash-functions` feature, for content
+//! addressing.
+//!
+//! Limbo has no way for the guest to add functions, so a call can only take what is known
+//! before its statement runs: a literal argument is replaced by the result, and a parameter by
+//! itself, with the statement binding the result in place of each value bound to it.
+//! Anything else, such as a column, is refused rather than left to fail as an unknown function.
+use std::collections::BTreeMap;
+
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue};
+use crate::sql::{self, Argument};
+use crate::{error, ParamFunctions};
+
+const FUNCTIONS: [(&str, fn(&RecordValue) -> RecordValue); 3] = [
+    ("sha256", |value| digest(value, sha256)),
+    ("blake3", |value| digest(value, blake3)),
+    ("base64", |value| match bytes(value) {
+        Some(bytes) => RecordValue::Text(base64(&bytes)),
+        None => RecordValue::Null,
+    }),
+];
+
+/// `sql` with the calls to the hash functions replaced, and the function to apply to the
+/// values bound to each parameter they were called on.
+pub(crate) fn rewrite(sql: &str) -> Result<(String, ParamFunctions), DbError> {
+    let names = FUNCTIONS.map(|(name, _)| name);
+    let calls = sql::calls(sql, &names);
+    let mut functions = BTreeMap::new();
+    // the function each parameter is hashed with, by name
+    let mut hashed = BTreeMap::new();
+    let mut out = sql.to_string();
+    for (range, name, argument) in calls.iter().rev() {
+        let function = FUNCTIONS[names.iter().position(|n| n == name).unwrap()].1;
+        let replacement = match argument {
+            Argument::Literal(value) => {
+                let mut literal = String::new();
+                sql::push_literal(&mut literal, Some(&function(value)));
+                literal
+            }
+            Argument::Parameter(index, text) => {
+                if hashed
+                    .insert(*index, name)
+                    .is_some_and(|other| other != name)
+                {
+                    return Err(error::unsupported(
+                        "calling different hash functions on one parameter",
+                    ));
+                }
+                functions.insert(*index, function);
+                text.to_string()
+            }
+            Argument::Other => {
+                return Err(error::unsupported(&format!(
+                    "{}() of anything but a literal or parameter",
+                    name
+                )))
+            }
+        };
+        out.replace_range(range.clone(), &replacement);
+    }
+    // a parameter is bound either as it is or hashed, not both
+    let inside_call = |at: usize| calls.iter().any(|(range, _, _)| range.contains(&at));
+    for (range, index) in sql::parameters(sql) {
+        if functions.contains_key(&index) && !inside_call(range.start) {
+            return Err(error::unsupported(
+                "using a parameter both as it is and in a hash function; bind it twice",
+            ));
+        }
+    }
+    Ok((out, functions))
+}
+
+/// The bytes `value` is hashed as: text as UTF-8, and numbers as their text.
+fn bytes(value: &RecordValue) -> Option<Vec<u8>> {
+    match value {
+        RecordValue::Null => None,
+        RecordValue::Integer(i) => Some(i.to_string().into_bytes()),
+        RecordValue::Float(f) => Some(f.to_string().into_bytes()),
+        RecordValue::Text(s) | RecordValue::TextDecimal(s) => Some(s.clone().into_bytes()),
+        RecordValue::Blob(b) => Some(b.clone()),
+    }
+}
+
+fn digest(value: &RecordValue, hash: fn(&[u8]) -> [u8; 32]) -> RecordValue {
+    match bytes(value) {
+        Some(bytes) => RecordValue::Blob(hash(&bytes).to_vec()),
+        None => RecordValue::Null,
+    }
+}
+
+/// The initial hash value of SHA-256, which BLAKE3 uses as its IV too.
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256, as FIPS 180-4 specifies it.
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    let mut h = IV;
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            (hh, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (word, add) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *word = word.wrapping_add(add);
+        }
+    }
+    let mut out = [0; 32];
+    for (bytes, word) in out.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+const CHUNK_LEN: usize = 1024;
+const CHUNK_START: u32 = 1;
+const CHUNK_END: u32 = 2;
+const PARENT: u32 = 4;
+const ROOT: u32 = 8;
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// BLAKE3 with its default 32 bytes of output, hashing the whole input at once.
+fn blake3(data: &[u8]) -> [u8; 32] {
+    let words = subtree(data, 0).compress(ROOT);
+    let mut out = [0; 32];
+    for (bytes, word) in out.chunks_mut(4).zip(words) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+/// A BLAKE3 compression not yet run, as the flags to run it with depend on where its node is.
+struct Node {
+    cv: [u32; 8],
+    block: [u32; 16],
+    counter: u64,
+    len: u32,
+    flags: u32,
+}
+
+impl Node {
+    fn compress(&self, flags: u32) -> [u32; 16] {
+        let mut state = [0; 16];
+        state[..8].copy_from_slice(&self.cv);
+        state[8..12].copy_from_slice(&IV[..4]);
+        state[12] = self.counter as u32;
+        state[13] = (self.counter >> 32) as u32;
+        state[14] = self.len;
+        state[15] = self.flags | flags;
+        let mut m = self.block;
+        for round in 0..7 {
+            for (i, [a, b, c, d]) in [
+                [0, 4, 8, 12],
+                [1, 5, 9, 13],
+                [2, 6, 10, 14],
+                [3, 7, 11, 15],
+                [0, 5, 10, 15],
+                [1, 6, 11, 12],
+                [2, 7, 8, 13],
+                [3, 4, 9, 14],
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                g(&mut state, [a, b, c, d], m[2 * i], m[2 * i + 1]);
+            }
+            if round < 6 {
+                m = MSG_PERMUTATION.map(|i| m[i]);
+            }
+        }
+        for i in 0..8 {
+            state[i] ^= state[i + 8];
+            state[i + 8] ^= self.cv[i];
+        }
+        state
+    }
+
+    fn chaining_value(&self) -> [u32; 8] {
+        self.compress(0)[..8].try_into().unwrap()
+    }
+}
+
+fn g(state: &mut [u32; 16], [a, b, c, d]: [usize; 4], x: u32, y: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(x);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(y);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+/// The root node of the tree over `input`, whose first chunk is chunk `counter`. The left
+/// subtree of a node takes the most chunks, a power of 2, that leave some for the right.
+fn subtree(input: &[u8], counter: u64) -> Node {
+    if input.len() > CHUNK_LEN {
+        let chunks = input.len().div_ceil(CHUNK_LEN);
+        let left_chunks = 1 << (usize::BITS - 1 - (chunks - 1).leading_zeros());
+        let (left, right) = input.split_at(left_chunks * CHUNK_LEN);
+        let mut block = [0; 16];
+        block[..8].copy_from_slice(&subtree(left, counter).chaining_value());
+        block[8..].copy_from_slice(&subtree(right, counter + left_chunks as u64).chaining_value());
+        return Node {
+            cv: IV,
+            block,
+            counter: 0,
+            len: 64,
+            flags: PARENT,
+        };
+    }
+    let blocks: Vec<&[u8]> = match input.is_empty() {
+        true => vec![&[]],
+        false => input.chunks(64).collect(),
+    };
+    let mut cv = IV;
+    let last = blocks.len() - 1;
+    for (i, block) in blocks.iter().enumerate() {
+        let mut words = [0; 16];
+        let mut padded = [0; 64];
+        padded[..block.len()].copy_from_slice(block);
+        for (word, bytes) in words.iter_mut().zip(padded.chunks(4)) {
+            *word = u32::from_le_bytes(bytes.try_into().unwrap());
+        }
+        let node = Node {
+            cv,
+            block: words,
+            counter,
+            len: block.len() as u32,
+            flags: if i == 0 { CHUNK_START } else { 0 } | if i == last { CHUNK_END } else { 0 },
+        };
+        if i == last {
+            return node;
+        }
+        cv = node.chaining_value();
+    }
+    unreachable!("a chunk has at least one block")
+}
+
+/// Standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= group.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,81 @@
+//! Heap accounting for `set-soft-heap-limit`.
+//!
+//! In the component every allocation goes through [Counting], so the soft limit is checked
+//! against the whole instance's heap, as SQLite's is against the whole process. With the
+//! `native` feature the host's allocator is left alone, and the limit never trips.
+#[cfg(not(feature = "native"))]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::bindings::exports::component::wit_limbo::limbo::DbError;
+
+/// Bytes currently allocated.
+static USED: AtomicUsize = AtomicUsize::new(0);
+
+/// The soft heap limit in bytes, 0 for none.
+static LIMIT: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting the bytes it hands out into [USED].
+#[cfg(not(feature = "native"))]
+struct Counting;
+
+#[cfg(not(feature = "native"))]
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            USED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            USED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        USED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            USED.fetch_sub(layout.size(), Ordering::Relaxed);
+            USED.fetch_add(new_size, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(not(feature = "native"))]
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Set the soft heap limit, 0 for none, returning the limit before.
+pub(crate) fn set_soft_limit(bytes: u64) -> u64 {
+    LIMIT.swap(bytes, Ordering::Relaxed)
+}
+
+/// Whether the heap has grown past half the soft limit, so caches should be let go.
+pub(crate) fn is_pressed() -> bool {
+    let limit = LIMIT.load(Ordering::Relaxed);
+    limit != 0 && USED.load(Ordering::Relaxed) as u64 > limit / 2
+}
+
+/// [DbError::OutOfMemory] once the heap has grown past the soft limit.
+pub(crate) fn check() -> Result<(), DbError> {
+    let limit = LIMIT.load(Ordering::Relaxed);
+    let used = USED.load(Ordering::Relaxed) as u64;
+    if limit != 0 && used > limit {
+        return Err(DbError::OutOfMemory(format!(
+            "heap of {} bytes is over the soft heap limit of {} bytes",
+            used, limit
+        )));
+    }
+    Ok(())
+}
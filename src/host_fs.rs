@@ -0,0 +1,256 @@
+//! [limbo_core::IO] backed by the `host-fs` import, for databases the host persists.
+//!
+//! Writes, syncs and sizes complete before the host call returns, so their completions are
+//! signalled straight away. Reads are submitted through `host-io` instead, so every page read
+//! limbo_core asks for before waiting can run in parallel on hosts with asynchronous IO, and
+//! [limbo_core::IO::run_once] waits for them. Sequential reads also read ahead, see
+//! [Readahead], and writes can be held until the file is synced, see [WriteBack].
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use limbo_core::{Completion, File, LimboError, MemoryIO, OpenFlags, Result, IO};
+
+use crate::bindings::component::wit_limbo::host_fs;
+use crate::bindings::component::wit_limbo::host_io::{self, IoRequest};
+use crate::faults::{self, StorageOp};
+use crate::readahead::{Read, Readahead};
+use crate::write_back::WriteBack;
+
+fn host_error(message: String) -> LimboError {
+    LimboError::IOError(std::io::Error::new(std::io::ErrorKind::Other, message))
+}
+
+/// What a file keeps between host calls.
+struct Buffers {
+    readahead: Readahead,
+    write_back: WriteBack,
+}
+
+/// A read submitted to the host and not yet completed.
+struct PendingRead {
+    /// The `host-io` id of the read.
+    id: u64,
+    c: Completion,
+    /// Where the read starts.
+    pos: usize,
+    /// The buffers of the file read.
+    buffers: Rc<Buffers>,
+    /// [Readahead::writes] when the read was submitted.
+    writes: u64,
+}
+
+type Pending = Rc<RefCell<Vec<PendingRead>>>;
+
+pub struct HostFsIO {
+    /// Used for the non-storage parts of [IO], like randomness and time.
+    memory: MemoryIO,
+    /// Shared with the files opened, which submit their reads here.
+    pending: Pending,
+    /// Reads to read ahead by once reads are sequential, shared with the files opened, see
+    /// `database.set-readahead-pages`.
+    readahead_pages: Rc<Cell<u32>>,
+    /// Whether writes are held until a sync, shared with the files opened, see
+    /// `database.set-coalesce-writes`.
+    coalesce_writes: Rc<Cell<bool>>,
+}
+
+impl HostFsIO {
+    pub fn new(readahead_pages: Rc<Cell<u32>>, coalesce_writes: Rc<Cell<bool>>) -> Result<Self> {
+        Ok(Self {
+            memory: MemoryIO::new()?,
+            pending: Pending::default(),
+            readahead_pages,
+            coalesce_writes,
+        })
+    }
+}
+
+impl IO for HostFsIO {
+    fn open_file(&self, path: &str, flags: OpenFlags, _direct: bool) -> Result<Rc<dyn File>> {
+        let create = matches!(flags, OpenFlags::Create);
+        let fd = host_fs::open(path, create).map_err(host_error)?;
+        Ok(Rc::new(HostFile {
+            fd,
+            path: path.to_string(),
+            pending: self.pending.clone(),
+            buffers: Rc::new(Buffers {
+                readahead: Readahead::new(self.readahead_pages.clone()),
+                write_back: WriteBack::new(self.coalesce_writes.clone()),
+            }),
+        }))
+    }
+
+    /// Wait for every read submitted so far, completing each as the host finishes it.
+    fn run_once(&self) -> Result<()> {
+        loop {
+            let done: Vec<_> = {
+                let mut pending = self.pending.borrow_mut();
+                if pending.is_empty() {
+                    return Ok(());
+                }
+                let mut done = vec![];
+                let mut i = 0;
+                while i < pending.len() {
+                    match host_io::poll_io(pending[i].id) {
+                        Some(result) => done.push((pending.remove(i), result)),
+                        None => i += 1,
+                    }
+                }
+                done
+            };
+            // completed outside the borrow, as completing can submit more reads
+            for (read, result) in done {
+                let mut data = result.map_err(host_error)?;
+                let len = match read.c {
+                    Completion::Read(ref r) => r.buf().len(),
+                    _ => unreachable!(),
+                };
+                // held writes can extend past the end of the file, where the host read nothing
+                if data.len() < len {
+                    data.resize(len, 0);
+                }
+                read.buffers.write_back.overlay(read.pos, &mut data);
+                let beyond = complete_read(read.c, data);
+                let readahead = &read.buffers.readahead;
+                readahead.keep(read.pos + len, beyond, read.writes);
+            }
+        }
+    }
+
+    fn generate_random_number(&self) -> i64 {
+        self.memory.generate_random_number()
+    }
+
+    fn get_current_time(&self) -> String {
+        self.memory.get_current_time()
+    }
+}
+
+pub struct HostFile {
+    fd: u32,
+    path: String,
+    pending: Pending,
+    buffers: Rc<Buffers>,
+}
+
+impl HostFile {
+    /// Write what is held by the write-back buffer.
+    fn flush(&self) -> Result<()> {
+        for (pos, data) in self.buffers.write_back.take() {
+            self.write(pos, &data)?;
+        }
+        Ok(())
+    }
+
+    /// Write `data` at `pos`, unless a fault is injected into the write, see [faults::write].
+    fn write(&self, pos: usize, data: &[u8]) -> Result<()> {
+        faults::write(&self.path, data, |data| {
+            host_fs::write(self.fd, pos as u64, data).map_err(host_error)
+        })
+    }
+}
+
+impl Drop for HostFile {
+    /// Write what is held, as a file dropped without a sync still keeps its writes on a
+    /// file system. There's no one to tell if that fails.
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Complete the read `c` with `data`, the bytes the host read, returning those beyond `c`'s
+/// buffer.
+fn complete_read(c: Completion, mut data: Vec<u8>) -> Vec<u8> {
+    let r = match c {
+        Completion::Read(ref r) => r,
+        _ => unreachable!(),
+    };
+    let len = r.buf().len();
+    let beyond = data.split_off(data.len().min(len));
+    {
+        let buf = r.buf_mut();
+        let buf = buf.as_mut_slice();
+        // reading past the end of the file gives zeroes
+        buf[..data.len()].copy_from_slice(&data);
+        buf[data.len()..].fill(0);
+    }
+    c.complete(len as i32);
+    beyond
+}
+
+impl File for HostFile {
+    fn lock_file(&self, _exclusive: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn unlock_file(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn pread(&self, pos: usize, c: Completion) -> Result<()> {
+        let len = match c {
+            Completion::Read(ref r) => r.buf().len(),
+            _ => unreachable!(),
+        };
+        let readahead = &self.buffers.readahead;
+        let extent = match readahead.read(pos, len) {
+            Read::Ahead(data) => {
+                complete_read(c, data);
+                return Ok(());
+            }
+            Read::Host(extent) => extent,
+        };
+        faults::check(&self.path, StorageOp::Read)?;
+        let id = host_io::submit_io(IoRequest {
+            fd: self.fd,
+            offset: pos as u64,
+            len: extent as u32,
+        });
+        self.pending.borrow_mut().push(PendingRead {
+            id,
+            c,
+            pos,
+            buffers: self.buffers.clone(),
+            writes: readahead.writes(),
+        });
+        Ok(())
+    }
+
+    fn pwrite(
+        &self,
+        pos: usize,
+        buffer: Rc<RefCell<limbo_core::Buffer>>,
+        c: Completion,
+    ) -> Result<()> {
+        let buffer = buffer.borrow();
+        let data = buffer.as_slice();
+        self.buffers.readahead.written(pos, data.len());
+        let write_back = &self.buffers.write_back;
+        if write_back.write(pos, data) {
+            if write_back.is_full() {
+                self.flush()?;
+            }
+        } else {
+            // what is held was written first
+            self.flush()?;
+            self.write(pos, data)?;
+        }
+        c.complete(data.len() as i32);
+        Ok(())
+    }
+
+    fn sync(&self, c: Completion) -> Result<()> {
+        self.flush()?;
+        faults::check(&self.path, StorageOp::Sync)?;
+        host_fs::sync(self.fd).map_err(host_error)?;
+        c.complete(0);
+        Ok(())
+    }
+
+    fn size(&self) -> Result<u64> {
+        let size = host_fs::size(self.fd).map_err(host_error)?;
+        Ok(size.max(self.buffers.write_back.end() as u64))
+    }
+}
@@ -0,0 +1,277 @@
+//! [limbo_core::IO] backed by the `host-kv` import, for hosts that only have key-value
+//! storage (like IndexedDB) rather than a file system.
+//!
+//! Each file is stored as [BLOCK_SIZE] blocks under `<file>/<block>` keys, plus its length
+//! under `<file>/size`. Writes that don't cover a whole block read-modify-write it, which
+//! happens for the WAL header and frame headers.
+//!
+//! With page compression on, see `database.set-compress-pages`, a block is stored as a zstd
+//! frame where that is smaller. Stored blocks are otherwise always [BLOCK_SIZE] bytes, so a
+//! shorter one is known to be compressed, and both kinds can be read whatever the setting.
+//!
+//! With write coalescing on, see `database.set-coalesce-writes`, writes are held by a
+//! [WriteBack] until the file is synced, so a block written in parts, like the WAL frame
+//! headers and the pages after them, is read and stored once.
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use limbo_core::{Completion, File, LimboError, MemoryIO, OpenFlags, Result, IO};
+
+use crate::bindings::component::wit_limbo::host_kv;
+use crate::faults::{self, StorageOp};
+use crate::write_back::WriteBack;
+
+pub const BLOCK_SIZE: usize = 4096;
+
+fn host_error(message: String) -> LimboError {
+    LimboError::IOError(std::io::Error::new(std::io::ErrorKind::Other, message))
+}
+
+pub struct HostKvIO {
+    /// Used for the non-storage parts of [IO], like randomness and time.
+    memory: MemoryIO,
+    /// Whether blocks are compressed as they are written, shared with the files opened.
+    compress: Rc<Cell<bool>>,
+    /// Whether writes are held until a sync, shared with the files opened.
+    coalesce_writes: Rc<Cell<bool>>,
+}
+
+impl HostKvIO {
+    pub fn new(compress: Rc<Cell<bool>>, coalesce_writes: Rc<Cell<bool>>) -> Result<Self> {
+        Ok(Self {
+            memory: MemoryIO::new()?,
+            compress,
+            coalesce_writes,
+        })
+    }
+}
+
+impl IO for HostKvIO {
+    fn open_file(&self, path: &str, flags: OpenFlags, _direct: bool) -> Result<Rc<dyn File>> {
+        let file = KvFile {
+            name: path.to_string(),
+            size: Cell::new(0),
+            compress: self.compress.clone(),
+            write_back: WriteBack::new(self.coalesce_writes.clone()),
+        };
+        match host_kv::get(&file.size_key()).map_err(host_error)? {
+            Some(size) => {
+                let size: [u8; 8] = size
+                    .try_into()
+                    .map_err(|_| LimboError::Corrupt(format!("bad size for {}", path)))?;
+                file.size.set(u64::from_le_bytes(size));
+            }
+            None if matches!(flags, OpenFlags::Create) => file.store_size()?,
+            None => return Err(host_error(format!("{} not found", path))),
+        }
+        Ok(Rc::new(file))
+    }
+
+    fn run_once(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn generate_random_number(&self) -> i64 {
+        self.memory.generate_random_number()
+    }
+
+    fn get_current_time(&self) -> String {
+        self.memory.get_current_time()
+    }
+}
+
+pub struct KvFile {
+    name: String,
+    size: Cell<u64>,
+    compress: Rc<Cell<bool>>,
+    write_back: WriteBack,
+}
+
+impl KvFile {
+    fn size_key(&self) -> String {
+        format!("{}/size", self.name)
+    }
+
+    fn block_key(&self, block: usize) -> String {
+        format!("{}/{}", self.name, block)
+    }
+
+    fn store_size(&self) -> Result<()> {
+        host_kv::set(&self.size_key(), &self.size.get().to_le_bytes()).map_err(host_error)
+    }
+
+    /// Get a block, zero filled if it was never written.
+    fn block(&self, block: usize) -> Result<Vec<u8>> {
+        let key = self.block_key(block);
+        let mut data = match host_kv::get(&key).map_err(host_error)? {
+            Some(data) if !data.is_empty() && data.len() < BLOCK_SIZE => decompress(&key, &data)?,
+            data => data.unwrap_or_default(),
+        };
+        data.resize(BLOCK_SIZE, 0);
+        Ok(data)
+    }
+
+    fn set_block(&self, block: usize, contents: Vec<u8>) -> Result<()> {
+        let contents = if self.compress.get() {
+            compress(contents)
+        } else {
+            contents
+        };
+        host_kv::set(&self.block_key(block), &contents).map_err(host_error)
+    }
+
+    /// Store `data` at `pos`, unless a fault is injected into the write, see [faults::write].
+    fn write(&self, pos: usize, data: &[u8]) -> Result<()> {
+        faults::write(&self.name, data, |data| self.store(pos, data))
+    }
+
+    /// Store `data` at `pos`, growing the file if it ends past it.
+    fn store(&self, pos: usize, data: &[u8]) -> Result<()> {
+        let mut done = 0;
+        while done < data.len() {
+            let offset = pos + done;
+            let (block, start) = (offset / BLOCK_SIZE, offset % BLOCK_SIZE);
+            let n = (BLOCK_SIZE - start).min(data.len() - done);
+            let mut contents = if n == BLOCK_SIZE {
+                vec![0; BLOCK_SIZE]
+            } else {
+                self.block(block)?
+            };
+            contents[start..start + n].copy_from_slice(&data[done..done + n]);
+            self.set_block(block, contents)?;
+            done += n;
+        }
+
+        let end = (pos + data.len()) as u64;
+        if end > self.size.get() {
+            self.size.set(end);
+            self.store_size()?;
+        }
+        Ok(())
+    }
+
+    /// Store what is held by the write-back buffer.
+    fn flush(&self) -> Result<()> {
+        for (pos, data) in self.write_back.take() {
+            self.write(pos, &data)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for KvFile {
+    /// Store what is held, as the blocks written before a sync are otherwise stored too.
+    /// There's no one to tell if that fails.
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// `block` as it is stored compressed: a zstd frame, unless that isn't smaller.
+#[cfg(feature = "compress-pages")]
+fn compress(block: Vec<u8>) -> Vec<u8> {
+    use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+    let compressed = compress_to_vec(&block[..], CompressionLevel::Fastest);
+    if compressed.len() < block.len() {
+        compressed
+    } else {
+        block
+    }
+}
+
+#[cfg(not(feature = "compress-pages"))]
+fn compress(block: Vec<u8>) -> Vec<u8> {
+    block
+}
+
+/// The block stored compressed under `key`.
+#[cfg(feature = "compress-pages")]
+fn decompress(key: &str, data: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut block = Vec::with_capacity(BLOCK_SIZE);
+    ruzstd::decoding::StreamingDecoder::new(data)
+        .map_err(|e| e.to_string())
+        .and_then(|mut decoder| decoder.read_to_end(&mut block).map_err(|e| e.to_string()))
+        .map_err(|e| LimboError::Corrupt(format!("{} doesn't decompress: {}", key, e)))?;
+    Ok(block)
+}
+
+#[cfg(not(feature = "compress-pages"))]
+fn decompress(key: &str, _data: &[u8]) -> Result<Vec<u8>> {
+    Err(host_error(format!(
+        "{} is compressed, which needs the component built with the compress-pages feature",
+        key
+    )))
+}
+
+impl File for KvFile {
+    fn lock_file(&self, _exclusive: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn unlock_file(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn pread(&self, pos: usize, c: Completion) -> Result<()> {
+        let r = match c {
+            Completion::Read(ref r) => r,
+            _ => unreachable!(),
+        };
+        let len = r.buf().len();
+        faults::check(&self.name, StorageOp::Read)?;
+        {
+            let buf = r.buf_mut();
+            let buf = buf.as_mut_slice();
+            let mut done = 0;
+            while done < len {
+                let offset = pos + done;
+                let (block, start) = (offset / BLOCK_SIZE, offset % BLOCK_SIZE);
+                let n = (BLOCK_SIZE - start).min(len - done);
+                let data = self.block(block)?;
+                buf[done..done + n].copy_from_slice(&data[start..start + n]);
+                done += n;
+            }
+            self.write_back.overlay(pos, buf);
+        }
+        c.complete(len as i32);
+        Ok(())
+    }
+
+    fn pwrite(
+        &self,
+        pos: usize,
+        buffer: Rc<RefCell<limbo_core::Buffer>>,
+        c: Completion,
+    ) -> Result<()> {
+        let buffer = buffer.borrow();
+        let data = buffer.as_slice();
+        if self.write_back.write(pos, data) {
+            if self.write_back.is_full() {
+                self.flush()?;
+            }
+        } else {
+            // what is held was written first
+            self.flush()?;
+            self.write(pos, data)?;
+        }
+        c.complete(data.len() as i32);
+        Ok(())
+    }
+
+    fn sync(&self, c: Completion) -> Result<()> {
+        self.flush()?;
+        faults::check(&self.name, StorageOp::Sync)?;
+        host_kv::flush().map_err(host_error)?;
+        c.complete(0);
+        Ok(())
+    }
+
+    fn size(&self) -> Result<u64> {
+        Ok(self.size.get().max(self.write_back.end() as u64))
+    }
+}
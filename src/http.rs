@@ -0,0 +1,103 @@
+//! `wasi:http/incoming-handler` export, so the component can be deployed straight to
+//! `wasmtime serve`, Spin or other wasi:http hosts as a serverless SQL endpoint.
+//!
+//! `POST /query` with a JSON body `{"sql": "...", "params": [...]}` runs the statement against
+//! an in-memory database that lives as long as the instance, and responds with
+//! `{"columns": [...], "rows": [[...]]}`.
+use std::cell::RefCell;
+
+use serde_json::{json, Value as Json};
+use wasi::http::types::{
+    Fields, IncomingRequest, Method, OutgoingBody, OutgoingResponse, ResponseOutparam,
+};
+use wasi::io::streams::StreamError;
+
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    DbError, GuestDatabase, GuestStatement,
+};
+use crate::json::{self, to_record_value};
+use crate::Component;
+
+thread_local! {
+    static DB: RefCell<Option<Component>> = const { RefCell::new(None) };
+}
+
+struct HttpServer;
+
+impl wasi::exports::http::incoming_handler::Guest for HttpServer {
+    fn handle(request: IncomingRequest, response_out: ResponseOutparam) {
+        let (status, body) = match (request.method(), request.path_with_query().as_deref()) {
+            (Method::Post, Some("/query")) => {
+                match read_body(&request).and_then(|body| query(&body)) {
+                    Ok(result) => (200, result),
+                    Err(e) => (400, json!({ "error": e })),
+                }
+            }
+            _ => (404, json!({ "error": "POST /query" })),
+        };
+        respond(response_out, status, body);
+    }
+}
+
+wasi::http::proxy::export!(HttpServer);
+
+fn read_body(request: &IncomingRequest) -> Result<Vec<u8>, String> {
+    let body = request.consume().map_err(|_| "request has no body")?;
+    let stream = body.stream().map_err(|_| "request body already read")?;
+    let mut bytes = Vec::new();
+    loop {
+        match stream.blocking_read(64 * 1024) {
+            Ok(chunk) => bytes.extend_from_slice(&chunk),
+            Err(StreamError::Closed) => break,
+            Err(e) => return Err(format!("reading request body: {:?}", e)),
+        }
+    }
+    Ok(bytes)
+}
+
+fn query(body: &[u8]) -> Result<Json, String> {
+    let request: Json = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+    let sql = request["sql"]
+        .as_str()
+        .ok_or("\"sql\" must be a string")?
+        .to_string();
+    let params = match &request["params"] {
+        Json::Null => vec![],
+        Json::Array(params) => params
+            .iter()
+            .map(to_record_value)
+            .collect::<Result<_, _>>()?,
+        _ => return Err("\"params\" must be an array".to_string()),
+    };
+
+    DB.with_borrow_mut(|db| {
+        let db = db.get_or_insert_with(|| Component::new(":memory:".to_string()));
+        let statement = db.prepare(sql).map_err(db_error)?;
+        let statement = statement.get::<crate::InnerStatement>();
+        for (i, param) in params.into_iter().enumerate() {
+            statement.bind(i as u32 + 1, param).map_err(db_error)?;
+        }
+        let result_set = statement.all_with_names().map_err(db_error)?;
+        Ok(json::result_set(result_set, &json::default_options()))
+    })
+}
+
+fn db_error(e: DbError) -> String {
+    e.message().to_string()
+}
+
+fn respond(response_out: ResponseOutparam, status: u16, body: Json) {
+    let headers =
+        Fields::from_list(&[("content-type".to_string(), b"application/json".to_vec())]).unwrap();
+    let response = OutgoingResponse::new(headers);
+    response.set_status_code(status).unwrap();
+    let outgoing_body = response.body().unwrap();
+    ResponseOutparam::set(response_out, Ok(response));
+
+    let stream = outgoing_body.write().unwrap();
+    stream
+        .blocking_write_and_flush(body.to_string().as_bytes())
+        .unwrap();
+    drop(stream);
+    OutgoingBody::finish(outgoing_body, None).unwrap();
+}
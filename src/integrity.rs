@@ -0,0 +1,92 @@
+//! Checking that every page of a database reads back, see `database.integrity-check` in the
+//! WIT.
+//!
+//! Limbo has no `PRAGMA integrity_check`, so each table is scanned in rowid order instead,
+//! decoding every row, which reads every page of its b-tree and every overflow page. A page
+//! that can't be read or decoded fails the scan, and a b-tree whose cells are out of order
+//! shows up as rowids that don't increase.
+use crate::bindings::component::wit_limbo::host_progress::on_progress;
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue};
+use crate::{next_row, quote_identifier, zero_stats, BusyWait, Component};
+
+/// The `operation` progress is reported for.
+const OPERATION: &str = "integrity-check";
+
+/// How many rows of a table are read between progress reports.
+const ROWS_PER_REPORT: u64 = 4096;
+
+/// What is wrong with `db`, up to `max_errors` problems, or any number if 0.
+pub(crate) fn check(db: &Component, max_errors: u32) -> Result<Vec<String>, DbError> {
+    let schema = db.schema()?;
+    let tables: Vec<&str> = schema
+        .iter()
+        .filter(|row| row.kind == "table" && row.root > 0)
+        .map(|row| row.name.as_str())
+        .collect();
+    let total = tables.len() as u64;
+    let mut errors = vec![];
+    on_progress(OPERATION, 0, total);
+    for (done, table) in tables.into_iter().enumerate() {
+        check_table(db, table, done as u64, total, max_errors, &mut errors)?;
+        if max_errors != 0 && errors.len() >= max_errors as usize {
+            errors.truncate(max_errors as usize);
+            break;
+        }
+        on_progress(OPERATION, done as u64 + 1, total);
+    }
+    Ok(errors)
+}
+
+/// Scan `table`, adding what is wrong with it to `errors`, and reporting progress through it
+/// as `done` tables of `total` checked.
+fn check_table(
+    db: &Component,
+    table: &str,
+    done: u64,
+    total: u64,
+    max_errors: u32,
+    errors: &mut Vec<String>,
+) -> Result<(), DbError> {
+    let sql = format!("SELECT rowid, * FROM {}", quote_identifier(table));
+    let mut stmt = match db.conn()?.prepare(sql) {
+        Ok(stmt) => stmt,
+        Err(e) => {
+            errors.push(format!("table {}: {}", table, DbError::from(e).message()));
+            return Ok(());
+        }
+    };
+    let mut busy = BusyWait::new(&db.state);
+    let mut stats = zero_stats();
+    let mut last_rowid = None;
+    let mut rows = 0u64;
+    loop {
+        let row = match next_row(&mut stmt, &db.io, &mut busy, &mut stats) {
+            Ok(Some(row)) => row,
+            Ok(None) => return Ok(()),
+            // a lock or the guest's memory running out say nothing about the table
+            Err(e @ (DbError::Busy(_) | DbError::OutOfMemory(_))) => return Err(e),
+            Err(e) => {
+                errors.push(format!("table {}: {}", table, e.message()));
+                return Ok(());
+            }
+        };
+        if let Some(RecordValue::Integer(rowid)) = row.first() {
+            if last_rowid.is_some_and(|last| *rowid <= last) {
+                errors.push(format!(
+                    "table {}: rowid {} out of order after {}",
+                    table,
+                    rowid,
+                    last_rowid.unwrap()
+                ));
+                if max_errors != 0 && errors.len() >= max_errors as usize {
+                    return Ok(());
+                }
+            }
+            last_rowid = Some(*rowid);
+        }
+        rows += 1;
+        if rows % ROWS_PER_REPORT == 0 {
+            on_progress(OPERATION, done, total);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Converting between JSON and `record-value`, for `statement.bind-json`,
+//! `database.query-json`, the audit log, NDJSON exports and the wasi:http endpoint.
+use serde_json::{json, Value as Json};
+
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    BlobEncoding, JsonOptions, RecordValue, ResultSet,
+};
+
+/// The largest integer a JavaScript number holds exactly, `Number.MAX_SAFE_INTEGER`.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Every integer as a number, floats exactly and blobs as arrays of bytes.
+pub(crate) fn default_options() -> JsonOptions {
+    JsonOptions {
+        big_integers_as_strings: false,
+        float_precision: None,
+        blob_encoding: BlobEncoding::Bytes,
+    }
+}
+
+/// The value to bind for `value`: null, an integer (booleans as 0 or 1), a float or text.
+/// Arrays and objects have no SQL type to bind as.
+pub(crate) fn to_record_value(value: &Json) -> Result<RecordValue, String> {
+    Ok(match value {
+        Json::Null => RecordValue::Null,
+        Json::Bool(b) => RecordValue::Integer(*b as i64),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => RecordValue::Integer(i),
+            None => RecordValue::Float(n.as_f64().ok_or("number out of range")?),
+        },
+        Json::String(s) => RecordValue::Text(s.clone()),
+        _ => return Err(format!("unsupported parameter {}", value)),
+    })
+}
+
+/// `value` as JSON, written as `options` say.
+fn to_json(value: RecordValue, options: &JsonOptions) -> Json {
+    match value {
+        RecordValue::Null => Json::Null,
+        RecordValue::Integer(i)
+            if options.big_integers_as_strings
+                && !(-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&i) =>
+        {
+            json!(i.to_string())
+        }
+        RecordValue::Integer(i) => json!(i),
+        // JSON can't write NaN or infinity, whatever `non-finite-floats` let through
+        RecordValue::Float(f) if !f.is_finite() => Json::Null,
+        RecordValue::Float(f) => match options.float_precision {
+            Some(digits) => {
+                let digits = digits.clamp(1, 17) as usize;
+                json!(format!("{:.*e}", digits - 1, f).parse::<f64>().unwrap_or(f))
+            }
+            None => json!(f),
+        },
+        RecordValue::Text(s) | RecordValue::TextDecimal(s) => json!(s),
+        RecordValue::Blob(b) => match options.blob_encoding {
+            BlobEncoding::Bytes => json!(b),
+            BlobEncoding::Base64 => json!(base64(&b)),
+            BlobEncoding::Hex => json!(hex(&b)),
+        },
+    }
+}
+
+/// `result_set` as `{"columns": [...], "rows": [[...], ...]}`.
+pub(crate) fn result_set(result_set: ResultSet, options: &JsonOptions) -> Json {
+    json!({
+        "columns": result_set.columns,
+        "rows": result_set
+            .rows
+            .into_iter()
+            .map(|row| row.into_iter().map(|value| to_json(value, options)).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// `row` as an object of `columns` to their values, written as `options` say.
+pub(crate) fn row_object(columns: &[String], row: Vec<RecordValue>, options: &JsonOptions) -> Json {
+    columns
+        .iter()
+        .cloned()
+        .zip(row.into_iter().map(|value| to_json(value, options)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// `bytes` as text in `encoding`, or as they are for [BlobEncoding::Bytes].
+pub(crate) fn encode_blob(bytes: Vec<u8>, encoding: BlobEncoding) -> RecordValue {
+    match encoding {
+        BlobEncoding::Bytes => RecordValue::Blob(bytes),
+        BlobEncoding::Base64 => RecordValue::Text(base64(&bytes)),
+        BlobEncoding::Hex => RecordValue::Text(hex(&bytes)),
+    }
+}
+
+/// `bytes` in standard base64, with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// `bytes` in lowercase hex, two digits per byte.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,111 @@
+//! A key-value store kept in a table, see the `kv` interface in the WIT.
+use std::cell::Cell;
+
+use crate::bindings::exports::component::wit_limbo::kv::{Guest, GuestStore};
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, GuestDatabase, RecordValue};
+use crate::Component;
+
+/// The table the entries are kept in, created on first use.
+const TABLE: &str = "wit_limbo_kv";
+
+pub struct KvStore {
+    db: Component,
+    /// Whether [TABLE] is known to exist.
+    created: Cell<bool>,
+}
+
+impl Guest for Component {
+    type Store = KvStore;
+}
+
+impl KvStore {
+    /// Run `sql` with `params` bound in order, creating the table first if needed.
+    fn query(&self, sql: &str, params: Vec<RecordValue>) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        if !self.created.get() {
+            self.db.exec(format!(
+                "CREATE TABLE IF NOT EXISTS {} (key TEXT PRIMARY KEY, value BLOB NOT NULL)",
+                TABLE
+            ))?;
+            self.created.set(true);
+        }
+        self.db.query_with(sql, params)
+    }
+}
+
+/// The value of an entry, which is always stored as a blob.
+fn value(value: RecordValue) -> Result<Vec<u8>, DbError> {
+    match value {
+        RecordValue::Blob(bytes) => Ok(bytes),
+        other => Err(DbError::Corrupt(format!(
+            "{} holds {:?} where a blob should be",
+            TABLE, other
+        ))),
+    }
+}
+
+impl GuestStore for KvStore {
+    fn new(path: String) -> Self {
+        Self {
+            db: Component::new(path),
+            created: Cell::new(false),
+        }
+    }
+
+    fn get(&self, key: String) -> Result<Option<Vec<u8>>, DbError> {
+        let sql = format!("SELECT value FROM {} WHERE key = ?", TABLE);
+        let rows = self.query(&sql, vec![RecordValue::Text(key)])?;
+        rows.into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next())
+            .map(value)
+            .transpose()
+    }
+
+    fn set(&self, key: String, value: Vec<u8>) -> Result<(), DbError> {
+        let sql = format!(
+            "INSERT INTO {} (key, value) VALUES (?, ?) \
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            TABLE
+        );
+        self.query(&sql, vec![RecordValue::Text(key), RecordValue::Blob(value)])?;
+        Ok(())
+    }
+
+    fn delete(&self, key: String) -> Result<bool, DbError> {
+        let sql = format!("SELECT 1 FROM {} WHERE key = ?", TABLE);
+        if self
+            .query(&sql, vec![RecordValue::Text(key.clone())])?
+            .is_empty()
+        {
+            return Ok(false);
+        }
+        let sql = format!("DELETE FROM {} WHERE key = ?", TABLE);
+        self.query(&sql, vec![RecordValue::Text(key)])?;
+        Ok(true)
+    }
+
+    fn scan(&self, prefix: String) -> Result<Vec<(String, Vec<u8>)>, DbError> {
+        // the lower bound lets the primary key skip the keys before the prefix
+        let sql = format!(
+            "SELECT key, value FROM {} WHERE key >= ?1 AND substr(key, 1, ?2) = ?1 ORDER BY key",
+            TABLE
+        );
+        let length = prefix.chars().count() as i64;
+        let rows = self.query(
+            &sql,
+            vec![RecordValue::Text(prefix), RecordValue::Integer(length)],
+        )?;
+        rows.into_iter()
+            .map(|row| {
+                let mut row = row.into_iter();
+                match (row.next(), row.next()) {
+                    (Some(RecordValue::Text(key)), Some(v)) => Ok((key, value(v)?)),
+                    other => Err(DbError::Corrupt(format!(
+                        "{} has an unexpected row: {:?}",
+                        TABLE, other
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
@@ -1,22 +1,261 @@
 #![allow(clippy::arc_with_non_send_sync)]
 
+mod audit;
+mod batch;
+/// The bindings for the `example` world, generated from `wit/` as the crate builds, so they
+/// can't fall behind the WIT.
+#[cfg(not(feature = "native"))]
 #[allow(warnings)]
-mod bindings;
+mod bindings {
+    wit_bindgen::generate!({
+        path: "wit",
+        world: "example",
+        runtime_path: "wit_bindgen_rt",
+        bitflags_path: "wit_bindgen_rt::bitflags",
+    });
+}
+mod blob;
+mod cursors;
+mod docs;
+mod error;
+mod events;
+mod faults;
+mod foreign_keys;
+#[cfg(feature = "hash-functions")]
+mod hash;
+mod heap;
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+mod host_fs;
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+mod host_kv;
+#[cfg(all(feature = "http", not(feature = "native")))]
+mod http;
+mod integrity;
+mod json;
+mod kv;
+#[cfg(all(feature = "host-locale", not(feature = "native")))]
+mod locale;
+mod log;
+mod manager;
+mod matview;
+mod metrics;
+#[cfg(feature = "native")]
+mod native;
+mod ndjson;
+#[cfg(any(
+    feature = "regexp",
+    all(feature = "host-locale", not(feature = "native"))
+))]
+mod operands;
+#[cfg(feature = "parquet")]
+mod parquet;
+mod plan;
+mod proto;
+mod query_cache;
+mod readahead;
+#[cfg(feature = "regexp")]
+mod regexp;
+mod schema;
+mod schema_cache;
+mod snapshot;
+mod span;
+mod sql;
+mod sqlar;
+mod storage;
+mod subscription;
+mod temp;
+mod write_back;
 
-use std::{cell::RefCell, rc::Rc, sync::Arc};
+#[cfg(feature = "native")]
+use native as bindings;
+
+use std::{
+    cell::{Cell, RefCell, RefMut},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    num::NonZeroUsize,
+    rc::Rc,
+    sync::{Arc, Weak},
+};
 
 use bindings::exports::component::wit_limbo;
 use bindings::{
-    component::wit_limbo::host::random_byte,
+    component::wit_limbo::{
+        host_clock::{now, sleep},
+        host_random::random_byte,
+    },
     exports::component::wit_limbo::limbo::{
-        Guest, GuestDatabase, GuestStatement, RecordValue, Statement as WitStatement,
+        BlobWriter as WitBlobWriter, Database as WitDatabase, DatabaseBorrow, Guest, GuestDatabase,
+        GuestRows, GuestStatement, NdjsonExporter as WitNdjsonExporter, Rows as WitRows,
+        Statement as WitStatement, Subscription as WitSubscription,
     },
 };
+// with the `native` feature these are among the types re-exported below
+#[cfg(not(feature = "native"))]
+use bindings::exports::component::wit_limbo::limbo::{
+    Affinity, AuditEntry, AuditOp, BlobEncoding, Chunk, ColumnInfo, DbError, FkViolation,
+    JsonOptions, KeysetPage, LimitKind, Limits, NonFiniteFloats, OpenFlags, ParseError, Prepared,
+    QueryEstimate, RecordValue, RefreshMode, ResultSet, RunStats, StatementKind, TempStore,
+    TickWork, TriggerInfo, UpsertCounts, ViewInfo,
+};
+
+use audit::Audited;
+use batch::Batch;
+use cursors::{Cursor, Cursors};
+use limbo_core::{Database, Result};
+use manager::Activity;
+use matview::Views;
+use metrics::Metrics;
+use query_cache::{QueryCache, QueryCaches};
+use schema::SchemaObject;
+use schema_cache::{SchemaCache, SchemaRow};
+use snapshot::Snapshots;
+use span::Span;
+use storage::StorageBackend;
+use subscription::Subscriptions;
+use temp::{Route, TempTables};
+
+pub use storage::DatabaseStorage;
+
+/// With the `native` feature, the `limbo` interface as plain Rust on limbo_core in-process,
+/// for hosts that want to run the same code without a wasm runtime.
+#[cfg(feature = "native")]
+pub use bindings::exports::component::wit_limbo::limbo::{
+    capabilities, Affinity, AuditEntry, AuditOp, BlobEncoding, BlobWriter, Chunk, ColumnInfo,
+    Database as NativeDatabase, DbError, FkViolation, GeneratedKind, JsonOptions, KeysetPage,
+    LimitKind, Limits, Manager, NdjsonExporter, NonFiniteFloats, OpenFlags, ParseError, Prepared,
+    QueryEstimate, RecordValue, RefreshMode, ResultSet, Rows, RunStats, SqlPosition, Statement,
+    StatementKind, Subscription, TempStore, TickWork, TriggerInfo, UpsertCounts, ViewInfo,
+};
+
+/// With the `native` feature, what to call when a subscribed query's rows change, see
+/// `database.subscribe` in the WIT.
+#[cfg(feature = "native")]
+pub use bindings::component::wit_limbo::host_subscriptions::set_handler as set_on_query_changed;
+
+/// With the `native` feature, what to call with the progress of a long call, see
+/// `database.integrity-check` in the WIT.
+#[cfg(feature = "native")]
+pub use bindings::component::wit_limbo::host_progress::set_handler as set_on_progress;
 
-use limbo_core::{
-    maybe_init_database_file, BufferPool, Database, MemoryIO, Pager, Result, WalFile, WalFileShared,
+/// With the `native` feature, the `kv` interface's store, see [KvStore::open].
+#[cfg(feature = "native")]
+pub use bindings::exports::component::wit_limbo::kv::Store as KvStore;
+
+/// With the `native` feature, the `docs` interface's store, see [DocStore::open].
+#[cfg(feature = "native")]
+pub use bindings::exports::component::wit_limbo::docs::{Document, Store as DocStore};
+
+/// With the `native` feature, the `events` interface's store, see [EventStore::open].
+#[cfg(feature = "native")]
+pub use bindings::exports::component::wit_limbo::events::{Event, Store as EventStore};
+
+/// With the `native` feature, the `sqlar` interface's archive, see [SqlArchive::open].
+#[cfg(feature = "native")]
+pub use bindings::exports::component::wit_limbo::sqlar::{
+    Archive as SqlArchive, Entry as SqlarEntry,
 };
 
+/// What `capabilities` reports: every method in `wit/world.wit`, kept in step with it.
+const CAPABILITIES: &[&str] = &[
+    "limbo.set-soft-heap-limit",
+    "database.exec",
+    "database.prepare",
+    "database.prepare-tail",
+    "database.query-json",
+    "database.clone-handle",
+    "database.insert-blob",
+    "database.is-readonly-sql",
+    "database.set-table-filter",
+    "database.set-defensive",
+    "database.set-unicode-case",
+    "database.set-busy-timeout",
+    "database.set-json-options",
+    "database.set-non-finite-floats",
+    "database.set-query-cache",
+    "database.cancel-running",
+    "database.set-limit",
+    "database.limits",
+    "database.metrics-prometheus",
+    "database.last-error",
+    "database.last-error-code",
+    "database.set-foreign-keys",
+    "database.foreign-key-check",
+    "database.integrity-check",
+    "database.triggers",
+    "database.columns",
+    "database.copy-table",
+    "database.upsert",
+    "database.diff-schema",
+    "database.estimate",
+    "database.views",
+    "database.schema-version",
+    "database.subscribe",
+    "database.create-materialized-view",
+    "database.refresh-view",
+    "database.snapshot",
+    "database.query-as-of",
+    "database.release-snapshot",
+    "database.enable-audit",
+    "database.audit-history",
+    "database.export-ndjson",
+    "database.set-readahead-pages",
+    "database.set-coalesce-writes",
+    "database.set-temp-store",
+    "database.begin-batched",
+    "database.flush",
+    "database.end-batched",
+    "database.tick",
+    "database.close",
+    "manager.open",
+    "manager.list",
+    "manager.drop-tenant",
+    "manager.set-memory-quota",
+    "manager.set-idle-close-ms",
+    "manager.close-idle",
+    "blob-writer.write",
+    "blob-writer.finish",
+    "statement.bind",
+    "statement.bind-json",
+    "statement.all",
+    "statement.run",
+    "statement.all-chunked",
+    "statement.keyset-page",
+    "statement.column-names",
+    "statement.column-decl-types",
+    "statement.all-with-names",
+    "statement.all-with-blob-encoding",
+    "statement.all-json",
+    "statement.all-proto",
+    "statement.last-run-stats",
+    "statement.is-readonly",
+    "statement.kind",
+    "statement.tables",
+    "statement.original-sql",
+    "statement.expanded-sql",
+    "statement.finalize",
+    "rows.next",
+    "rows.next-chunk",
+    "subscription.id",
+    "subscription.latest",
+    "ndjson-exporter.next-chunk",
+    "kv.get",
+    "kv.set",
+    "kv.delete",
+    "kv.scan",
+    "docs.put",
+    "docs.get",
+    "docs.find",
+    "events.append",
+    "events.read",
+    "sqlar.add-file",
+    "sqlar.extract",
+    "sqlar.list",
+];
+
+/// The WIT world this component implements, so hosts can embed and link against
+/// the exact interface version they were built with.
+pub const WIT_WORLD: &str = include_str!("../wit/world.wit");
+
 /// Custom function to use the import for random byte generation.
 ///
 /// We do this is because "js" feature is incompatible with the component model
@@ -33,106 +272,2234 @@ fn imported_random(dest: &mut [u8]) -> Result<(), getrandom::Error> {
 
 getrandom::register_custom_getrandom!(imported_random);
 
+/// What every connection to one database shares.
+#[derive(Clone, Default)]
+struct Shared {
+    metrics: Rc<Metrics>,
+    subscriptions: Rc<Subscriptions>,
+    query_caches: Rc<QueryCaches>,
+    views: Rc<Views>,
+    snapshots: Rc<Snapshots>,
+    audited: Rc<Audited>,
+    cursors: Rc<Cursors>,
+    /// Whether the storage compresses what it writes, `None` if it can't, see
+    /// `set-compress-pages`.
+    compress_pages: Option<Rc<Cell<bool>>>,
+    /// How many pages the storage reads ahead by, `None` if it can't, see
+    /// `set-readahead-pages`.
+    readahead_pages: Option<Rc<Cell<u32>>>,
+    /// Whether the storage holds writes until a sync, `None` if it can't, see
+    /// `set-coalesce-writes`.
+    coalesce_writes: Option<Rc<Cell<bool>>>,
+    /// Whether anything has been committed since the WAL was last checkpointed, see `tick`.
+    uncheckpointed: Rc<Cell<bool>>,
+}
+
+/// A database open on storage, with the IO and [Shared] state its connections use.
+type OpenDatabase = (Weak<Database>, Arc<dyn limbo_core::IO>, Shared);
+
+thread_local! {
+    /// Databases open on storage, by path. A second `database` opened on the same path joins
+    /// the existing one, so both connections share its WAL, the locks that guard it, and its
+    /// [Shared] state.
+    static OPEN: RefCell<HashMap<String, OpenDatabase>> = RefCell::new(HashMap::new());
+}
+
 struct Component {
     inner: Arc<Database>,
-    conn: Rc<limbo_core::Connection>,
+    io: Arc<dyn limbo_core::IO>,
+    /// Settings shared with the statements prepared on this connection.
+    state: Rc<ConnState>,
+}
+
+/// The state of a connection shared with the statements prepared on it.
+struct ConnState {
+    /// The connection, `None` once the database has been closed, or while a manager has
+    /// closed it for being idle.
+    conn: RefCell<Option<Rc<limbo_core::Connection>>>,
+    /// Why the constructor couldn't open the database, returned by every call in place of
+    /// `closed`.
+    open_error: RefCell<Option<DbError>>,
+    /// Whether `conn` was closed by a manager for being idle, so the next call reconnects.
+    idle_closed: Cell<bool>,
+    /// Where the manager that opened this connection tracks its use, see
+    /// `manager.set-idle-close-ms`.
+    activity: RefCell<Option<Rc<Activity>>>,
+    /// Whether SQL that writes is refused, for a connection opened with `read-only`.
+    read_only: Cell<bool>,
+    /// Whether SQL that could corrupt the database is refused, see `set-defensive`.
+    defensive: Cell<bool>,
+    /// Whether LIKE and `COLLATE NOCASE` fold case beyond ASCII, see `set-unicode-case`.
+    unicode_case: Cell<bool>,
+    /// Milliseconds to wait for a lock, see `set-busy-timeout`.
+    busy_timeout: Cell<u32>,
+    /// The limits on the statements the connection prepares, see `set-limit`.
+    limits: Cell<Limits>,
+    /// Counts the calls to `cancel-running`, so runs started before one know to stop.
+    cancels: Cell<u64>,
+    /// The error the most recent failed call returned, see `last-error`.
+    last_error: RefCell<Option<DbError>>,
+    /// Predicates limiting the rows of tables, by lowercase table name, see
+    /// `set-table-filter`.
+    filters: RefCell<BTreeMap<String, String>>,
+    /// Bytes of rows a single call may collect, 0 for no limit, see `manager.set-memory-quota`.
+    memory_quota: Cell<u64>,
+    /// How `query-json` and `all-json` write values, see `set-json-options`.
+    json_options: RefCell<JsonOptions>,
+    /// What NaN and infinite floats in results become, see `set-non-finite-floats`.
+    non_finite_floats: Cell<NonFiniteFloats>,
+    /// The writes queued since `begin-batched`, `None` when not batching.
+    batch: RefCell<Option<Batch>>,
+    /// The connection's temporary tables, see `set-temp-store`.
+    temp: TempTables,
+    /// The metrics of the database, shared with its other connections.
+    metrics: Rc<Metrics>,
+    /// The queries subscribed to on the database, shared with its other connections, see
+    /// `subscribe`.
+    subscriptions: Rc<Subscriptions>,
+    /// The rows of queries kept for when they run again, see `set-query-cache`.
+    query_cache: Rc<QueryCache>,
+    /// The rows of `sqlite_schema` as last read, see `schema-version`.
+    schema: SchemaCache,
+    /// The query caches of the database's connections, this one's among them.
+    query_caches: Rc<QueryCaches>,
+    /// The materialized views of the database, shared with its other connections.
+    views: Rc<Views>,
+    /// The snapshots taken of the database, shared with its other connections, see
+    /// `snapshot`.
+    snapshots: Rc<Snapshots>,
+    /// The audited tables of the database, shared with its other connections, see
+    /// `enable-audit`.
+    audited: Rc<Audited>,
+    /// The runs part way through on the database's connections, this one's among them, see
+    /// [Cursors].
+    cursors: Rc<Cursors>,
+    /// Whether the database's storage compresses its pages, shared with its other
+    /// connections, see `set-compress-pages`.
+    compress_pages: Option<Rc<Cell<bool>>>,
+    /// How many pages the database's storage reads ahead by, shared with its other
+    /// connections, see `set-readahead-pages`.
+    readahead_pages: Option<Rc<Cell<u32>>>,
+    /// Whether the database's storage holds writes until a sync, shared with its other
+    /// connections, see `set-coalesce-writes`.
+    coalesce_writes: Option<Rc<Cell<bool>>>,
+    /// Whether anything has been committed since the WAL was last checkpointed, shared with
+    /// the database's other connections, see `tick`.
+    uncheckpointed: Rc<Cell<bool>>,
+    /// Whether BEGIN has run on the connection with no COMMIT or ROLLBACK since.
+    in_transaction: Cell<bool>,
+    /// The writes made in that transaction, refreshing the on-commit materialized views they
+    /// affect once it commits.
+    pending: RefCell<Vec<sql::Changes>>,
+    /// The database, so statements can run SQL on the connection too, see [Component::of].
+    database: Arc<Database>,
+}
+
+impl ConnState {
+    fn shared(&self) -> Shared {
+        Shared {
+            metrics: self.metrics.clone(),
+            subscriptions: self.subscriptions.clone(),
+            query_caches: self.query_caches.clone(),
+            views: self.views.clone(),
+            snapshots: self.snapshots.clone(),
+            audited: self.audited.clone(),
+            cursors: self.cursors.clone(),
+            compress_pages: self.compress_pages.clone(),
+            readahead_pages: self.readahead_pages.clone(),
+            coalesce_writes: self.coalesce_writes.clone(),
+            uncheckpointed: self.uncheckpointed.clone(),
+        }
+    }
+
+    /// Run a call on the connection, keeping the error it returns as the last, see
+    /// `last-error`.
+    fn track<T>(&self, call: impl FnOnce() -> Result<T, DbError>) -> Result<T, DbError> {
+        let result = call();
+        if let Err(e) = &result {
+            self.last_error.replace(Some(e.clone()));
+        }
+        result
+    }
+
+    /// Have the runs part way through on the database's other connections read the rest of
+    /// their rows, before this one runs a statement that makes `changes`, see [Cursors].
+    fn before_write(&self, changes: &sql::Changes) {
+        if !matches!(changes, sql::Changes::Nothing | sql::Changes::Begin) {
+            self.cursors.before_write(self);
+        }
+    }
 }
 
 impl Guest for Component {
     type Database = Component;
 
     type Statement = InnerStatement;
+
+    type Rows = InnerRows;
+
+    type BlobWriter = blob::BlobWriter;
+
+    type Manager = manager::Manager;
+
+    type Subscription = subscription::Subscription;
+
+    type NdjsonExporter = ndjson::NdjsonExporter;
+
+    fn capabilities() -> Vec<String> {
+        let mut capabilities: Vec<String> = CAPABILITIES.iter().map(|c| c.to_string()).collect();
+        if cfg!(all(feature = "http", not(feature = "native"))) {
+            capabilities.push("wasi-http".to_string());
+        }
+        if cfg!(all(feature = "wasi-logging", not(feature = "native"))) {
+            capabilities.push("wasi-logging".to_string());
+        }
+        if cfg!(all(feature = "fault-injection", not(feature = "native"))) {
+            capabilities.push("fault-injection".to_string());
+        }
+        if cfg!(feature = "parquet") {
+            capabilities.push("statement.all-parquet".to_string());
+        }
+        if cfg!(feature = "compress-pages") {
+            capabilities.push("database.set-compress-pages".to_string());
+        }
+        if cfg!(feature = "hash-functions") {
+            capabilities.push("hash-functions".to_string());
+        }
+        if cfg!(all(feature = "host-locale", not(feature = "native"))) {
+            capabilities.push("host-locale".to_string());
+        }
+        if cfg!(feature = "regexp") {
+            capabilities.push("regexp".to_string());
+        }
+        capabilities
+    }
+
+    fn set_soft_heap_limit(bytes: u64) -> u64 {
+        heap::set_soft_limit(bytes)
+    }
+}
+
+impl Component {
+    /// Open the database at `path` on `backend`, as `flags` say.
+    fn open(backend: &dyn StorageBackend, path: &str, flags: OpenFlags) -> Result<Self, DbError> {
+        let db = backend.open(path, flags)?;
+        let shared = Shared {
+            compress_pages: backend.compress_pages(),
+            readahead_pages: backend.readahead_pages(),
+            coalesce_writes: backend.coalesce_writes(),
+            ..Shared::default()
+        };
+        Ok(Self::connect(db, backend.io(), shared))
+    }
+
+    /// A new connection to `db`, sharing `shared` with its other connections.
+    fn connect(db: Arc<Database>, io: Arc<dyn limbo_core::IO>, shared: Shared) -> Self {
+        let conn = db.connect();
+        let query_cache = Rc::new(QueryCache::default());
+        shared.query_caches.add(&query_cache);
+        Self {
+            inner: db.clone(),
+            io,
+            state: Rc::new(ConnState {
+                conn: RefCell::new(Some(conn)),
+                open_error: RefCell::new(None),
+                idle_closed: Cell::new(false),
+                activity: RefCell::new(None),
+                read_only: Cell::new(false),
+                defensive: Cell::new(false),
+                unicode_case: Cell::new(false),
+                busy_timeout: Cell::new(0),
+                limits: Cell::new(DEFAULT_LIMITS),
+                cancels: Cell::new(0),
+                last_error: RefCell::new(None),
+                filters: RefCell::new(BTreeMap::new()),
+                memory_quota: Cell::new(0),
+                json_options: RefCell::new(json::default_options()),
+                non_finite_floats: Cell::new(NonFiniteFloats::Pass),
+                batch: RefCell::new(None),
+                temp: TempTables::new(),
+                metrics: shared.metrics,
+                subscriptions: shared.subscriptions,
+                query_cache,
+                schema: SchemaCache::default(),
+                query_caches: shared.query_caches,
+                views: shared.views,
+                snapshots: shared.snapshots,
+                audited: shared.audited,
+                cursors: shared.cursors,
+                compress_pages: shared.compress_pages,
+                readahead_pages: shared.readahead_pages,
+                coalesce_writes: shared.coalesce_writes,
+                uncheckpointed: shared.uncheckpointed,
+                in_transaction: Cell::new(false),
+                pending: RefCell::new(vec![]),
+                database: db,
+            }),
+        }
+    }
+
+    /// Connect to the database already open at `path`, or open it with `open`.
+    fn open_shared(
+        path: &str,
+        open: impl FnOnce() -> Result<Self, DbError>,
+    ) -> Result<Self, DbError> {
+        let existing = OPEN.with_borrow(|dbs| {
+            let (db, io, shared) = dbs.get(path)?;
+            Some((db.upgrade()?, io.clone(), shared.clone()))
+        });
+        if let Some((inner, io, shared)) = existing {
+            metrics::add(&shared.metrics.cache_hits, 1);
+            return Ok(Self::connect(inner, io, shared));
+        }
+
+        let db = open()?;
+        OPEN.with_borrow_mut(|dbs| {
+            let entry = (Arc::downgrade(&db.inner), db.io.clone(), db.state.shared());
+            dbs.insert(path.to_string(), entry)
+        });
+        Ok(db)
+    }
+
+    /// Open the database at `uri`, choosing its storage by scheme, as `flags` say.
+    fn open_uri(uri: &str, flags: OpenFlags) -> Result<Self, DbError> {
+        let (uri, flags) = storage::uri_params(uri, flags)?;
+        if flags.contains(OpenFlags::READ_ONLY | OpenFlags::CREATE) {
+            return Err(DbError::Misuse(
+                "a database opened read-only can't be created".to_string(),
+            ));
+        }
+        let (backend, path) = storage::from_uri(uri)?;
+        let db = if backend.scheme() == "memory" {
+            // every `:memory:` database is its own, and new
+            Self::open(&*backend, path, OpenFlags::CREATE)?
+        } else {
+            let key = format!("{}:{}", backend.scheme(), path);
+            if flags.contains(OpenFlags::EXCLUSIVE) && Self::is_open(&key) {
+                return Err(DbError::CannotOpen(format!("{} is already open", path)));
+            }
+            Self::open_shared(&key, || {
+                let db = Self::open(&*backend, path, flags)?;
+                temp::drop_stale(&db)?;
+                Ok(db)
+            })?
+        };
+        db.state.read_only.set(flags.contains(OpenFlags::READ_ONLY));
+        Ok(db)
+    }
+
+    /// Whether the database at `path` is open in this instance.
+    fn is_open(path: &str) -> bool {
+        OPEN.with_borrow(|dbs| dbs.get(path).is_some_and(|(db, ..)| db.strong_count() > 0))
+    }
+
+    /// Run each statement of `sql` in turn, collecting the rows they produce.
+    fn run_script(&self, sql: &str) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        // statements with a RETURNING clause produce rows, everything else returns none
+        let mut rows = vec![];
+        let mut rest = sql;
+        while sql::statement_start(rest).is_some() {
+            let (statement, tail) = sql::split_first(rest);
+            let at = sql.len() - rest.len();
+            let statement_rows = self
+                .run_sql(statement.to_string(), true)
+                .map_err(|e| error::shifted(e, sql, at))?;
+            rows.extend(statement_rows);
+            rest = tail;
+        }
+        Ok(rows)
+    }
+
+    /// Refuse `sql` if it writes and the connection was opened `read-only`, or if it is one
+    /// of the statements defensive mode refuses.
+    fn check_writable(&self, sql: &str) -> Result<(), DbError> {
+        if self.state.read_only.get() && !sql::is_readonly(sql) {
+            return Err(DbError::Misuse(
+                "the database was opened read-only".to_string(),
+            ));
+        }
+        if self.state.defensive.get() {
+            if let Some(what) = sql::defensive_refuses(sql) {
+                return Err(DbError::Misuse(format!(
+                    "{} is refused in defensive mode",
+                    what
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Refuse `sql` if it is longer, nests deeper or creates a table with more columns than
+    /// the connection's limits allow.
+    fn check_limits(&self, sql: &str) -> Result<(), DbError> {
+        let limits = self.state.limits.get();
+        check_limit(sql.len(), limits.sql_length, "bytes of SQL")?;
+        check_limit(sql::depth(sql), limits.expr_depth, "levels of nesting")?;
+        check_limit(sql::created_columns(sql), limits.column, "columns")
+    }
+
+    /// Refuse `stmt` if it returns more columns than the connection's limit.
+    fn check_columns(&self, stmt: &limbo_core::Statement) -> Result<(), DbError> {
+        check_limit(
+            stmt.num_columns(),
+            self.state.limits.get().column,
+            "columns",
+        )
+    }
+
+    /// Another connection to the same database, sharing its storage and [Shared] state.
+    fn new_connection(&self) -> Self {
+        let db = Self::connect(self.inner.clone(), self.io.clone(), self.state.shared());
+        match self.state.open_error.borrow().clone() {
+            Some(e) => db.failed(e),
+            None => db,
+        }
+    }
+
+    /// This connection closed, returning `e` from every call, for a constructor that
+    /// couldn't open its database.
+    fn failed(self, e: DbError) -> Self {
+        self.state.conn.borrow_mut().take();
+        self.state.last_error.replace(Some(e.clone()));
+        self.state.open_error.replace(Some(e));
+        self
+    }
+
+    /// The connection `state` belongs to, for statements that need to run SQL on it.
+    fn of(state: &Rc<ConnState>, io: &Arc<dyn limbo_core::IO>) -> Self {
+        Self {
+            inner: state.database.clone(),
+            io: io.clone(),
+            state: state.clone(),
+        }
+    }
+
+    /// The connection, reconnecting if a manager closed it for being idle, or
+    /// [DbError::Closed] once the database has been closed, or the error the constructor
+    /// failed to open it with.
+    fn conn(&self) -> Result<Rc<limbo_core::Connection>, DbError> {
+        if let Some(activity) = &*self.state.activity.borrow() {
+            activity.touch();
+        }
+        if self.state.idle_closed.replace(false) {
+            // closing rolled back any transaction
+            self.state.in_transaction.set(false);
+            self.state.pending.borrow_mut().clear();
+            self.state.conn.replace(Some(self.inner.connect()));
+        }
+        self.state.conn.borrow().clone().ok_or_else(|| {
+            let open_error = self.state.open_error.borrow();
+            open_error
+                .clone()
+                .unwrap_or_else(|| error::closed("database"))
+        })
+    }
+
+    /// Prepare `sql`, bind `params` in order, and collect every row it produces.
+    fn query_with(
+        &self,
+        sql: &str,
+        params: Vec<RecordValue>,
+    ) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        let statement = self.prepare_sql(sql.to_string(), false)?;
+        let statement = statement.get::<InnerStatement>();
+        for (i, param) in params.into_iter().enumerate() {
+            statement.bind(i as u32 + 1, param)?;
+        }
+        statement.all()
+    }
+
+    /// Prepare `sql` and collect every row it produces.
+    fn query_all(&self, sql: String) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        self.run_sql(sql, false)
+    }
+
+    /// `sql` as the connection runs it, with REGEXP and the ID and hash functions rewritten,
+    /// its table filters applied and case folded as `set-unicode-case` asks, and the functions
+    /// to apply to the values bound to its parameters. REGEXP matches given `params`, see
+    /// [regexp::rewrite], and is refused without the `regexp` feature.
+    #[cfg_attr(
+        not(any(
+            feature = "regexp",
+            all(feature = "host-locale", not(feature = "native"))
+        )),
+        allow(unused_variables)
+    )]
+    fn rewrite(
+        &self,
+        sql: &str,
+        params: Option<&BTreeMap<usize, RecordValue>>,
+    ) -> Result<(String, ParamFunctions), DbError> {
+        #[cfg(feature = "regexp")]
+        let sql = &regexp::rewrite(self, sql, params)?;
+        #[cfg(not(feature = "regexp"))]
+        if !sql::regexps(sql)?.is_empty() {
+            return Err(error::unsupported("REGEXP without the `regexp` feature"));
+        }
+        let sql = sql::id_functions(sql, now() / 1000)?;
+        #[cfg(feature = "hash-functions")]
+        let (sql, functions) = hash::rewrite(&sql)?;
+        #[cfg(not(feature = "hash-functions"))]
+        let functions = ParamFunctions::new();
+        self.check_filtered(&sql)?;
+        let filtered = sql::apply_filters(&sql, &self.state.filters.borrow())?;
+        #[cfg(all(feature = "host-locale", not(feature = "native")))]
+        let filtered = locale::collate(self, &filtered, params)?;
+        let sql = match self.state.unicode_case.get() {
+            #[cfg(all(feature = "host-locale", not(feature = "native")))]
+            true => locale::fold_case(self, &filtered, params)?,
+            #[cfg(not(all(feature = "host-locale", not(feature = "native"))))]
+            true => sql::fold_case(&filtered),
+            false => filtered,
+        };
+        Ok((sql, functions))
+    }
+
+    /// Refuse `sql` if it would reach a filtered table where [sql::apply_filters] can't add the
+    /// filter: through a view, or a trigger on the table it writes, which run SQL of their own,
+    /// or by creating a view of it.
+    fn check_filtered(&self, sql: &str) -> Result<(), DbError> {
+        let filters = self.state.filters.borrow();
+        if filters.is_empty() {
+            return Ok(());
+        }
+        let reads_filtered = |sql: &str| {
+            sql::named(sql)
+                .into_iter()
+                .find(|name| filters.contains_key(name))
+        };
+        let refused = |what: String, table: String| {
+            DbError::Misuse(format!(
+                "{} reads {}, which is filtered, so it is refused",
+                what, table
+            ))
+        };
+        if sql::is_create_view(sql) {
+            return match reads_filtered(sql) {
+                Some(table) => Err(refused("the view".to_string(), table)),
+                None => Ok(()),
+            };
+        }
+        let written = match sql::changes(sql) {
+            sql::Changes::Table(table) => Some(table),
+            _ => None,
+        };
+        // filtered tables get their filter as they are, and the schema tables have none
+        let mut names: Vec<_> = sql::referenced_tables(sql)
+            .into_iter()
+            .map(|name| name.to_ascii_lowercase())
+            .filter(|name| !name.starts_with("sqlite_") && !filters.contains_key(name))
+            .collect();
+        if names.is_empty() && written.is_none() {
+            return Ok(());
+        }
+        let schema = self.schema()?;
+        let triggers = schema.iter().filter(|row| {
+            row.kind == "trigger"
+                && written
+                    .as_ref()
+                    .is_some_and(|table| row.table.eq_ignore_ascii_case(table))
+        });
+        for trigger in triggers {
+            if let Some(table) = trigger.sql.as_deref().and_then(reads_filtered) {
+                return Err(refused(format!("the trigger {}", trigger.name), table));
+            }
+        }
+        // a view may read other views, each checked in turn
+        let mut checked = BTreeSet::new();
+        while let Some(name) = names.pop() {
+            if !checked.insert(name.clone()) {
+                continue;
+            }
+            let view = schema
+                .iter()
+                .find(|row| row.kind == "view" && row.name.eq_ignore_ascii_case(&name));
+            let Some(body) = view.and_then(|view| view.sql.as_deref()) else {
+                continue;
+            };
+            if let Some(table) = reads_filtered(body) {
+                return Err(refused(format!("the view {}", name), table));
+            }
+            names.extend(
+                sql::referenced_tables(body)
+                    .into_iter()
+                    .map(|name| name.to_ascii_lowercase()),
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether a statement of `sql` has to be prepared again at the start of each run, as the
+    /// guest works out some of it from the values as they are then: REGEXP, and folding case
+    /// and collating by the host's locale.
+    fn prepared_per_run(&self, sql: &str) -> bool {
+        #[cfg(all(feature = "host-locale", not(feature = "native")))]
+        if locale::uses(sql, self.state.unicode_case.get()) {
+            return true;
+        }
+        cfg!(feature = "regexp") && sql::regexps(sql).is_ok_and(|regexps| !regexps.is_empty())
+    }
+
+    /// Prepare `sql` and collect every row it produces, refusing it if it is beyond the
+    /// connection's limits and `limited`, as the SQL hosts pass in is. The SQL the guest runs
+    /// for itself isn't limited.
+    fn run_sql(&self, sql: String, limited: bool) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        check_supported(&sql)?;
+        if limited {
+            self.check_limits(&sql)?;
+        }
+        let sql = match self.state.temp.route(&sql) {
+            Route::Main(sql) => sql,
+            Route::Memory(sql) => return self.state.temp.in_memory(|db| db.query_all(sql))?,
+        };
+        self.check_writable(&sql)?;
+        let changes = sql::changes(&sql);
+        self.state.before_write(&changes);
+        let before = audit::before(self, &changes)?;
+        let (filtered, _) = self.rewrite(&sql, Some(&BTreeMap::new()))?;
+        let mut stmt = {
+            let _span = Span::start("limbo.prepare");
+            let conn = self.conn()?;
+            conn.prepare(filtered)
+                .map_err(|e| error::located(e.into(), &sql))?
+        };
+        if limited {
+            self.check_columns(&stmt)?;
+        }
+        metrics::add(&self.state.metrics.queries, 1);
+        let _span = Span::start("limbo.step");
+        let rows = collect_rows(&mut stmt, &self.io, &self.state, &mut zero_stats())?;
+        metrics::add(&self.state.metrics.rows_read, rows.len() as u64);
+        drop(stmt);
+        audit::record(self, before)?;
+        self.changed(&changes);
+        Ok(rows)
+    }
+
+    /// Prepare `sql`, a single statement, refusing it if it is beyond the connection's limits
+    /// and `limited`, as [Component::run_sql] does.
+    fn prepare_sql(&self, sql: String, limited: bool) -> Result<WitStatement, DbError> {
+        let (first, tail) = sql::split_first(&sql);
+        if let Some(at) = sql::statement_start(tail) {
+            let position = sql::position(&sql, first.len() + at);
+            return Err(DbError::Parse(ParseError {
+                message: format!(
+                    "only one statement can be prepared, and another starts at byte {}; \
+                     see prepare-tail",
+                    position.offset
+                ),
+                position: Some(position),
+            }));
+        }
+        if sql::statement_start(&sql).is_none() {
+            return Err(DbError::Misuse(
+                "there is no statement to prepare".to_string(),
+            ));
+        }
+        check_supported(&sql)?;
+        if limited {
+            self.check_limits(&sql)?;
+        }
+        let sql = match self.state.temp.route(&sql) {
+            Route::Main(sql) => sql,
+            Route::Memory(sql) => return self.state.temp.in_memory(|db| db.prepare(sql))?,
+        };
+        self.check_writable(&sql)?;
+        let _span = Span::start("limbo.prepare");
+        let (filtered, functions) = self.rewrite(&sql, None)?;
+        let stmt = self
+            .conn()?
+            .prepare(filtered)
+            .map_err(|e| error::located(e.into(), &sql))?;
+        if limited {
+            self.check_columns(&stmt)?;
+        }
+        let mut inner_stmt =
+            InnerStatement::new(stmt, sql, self.io.clone(), self.state.clone(), false);
+        inner_stmt.functions = functions;
+        inner_stmt.per_run = self.prepared_per_run(&inner_stmt.sql);
+        Ok(WitStatement::new(inner_stmt))
+    }
+
+    /// Move the committed frames in the WAL into the database file.
+    fn checkpoint(&self) -> Result<(), DbError> {
+        let conn = self.conn()?;
+        let _span = Span::start("limbo.checkpoint");
+        let mut stmt = conn.prepare("PRAGMA wal_checkpoint")?;
+        run_to_completion(&mut stmt, &self.io, &self.state)?;
+        self.state.uncheckpointed.set(false);
+        Ok(())
+    }
+
+    /// Follow `changes` made on this connection: refresh the on-commit materialized views they
+    /// affect, once the transaction they are part of commits, then re-run the subscribed
+    /// queries.
+    fn changed(&self, changes: &sql::Changes) {
+        use sql::Changes as C;
+        let state = &self.state;
+        let wrote =
+            |name: &str| matches!(changes, C::Table(table) if table.eq_ignore_ascii_case(name));
+        if matches!(changes, C::Everything) || wrote(matview::TABLE) {
+            state.views.forget();
+        }
+        if matches!(changes, C::Everything) || wrote(audit::AUDITED) {
+            state.audited.forget();
+        }
+        let written = match changes {
+            C::Nothing => return,
+            C::Begin => {
+                state.in_transaction.set(true);
+                state.pending.borrow_mut().clear();
+                vec![]
+            }
+            C::Rollback => {
+                state.in_transaction.set(false);
+                state.pending.borrow_mut().clear();
+                vec![]
+            }
+            C::Commit => {
+                state.in_transaction.set(false);
+                state.pending.take()
+            }
+            C::Table(_) | C::Everything if state.in_transaction.get() => {
+                state.pending.borrow_mut().push(changes.clone());
+                vec![]
+            }
+            C::Table(_) | C::Everything => vec![changes.clone()],
+        };
+        for changes in &written {
+            state.query_caches.changed(changes);
+        }
+        if !written.is_empty() {
+            state.uncheckpointed.set(true);
+            matview::refresh_affected(self, &written);
+        }
+        state.subscriptions.notify(changes);
+    }
+
+    /// What to cache the rows of `sql` under, with `params` bound, if they can be: when the
+    /// connection has a query cache and no transaction open, for a query that only reads
+    /// tables.
+    fn cache_key(&self, sql: &str, params: &BTreeMap<usize, RecordValue>) -> Option<String> {
+        let cacheable = self.state.query_cache.is_enabled()
+            && !self.state.in_transaction.get()
+            && sql::kind(sql) == StatementKind::Select
+            && !sql::is_volatile(sql);
+        cacheable.then(|| sql::expand(sql, params))
+    }
+
+    /// The tables `sql` reads, with those of the views it reads.
+    fn tables_read(&self, sql: &str) -> Result<Vec<String>, DbError> {
+        let mut tables = sql::referenced_tables(sql);
+        for view in self.views()? {
+            if tables
+                .iter()
+                .any(|table| table.eq_ignore_ascii_case(&view.name))
+            {
+                tables.extend(view.tables);
+            }
+        }
+        Ok(tables)
+    }
+
+    /// Replace the rows of the table `name` with those `sql` returns, without the
+    /// connection's table filters, returning how many there are. See `refresh-view`.
+    fn materialize(&self, name: &str, sql: &str) -> Result<u64, DbError> {
+        let conn = self.conn()?;
+        let rows = {
+            let mut stmt = conn.prepare(sql)?;
+            collect_rows(&mut stmt, &self.io, &self.state, &mut zero_stats())?
+        };
+        let table = quote_identifier(name);
+        let mut delete = conn.prepare(format!("DELETE FROM {}", table))?;
+        run_to_completion(&mut delete, &self.io, &self.state)?;
+        let Some(first) = rows.first() else {
+            return Ok(0);
+        };
+        let placeholders = vec!["?"; first.len()].join(", ");
+        let mut insert =
+            conn.prepare(format!("INSERT INTO {} VALUES ({})", table, placeholders))?;
+        let count = rows.len() as u64;
+        for row in rows {
+            for (i, value) in row.into_iter().enumerate() {
+                insert.bind_at(NonZeroUsize::new(i + 1).unwrap(), value.into());
+            }
+            run_to_completion(&mut insert, &self.io, &self.state)?;
+            insert.reset();
+        }
+        Ok(count)
+    }
+
+    /// Run `f` in a transaction, committed if it succeeds and rolled back if it fails.
+    /// `what` names the work in the log if rolling back fails too.
+    fn transaction<T>(
+        &self,
+        what: &str,
+        f: impl FnOnce() -> Result<T, DbError>,
+    ) -> Result<T, DbError> {
+        self.query_all("BEGIN".to_string())?;
+        match f() {
+            Ok(value) => {
+                self.query_all("COMMIT".to_string())?;
+                Ok(value)
+            }
+            Err(e) => {
+                // the error that stopped the work matters more than one from rolling back
+                if let Err(rollback) = self.query_all("ROLLBACK".to_string()) {
+                    log::warn(&format!("rolling back {} failed: {:?}", what, rollback));
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Insert or update each of `rows`, see `upsert` in the WIT.
+    fn upsert_rows(
+        &self,
+        table: &str,
+        keys: &[usize],
+        columns: &[String],
+        rows: Vec<Vec<RecordValue>>,
+    ) -> Result<UpsertCounts, DbError> {
+        let table = quote_identifier(table);
+        let quoted: Vec<String> = columns.iter().map(|c| quote_identifier(c)).collect();
+        let key_columns: Vec<&str> = keys.iter().map(|&k| quoted[k].as_str()).collect();
+        let updates: Vec<String> = (0..columns.len())
+            .filter(|i| !keys.contains(i))
+            .map(|i| format!("{} = excluded.{}", quoted[i], quoted[i]))
+            .collect();
+        let action = if updates.is_empty() {
+            "NOTHING".to_string()
+        } else {
+            format!("UPDATE SET {}", updates.join(", "))
+        };
+        let upsert = format!(
+            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO {}",
+            table,
+            quoted.join(", "),
+            vec!["?"; columns.len()].join(", "),
+            key_columns.join(", "),
+            action
+        );
+        let exists = format!(
+            "SELECT 1 FROM {} WHERE {}",
+            table,
+            key_columns
+                .iter()
+                .map(|column| format!("{} IS ?", column))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        );
+
+        self.check_writable(&upsert)?;
+        // a row outside the table's filter reads as missing, and is left as it is
+        let filters = self.state.filters.borrow();
+        let conn = self.conn()?;
+        let mut exists = conn.prepare(sql::apply_filters(&exists, &filters)?)?;
+        let mut upsert = conn.prepare(sql::apply_filters(&upsert, &filters)?)?;
+        drop(filters);
+        let mut busy = BusyWait::new(&self.state);
+        let mut counts = UpsertCounts {
+            inserted: 0,
+            updated: 0,
+        };
+        for row in rows {
+            for (i, &key) in keys.iter().enumerate() {
+                exists.bind_at(NonZeroUsize::new(i + 1).unwrap(), row[key].clone().into());
+            }
+            let existed = next_row(&mut exists, &self.io, &mut busy, &mut zero_stats())?.is_some();
+            exists.reset();
+            for (i, value) in row.into_iter().enumerate() {
+                upsert.bind_at(NonZeroUsize::new(i + 1).unwrap(), value.into());
+            }
+            run_to_completion(&mut upsert, &self.io, &self.state)?;
+            upsert.reset();
+            if existed {
+                counts.updated += 1;
+            } else {
+                counts.inserted += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Create the table `create` makes, with its indexes from `objects`, and fill it with the
+    /// rows of `src`, returning how many there were. `replace` drops the table already here.
+    /// `raw` copies the table as it is stored, without the rewrites either connection applies
+    /// to its SQL, `src`'s table filters among them, as a snapshot has to.
+    fn copy_rows(
+        &self,
+        src: &Component,
+        create: &SchemaObject,
+        objects: &[SchemaObject],
+        replace: bool,
+        raw: bool,
+    ) -> Result<u64, DbError> {
+        let run = |sql: String| match raw {
+            true => self.run_raw(sql),
+            false => self.query_all(sql).map(|_| ()),
+        };
+        let table = quote_identifier(&create.name);
+        if replace {
+            run(format!("DROP TABLE {}", table))?;
+        }
+        run(create.sql.clone())?;
+        for index in objects.iter().filter(|object| {
+            object.kind == "index" && object.table.eq_ignore_ascii_case(&create.name)
+        }) {
+            run(index.sql.clone())?;
+        }
+
+        // the rows go straight from one statement to the other, never all held at once
+        let select = format!("SELECT * FROM {}", table);
+        let select = match raw {
+            true => select,
+            false => sql::apply_filters(&select, &src.state.filters.borrow())?,
+        };
+        let mut read = src.conn()?.prepare(select)?;
+        let placeholders = vec!["?"; read.num_columns()].join(", ");
+        let mut write = self
+            .conn()?
+            .prepare(format!("INSERT INTO {} VALUES ({})", table, placeholders))?;
+        let mut busy = BusyWait::new(&src.state);
+        let mut stats = zero_stats();
+        let mut copied = 0;
+        while let Some(row) = next_row(&mut read, &src.io, &mut busy, &mut stats)? {
+            for (i, value) in row.into_iter().enumerate() {
+                write.bind_at(NonZeroUsize::new(i + 1).unwrap(), value.into());
+            }
+            run_to_completion(&mut write, &self.io, &self.state)?;
+            write.reset();
+            copied += 1;
+        }
+        metrics::add(&src.state.metrics.rows_read, copied);
+        Ok(copied)
+    }
+
+    /// Prepare `sql`, a single statement that produces no rows, and run it as it is, without
+    /// [Component::rewrite] or any of the checks [Component::run_sql] makes.
+    fn run_raw(&self, sql: String) -> Result<(), DbError> {
+        let mut stmt = self.conn()?.prepare(sql)?;
+        run_to_completion(&mut stmt, &self.io, &self.state)
+    }
+
+    /// The tables, indexes, views and triggers created in the database, in creation order.
+    fn schema_objects(&self) -> Result<Vec<SchemaObject>, DbError> {
+        // internal tables and automatic indexes have no SQL of their own
+        Ok(self
+            .schema()?
+            .iter()
+            .filter(|row| !row.is_internal())
+            .filter_map(|row| {
+                Some(SchemaObject {
+                    kind: row.kind.clone(),
+                    name: row.name.clone(),
+                    table: row.table.clone(),
+                    sql: row.sql.clone()?,
+                })
+            })
+            .collect())
+    }
+
+    /// The rows of `sqlite_schema`, in creation order, read again only once the schema version
+    /// has changed since they were last read.
+    fn schema(&self) -> Result<Rc<Vec<SchemaRow>>, DbError> {
+        let version = self.schema_version()?;
+        if let Some(rows) = self.state.schema.get(version) {
+            return Ok(rows);
+        }
+        let sql = "SELECT type, name, tbl_name, rootpage, sql FROM sqlite_schema";
+        let rows: Vec<_> = self
+            .query_all(sql.to_string())?
+            .into_iter()
+            .map(|row| {
+                use RecordValue as V;
+                match &row[..] {
+                    [V::Text(kind), V::Text(name), V::Text(table), root, sql] => Ok(SchemaRow {
+                        kind: kind.clone(),
+                        name: name.clone(),
+                        table: table.clone(),
+                        root: match root {
+                            V::Integer(root) => *root,
+                            _ => 0,
+                        },
+                        sql: match sql {
+                            V::Text(sql) => Some(sql.clone()),
+                            _ => None,
+                        },
+                    }),
+                    _ => Err(DbError::Other(format!(
+                        "Unexpected sqlite_schema row: {:?}",
+                        row
+                    ))),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let rows = Rc::new(rows);
+        self.state.schema.set(version, rows.clone());
+        Ok(rows)
+    }
+
+    /// Every table and index by its root page, with the table it belongs to, for reading
+    /// plans with [plan].
+    fn root_pages(&self) -> Result<Vec<(i64, String)>, DbError> {
+        Ok(self
+            .schema()?
+            .iter()
+            .filter(|row| row.root > 0)
+            .map(|row| (row.root, row.table.clone()))
+            .collect())
+    }
 }
 
 impl GuestDatabase for Component {
+    /// A constructor can't fail, so a database that doesn't open is returned closed, with
+    /// every call returning why.
     fn new(path: String) -> Self {
-        match path.as_str() {
-            ":memory:" => {
-                let io: Arc<dyn limbo_core::IO> = Arc::new(MemoryIO::new().unwrap());
-
-                let file = io
-                    .open_file(&path, limbo_core::OpenFlags::Create, false)
-                    .unwrap();
-
-                maybe_init_database_file(&file, &io).unwrap();
-                let page_io = Rc::new(DatabaseStorage::new(file));
-                let db_header = Pager::begin_open(page_io.clone()).unwrap();
-
-                // ensure db header is there
-                io.run_once().unwrap();
-
-                let page_size = db_header.borrow().page_size;
-
-                let wal_path = format!("{}-wal", path);
-                let wal_shared =
-                    WalFileShared::open_shared(&io, wal_path.as_str(), page_size).unwrap();
-                let buffer_pool = Rc::new(BufferPool::new(page_size as usize));
-                let wal = Rc::new(RefCell::new(WalFile::new(
-                    io.clone(),
-                    db_header.borrow().page_size as usize,
-                    wal_shared.clone(),
-                    buffer_pool.clone(),
+        Self::open_uri(&path, OpenFlags::CREATE).unwrap_or_else(|e| {
+            Self::open_uri(":memory:", OpenFlags::CREATE)
+                .expect("an in-memory database needs no storage to open")
+                .failed(e)
+        })
+    }
+
+    fn open_with(path: String, flags: OpenFlags) -> Result<WitDatabase, DbError> {
+        Ok(WitDatabase::new(Self::open_uri(&path, flags)?))
+    }
+
+    fn exec(&self, sql: String) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        self.state.track(|| {
+            if self.state.batch.borrow().is_some() {
+                if !sql::is_readonly(&sql) {
+                    check_supported(&sql)?;
+                    let due = self
+                        .state
+                        .batch
+                        .borrow_mut()
+                        .as_mut()
+                        .is_some_and(|batch| batch.push(sql, now() / 1000));
+                    if due {
+                        self.flush()?;
+                    }
+                    return Ok(vec![]);
+                }
+                self.flush()?;
+            }
+            self.run_script(&sql)
+        })
+    }
+
+    fn prepare(&self, sql: String) -> Result<WitStatement, DbError> {
+        self.state.track(|| self.prepare_sql(sql, true))
+    }
+
+    fn prepare_tail(&self, sql: String) -> Result<Prepared, DbError> {
+        self.state.track(|| {
+            let (first, tail) = sql::split_first(&sql);
+            Ok(Prepared {
+                statement: self.prepare(first.to_string())?,
+                tail: tail.to_string(),
+            })
+        })
+    }
+
+    fn query_json(&self, sql: String, params: String) -> Result<String, DbError> {
+        self.state.track(|| {
+            let statement = self.prepare(sql)?;
+            let statement = statement.get::<InnerStatement>();
+            if !params.trim().is_empty() {
+                statement.bind_json(params)?;
+            }
+            statement.all_json()
+        })
+    }
+
+    fn clone_handle(&self) -> WitDatabase {
+        // share the storage, but give the new handle its own connection
+        WitDatabase::new(self.new_connection())
+    }
+
+    fn subscribe(&self, sql: String, params: Vec<RecordValue>) -> Result<WitSubscription, DbError> {
+        self.state.track(|| {
+            if !sql::is_readonly(&sql) {
+                return Err(DbError::Misuse(
+                    "only queries can be subscribed to".to_string(),
+                ));
+            }
+            let tables = self.tables_read(&sql)?;
+            // the subscription runs on this connection, so it sees what this handle would
+            let db = Component::of(&self.state, &self.io);
+            Ok(WitSubscription::new(subscription::Subscription::new(
+                db, sql, params, tables,
+            )?))
+        })
+    }
+
+    fn create_materialized_view(
+        &self,
+        name: String,
+        sql: String,
+        refresh: RefreshMode,
+    ) -> Result<(), DbError> {
+        self.state
+            .track(|| matview::create(self, name, sql, refresh))
+    }
+
+    fn refresh_view(&self, name: String) -> Result<u64, DbError> {
+        self.state.track(|| matview::refresh_named(self, &name))
+    }
+
+    fn snapshot(&self) -> Result<u64, DbError> {
+        self.state.track(|| {
+            // limbo_core can't read at an older WAL frame, so the snapshot is a copy in memory
+            let copy = Component::new(":memory:".to_string());
+            let objects = self.schema_objects()?;
+            copy.transaction("snapshot", || {
+                for object in &objects {
+                    match object.kind.as_str() {
+                        "table" => {
+                            copy.copy_rows(self, object, &objects, false, true)?;
+                        }
+                        "view" => {
+                            copy.run_raw(object.sql.clone())?;
+                        }
+                        // indexes come with their tables, and triggers would never fire on a copy
+                        // that is only read
+                        _ => {}
+                    }
+                }
+                Ok(())
+            })?;
+            Ok(self.state.snapshots.add(copy))
+        })
+    }
+
+    fn query_as_of(&self, sql: String, snapshot_id: u64) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        self.state.track(|| {
+            let Some(copy) = self.state.snapshots.get(snapshot_id) else {
+                return Err(DbError::Misuse(format!(
+                    "there is no snapshot {}",
+                    snapshot_id
+                )));
+            };
+            if !sql::is_readonly(&sql) {
+                return Err(DbError::Misuse(
+                    "a snapshot can only be queried".to_string(),
+                ));
+            }
+            // the copy holds every row, so this connection's filters apply as it reads it
+            self.check_filtered(&sql)?;
+            copy.query_all(sql::apply_filters(&sql, &self.state.filters.borrow())?)
+        })
+    }
+
+    fn release_snapshot(&self, snapshot_id: u64) -> bool {
+        self.state.snapshots.release(snapshot_id)
+    }
+
+    fn enable_audit(&self, table: String) -> Result<(), DbError> {
+        self.state.track(|| {
+            let objects = self.schema_objects()?;
+            let Some(create) = objects
+                .iter()
+                .find(|object| object.kind == "table" && object.name.eq_ignore_ascii_case(&table))
+            else {
+                return Err(DbError::Misuse(format!("no such table: {}", table)));
+            };
+            self.transaction(&format!("enable-audit of {:?}", table), || {
+                for sql in [
+                    format!(
+                        "CREATE TABLE IF NOT EXISTS {} (id INTEGER PRIMARY KEY, \
+                         table_name TEXT NOT NULL, row_id INTEGER NOT NULL, op TEXT NOT NULL, \
+                         old TEXT, new TEXT, at INTEGER NOT NULL)",
+                        audit::LOG
+                    ),
+                    format!(
+                        "CREATE INDEX IF NOT EXISTS {}_row ON {} (table_name, row_id)",
+                        audit::LOG,
+                        audit::LOG
+                    ),
+                    format!(
+                        "CREATE TABLE IF NOT EXISTS {} (name TEXT PRIMARY KEY)",
+                        audit::AUDITED
+                    ),
+                ] {
+                    self.query_all(sql)?;
+                }
+                self.query_with(
+                    &format!("INSERT OR IGNORE INTO {} (name) VALUES (?)", audit::AUDITED),
+                    vec![RecordValue::Text(create.name.clone())],
+                )?;
+                Ok(())
+            })
+        })
+    }
+
+    fn set_compress_pages(&self, enabled: bool) -> Result<(), DbError> {
+        self.state.track(|| {
+            let Some(compress_pages) = &self.state.compress_pages else {
+                return Err(DbError::Misuse(
+                    "only databases stored through host-kv can compress their pages".to_string(),
+                ));
+            };
+            if enabled && !cfg!(feature = "compress-pages") {
+                return Err(DbError::Misuse(
+                    "compressing pages needs the component built with the compress-pages feature"
+                        .to_string(),
+                ));
+            }
+            compress_pages.set(enabled);
+            Ok(())
+        })
+    }
+
+    fn set_readahead_pages(&self, pages: u32) -> Result<(), DbError> {
+        self.state.track(|| {
+            let Some(readahead_pages) = &self.state.readahead_pages else {
+                return Err(DbError::Misuse(
+                    "only databases stored through host-fs read ahead".to_string(),
+                ));
+            };
+            readahead_pages.set(pages);
+            Ok(())
+        })
+    }
+
+    fn set_temp_store(&self, store: TempStore) -> Result<(), DbError> {
+        self.state.track(|| self.state.temp.set_store(store))
+    }
+
+    fn set_coalesce_writes(&self, enabled: bool) -> Result<(), DbError> {
+        self.state.track(|| {
+            let Some(coalesce_writes) = &self.state.coalesce_writes else {
+                return Err(DbError::Misuse(
+                    "an in-memory database has no writes to coalesce".to_string(),
+                ));
+            };
+            coalesce_writes.set(enabled);
+            Ok(())
+        })
+    }
+
+    fn export_ndjson(&self, table: String) -> Result<WitNdjsonExporter, DbError> {
+        self.state.track(|| {
+            let statement = self.prepare(format!("SELECT * FROM {}", quote_identifier(&table)))?;
+            let statement = statement.get::<InnerStatement>();
+            let columns = statement.column_names();
+            let options = self.state.json_options.borrow().clone();
+            Ok(WitNdjsonExporter::new(ndjson::NdjsonExporter::new(
+                statement.rows()?,
+                columns,
+                options,
+            )))
+        })
+    }
+
+    fn audit_history(&self, table: String, rowid: i64) -> Result<Vec<AuditEntry>, DbError> {
+        self.state.track(|| {
+            let Some(table) = self.state.audited.name(self, &table)? else {
+                return Err(DbError::Misuse(format!("{} is not audited", table)));
+            };
+            let sql = format!(
+                "SELECT op, old, new, at FROM {} WHERE table_name = ? AND row_id = ? ORDER BY id",
+                audit::LOG
+            );
+            self.query_with(
+                &sql,
+                vec![RecordValue::Text(table), RecordValue::Integer(rowid)],
+            )?
+            .into_iter()
+            .map(|row| {
+                use RecordValue as V;
+                let json = |value: &V| match value {
+                    V::Text(json) => Some(json.clone()),
+                    _ => None,
+                };
+                let op = match &row[..] {
+                    [V::Text(op), ..] => match op.as_str() {
+                        "insert" => Some(AuditOp::Insert),
+                        "update" => Some(AuditOp::Update),
+                        "delete" => Some(AuditOp::Delete),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match (op, &row[..]) {
+                    (Some(op), [_, old, new, V::Integer(at)]) => Ok(AuditEntry {
+                        op,
+                        old: json(old),
+                        new: json(new),
+                        at: *at as u64,
+                    }),
+                    _ => Err(DbError::Corrupt(format!(
+                        "{} has an unexpected row: {:?}",
+                        audit::LOG,
+                        row
+                    ))),
+                }
+            })
+            .collect()
+        })
+    }
+
+    fn begin_batched(&self, max_delay_ms: u32, max_ops: u32) -> Result<(), DbError> {
+        self.state.track(|| {
+            self.conn()?;
+            let mut batch = self.state.batch.borrow_mut();
+            if batch.is_some() {
+                return Err(DbError::Misuse("writes are already batched".to_string()));
+            }
+            *batch = Some(Batch::new(max_delay_ms, max_ops));
+            Ok(())
+        })
+    }
+
+    fn flush(&self) -> Result<u32, DbError> {
+        self.state.track(|| {
+            let queued = match self.state.batch.borrow_mut().as_mut() {
+                Some(batch) => batch.take(),
+                None => return Ok(0),
+            };
+            if queued.is_empty() {
+                return Ok(0);
+            }
+            self.transaction("a batch", || {
+                for sql in &queued {
+                    self.run_script(sql)?;
+                }
+                Ok(queued.len() as u32)
+            })
+        })
+    }
+
+    fn end_batched(&self) -> Result<u32, DbError> {
+        self.state.track(|| {
+            let flushed = self.flush()?;
+            self.state.batch.replace(None);
+            Ok(flushed)
+        })
+    }
+
+    fn tick(&self) -> Result<TickWork, DbError> {
+        self.state.track(|| {
+            let late = self
+                .state
+                .batch
+                .borrow()
+                .as_ref()
+                .is_some_and(|batch| batch.is_late(now() / 1000));
+            let flushed = if late { self.flush()? } else { 0 };
+            let checkpointed = !self.state.in_transaction.get() && self.state.uncheckpointed.get();
+            if checkpointed {
+                self.checkpoint()?;
+            }
+            let trimmed = heap::is_pressed() && self.state.query_cache.clear();
+            Ok(TickWork {
+                flushed,
+                checkpointed,
+                trimmed,
+            })
+        })
+    }
+
+    fn close(&self) -> Result<(), DbError> {
+        self.state.track(|| {
+            self.flush()?;
+            self.state.temp.clear(self)?;
+            // move committed frames from the WAL into the database file before letting go
+            self.checkpoint()?;
+            self.state.conn.borrow_mut().take();
+            Ok(())
+        })
+    }
+
+    fn insert_blob(
+        &self,
+        table: String,
+        column: String,
+        rowid: i64,
+        len: u64,
+    ) -> Result<WitBlobWriter, DbError> {
+        self.state.track(|| {
+            self.conn()?;
+            let writer = blob::BlobWriter::new(self, &table, &column, rowid, len)?;
+            self.check_writable(writer.sql())?;
+            Ok(WitBlobWriter::new(writer))
+        })
+    }
+
+    fn is_readonly_sql(&self, sql: String) -> bool {
+        sql::is_readonly(&sql)
+    }
+
+    fn set_table_filter(&self, table: String, predicate: String) -> Result<(), DbError> {
+        self.state.track(|| {
+            // the rows cached from the table were read through the filter before
+            self.state.query_cache.forget_table(&table);
+            let key = table.to_ascii_lowercase();
+            if predicate.trim().is_empty() {
+                self.state.filters.borrow_mut().remove(&key);
+                return Ok(());
+            }
+            // prepare it once, so a mistake fails here rather than in every later query
+            self.conn()?.prepare(format!(
+                "SELECT 1 FROM {} WHERE ({})",
+                quote_identifier(&table),
+                predicate
+            ))?;
+            self.state.filters.borrow_mut().insert(key, predicate);
+            Ok(())
+        })
+    }
+
+    fn set_defensive(&self, on: bool) {
+        self.state.defensive.set(on);
+    }
+
+    fn set_unicode_case(&self, on: bool) {
+        self.state.unicode_case.set(on);
+    }
+
+    fn set_busy_timeout(&self, ms: u32) {
+        self.state.busy_timeout.set(ms);
+    }
+
+    fn set_json_options(&self, options: JsonOptions) {
+        self.state.json_options.replace(options);
+    }
+
+    fn set_non_finite_floats(&self, policy: NonFiniteFloats) {
+        self.state.non_finite_floats.set(policy);
+        self.state.query_cache.clear();
+    }
+
+    fn set_query_cache(&self, max_entries: u32, max_bytes: u64) {
+        self.state.query_cache.set_limits(max_entries, max_bytes);
+    }
+
+    fn cancel_running(&self) {
+        self.state.cancels.set(self.state.cancels.get() + 1);
+    }
+
+    fn set_limit(&self, kind: LimitKind, value: u32) -> u32 {
+        let mut limits = self.state.limits.get();
+        let limit = match kind {
+            LimitKind::SqlLength => &mut limits.sql_length,
+            LimitKind::Column => &mut limits.column,
+            LimitKind::ExprDepth => &mut limits.expr_depth,
+        };
+        let previous = std::mem::replace(limit, value);
+        self.state.limits.set(limits);
+        previous
+    }
+
+    fn limits(&self) -> Limits {
+        self.state.limits.get()
+    }
+
+    fn metrics_prometheus(&self) -> String {
+        self.state.metrics.prometheus()
+    }
+
+    fn last_error(&self) -> Option<DbError> {
+        self.state.last_error.borrow().clone()
+    }
+
+    fn last_error_code(&self) -> Option<u32> {
+        self.state.last_error.borrow().as_ref().map(error::code)
+    }
+
+    fn set_foreign_keys(&self, on: bool) -> Result<(), DbError> {
+        // limbo parses REFERENCES clauses but never enforces them, so they are always off
+        match on {
+            true => Err(error::unsupported("enforcing foreign keys")),
+            false => Ok(()),
+        }
+    }
+
+    fn foreign_key_check(&self, table: Option<String>) -> Result<Vec<FkViolation>, DbError> {
+        self.state
+            .track(|| foreign_keys::check(self, table.as_deref()))
+    }
+
+    fn integrity_check(&self, max_errors: u32) -> Result<Vec<String>, DbError> {
+        self.state.track(|| integrity::check(self, max_errors))
+    }
+
+    fn triggers(&self, table: Option<String>) -> Result<Vec<TriggerInfo>, DbError> {
+        self.state.track(|| {
+            Ok(self
+                .schema()?
+                .iter()
+                .filter(|row| row.kind == "trigger")
+                .filter(|row| {
+                    table
+                        .as_ref()
+                        .map_or(true, |table| row.table.eq_ignore_ascii_case(table))
+                })
+                .map(|row| TriggerInfo {
+                    name: row.name.clone(),
+                    table: row.table.clone(),
+                    sql: row.sql.clone(),
+                })
+                .collect())
+        })
+    }
+
+    fn columns(&self, table: String) -> Result<Vec<ColumnInfo>, DbError> {
+        self.state.track(|| {
+            let schema = self.schema()?;
+            let generated = match schema
+                .iter()
+                .find(|row| row.kind == "table" && row.name.eq_ignore_ascii_case(&table))
+            {
+                Some(row) => row.sql.as_deref().map_or(vec![], sql::generated_columns),
+                None => return Err(DbError::Misuse(format!("no such table: {}", table))),
+            };
+            let sql = format!("PRAGMA table_info({})", quote_identifier(&table));
+            // each row is: cid, name, type, notnull, dflt_value, pk
+            self.query_all(sql)?
+                .into_iter()
+                .map(|row| {
+                    use RecordValue as V;
+                    match &row[..] {
+                        [
+                            _,
+                            V::Text(name),
+                            decl_type,
+                            V::Integer(not_null),
+                            default,
+                            V::Integer(pk),
+                        ] => {
+                            Ok(ColumnInfo {
+                                name: name.clone(),
+                                decl_type: match decl_type {
+                                    V::Text(t) if !t.is_empty() => Some(t.clone()),
+                                    _ => None,
+                                },
+                                not_null: *not_null != 0,
+                                default_expr: match default {
+                                    V::Text(default) => Some(default.clone()),
+                                    _ => None,
+                                },
+                                primary_key: *pk as u32,
+                                generated: generated
+                                    .iter()
+                                    .find(|(column, _)| column.eq_ignore_ascii_case(name))
+                                    .map(|(_, kind)| *kind),
+                            })
+                        }
+                        _ => Err(DbError::Other(format!(
+                            "Unexpected table_info row: {:?}",
+                            row
+                        ))),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn copy_table(
+        &self,
+        src: DatabaseBorrow<'_>,
+        table: String,
+        overwrite: bool,
+    ) -> Result<u64, DbError> {
+        self.state.track(|| {
+            let src = src.get::<Component>();
+            let objects = src.schema_objects()?;
+            let create = objects
+                .iter()
+                .find(|object| object.kind == "table" && object.name.eq_ignore_ascii_case(&table))
+                .ok_or_else(|| DbError::Misuse(format!("no such table: {}", table)))?;
+            let exists = self
+                .schema_objects()?
+                .iter()
+                .any(|object| object.kind == "table" && object.name.eq_ignore_ascii_case(&table));
+            if exists && !overwrite {
+                return Err(DbError::Misuse(format!(
+                    "table {} already exists, pass overwrite to replace it",
+                    table
                 )));
+            }
 
-                let db =
-                    limbo_core::Database::open(io, page_io, wal, wal_shared, buffer_pool).unwrap();
+            self.transaction(&format!("copy-table of {:?}", table), || {
+                self.copy_rows(src, create, &objects, exists, false)
+            })
+        })
+    }
 
-                let conn = db.connect();
-                Self { inner: db, conn }
+    fn upsert(
+        &self,
+        table: String,
+        key_columns: Vec<String>,
+        columns: Vec<String>,
+        rows: Vec<Vec<RecordValue>>,
+    ) -> Result<UpsertCounts, DbError> {
+        self.state.track(|| {
+            if key_columns.is_empty() {
+                return Err(DbError::Misuse(
+                    "upsert needs at least one key column".to_string(),
+                ));
             }
-            _ => todo!(),
+            let keys = key_columns
+                .iter()
+                .map(|key| {
+                    columns
+                        .iter()
+                        .position(|column| column.eq_ignore_ascii_case(key))
+                        .ok_or_else(|| {
+                            DbError::Misuse(format!("key column {} is not one of the columns", key))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(row) = rows.iter().find(|row| row.len() != columns.len()) {
+                return Err(DbError::Misuse(format!(
+                    "a row has {} values for {} columns",
+                    row.len(),
+                    columns.len()
+                )));
+            }
+            rows.iter().flatten().try_for_each(check_decimal)?;
+            let changes = sql::Changes::Table(table.clone());
+            self.transaction(&format!("upsert into {:?}", table), || {
+                self.state.before_write(&changes);
+                let before = audit::before(self, &changes)?;
+                let counts = self.upsert_rows(&table, &keys, &columns, rows)?;
+                audit::record(self, before)?;
+                self.changed(&changes);
+                Ok(counts)
+            })
+        })
+    }
+
+    fn diff_schema(&self, other: DatabaseBorrow<'_>) -> Result<Vec<String>, DbError> {
+        self.state.track(|| {
+            let to = other.get::<Component>().schema_objects()?;
+            Ok(schema::diff(&self.schema_objects()?, &to))
+        })
+    }
+
+    fn estimate(&self, sql: String) -> Result<QueryEstimate, DbError> {
+        self.state.track(|| {
+            use RecordValue as V;
+            check_supported(&sql)?;
+            let explain = self.query_all(format!("EXPLAIN {}", sql))?;
+            let plan = plan::read(&explain, &self.root_pages()?)?;
+
+            // each scanned table multiplies the rows considered by its size, as nested loops do;
+            // lookups by key are taken to find one row
+            let mut estimated_rows = 1u64;
+            for table in &plan.scanned {
+                let count = format!("SELECT count(*) FROM {}", quote_identifier(table));
+                let rows = match &self.query_all(count)?[..] {
+                    [row] => match row[..] {
+                        [V::Integer(n)] => n as u64,
+                        _ => 0,
+                    },
+                    _ => 0,
+                };
+                estimated_rows = estimated_rows.saturating_mul(rows);
+            }
+            Ok(QueryEstimate {
+                estimated_rows,
+                full_scan: !plan.scanned.is_empty(),
+                scanned_tables: plan.scanned,
+                searched_tables: plan.searched,
+            })
+        })
+    }
+
+    fn views(&self) -> Result<Vec<ViewInfo>, DbError> {
+        self.state.track(|| {
+            let mut views: Vec<_> = self
+                .schema()?
+                .iter()
+                .filter(|row| row.kind == "view")
+                .map(|row| ViewInfo {
+                    name: row.name.clone(),
+                    sql: row.sql.clone(),
+                    tables: row.sql.as_deref().map_or(vec![], sql::referenced_tables),
+                })
+                .collect();
+            views.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(views)
+        })
+    }
+
+    fn schema_version(&self) -> Result<u32, DbError> {
+        self.state.track(|| {
+            let rows = self.query_all("PRAGMA schema_version".to_string())?;
+            match &rows[..] {
+                [row] => match row[..] {
+                    [RecordValue::Integer(version)] => Ok(version as u32),
+                    _ => Err(DbError::Other(format!(
+                        "Unexpected schema_version row: {:?}",
+                        row
+                    ))),
+                },
+                _ => Err(DbError::Other(format!(
+                    "Unexpected schema_version rows: {:?}",
+                    rows
+                ))),
+            }
+        })
+    }
+}
+
+/// A prepared statement, shared by its `statement` resource and the `rows` iterating it.
+struct SharedStatement {
+    /// The statement, `None` once it has been finalized.
+    stmt: RefCell<Option<limbo_core::Statement>>,
+    /// Counts the runs of the statement, so rows know when a later run has ended them.
+    run: Cell<u64>,
+    /// Whether the statement has been stepped since it was last reset.
+    stepped: Cell<bool>,
+    /// What the current or last run did, see `last-run-stats`.
+    stats: RefCell<RunStats>,
+    /// What a run of the statement can change, for the database's subscriptions.
+    changes: sql::Changes,
+}
+
+impl SharedStatement {
+    /// The statement, or [DbError::Closed] once it has been finalized.
+    fn stmt(&self) -> Result<RefMut<'_, limbo_core::Statement>, DbError> {
+        RefMut::filter_map(self.stmt.borrow_mut(), Option::as_mut)
+            .map_err(|_| error::closed("statement"))
+    }
+
+    /// Start a new run, ending any earlier one that is still part way through.
+    fn start(&self) -> u64 {
+        if self.stepped.get() {
+            self.reset();
         }
+        self.stepped.set(true);
+        self.stats.replace(zero_stats());
+        self.run.set(self.run.get() + 1);
+        self.run.get()
     }
 
-    fn exec(&self, sql: String) {
-        self.conn.execute(sql).unwrap();
+    /// Call `f` to step the current run, adding what it did and how long it took to its stats.
+    fn timed<T>(&self, f: impl FnOnce(&mut RunStats) -> T) -> T {
+        let started = now();
+        let mut stats = self.stats.borrow_mut();
+        let result = f(&mut stats);
+        stats.elapsed_micros += now().saturating_sub(started);
+        result
     }
 
-    fn prepare(&self, sql: String) -> WitStatement {
-        let stmt = self.conn.prepare(sql).unwrap();
-        let inner_stmt = InnerStatement::new(stmt, false);
-        WitStatement::new(inner_stmt)
+    /// Rewind the statement so it can be bound and run again.
+    fn reset(&self) {
+        if let Some(stmt) = self.stmt.borrow_mut().as_mut() {
+            stmt.reset();
+        }
+        self.stepped.set(false);
     }
 }
 
 struct InnerStatement {
-    inner: RefCell<limbo_core::Statement>,
+    shared: Rc<SharedStatement>,
+    /// The SQL the statement was prepared from.
+    sql: String,
+    /// The values bound so far, by parameter index.
+    params: RefCell<BTreeMap<usize, RecordValue>>,
+    io: Arc<dyn limbo_core::IO>,
+    state: Rc<ConnState>,
+    /// The run `all-chunked` is part way through, if any.
+    chunked: RefCell<Option<InnerRows>>,
     raw: bool,
+    /// What to bind in place of the values bound to some parameters, see [ParamFunctions].
+    functions: ParamFunctions,
+    /// Whether each run prepares the SQL again, see [Component::prepared_per_run].
+    per_run: bool,
 }
 
 impl InnerStatement {
-    fn new(stmt: limbo_core::Statement, raw: bool) -> Self {
+    fn new(
+        stmt: limbo_core::Statement,
+        sql: String,
+        io: Arc<dyn limbo_core::IO>,
+        state: Rc<ConnState>,
+        raw: bool,
+    ) -> Self {
         Self {
-            inner: RefCell::new(stmt),
+            shared: Rc::new(SharedStatement {
+                stmt: RefCell::new(Some(stmt)),
+                run: Cell::new(0),
+                stepped: Cell::new(false),
+                stats: RefCell::new(zero_stats()),
+                changes: sql::changes(&sql),
+            }),
+            sql,
+            params: RefCell::new(BTreeMap::new()),
+            io,
+            state,
+            chunked: RefCell::new(None),
             raw,
+            functions: ParamFunctions::new(),
+            per_run: false,
+        }
+    }
+
+    /// The statement, or [DbError::Closed] once it has been finalized.
+    fn stmt(&self) -> Result<RefMut<'_, limbo_core::Statement>, DbError> {
+        self.shared.stmt()
+    }
+
+    /// The index of the parameter `name`, which may leave out its `:`, `@` or `$` prefix.
+    fn parameter_index(&self, name: &str) -> Result<Option<NonZeroUsize>, DbError> {
+        let stmt = self.stmt()?;
+        let parameters = stmt.parameters();
+        Ok(parameters.index(name).or_else(|| {
+            [":", "@", "$"]
+                .iter()
+                .find_map(|prefix| parameters.index(format!("{}{}", prefix, name)))
+        }))
+    }
+
+    /// Prepare the statement again if it has to be for each run, so the guest works out what
+    /// it does from the values as they are for this run, binding what was bound to it before.
+    fn refresh(&self) -> Result<(), DbError> {
+        self.stmt()?;
+        if !self.per_run {
+            return Ok(());
         }
+        let db = Component::of(&self.state, &self.io);
+        let params = self.params.borrow();
+        let (filtered, _) = db.rewrite(&self.sql, Some(&params))?;
+        let mut stmt = {
+            let _span = Span::start("limbo.prepare");
+            db.conn()?
+                .prepare(filtered)
+                .map_err(|e| error::located(e.into(), &self.sql))?
+        };
+        for (&index, value) in params.iter() {
+            let bound = match self.functions.get(&index) {
+                Some(function) => function(value),
+                None => value.clone(),
+            };
+            stmt.bind_at(NonZeroUsize::new(index).unwrap(), bound.into());
+        }
+        self.shared.stmt.replace(Some(stmt));
+        self.shared.stepped.set(false);
+        Ok(())
+    }
+
+    /// Start a new run of the statement.
+    fn rows(&self) -> Result<InnerRows, DbError> {
+        self.refresh()?;
+        self.state.before_write(&self.shared.changes);
+        let before = audit::before(&Component::of(&self.state, &self.io), &self.shared.changes)?;
+        metrics::add(&self.state.metrics.queries, 1);
+        let run = self.shared.start();
+        let cursor = matches!(self.shared.changes, sql::Changes::Nothing).then(|| {
+            let cursor = Cursor::new(
+                self.shared.clone(),
+                self.io.clone(),
+                self.state.clone(),
+                run,
+            );
+            self.state.cursors.add(&cursor);
+            cursor
+        });
+        Ok(InnerRows {
+            run,
+            cursor,
+            shared: self.shared.clone(),
+            io: self.io.clone(),
+            cancels: self.state.cancels.get(),
+            state: self.state.clone(),
+            done: Cell::new(false),
+            before: RefCell::new(before),
+        })
     }
 }
 
 impl GuestStatement for InnerStatement {
-    fn all(&self) -> Vec<Vec<RecordValue>> {
-        let mut ret = vec![];
-        loop {
-            let mut stmt = self.inner.borrow_mut();
-            match stmt.step() {
-                Ok(limbo_core::StepResult::Row) => {
-                    let row = stmt.row().unwrap();
-                    let mut row_array = vec![];
-                    for value in row.get_values() {
-                        let value = value.to_value();
-                        //let value = to_js_value(value);
-                        row_array.push(value.into());
+    fn bind(&self, index: u32, value: RecordValue) -> Result<(), DbError> {
+        self.state.track(|| {
+            let index = NonZeroUsize::new(index as usize)
+                .ok_or_else(|| DbError::Misuse("parameter indexes start at 1".to_string()))?;
+            check_decimal(&value)?;
+            let bound = match self.functions.get(&index.get()) {
+                Some(function) => function(&value),
+                None => value.clone(),
+            };
+            self.stmt()?.bind_at(index, bound.into());
+            self.params.borrow_mut().insert(index.get(), value);
+            Ok(())
+        })
+    }
+
+    fn bind_json(&self, params: String) -> Result<(), DbError> {
+        self.state.track(|| {
+            let params: serde_json::Value = serde_json::from_str(&params)
+                .map_err(|e| DbError::Misuse(format!("params are not valid JSON: {}", e)))?;
+            let type_mismatch = |param: &str, e: String| {
+                DbError::Misuse(format!("type mismatch for {}: {}", param, e))
+            };
+            match params {
+                serde_json::Value::Array(values) => {
+                    for (i, value) in values.iter().enumerate() {
+                        let index = i as u32 + 1;
+                        let value = json::to_record_value(value)
+                            .map_err(|e| type_mismatch(&format!("parameter {}", index), e))?;
+                        self.bind(index, value)?;
+                    }
+                }
+                serde_json::Value::Object(values) => {
+                    for (name, value) in &values {
+                        let index = self.parameter_index(name)?.ok_or_else(|| {
+                            DbError::Misuse(format!("no parameter named {:?}", name))
+                        })?;
+                        let value = json::to_record_value(value)
+                            .map_err(|e| type_mismatch(&format!("parameter {:?}", name), e))?;
+                        self.bind(index.get() as u32, value)?;
                     }
-                    ret.push(row_array);
                 }
-                Ok(limbo_core::StepResult::IO) => {}
-                Ok(limbo_core::StepResult::Interrupt) => break,
-                Ok(limbo_core::StepResult::Done) => break,
-                Ok(limbo_core::StepResult::Busy) => break,
-                Err(e) => panic!("Error: {:?}", e),
+                other => {
+                    return Err(DbError::Misuse(format!(
+                        "params must be a JSON array or object, not {}",
+                        other
+                    )))
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn all(&self) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        self.state.track(|| {
+            self.stmt()?;
+            let db = Component::of(&self.state, &self.io);
+            let key = db.cache_key(&self.sql, &self.params.borrow());
+            if let Some(rows) = key
+                .as_deref()
+                .and_then(|key| self.state.query_cache.get(key))
+            {
+                metrics::add(&self.state.metrics.query_cache_hits, 1);
+                return Ok(rows);
+            }
+            self.refresh()?;
+            self.state.before_write(&self.shared.changes);
+            let before = audit::before(&db, &self.shared.changes)?;
+            self.shared.start();
+            metrics::add(&self.state.metrics.queries, 1);
+            let _span = Span::start("limbo.step");
+            let rows = self
+                .shared
+                .timed(|stats| collect_rows(&mut self.stmt()?, &self.io, &self.state, stats));
+            self.shared.reset();
+            let rows = rows?;
+            metrics::add(&self.state.metrics.rows_read, rows.len() as u64);
+            audit::record(&db, before)?;
+            db.changed(&self.shared.changes);
+            if let (Some(key), Ok(tables)) = (key, db.tables_read(&self.sql)) {
+                self.state.query_cache.insert(key, tables, &rows);
+            }
+            Ok(rows)
+        })
+    }
+
+    fn run(&self) -> Result<WitRows, DbError> {
+        self.state.track(|| Ok(WitRows::new(self.rows()?)))
+    }
+
+    fn all_chunked(&self, max_rows: u32) -> Result<Chunk, DbError> {
+        self.state.track(|| {
+            if max_rows == 0 {
+                return Err(DbError::Misuse("max-rows must be at least 1".to_string()));
+            }
+            let mut chunked = self.chunked.borrow_mut();
+            // an error drops the run, so the next call starts over
+            let rows = match chunked.take() {
+                Some(rows) => rows,
+                None => self.rows()?,
+            };
+            let chunk = rows.next_chunk(max_rows)?;
+            let done = chunk.len() < max_rows as usize;
+            if !done {
+                *chunked = Some(rows);
+            }
+            Ok(Chunk { rows: chunk, done })
+        })
+    }
+
+    fn keyset_page(
+        &self,
+        after: Option<Vec<RecordValue>>,
+        limit: u32,
+    ) -> Result<KeysetPage, DbError> {
+        self.state.track(|| {
+            if limit == 0 {
+                return Err(DbError::Misuse("limit must be at least 1".to_string()));
+            }
+            self.stmt()?;
+            let keyset = sql::keyset(&self.expanded_sql(), after.as_deref(), limit)?;
+            let columns = self.column_names();
+            let keys = keyset
+                .keys
+                .iter()
+                .map(|key| {
+                    columns
+                        .iter()
+                        .position(|column| column.eq_ignore_ascii_case(key))
+                        .ok_or_else(|| {
+                            DbError::Misuse(format!("ORDER BY column {:?} must be selected", key))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // the page is prepared as the connection prepares any statement, its values written
+            // into it, so it is rewritten and refused as the statement itself would be
+            let db = Component::of(&self.state, &self.io);
+            db.check_writable(&keyset.sql)?;
+            let mut stmt = {
+                let _span = Span::start("limbo.prepare");
+                let (rewritten, _) = db.rewrite(&keyset.sql, Some(&BTreeMap::new()))?;
+                db.conn()?
+                    .prepare(rewritten)
+                    .map_err(|e| error::located(e.into(), &keyset.sql))?
+            };
+            metrics::add(&self.state.metrics.queries, 1);
+            let rows = {
+                let _span = Span::start("limbo.step");
+                collect_rows(&mut stmt, &self.io, &self.state, &mut zero_stats())?
+            };
+            metrics::add(&self.state.metrics.rows_read, rows.len() as u64);
+
+            let next = match rows.last() {
+                Some(last) if rows.len() == limit as usize => {
+                    Some(keys.iter().map(|&i| last[i].clone()).collect())
+                }
+                _ => None,
+            };
+            Ok(KeysetPage { rows, next })
+        })
+    }
+
+    /// Empty once the statement has been finalized.
+    fn column_names(&self) -> Vec<String> {
+        let Ok(stmt) = self.stmt() else {
+            return vec![];
+        };
+        (0..stmt.num_columns())
+            .map(|i| stmt.get_column_name(i).to_string())
+            .collect()
+    }
+
+    /// Empty once the statement has been finalized.
+    fn column_decl_types(&self) -> Vec<Option<String>> {
+        let Ok(stmt) = self.stmt() else {
+            return vec![];
+        };
+        (0..stmt.num_columns())
+            .map(|i| stmt.get_column_type(i))
+            .collect()
+    }
+
+    fn all_with_names(&self) -> Result<ResultSet, DbError> {
+        self.state.track(|| {
+            let columns = self.column_names();
+            let affinities = self
+                .column_decl_types()
+                .iter()
+                .map(|decl_type| affinity(decl_type.as_deref()))
+                .collect();
+            let rows = self.all()?;
+            Ok(ResultSet {
+                columns,
+                rows,
+                affinities,
+            })
+        })
+    }
+
+    fn all_with_blob_encoding(
+        &self,
+        encoding: BlobEncoding,
+    ) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        self.state.track(|| {
+            let mut rows = self.all()?;
+            for value in rows.iter_mut().flatten() {
+                if let RecordValue::Blob(bytes) = value {
+                    *value = json::encode_blob(std::mem::take(bytes), encoding);
+                }
+            }
+            Ok(rows)
+        })
+    }
+
+    fn all_json(&self) -> Result<String, DbError> {
+        self.state.track(|| {
+            let result_set = self.all_with_names()?;
+            Ok(json::result_set(result_set, &self.state.json_options.borrow()).to_string())
+        })
+    }
+
+    fn all_proto(&self) -> Result<Vec<u8>, DbError> {
+        self.state.track(|| {
+            use prost::Message;
+            Ok(proto::QueryResponse::from(self.all_with_names()?).encode_to_vec())
+        })
+    }
+
+    #[cfg(feature = "parquet")]
+    fn all_parquet(&self) -> Result<Vec<u8>, DbError> {
+        self.state.track(|| parquet::write(self.all_with_names()?))
+    }
+
+    #[cfg(not(feature = "parquet"))]
+    fn all_parquet(&self) -> Result<Vec<u8>, DbError> {
+        self.state.track(|| {
+            Err(DbError::Misuse(
+                "all-parquet needs the component built with the parquet feature".to_string(),
+            ))
+        })
+    }
+
+    fn last_run_stats(&self) -> RunStats {
+        self.shared.stats.borrow().clone()
+    }
+
+    fn is_readonly(&self) -> bool {
+        sql::is_readonly(&self.sql)
+    }
+
+    fn kind(&self) -> StatementKind {
+        sql::kind(&self.sql)
+    }
+
+    fn tables(&self) -> Result<Vec<String>, DbError> {
+        self.state.track(|| {
+            self.stmt()?;
+            let db = Component::of(&self.state, &self.io);
+            let explain = db.query_all(format!("EXPLAIN {}", self.sql))?;
+            plan::tables(&explain, &db.root_pages()?)
+        })
+    }
+
+    fn original_sql(&self) -> String {
+        self.sql.clone()
+    }
+
+    fn expanded_sql(&self) -> String {
+        sql::expand(&self.sql, &self.params.borrow())
+    }
+
+    fn finalize(&self) -> Result<(), DbError> {
+        self.state.track(|| {
+            self.shared
+                .stmt
+                .borrow_mut()
+                .take()
+                .map(drop)
+                .ok_or_else(|| error::closed("statement"))
+        })
+    }
+}
+
+/// The rows of one run of a statement, stepped as the host asks for them.
+struct InnerRows {
+    shared: Rc<SharedStatement>,
+    io: Arc<dyn limbo_core::IO>,
+    state: Rc<ConnState>,
+    /// Which run of the statement these rows belong to.
+    run: u64,
+    /// [ConnState::cancels] when the run started.
+    cancels: u64,
+    /// Where the rest of the rows are read ahead of other connections' writes, for a
+    /// statement that only reads.
+    cursor: Option<Rc<Cursor>>,
+    done: Cell<bool>,
+    /// The audited tables the statement can write, as they were when the run started.
+    before: RefCell<Option<audit::Before>>,
+}
+
+impl InnerRows {
+    /// Whether these rows still own the statement, rather than a later run.
+    fn is_current(&self) -> bool {
+        self.shared.run.get() == self.run
+    }
+
+    /// Mark every row as returned.
+    fn finish(&self) {
+        self.done.set(true);
+        if let Some(cursor) = &self.cursor {
+            cursor.finish();
+        }
+    }
+
+    /// Step to the next row, `None` once every row has been returned.
+    fn step(&self) -> Result<Option<Vec<RecordValue>>, DbError> {
+        if self.done.get() {
+            return Ok(None);
+        }
+        if !self.is_current() {
+            return Err(error::closed("rows"));
+        }
+        if self.state.cancels.get() != self.cancels {
+            self.finish();
+            self.shared.reset();
+            return Err(error::interrupted());
+        }
+        let row = match self
+            .cursor
+            .as_ref()
+            .and_then(|cursor| cursor.next_buffered())
+        {
+            Some(row) => row?,
+            None => {
+                let mut busy = BusyWait::new(&self.state);
+                self.shared
+                    .timed(|stats| next_row(&mut self.shared.stmt()?, &self.io, &mut busy, stats))?
+            }
+        }
+        .map(|row| non_finite_floats(row, self.state.non_finite_floats.get()))
+        .transpose()?;
+        match row {
+            Some(_) => metrics::add(&self.state.metrics.rows_read, 1),
+            None => {
+                self.finish();
+                self.shared.reset();
+                let db = Component::of(&self.state, &self.io);
+                audit::record(&db, self.before.take())?;
+                db.changed(&self.shared.changes);
             }
         }
-        ret
+        Ok(row)
+    }
+}
+
+impl GuestRows for InnerRows {
+    fn next(&self) -> Result<Option<Vec<RecordValue>>, DbError> {
+        self.state.track(|| {
+            let _span = Span::start("limbo.step");
+            self.step()
+        })
+    }
+
+    fn next_chunk(&self, n: u32) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        self.state.track(|| {
+            let _span = Span::start("limbo.step");
+            let mut rows = vec![];
+            while rows.len() < n as usize {
+                match self.step()? {
+                    Some(row) => rows.push(row),
+                    None => break,
+                }
+            }
+            Ok(rows)
+        })
+    }
+}
+
+impl Drop for InnerRows {
+    /// Rows dropped part way through reset the statement, ready for its next run.
+    fn drop(&mut self) {
+        if !self.done.get() && self.is_current() {
+            self.shared.reset();
+        }
     }
 }
 
@@ -148,47 +2515,322 @@ impl From<limbo_core::Value<'_>> for RecordValue {
     }
 }
 
-bindings::export!(Component with_types_in bindings);
-
-pub struct DatabaseStorage {
-    file: Rc<dyn limbo_core::File>,
+/// `value` as a [RecordValue], or [error::out_of_memory] if there isn't room to copy its text
+/// or blob, rather than aborting the instance.
+fn owned(value: limbo_core::Value) -> Result<RecordValue, DbError> {
+    Ok(match value {
+        limbo_core::Value::Text(s) => {
+            let mut text = String::new();
+            text.try_reserve_exact(s.len())
+                .map_err(|_| error::out_of_memory())?;
+            text.push_str(s);
+            RecordValue::Text(text)
+        }
+        limbo_core::Value::Blob(b) => {
+            let mut blob = Vec::new();
+            blob.try_reserve_exact(b.len())
+                .map_err(|_| error::out_of_memory())?;
+            blob.extend_from_slice(b);
+            RecordValue::Blob(blob)
+        }
+        value => value.into(),
+    })
 }
 
-impl DatabaseStorage {
-    pub fn new(file: Rc<dyn limbo_core::File>) -> Self {
-        Self { file }
+impl From<u64> for RecordValue {
+    /// Values above `i64::MAX` become [RecordValue::TextDecimal] rather than wrapping.
+    fn from(value: u64) -> Self {
+        match i64::try_from(value) {
+            Ok(i) => RecordValue::Integer(i),
+            Err(_) => RecordValue::TextDecimal(value.to_string()),
+        }
     }
 }
 
-impl limbo_core::DatabaseStorage for DatabaseStorage {
-    fn read_page(&self, page_idx: usize, c: limbo_core::Completion) -> Result<()> {
-        let r = match c {
-            limbo_core::Completion::Read(ref r) => r,
-            _ => unreachable!(),
-        };
-        let size = r.buf().len();
-        assert!(page_idx > 0);
-        if !(512..=65536).contains(&size) || size & (size - 1) != 0 {
-            return Err(limbo_core::LimboError::NotADB);
+impl From<i128> for RecordValue {
+    /// Values outside the `i64` range become [RecordValue::TextDecimal] rather than wrapping.
+    fn from(value: i128) -> Self {
+        match i64::try_from(value) {
+            Ok(i) => RecordValue::Integer(i),
+            Err(_) => RecordValue::TextDecimal(value.to_string()),
         }
-        let pos = (page_idx - 1) * size;
-        self.file.pread(pos, c)?;
+    }
+}
+
+/// Refuse a [RecordValue::TextDecimal] that isn't a decimal number, an optional sign and
+/// digits with an optional fraction, e.g. `-1234.5600`, so nothing else is stored as one.
+fn check_decimal(value: &RecordValue) -> Result<(), DbError> {
+    let RecordValue::TextDecimal(text) = value else {
+        return Ok(());
+    };
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, "0"));
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if is_digits(whole) && is_digits(fraction) {
         Ok(())
+    } else {
+        Err(DbError::Misuse(format!(
+            "text-decimal {:?} is not a decimal number",
+            text
+        )))
     }
+}
 
-    fn write_page(
-        &self,
-        page_idx: usize,
-        buffer: Rc<std::cell::RefCell<limbo_core::Buffer>>,
-        c: limbo_core::Completion,
-    ) -> Result<()> {
-        let size = buffer.borrow().len();
-        let pos = (page_idx - 1) * size;
-        self.file.pwrite(pos, buffer, c)?;
+impl From<RecordValue> for limbo_core::OwnedValue {
+    /// Decimals are stored as TEXT so that no precision is lost.
+    fn from(value: RecordValue) -> Self {
+        match value {
+            RecordValue::Null => limbo_core::OwnedValue::Null,
+            RecordValue::Integer(i) => limbo_core::OwnedValue::Integer(i),
+            RecordValue::Float(f) => limbo_core::OwnedValue::Float(f),
+            RecordValue::Text(s) | RecordValue::TextDecimal(s) => {
+                limbo_core::OwnedValue::build_text(Rc::new(s))
+            }
+            RecordValue::Blob(b) => limbo_core::OwnedValue::Blob(Rc::new(b)),
+        }
+    }
+}
+
+/// The affinity of a column declared as `decl_type`, by SQLite's rules, which are checked
+/// in this order.
+fn affinity(decl_type: Option<&str>) -> Affinity {
+    let Some(decl_type) = decl_type else {
+        return Affinity::Blob;
+    };
+    let decl_type = decl_type.to_ascii_uppercase();
+    if decl_type.contains("INT") {
+        Affinity::Integer
+    } else if ["CHAR", "CLOB", "TEXT"]
+        .iter()
+        .any(|t| decl_type.contains(t))
+    {
+        Affinity::Text
+    } else if decl_type.contains("BLOB") || decl_type.is_empty() {
+        Affinity::Blob
+    } else if ["REAL", "FLOA", "DOUB"]
+        .iter()
+        .any(|t| decl_type.contains(t))
+    {
+        Affinity::Real
+    } else {
+        Affinity::Numeric
+    }
+}
+
+/// The limits a connection starts with, SQLite's defaults, see `set-limit`.
+const DEFAULT_LIMITS: Limits = Limits {
+    sql_length: 1_000_000_000,
+    column: 2000,
+    expr_depth: 1000,
+};
+
+/// Fail with [DbError::LimitExceeded] if there are more than `limit` `what`, unless `limit` is 0.
+fn check_limit(n: usize, limit: u32, what: &str) -> Result<(), DbError> {
+    if limit != 0 && n > limit as usize {
+        return Err(DbError::LimitExceeded(format!(
+            "{} {}, over the limit of {}",
+            n, what, limit
+        )));
+    }
+    Ok(())
+}
+
+/// Fail statements limbo_core can't run yet, rather than letting them look like they worked.
+fn check_supported(sql: &str) -> Result<(), DbError> {
+    if sql::is_create_trigger(sql) {
+        return Err(error::unsupported("CREATE TRIGGER"));
+    }
+    Ok(())
+}
+
+/// Functions of the values bound to a statement's parameters, by parameter number, whose
+/// results are bound instead, for the calls on parameters that limbo can't make itself.
+type ParamFunctions = BTreeMap<usize, fn(&RecordValue) -> RecordValue>;
+
+/// Quote an SQL identifier (table or column name), doubling any embedded quotes.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Delays between retries of a busy statement, in milliseconds, as SQLite's default
+/// busy handler uses. The last one repeats until the timeout runs out.
+const BUSY_DELAYS: [u32; 12] = [1, 2, 5, 10, 15, 20, 25, 25, 25, 50, 50, 100];
+
+/// Waits out [limbo_core::StepResult::Busy] with `host-clock.sleep` until `timeout` milliseconds
+/// have been spent, see `set-busy-timeout`.
+struct BusyWait {
+    timeout: u32,
+    waited: u32,
+    retries: usize,
+    metrics: Rc<Metrics>,
+}
+
+impl BusyWait {
+    fn new(state: &ConnState) -> Self {
+        Self {
+            timeout: state.busy_timeout.get(),
+            waited: 0,
+            retries: 0,
+            metrics: state.metrics.clone(),
+        }
+    }
+
+    /// Sleep before the next retry, or fail with [DbError::Busy] once the timeout is spent.
+    fn wait(&mut self) -> Result<(), DbError> {
+        if self.waited >= self.timeout {
+            if self.timeout > 0 {
+                log::warn(&format!("still busy after {} ms, giving up", self.waited));
+            }
+            return Err(error::busy());
+        }
+        let delay =
+            BUSY_DELAYS[self.retries.min(BUSY_DELAYS.len() - 1)].min(self.timeout - self.waited);
+        sleep(delay);
+        self.waited += delay;
+        self.retries += 1;
+        metrics::add(&self.metrics.busy_retries, 1);
         Ok(())
     }
+}
+
+/// Stats with nothing counted yet.
+fn zero_stats() -> RunStats {
+    RunStats {
+        vm_steps: 0,
+        rows_produced: 0,
+        io_operations: 0,
+        elapsed_micros: 0,
+    }
+}
+
+/// Step a statement to its next row, driving IO as needed. `None` once it is done.
+fn next_row(
+    stmt: &mut limbo_core::Statement,
+    io: &Arc<dyn limbo_core::IO>,
+    busy: &mut BusyWait,
+    stats: &mut RunStats,
+) -> Result<Option<Vec<RecordValue>>, DbError> {
+    loop {
+        stats.vm_steps += 1;
+        match stmt.step()? {
+            limbo_core::StepResult::Row => {
+                stats.rows_produced += 1;
+                heap::check()?;
+                let row = stmt.row().unwrap();
+                let mut row_array = vec![];
+                for value in row.get_values() {
+                    row_array
+                        .try_reserve(1)
+                        .map_err(|_| error::out_of_memory())?;
+                    row_array.push(owned(value.to_value())?);
+                }
+                return Ok(Some(row_array));
+            }
+            limbo_core::StepResult::IO => {
+                stats.io_operations += 1;
+                io.run_once()?
+            }
+            limbo_core::StepResult::Interrupt => return Ok(None),
+            limbo_core::StepResult::Done => return Ok(None),
+            limbo_core::StepResult::Busy => busy.wait()?,
+        }
+    }
+}
 
-    fn sync(&self, _c: limbo_core::Completion) -> Result<()> {
-        todo!()
+/// Step a statement until it is done, collecting every row it produces, or until
+/// `database.cancel-running` ends the run, as [InnerRows::step] checks between rows.
+fn collect_rows(
+    stmt: &mut limbo_core::Statement,
+    io: &Arc<dyn limbo_core::IO>,
+    state: &ConnState,
+    stats: &mut RunStats,
+) -> Result<Vec<Vec<RecordValue>>, DbError> {
+    let mut busy = BusyWait::new(state);
+    let cancels = state.cancels.get();
+    let quota = state.memory_quota.get();
+    let mut size = 0;
+    let mut ret = vec![];
+    while let Some(row) = next_row(stmt, io, &mut busy, stats)? {
+        if state.cancels.get() != cancels {
+            return Err(error::interrupted());
+        }
+        let row = non_finite_floats(row, state.non_finite_floats.get())?;
+        size += row.iter().map(value_size).sum::<u64>();
+        if quota != 0 && size > quota {
+            return Err(DbError::OutOfMemory(format!(
+                "result is over the memory quota of {} bytes",
+                quota
+            )));
+        }
+        ret.try_reserve(1).map_err(|_| error::out_of_memory())?;
+        ret.push(row);
     }
+    Ok(ret)
 }
+
+/// `row` with its NaN and infinite floats replaced as `policy` says.
+fn non_finite_floats(
+    mut row: Vec<RecordValue>,
+    policy: NonFiniteFloats,
+) -> Result<Vec<RecordValue>, DbError> {
+    if policy == NonFiniteFloats::Pass {
+        return Ok(row);
+    }
+    for value in &mut row {
+        let RecordValue::Float(f) = *value else {
+            continue;
+        };
+        if f.is_finite() {
+            continue;
+        }
+        let text = if f.is_nan() {
+            "NaN"
+        } else if f > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        };
+        *value = match policy {
+            NonFiniteFloats::Pass => continue,
+            NonFiniteFloats::Null => RecordValue::Null,
+            NonFiniteFloats::Text => RecordValue::Text(text.to_string()),
+            NonFiniteFloats::Error => {
+                return Err(DbError::Other(format!(
+                    "result has a non-finite float, {}",
+                    text
+                )))
+            }
+        };
+    }
+    Ok(row)
+}
+
+/// Roughly how many bytes `value` takes up.
+fn value_size(value: &RecordValue) -> u64 {
+    let payload = match value {
+        RecordValue::Text(s) | RecordValue::TextDecimal(s) => s.len(),
+        RecordValue::Blob(b) => b.len(),
+        RecordValue::Null | RecordValue::Integer(_) | RecordValue::Float(_) => 0,
+    };
+    std::mem::size_of::<RecordValue>() as u64 + payload as u64
+}
+
+/// Step a statement that produces no rows until it is done, driving IO as needed.
+fn run_to_completion(
+    stmt: &mut limbo_core::Statement,
+    io: &Arc<dyn limbo_core::IO>,
+    state: &ConnState,
+) -> Result<(), DbError> {
+    let mut busy = BusyWait::new(state);
+    loop {
+        match stmt.step()? {
+            limbo_core::StepResult::IO => io.run_once()?,
+            limbo_core::StepResult::Row => {}
+            limbo_core::StepResult::Done | limbo_core::StepResult::Interrupt => return Ok(()),
+            limbo_core::StepResult::Busy => busy.wait()?,
+        }
+    }
+}
+
+#[cfg(not(feature = "native"))]
+bindings::export!(Component with_types_in bindings);
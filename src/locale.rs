@@ -0,0 +1,171 @@
+//! Folding case and collating text by the host's locale through `host-locale`, with the
+//! `host-locale` feature.
+//!
+//! Limbo can't call back into the guest from LIKE or a collation, so, as for REGEXP, the guest
+//! works them out before a statement runs from the values each operand can take, see
+//! [operands::values]. Folding replaces a column, literal or parameter with a CASE giving the
+//! host's lowercase of each of its values where that differs from limbo's `lower()`, which
+//! still folds the rest. `x COLLATE "<locale>"` ranks the text values of `x`, and of what it
+//! is compared to, in the order `host-locale.collate` sorts them, and compares and sorts by
+//! rank instead. Only text has a rank, so an operand holding a number or blob is refused, as is
+//! a column with more than [operands::MAX_VALUES] distinct values.
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::bindings::component::wit_limbo::host_locale;
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue};
+use crate::sql;
+use crate::{error, operands, Component};
+
+/// `sql` with LIKE and `COLLATE NOCASE` folding case by the host's locale, where
+/// [sql::fold_case] would fold with `lower()`, given `params` as [operands::values] takes them.
+pub(crate) fn fold_case(
+    db: &Component,
+    sql: &str,
+    params: Option<&BTreeMap<usize, RecordValue>>,
+) -> Result<String, DbError> {
+    sql::fold_case_with(sql, |written, operand| {
+        let Some(values) = operands::values(db, sql, operand, params, "LIKE")? else {
+            return Ok(None);
+        };
+        let mut cases = String::new();
+        for value in values {
+            let RecordValue::Text(text) = &value else {
+                continue;
+            };
+            let folded = host_locale::to_lower(text);
+            // limbo's `lower()` folds the rest the same
+            if folded != text.to_lowercase() {
+                cases.push_str(" WHEN ");
+                sql::push_literal(&mut cases, Some(&value));
+                cases.push_str(" THEN ");
+                sql::push_literal(&mut cases, Some(&RecordValue::Text(folded)));
+            }
+        }
+        if cases.is_empty() {
+            return Ok(None);
+        }
+        let x = operands::written(written, operand);
+        Ok(Some(format!("(CASE {}{} ELSE lower({}) END)", x, cases, x)))
+    })
+}
+
+/// `sql` with each `COLLATE "<locale>"` comparing and sorting by rank in the order the host
+/// sorts the values in, given `params` as [operands::values] takes them.
+pub(crate) fn collate(
+    db: &Component,
+    sql: &str,
+    params: Option<&BTreeMap<usize, RecordValue>>,
+) -> Result<String, DbError> {
+    let mut edits: Vec<(Range<usize>, String)> = vec![];
+    for collation in sql::collations(sql) {
+        let operator = format!("COLLATE \"{}\"", collation.name);
+        // the text values of each operand
+        let mut texts: Vec<Vec<String>> = vec![];
+        for (_, written, operand) in &collation.operands {
+            let mut own = vec![];
+            let values = operands::values(db, sql, operand, params, &operator)?
+                .ok_or_else(|| error::unsupported(&format!("{} on {}", operator, written)))?;
+            for value in values {
+                match value {
+                    RecordValue::Text(text) | RecordValue::TextDecimal(text) => own.push(text),
+                    // compares as NULL under any collation
+                    RecordValue::Null => {}
+                    _ => {
+                        return Err(error::unsupported(&format!(
+                            "{} on {}, which holds a number or blob,",
+                            operator, written
+                        )))
+                    }
+                }
+            }
+            texts.push(own);
+        }
+        let mut all: Vec<String> = texts.iter().flatten().cloned().collect();
+        all.sort();
+        all.dedup();
+        if all.len() > operands::MAX_VALUES {
+            return Err(error::unsupported(&format!(
+                "{} over more than {} distinct values",
+                operator,
+                operands::MAX_VALUES
+            )));
+        }
+        let ranks: BTreeMap<String, usize> = rank(&collation.name, all).into_iter().collect();
+        for ((span, written, operand), own) in collation.operands.iter().zip(texts) {
+            // only the operand's own values, each with its rank among all of them
+            let mut cases = String::new();
+            for text in own {
+                let rank = ranks[&text];
+                cases.push_str(" WHEN ");
+                sql::push_literal(&mut cases, Some(&RecordValue::Text(text)));
+                cases.push_str(&format!(" THEN {}", rank));
+            }
+            // still naming the operand, so the parameters after it keep their numbers
+            if cases.is_empty() {
+                cases.push_str(" WHEN NULL THEN NULL");
+            }
+            let x = operands::written(written, operand);
+            edits.push((span.clone(), format!("(CASE {}{} END)", x, cases)));
+        }
+    }
+    // an operand with a collation of its own, compared to another with one, is ranked twice
+    // over the same values; the edit with its COLLATE clause spans the other
+    edits.sort_by_key(|(span, _)| (span.start, Reverse(span.end)));
+    let mut out = String::with_capacity(sql.len());
+    let mut at = 0;
+    for (span, text) in edits {
+        if span.start < at {
+            continue;
+        }
+        out.push_str(&sql[at..span.start]);
+        out.push_str(&text);
+        at = span.end;
+    }
+    out.push_str(&sql[at..]);
+    Ok(out)
+}
+
+/// Whether `sql`, with case folded if `unicode_case`, has anything for the host's locale to
+/// work out, so its statement has to be prepared again for each run.
+pub(crate) fn uses(sql: &str, unicode_case: bool) -> bool {
+    !sql::collations(sql).is_empty() || (unicode_case && sql::fold_case(sql) != sql)
+}
+
+/// `texts`, which have no duplicates, in the order `locale` sorts them, each with its rank:
+/// texts the host sorts as equal share one.
+fn rank(locale: &str, texts: Vec<String>) -> Vec<(String, usize)> {
+    let mut ranks: Vec<(String, usize)> = Vec::with_capacity(texts.len());
+    for text in sort(locale, texts) {
+        let rank = match ranks.last() {
+            Some((last, rank)) if host_locale::collate(locale, last, &text) == 0 => *rank,
+            Some((_, rank)) => rank + 1,
+            None => 0,
+        };
+        ranks.push((text, rank));
+    }
+    ranks
+}
+
+/// `texts` in the order `locale` sorts them, merged by halves, so with O(n log n) calls to the
+/// host, and without the panic `sort_by` may give for a host that doesn't sort consistently.
+fn sort(locale: &str, mut texts: Vec<String>) -> Vec<String> {
+    if texts.len() <= 1 {
+        return texts;
+    }
+    let right = texts.split_off(texts.len() / 2);
+    let mut left = sort(locale, texts).into_iter().peekable();
+    let mut right = sort(locale, right).into_iter().peekable();
+    let mut sorted = Vec::with_capacity(left.len() + right.len());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if host_locale::collate(locale, a, b) <= 0 {
+            sorted.extend(left.next());
+        } else {
+            sorted.extend(right.next());
+        }
+    }
+    sorted.extend(left);
+    sorted.extend(right);
+    sorted
+}
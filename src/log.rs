@@ -0,0 +1,72 @@
+//! Logging from inside the component, through `host-log`, or `wasi:logging/logging` when
+//! built with the `wasi-logging` feature.
+
+/// How much a message matters, the levels of `wasi:logging/logging` the guest uses.
+#[derive(Clone, Copy)]
+pub(crate) enum Level {
+    Info,
+    Warn,
+}
+
+impl Level {
+    #[cfg(not(all(feature = "wasi-logging", not(feature = "native"))))]
+    fn name(self) -> &'static str {
+        match self {
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+        }
+    }
+}
+
+/// The `context` every message is logged with through `wasi:logging/logging`.
+#[cfg(all(feature = "wasi-logging", not(feature = "native")))]
+const CONTEXT: &str = "wit-limbo";
+
+#[cfg(not(all(feature = "wasi-logging", not(feature = "native"))))]
+pub(crate) fn log(level: Level, message: &str) {
+    crate::bindings::component::wit_limbo::host_log::log(&format!(
+        "[{}] {}",
+        level.name(),
+        message
+    ));
+}
+
+/// Logs through `wasi:logging/logging`, which the `example-wasi-logging` world imports.
+/// The bindings are only generated for the `example` world, so the import is declared
+/// by hand, lowered the way the generated bindings would.
+#[cfg(all(feature = "wasi-logging", not(feature = "native")))]
+pub(crate) fn log(level: Level, message: &str) {
+    #[link(wasm_import_module = "wasi:logging/logging@0.1.0-draft")]
+    extern "C" {
+        #[link_name = "log"]
+        fn wasi_log(
+            level: i32,
+            context: *const u8,
+            context_len: usize,
+            message: *const u8,
+            message_len: usize,
+        );
+    }
+    // the cases of `wasi:logging/logging.level`, in order
+    let level = match level {
+        Level::Info => 2,
+        Level::Warn => 3,
+    };
+    unsafe {
+        wasi_log(
+            level,
+            CONTEXT.as_ptr(),
+            CONTEXT.len(),
+            message.as_ptr(),
+            message.len(),
+        )
+    };
+}
+
+pub(crate) fn info(message: &str) {
+    log(Level::Info, message);
+}
+
+pub(crate) fn warn(message: &str) {
+    log(Level::Warn, message);
+}
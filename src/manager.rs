@@ -0,0 +1,173 @@
+//! One database per tenant, see the `manager` resource in the WIT.
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::{Rc, Weak},
+};
+
+use crate::bindings::component::wit_limbo::host_clock::now;
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    Database as WitDatabase, DbError, GuestDatabase, GuestManager, OpenFlags,
+};
+use crate::{error, log, storage, Component, ConnState};
+
+pub struct Manager {
+    /// Where tenant databases are stored, e.g. `kv:tenants`, see `manager` in the WIT.
+    root: String,
+    /// The open tenants.
+    tenants: RefCell<BTreeMap<String, Tenant>>,
+    /// Memory quota for the databases opened from now on, 0 for none.
+    memory_quota: Cell<u64>,
+    /// Milliseconds a tenant may go unused before it is closed, 0 for never.
+    idle_close_ms: Cell<u32>,
+}
+
+struct Tenant {
+    /// The manager's connection, keeping the database open; `None` while it is idle-closed.
+    db: Option<Component>,
+    activity: Rc<Activity>,
+}
+
+/// When a tenant's connections were last used, and which connections those are.
+pub(crate) struct Activity {
+    /// Milliseconds since the host's fixed point in time, see `host-clock.now`.
+    last_used: Cell<u64>,
+    /// The connections handed out for the tenant, closed with it when it goes idle.
+    conns: RefCell<Vec<Weak<ConnState>>>,
+}
+
+impl Activity {
+    /// Record that one of the tenant's connections is being used.
+    pub(crate) fn touch(&self) {
+        self.last_used.set(now_ms());
+    }
+
+    /// Close every connection still handed out, so that each reconnects on its next call.
+    fn close_all(&self) {
+        self.conns.borrow_mut().retain(|conn| {
+            let Some(state) = conn.upgrade() else {
+                return false;
+            };
+            if state.conn.borrow_mut().take().is_some() {
+                state.idle_closed.set(true);
+            }
+            true
+        });
+    }
+}
+
+fn now_ms() -> u64 {
+    now() / 1000
+}
+
+impl Manager {
+    /// Open the database of `tenant_id`, which has not been opened yet.
+    fn open_tenant(&self, tenant_id: &str) -> Result<Component, DbError> {
+        let (backend, path) = storage::from_uri(&self.root)?;
+        if backend.scheme() == "memory" {
+            return Component::open(&*backend, path, OpenFlags::CREATE);
+        }
+        let uri = format!("{}:{}/{}", backend.scheme(), path, tenant_id);
+        Ok(Component::new(uri))
+    }
+}
+
+/// Tenant ids become part of a storage path or key, so they are kept to characters that
+/// can't step outside the manager's root.
+fn check_tenant_id(tenant_id: &str) -> Result<(), DbError> {
+    let valid = !tenant_id.is_empty()
+        && tenant_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(DbError::Misuse(format!(
+            "tenant id {:?} must be letters, digits, '-' or '_'",
+            tenant_id
+        )));
+    }
+    Ok(())
+}
+
+impl GuestManager for Manager {
+    fn new(root: String) -> Self {
+        Self {
+            root,
+            tenants: RefCell::new(BTreeMap::new()),
+            memory_quota: Cell::new(0),
+            idle_close_ms: Cell::new(0),
+        }
+    }
+
+    fn open(&self, tenant_id: String) -> Result<WitDatabase, DbError> {
+        check_tenant_id(&tenant_id)?;
+        self.close_idle()?;
+        let mut tenants = self.tenants.borrow_mut();
+        let tenant = tenants.entry(tenant_id.clone()).or_insert_with(|| Tenant {
+            db: None,
+            activity: Rc::new(Activity {
+                last_used: Cell::new(0),
+                conns: RefCell::new(vec![]),
+            }),
+        });
+        if tenant.db.is_none() {
+            tenant.db = Some(self.open_tenant(&tenant_id)?);
+        }
+        let db = tenant.db.as_ref().unwrap().new_connection();
+        db.state.memory_quota.set(self.memory_quota.get());
+        db.state.activity.replace(Some(tenant.activity.clone()));
+        let mut conns = tenant.activity.conns.borrow_mut();
+        conns.retain(|conn| conn.strong_count() > 0);
+        conns.push(Rc::downgrade(&db.state));
+        tenant.activity.touch();
+        Ok(WitDatabase::new(db))
+    }
+
+    fn list(&self) -> Vec<String> {
+        self.tenants.borrow().keys().cloned().collect()
+    }
+
+    fn drop_tenant(&self, tenant_id: String) -> Result<(), DbError> {
+        let tenant = self
+            .tenants
+            .borrow_mut()
+            .remove(&tenant_id)
+            .ok_or_else(|| error::closed(&format!("tenant {:?}", tenant_id)))?;
+        match tenant.db {
+            Some(db) => db.close(),
+            None => Ok(()),
+        }
+    }
+
+    fn set_memory_quota(&self, bytes: u64) {
+        self.memory_quota.set(bytes);
+    }
+
+    fn set_idle_close_ms(&self, ms: u32) {
+        self.idle_close_ms.set(ms);
+    }
+
+    fn close_idle(&self) -> Result<Vec<String>, DbError> {
+        let idle_close_ms = self.idle_close_ms.get();
+        if idle_close_ms == 0 {
+            return Ok(vec![]);
+        }
+        let now = now_ms();
+        let mut closed = vec![];
+        for (tenant_id, tenant) in self.tenants.borrow_mut().iter_mut() {
+            let idle = now.saturating_sub(tenant.activity.last_used.get());
+            if idle < idle_close_ms as u64 {
+                continue;
+            }
+            if let Some(db) = tenant.db.take() {
+                db.close()?;
+                tenant.activity.close_all();
+                log::info(&format!(
+                    "closed tenant {:?}, idle for {} ms",
+                    tenant_id, idle
+                ));
+                closed.push(tenant_id.clone());
+            }
+        }
+        Ok(closed)
+    }
+}
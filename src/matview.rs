@@ -0,0 +1,205 @@
+//! Tables kept filled with what a query returns, see `database.create-materialized-view` in
+//! the WIT.
+use std::{cell::RefCell, rc::Rc};
+
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue, RefreshMode};
+use crate::sql::{self, Changes};
+use crate::{log, quote_identifier, Component};
+
+/// The materialized views of a database: their names, queries and refresh modes.
+pub(crate) const TABLE: &str = "wit_limbo_matviews";
+
+/// A materialized view, as registered in [TABLE].
+struct View {
+    name: String,
+    sql: String,
+    /// Whether it is refreshed when a write it could be affected by commits.
+    on_commit: bool,
+    /// The tables its query reads, including those of the views it reads.
+    tables: Vec<String>,
+}
+
+/// The materialized views of one database, shared by its connections like its
+/// subscriptions, and loaded from [TABLE] when first needed.
+#[derive(Default)]
+pub(crate) struct Views {
+    known: RefCell<Option<Rc<[View]>>>,
+}
+
+impl Views {
+    /// Load the views again when next needed, after [TABLE] or the schema changed.
+    pub(crate) fn forget(&self) {
+        self.known.replace(None);
+    }
+
+    fn get(&self, db: &Component) -> Rc<[View]> {
+        if let Some(known) = &*self.known.borrow() {
+            return known.clone();
+        }
+        // not kept on an error, so the next write tries again
+        let views: Rc<[View]> = match load(db) {
+            Ok(views) => views.into(),
+            Err(e) => {
+                log::warn(&format!("loading the materialized views failed: {:?}", e));
+                return Rc::new([]);
+            }
+        };
+        self.known.replace(Some(views.clone()));
+        views
+    }
+}
+
+/// The views registered in [TABLE], none if it doesn't exist yet.
+fn load(db: &Component) -> Result<Vec<View>, DbError> {
+    let exists = db.query_with(
+        "SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = ?",
+        vec![RecordValue::Text(TABLE.to_string())],
+    )?;
+    if exists.is_empty() {
+        return Ok(vec![]);
+    }
+    let rows = db.query_with(
+        &format!("SELECT name, sql, refresh FROM {} ORDER BY rowid", TABLE),
+        vec![],
+    )?;
+    rows.into_iter()
+        .map(|row| {
+            let mut row = row.into_iter();
+            match (row.next(), row.next(), row.next()) {
+                (
+                    Some(RecordValue::Text(name)),
+                    Some(RecordValue::Text(sql)),
+                    Some(RecordValue::Text(refresh)),
+                ) => Ok(View {
+                    tables: db.tables_read(&sql)?,
+                    on_commit: refresh == "on-commit",
+                    name,
+                    sql,
+                }),
+                other => Err(DbError::Corrupt(format!(
+                    "{} has an unexpected row: {:?}",
+                    TABLE, other
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Whether a view reading `tables` could be affected by `changes`.
+fn affected(tables: &[String], changes: &Changes) -> bool {
+    match changes {
+        Changes::Table(table) => tables.iter().any(|t| t.eq_ignore_ascii_case(table)),
+        Changes::Everything => true,
+        _ => false,
+    }
+}
+
+/// Refresh the on-commit views `written` could have affected, now that the writes are
+/// committed. Failures are logged, as the writes themselves succeeded.
+pub(crate) fn refresh_affected(db: &Component, written: &[Changes]) {
+    let views = db.state.views.get(db);
+    for view in views.iter() {
+        if !view.on_commit || !written.iter().any(|c| affected(&view.tables, c)) {
+            continue;
+        }
+        if let Err(e) = refresh(db, view) {
+            log::warn(&format!("refreshing {:?} failed: {:?}", view.name, e));
+        }
+    }
+}
+
+/// Recompute `view` in full, then follow the write to its table like any other, refreshing
+/// the views that read it in turn.
+fn refresh(db: &Component, view: &View) -> Result<u64, DbError> {
+    let rows = db.transaction(&format!("refresh of {:?}", view.name), || {
+        db.materialize(&view.name, &view.sql)
+    })?;
+    db.changed(&Changes::Table(view.name.clone()));
+    Ok(rows)
+}
+
+/// See `database.refresh-view` in the WIT.
+pub(crate) fn refresh_named(db: &Component, name: &str) -> Result<u64, DbError> {
+    let views = db.state.views.get(db);
+    let Some(view) = views.iter().find(|v| v.name.eq_ignore_ascii_case(name)) else {
+        return Err(DbError::Misuse(format!(
+            "there is no materialized view named {:?}",
+            name
+        )));
+    };
+    refresh(db, view)
+}
+
+/// See `database.create-materialized-view` in the WIT.
+pub(crate) fn create(
+    db: &Component,
+    name: String,
+    sql: String,
+    refresh_mode: RefreshMode,
+) -> Result<(), DbError> {
+    if !sql::is_readonly(&sql) {
+        return Err(DbError::Misuse(
+            "a materialized view must be defined by a query".to_string(),
+        ));
+    }
+    let taken = db.query_with(
+        "SELECT 1 FROM sqlite_schema WHERE lower(name) = lower(?)",
+        vec![RecordValue::Text(name.clone())],
+    )?;
+    if !taken.is_empty() {
+        return Err(DbError::Misuse(format!("{:?} already exists", name)));
+    }
+    let columns: Vec<String> = {
+        let stmt = db.conn()?.prepare(&sql)?;
+        (0..stmt.num_columns())
+            .map(|i| quote_identifier(&stmt.get_column_name(i)))
+            .collect()
+    };
+    if columns.is_empty() {
+        return Err(DbError::Misuse(format!(
+            "the query of {:?} returns no columns",
+            name
+        )));
+    }
+
+    db.query_with(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {} \
+             (name TEXT PRIMARY KEY, sql TEXT NOT NULL, refresh TEXT NOT NULL)",
+            TABLE
+        ),
+        vec![],
+    )?;
+    // untyped columns keep each value as the query returned it
+    db.query_with(
+        &format!(
+            "CREATE TABLE {} ({})",
+            quote_identifier(&name),
+            columns.join(", ")
+        ),
+        vec![],
+    )?;
+    let refresh_mode = match refresh_mode {
+        RefreshMode::Manual => "manual",
+        RefreshMode::OnCommit => "on-commit",
+    };
+    db.query_with(
+        &format!(
+            "INSERT INTO {} (name, sql, refresh) VALUES (?, ?, ?)",
+            TABLE
+        ),
+        vec![
+            RecordValue::Text(name.clone()),
+            RecordValue::Text(sql.clone()),
+            RecordValue::Text(refresh_mode.to_string()),
+        ],
+    )?;
+    let view = View {
+        tables: db.tables_read(&sql)?,
+        on_commit: refresh_mode == "on-commit",
+        name,
+        sql,
+    };
+    refresh(db, &view)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Database-wide counters, see `database.metrics-prometheus` in the WIT.
+use std::{cell::Cell, fmt::Write};
+
+/// Counters shared by every connection to one database.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    /// Statements run, including those run by `exec`.
+    pub(crate) queries: Cell<u64>,
+    /// Rows produced by those statements.
+    pub(crate) rows_read: Cell<u64>,
+    /// Sleeps while waiting for a lock, see `set-busy-timeout`.
+    pub(crate) busy_retries: Cell<u64>,
+    /// Opens that joined the database already open in this instance, rather than reading it
+    /// from storage.
+    pub(crate) cache_hits: Cell<u64>,
+    /// Queries whose rows came from a connection's query cache, see `set-query-cache`.
+    pub(crate) query_cache_hits: Cell<u64>,
+}
+
+/// Add `n` to `counter`.
+pub(crate) fn add(counter: &Cell<u64>, n: u64) {
+    counter.set(counter.get() + n);
+}
+
+impl Metrics {
+    /// The counters in the Prometheus text exposition format.
+    pub(crate) fn prometheus(&self) -> String {
+        let counters = [
+            ("limbo_queries_total", "Statements run.", &self.queries),
+            (
+                "limbo_rows_read_total",
+                "Rows produced by statements.",
+                &self.rows_read,
+            ),
+            (
+                "limbo_busy_retries_total",
+                "Retries of statements that found the database locked.",
+                &self.busy_retries,
+            ),
+            (
+                "limbo_cache_hits_total",
+                "Opens served by the database already open in this instance.",
+                &self.cache_hits,
+            ),
+            (
+                "limbo_query_cache_hits_total",
+                "Queries answered from a query cache without running them.",
+                &self.query_cache_hits,
+            ),
+        ];
+        let mut out = String::new();
+        for (name, help, counter) in counters {
+            // writing to a String can't fail
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, counter.get());
+        }
+        out
+    }
+}
@@ -0,0 +1,1388 @@
+//! Stand-in for the generated bindings when built with the `native` feature, so the same
+//! Database/Statement code runs on limbo_core in-process instead of inside a component.
+//!
+//! The module tree mirrors the generated `bindings` module, so the rest of the crate builds unchanged:
+//!
+//! - the `limbo` export's types are plain Rust types, and its resources are boxed values
+//!   with inherent methods for calling them, see [Database], [Statement] and [Rows]
+//! - the `host-*` imports are implemented in the process, on the OS random source, stderr,
+//!   the system clock, [std::fs] (relative to the working directory, read synchronously for
+//!   `host-io`) and an in-memory map
+//!
+//! [Database]: exports::component::wit_limbo::limbo::Database
+//! [Statement]: exports::component::wit_limbo::limbo::Statement
+//! [Rows]: exports::component::wit_limbo::limbo::Rows
+
+pub mod component {
+    pub mod wit_limbo {
+        pub mod host_random {
+            pub fn random_byte() -> u8 {
+                let mut byte = [0];
+                getrandom::getrandom(&mut byte).expect("OS random source");
+                byte[0]
+            }
+        }
+
+        pub mod host_log {
+            pub fn log(message: &str) {
+                eprintln!("{}", message);
+            }
+        }
+
+        pub mod host_clock {
+            pub fn sleep(ms: u32) {
+                std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+            }
+
+            pub fn now() -> u64 {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_micros() as u64)
+            }
+        }
+
+        pub mod host_trace {
+            /// Spans aren't reported anywhere in-process.
+            pub fn span_start(_name: &str) -> u64 {
+                0
+            }
+
+            pub fn span_end(_id: u64) {}
+        }
+
+        pub mod host_subscriptions {
+            use std::cell::RefCell;
+
+            thread_local! {
+                /// What to call when a subscribed query's rows change, see [set_handler].
+                static HANDLER: RefCell<Option<Box<dyn Fn(u64)>>> = const { RefCell::new(None) };
+            }
+
+            /// Call `handler` with the id of each subscription whose rows change, in place of
+            /// the host's `on-query-changed`.
+            pub fn set_handler(handler: impl Fn(u64) + 'static) {
+                HANDLER.with_borrow_mut(|slot| *slot = Some(Box::new(handler)));
+            }
+
+            pub fn on_query_changed(subscription_id: u64) {
+                HANDLER.with_borrow(|handler| {
+                    if let Some(handler) = handler {
+                        handler(subscription_id)
+                    }
+                });
+            }
+        }
+
+        pub mod host_progress {
+            use std::cell::RefCell;
+
+            thread_local! {
+                /// What to call with the progress of a long call, see [set_handler].
+                static HANDLER: RefCell<Option<Box<dyn Fn(&str, u64, u64)>>> =
+                    const { RefCell::new(None) };
+            }
+
+            /// Call `handler` with the operation, steps done and total steps of long calls,
+            /// in place of the host's `on-progress`.
+            pub fn set_handler(handler: impl Fn(&str, u64, u64) + 'static) {
+                HANDLER.with_borrow_mut(|slot| *slot = Some(Box::new(handler)));
+            }
+
+            pub fn on_progress(operation: &str, done: u64, total: u64) {
+                HANDLER.with_borrow(|handler| {
+                    if let Some(handler) = handler {
+                        handler(operation, done, total)
+                    }
+                });
+            }
+        }
+
+        pub mod host_fs {
+            use std::cell::RefCell;
+            use std::fs::{File, OpenOptions};
+            use std::io::{Read, Seek, SeekFrom, Write};
+
+            thread_local! {
+                /// Open files, indexed by file descriptor.
+                static FILES: RefCell<Vec<File>> = const { RefCell::new(Vec::new()) };
+            }
+
+            fn with_file<T>(
+                fd: u32,
+                f: impl FnOnce(&mut File) -> std::io::Result<T>,
+            ) -> Result<T, String> {
+                FILES.with_borrow_mut(|files| {
+                    let file = files
+                        .get_mut(fd as usize)
+                        .ok_or_else(|| format!("bad file descriptor {}", fd))?;
+                    f(file).map_err(|e| e.to_string())
+                })
+            }
+
+            pub fn open(path: &str, create: bool) -> Result<u32, String> {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(create)
+                    .truncate(false)
+                    .open(path)
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                FILES.with_borrow_mut(|files| {
+                    files.push(file);
+                    Ok(files.len() as u32 - 1)
+                })
+            }
+
+            pub fn read(fd: u32, offset: u64, len: u32) -> Result<Vec<u8>, String> {
+                with_file(fd, |file| {
+                    file.seek(SeekFrom::Start(offset))?;
+                    let mut buf = Vec::with_capacity(len as usize);
+                    file.take(len as u64).read_to_end(&mut buf)?;
+                    Ok(buf)
+                })
+            }
+
+            pub fn write(fd: u32, offset: u64, data: &[u8]) -> Result<(), String> {
+                with_file(fd, |file| {
+                    file.seek(SeekFrom::Start(offset))?;
+                    file.write_all(data)
+                })
+            }
+
+            pub fn sync(fd: u32) -> Result<(), String> {
+                with_file(fd, |file| file.sync_all())
+            }
+
+            pub fn size(fd: u32) -> Result<u64, String> {
+                with_file(fd, |file| Ok(file.metadata()?.len()))
+            }
+        }
+
+        /// Reads run as they are submitted, as the process has no pool to run them on.
+        pub mod host_io {
+            use std::cell::{Cell, RefCell};
+            use std::collections::HashMap;
+
+            /// See `io-request` in the WIT.
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct IoRequest {
+                pub fd: u32,
+                pub offset: u64,
+                pub len: u32,
+            }
+
+            thread_local! {
+                static NEXT_ID: Cell<u64> = const { Cell::new(1) };
+                /// The results of the reads not yet polled, by id.
+                static DONE: RefCell<HashMap<u64, Result<Vec<u8>, String>>> =
+                    RefCell::new(HashMap::new());
+            }
+
+            pub fn submit_io(request: IoRequest) -> u64 {
+                let result = super::host_fs::read(request.fd, request.offset, request.len);
+                let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+                DONE.with_borrow_mut(|done| done.insert(id, result));
+                id
+            }
+
+            pub fn poll_io(id: u64) -> Option<Result<Vec<u8>, String>> {
+                DONE.with_borrow_mut(|done| done.remove(&id))
+            }
+        }
+
+        pub mod host_kv {
+            use std::cell::RefCell;
+            use std::collections::HashMap;
+
+            thread_local! {
+                static ENTRIES: RefCell<HashMap<String, Vec<u8>>> = RefCell::new(HashMap::new());
+            }
+
+            pub fn get(key: &str) -> Result<Option<Vec<u8>>, String> {
+                Ok(ENTRIES.with_borrow(|entries| entries.get(key).cloned()))
+            }
+
+            pub fn set(key: &str, value: &[u8]) -> Result<(), String> {
+                ENTRIES.with_borrow_mut(|entries| entries.insert(key.to_string(), value.to_vec()));
+                Ok(())
+            }
+
+            pub fn flush() -> Result<(), String> {
+                Ok(())
+            }
+        }
+    }
+}
+
+pub mod exports {
+    pub mod component {
+        pub mod wit_limbo {
+            pub mod limbo {
+                use std::any::Any;
+
+                use crate::Component;
+
+                /// See `record-value` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub enum RecordValue {
+                    Null,
+                    Integer(i64),
+                    Float(f64),
+                    Text(String),
+                    Blob(Vec<u8>),
+                    TextDecimal(String),
+                }
+
+                /// See `affinity` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum Affinity {
+                    Integer,
+                    Text,
+                    Blob,
+                    Real,
+                    Numeric,
+                }
+
+                /// See `result-set` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct ResultSet {
+                    pub columns: Vec<String>,
+                    pub rows: Vec<Vec<RecordValue>>,
+                    pub affinities: Vec<Affinity>,
+                }
+
+                /// See `chunk` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct Chunk {
+                    pub rows: Vec<Vec<RecordValue>>,
+                    pub done: bool,
+                }
+
+                /// See `trigger-info` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct TriggerInfo {
+                    pub name: String,
+                    pub table: String,
+                    pub sql: Option<String>,
+                }
+
+                /// See `generated-kind` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum GeneratedKind {
+                    Virtual,
+                    Stored,
+                }
+
+                /// See `column-info` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct ColumnInfo {
+                    pub name: String,
+                    pub decl_type: Option<String>,
+                    pub not_null: bool,
+                    pub default_expr: Option<String>,
+                    pub primary_key: u32,
+                    pub generated: Option<GeneratedKind>,
+                }
+
+                /// See `blob-encoding` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum BlobEncoding {
+                    Bytes,
+                    Base64,
+                    Hex,
+                }
+
+                /// See `statement-kind` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum StatementKind {
+                    Select,
+                    Insert,
+                    Update,
+                    Delete,
+                    Ddl,
+                    Pragma,
+                    Other,
+                }
+
+                /// See `temp-store` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum TempStore {
+                    Memory,
+                    Backend,
+                }
+
+                /// See `non-finite-floats` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum NonFiniteFloats {
+                    Pass,
+                    Null,
+                    Text,
+                    Error,
+                }
+
+                /// See `refresh-mode` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum RefreshMode {
+                    Manual,
+                    OnCommit,
+                }
+
+                /// See `audit-op` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum AuditOp {
+                    Insert,
+                    Update,
+                    Delete,
+                }
+
+                /// See `audit-entry` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct AuditEntry {
+                    pub op: AuditOp,
+                    pub old: Option<String>,
+                    pub new: Option<String>,
+                    pub at: u64,
+                }
+
+                /// See `json-options` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct JsonOptions {
+                    pub big_integers_as_strings: bool,
+                    pub float_precision: Option<u8>,
+                    pub blob_encoding: BlobEncoding,
+                }
+
+                /// See `limit-kind` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub enum LimitKind {
+                    SqlLength,
+                    Column,
+                    ExprDepth,
+                }
+
+                /// See `limits` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub struct Limits {
+                    pub sql_length: u32,
+                    pub column: u32,
+                    pub expr_depth: u32,
+                }
+
+                /// See `tick-work` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub struct TickWork {
+                    pub flushed: u32,
+                    pub checkpointed: bool,
+                    pub trimmed: bool,
+                }
+
+                /// See `prepared` in the WIT.
+                pub struct Prepared {
+                    pub statement: Statement,
+                    pub tail: String,
+                }
+
+                /// See `upsert-counts` in the WIT.
+                #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                pub struct UpsertCounts {
+                    pub inserted: u64,
+                    pub updated: u64,
+                }
+
+                /// See `query-estimate` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct QueryEstimate {
+                    pub estimated_rows: u64,
+                    pub full_scan: bool,
+                    pub scanned_tables: Vec<String>,
+                    pub searched_tables: Vec<String>,
+                }
+
+                /// See `view-info` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct ViewInfo {
+                    pub name: String,
+                    pub sql: Option<String>,
+                    pub tables: Vec<String>,
+                }
+
+                /// See `keyset-page` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct KeysetPage {
+                    pub rows: Vec<Vec<RecordValue>>,
+                    pub next: Option<Vec<RecordValue>>,
+                }
+
+                /// See `run-stats` in the WIT.
+                #[derive(Clone, Debug, Default, PartialEq)]
+                pub struct RunStats {
+                    pub vm_steps: u64,
+                    pub rows_produced: u64,
+                    pub io_operations: u64,
+                    pub elapsed_micros: u64,
+                }
+
+                /// See `fk-violation` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct FkViolation {
+                    pub table: String,
+                    pub rowid: Option<i64>,
+                    pub parent: String,
+                    pub fkid: i64,
+                }
+
+                /// See `sql-position` in the WIT.
+                #[derive(Clone, Debug, PartialEq, Eq)]
+                pub struct SqlPosition {
+                    pub line: u32,
+                    pub column: u32,
+                    pub offset: u32,
+                    pub token: String,
+                }
+
+                /// See `parse-error` in the WIT.
+                #[derive(Clone, Debug, PartialEq, Eq)]
+                pub struct ParseError {
+                    pub message: String,
+                    pub position: Option<SqlPosition>,
+                }
+
+                /// See `db-error` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub enum DbError {
+                    Parse(ParseError),
+                    Corrupt(String),
+                    Io(String),
+                    Misuse(String),
+                    Busy(String),
+                    Closed(String),
+                    Interrupted(String),
+                    OutOfMemory(String),
+                    LimitExceeded(String),
+                    CannotOpen(String),
+                    Other(String),
+                }
+
+                impl std::fmt::Display for DbError {
+                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        write!(f, "{:?}", self)
+                    }
+                }
+
+                impl std::error::Error for DbError {}
+
+                wit_bindgen_rt::bitflags::bitflags! {
+                    /// See `open-flags` in the WIT.
+                    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+                    pub struct OpenFlags: u8 {
+                        const CREATE = 1 << 0;
+                        const READ_ONLY = 1 << 1;
+                        const EXCLUSIVE = 1 << 2;
+                    }
+                }
+
+                pub trait Guest {
+                    type Database: GuestDatabase;
+                    type Statement: GuestStatement;
+                    type Rows: GuestRows;
+                    type BlobWriter: GuestBlobWriter;
+                    type Manager: GuestManager;
+                    type Subscription: GuestSubscription;
+                    type NdjsonExporter: GuestNdjsonExporter;
+
+                    fn capabilities() -> Vec<String>;
+                    fn set_soft_heap_limit(bytes: u64) -> u64;
+                }
+
+                /// See `capabilities` in the WIT.
+                pub fn capabilities() -> Vec<String> {
+                    <Component as Guest>::capabilities()
+                }
+
+                /// See `set-soft-heap-limit` in the WIT.
+                pub fn set_soft_heap_limit(bytes: u64) -> u64 {
+                    <Component as Guest>::set_soft_heap_limit(bytes)
+                }
+
+                pub trait GuestDatabase: 'static {
+                    fn new(path: String) -> Self;
+                    fn open_with(path: String, flags: OpenFlags) -> Result<Database, DbError>;
+                    fn exec(&self, sql: String) -> Result<Vec<Vec<RecordValue>>, DbError>;
+                    fn prepare(&self, sql: String) -> Result<Statement, DbError>;
+                    fn prepare_tail(&self, sql: String) -> Result<Prepared, DbError>;
+                    fn query_json(&self, sql: String, params: String) -> Result<String, DbError>;
+                    fn clone_handle(&self) -> Database;
+                    fn insert_blob(
+                        &self,
+                        table: String,
+                        column: String,
+                        rowid: i64,
+                        len: u64,
+                    ) -> Result<BlobWriter, DbError>;
+                    fn is_readonly_sql(&self, sql: String) -> bool;
+                    fn set_table_filter(
+                        &self,
+                        table: String,
+                        predicate: String,
+                    ) -> Result<(), DbError>;
+                    fn set_defensive(&self, on: bool);
+                    fn set_unicode_case(&self, on: bool);
+                    fn set_busy_timeout(&self, ms: u32);
+                    fn set_json_options(&self, options: JsonOptions);
+                    fn set_non_finite_floats(&self, policy: NonFiniteFloats);
+                    fn set_query_cache(&self, max_entries: u32, max_bytes: u64);
+                    fn cancel_running(&self);
+                    fn set_limit(&self, kind: LimitKind, value: u32) -> u32;
+                    fn limits(&self) -> Limits;
+                    fn metrics_prometheus(&self) -> String;
+                    fn last_error(&self) -> Option<DbError>;
+                    fn last_error_code(&self) -> Option<u32>;
+                    fn set_foreign_keys(&self, on: bool) -> Result<(), DbError>;
+                    fn foreign_key_check(
+                        &self,
+                        table: Option<String>,
+                    ) -> Result<Vec<FkViolation>, DbError>;
+                    fn integrity_check(&self, max_errors: u32) -> Result<Vec<String>, DbError>;
+                    fn triggers(&self, table: Option<String>) -> Result<Vec<TriggerInfo>, DbError>;
+                    fn columns(&self, table: String) -> Result<Vec<ColumnInfo>, DbError>;
+                    fn copy_table(
+                        &self,
+                        src: DatabaseBorrow<'_>,
+                        table: String,
+                        overwrite: bool,
+                    ) -> Result<u64, DbError>;
+                    fn upsert(
+                        &self,
+                        table: String,
+                        key_columns: Vec<String>,
+                        columns: Vec<String>,
+                        rows: Vec<Vec<RecordValue>>,
+                    ) -> Result<UpsertCounts, DbError>;
+                    fn diff_schema(
+                        &self,
+                        other: DatabaseBorrow<'_>,
+                    ) -> Result<Vec<String>, DbError>;
+                    fn estimate(&self, sql: String) -> Result<QueryEstimate, DbError>;
+                    fn views(&self) -> Result<Vec<ViewInfo>, DbError>;
+                    fn schema_version(&self) -> Result<u32, DbError>;
+                    fn subscribe(
+                        &self,
+                        sql: String,
+                        params: Vec<RecordValue>,
+                    ) -> Result<Subscription, DbError>;
+                    fn create_materialized_view(
+                        &self,
+                        name: String,
+                        sql: String,
+                        refresh: RefreshMode,
+                    ) -> Result<(), DbError>;
+                    fn refresh_view(&self, name: String) -> Result<u64, DbError>;
+                    fn snapshot(&self) -> Result<u64, DbError>;
+                    fn query_as_of(
+                        &self,
+                        sql: String,
+                        snapshot_id: u64,
+                    ) -> Result<Vec<Vec<RecordValue>>, DbError>;
+                    fn release_snapshot(&self, snapshot_id: u64) -> bool;
+                    fn enable_audit(&self, table: String) -> Result<(), DbError>;
+                    fn audit_history(
+                        &self,
+                        table: String,
+                        rowid: i64,
+                    ) -> Result<Vec<AuditEntry>, DbError>;
+                    fn set_compress_pages(&self, enabled: bool) -> Result<(), DbError>;
+                    fn set_readahead_pages(&self, pages: u32) -> Result<(), DbError>;
+                    fn set_coalesce_writes(&self, enabled: bool) -> Result<(), DbError>;
+                    fn set_temp_store(&self, store: TempStore) -> Result<(), DbError>;
+                    fn export_ndjson(&self, table: String) -> Result<NdjsonExporter, DbError>;
+                    fn begin_batched(&self, max_delay_ms: u32, max_ops: u32)
+                        -> Result<(), DbError>;
+                    fn flush(&self) -> Result<u32, DbError>;
+                    fn end_batched(&self) -> Result<u32, DbError>;
+                    fn tick(&self) -> Result<TickWork, DbError>;
+                    fn close(&self) -> Result<(), DbError>;
+                }
+
+                pub trait GuestStatement: 'static {
+                    fn bind(&self, index: u32, value: RecordValue) -> Result<(), DbError>;
+                    fn bind_json(&self, params: String) -> Result<(), DbError>;
+                    fn all(&self) -> Result<Vec<Vec<RecordValue>>, DbError>;
+                    fn run(&self) -> Result<Rows, DbError>;
+                    fn all_chunked(&self, max_rows: u32) -> Result<Chunk, DbError>;
+                    fn keyset_page(
+                        &self,
+                        after: Option<Vec<RecordValue>>,
+                        limit: u32,
+                    ) -> Result<KeysetPage, DbError>;
+                    fn column_names(&self) -> Vec<String>;
+                    fn column_decl_types(&self) -> Vec<Option<String>>;
+                    fn all_with_names(&self) -> Result<ResultSet, DbError>;
+                    fn all_with_blob_encoding(
+                        &self,
+                        encoding: BlobEncoding,
+                    ) -> Result<Vec<Vec<RecordValue>>, DbError>;
+                    fn all_json(&self) -> Result<String, DbError>;
+                    fn all_proto(&self) -> Result<Vec<u8>, DbError>;
+                    fn all_parquet(&self) -> Result<Vec<u8>, DbError>;
+                    fn last_run_stats(&self) -> RunStats;
+                    fn is_readonly(&self) -> bool;
+                    fn kind(&self) -> StatementKind;
+                    fn tables(&self) -> Result<Vec<String>, DbError>;
+                    fn original_sql(&self) -> String;
+                    fn expanded_sql(&self) -> String;
+                    fn finalize(&self) -> Result<(), DbError>;
+                }
+
+                pub trait GuestRows: 'static {
+                    fn next(&self) -> Result<Option<Vec<RecordValue>>, DbError>;
+                    fn next_chunk(&self, n: u32) -> Result<Vec<Vec<RecordValue>>, DbError>;
+                }
+
+                pub trait GuestBlobWriter: 'static {
+                    fn write(&self, chunk: Vec<u8>) -> Result<(), DbError>;
+                    fn finish(&self) -> Result<(), DbError>;
+                }
+
+                pub trait GuestSubscription: 'static {
+                    fn id(&self) -> u64;
+                    fn latest(&self) -> Result<Vec<Vec<RecordValue>>, DbError>;
+                }
+
+                pub trait GuestNdjsonExporter: 'static {
+                    fn next_chunk(&self, max_bytes: u32) -> Result<Option<String>, DbError>;
+                }
+
+                pub trait GuestManager: 'static {
+                    fn new(root: String) -> Self;
+                    fn open(&self, tenant_id: String) -> Result<Database, DbError>;
+                    fn list(&self) -> Vec<String>;
+                    fn drop_tenant(&self, tenant_id: String) -> Result<(), DbError>;
+                    fn set_memory_quota(&self, bytes: u64);
+                    fn set_idle_close_ms(&self, ms: u32);
+                    fn close_idle(&self) -> Result<Vec<String>, DbError>;
+                }
+
+                /// Owned resources, holding the implementation the way the generated
+                /// resource types hold a handle to it.
+                macro_rules! resource {
+                    ($name:ident, $guest:ident) => {
+                        pub struct $name(Box<dyn Any>);
+
+                        impl $name {
+                            pub fn new<T: $guest>(val: T) -> Self {
+                                Self(Box::new(val))
+                            }
+
+                            pub fn get<T: $guest>(&self) -> &T {
+                                self.0
+                                    .downcast_ref()
+                                    .expect(concat!(stringify!($name), " of a different type"))
+                            }
+                        }
+                    };
+                }
+
+                resource!(Database, GuestDatabase);
+                resource!(Statement, GuestStatement);
+                resource!(Rows, GuestRows);
+                resource!(BlobWriter, GuestBlobWriter);
+                resource!(Manager, GuestManager);
+                resource!(Subscription, GuestSubscription);
+                resource!(NdjsonExporter, GuestNdjsonExporter);
+
+                /// A borrowed `database`, as a `borrow<database>` parameter is passed.
+                pub type DatabaseBorrow<'a> = &'a Database;
+
+                type InnerStatement = <Component as Guest>::Statement;
+                type InnerRows = <Component as Guest>::Rows;
+                type InnerBlobWriter = <Component as Guest>::BlobWriter;
+                type InnerManager = <Component as Guest>::Manager;
+                type InnerSubscription = <Component as Guest>::Subscription;
+                type InnerNdjsonExporter = <Component as Guest>::NdjsonExporter;
+
+                impl Database {
+                    /// Open the database at `path`, see the `database` constructor in the WIT.
+                    pub fn open(path: &str) -> Self {
+                        Self::new(Component::new(path.to_string()))
+                    }
+
+                    /// Open the database at `path` as `flags` say, see `open-with` in the WIT.
+                    pub fn open_with(path: &str, flags: OpenFlags) -> Result<Self, DbError> {
+                        Component::open_with(path.to_string(), flags)
+                    }
+
+                    pub fn exec(&self, sql: &str) -> Result<Vec<Vec<RecordValue>>, DbError> {
+                        self.get::<Component>().exec(sql.to_string())
+                    }
+
+                    pub fn prepare(&self, sql: &str) -> Result<Statement, DbError> {
+                        self.get::<Component>().prepare(sql.to_string())
+                    }
+
+                    pub fn prepare_tail(&self, sql: &str) -> Result<Prepared, DbError> {
+                        self.get::<Component>().prepare_tail(sql.to_string())
+                    }
+
+                    pub fn query_json(&self, sql: &str, params: &str) -> Result<String, DbError> {
+                        self.get::<Component>()
+                            .query_json(sql.to_string(), params.to_string())
+                    }
+
+                    pub fn clone_handle(&self) -> Database {
+                        self.get::<Component>().clone_handle()
+                    }
+
+                    pub fn insert_blob(
+                        &self,
+                        table: &str,
+                        column: &str,
+                        rowid: i64,
+                        len: u64,
+                    ) -> Result<BlobWriter, DbError> {
+                        self.get::<Component>().insert_blob(
+                            table.to_string(),
+                            column.to_string(),
+                            rowid,
+                            len,
+                        )
+                    }
+
+                    pub fn is_readonly_sql(&self, sql: &str) -> bool {
+                        self.get::<Component>().is_readonly_sql(sql.to_string())
+                    }
+
+                    pub fn set_table_filter(
+                        &self,
+                        table: &str,
+                        predicate: &str,
+                    ) -> Result<(), DbError> {
+                        self.get::<Component>()
+                            .set_table_filter(table.to_string(), predicate.to_string())
+                    }
+
+                    pub fn set_defensive(&self, on: bool) {
+                        self.get::<Component>().set_defensive(on)
+                    }
+
+                    pub fn set_unicode_case(&self, on: bool) {
+                        self.get::<Component>().set_unicode_case(on)
+                    }
+
+                    pub fn set_busy_timeout(&self, ms: u32) {
+                        self.get::<Component>().set_busy_timeout(ms)
+                    }
+
+                    pub fn set_json_options(&self, options: JsonOptions) {
+                        self.get::<Component>().set_json_options(options)
+                    }
+
+                    pub fn set_non_finite_floats(&self, policy: NonFiniteFloats) {
+                        self.get::<Component>().set_non_finite_floats(policy)
+                    }
+
+                    pub fn set_query_cache(&self, max_entries: u32, max_bytes: u64) {
+                        self.get::<Component>()
+                            .set_query_cache(max_entries, max_bytes)
+                    }
+
+                    pub fn cancel_running(&self) {
+                        self.get::<Component>().cancel_running()
+                    }
+
+                    pub fn set_limit(&self, kind: LimitKind, value: u32) -> u32 {
+                        self.get::<Component>().set_limit(kind, value)
+                    }
+
+                    pub fn limits(&self) -> Limits {
+                        self.get::<Component>().limits()
+                    }
+
+                    pub fn metrics_prometheus(&self) -> String {
+                        self.get::<Component>().metrics_prometheus()
+                    }
+
+                    pub fn last_error(&self) -> Option<DbError> {
+                        self.get::<Component>().last_error()
+                    }
+
+                    pub fn last_error_code(&self) -> Option<u32> {
+                        self.get::<Component>().last_error_code()
+                    }
+
+                    pub fn set_foreign_keys(&self, on: bool) -> Result<(), DbError> {
+                        self.get::<Component>().set_foreign_keys(on)
+                    }
+
+                    pub fn foreign_key_check(
+                        &self,
+                        table: Option<&str>,
+                    ) -> Result<Vec<FkViolation>, DbError> {
+                        self.get::<Component>()
+                            .foreign_key_check(table.map(str::to_string))
+                    }
+
+                    pub fn integrity_check(&self, max_errors: u32) -> Result<Vec<String>, DbError> {
+                        self.get::<Component>().integrity_check(max_errors)
+                    }
+
+                    pub fn triggers(
+                        &self,
+                        table: Option<&str>,
+                    ) -> Result<Vec<TriggerInfo>, DbError> {
+                        self.get::<Component>().triggers(table.map(str::to_string))
+                    }
+
+                    pub fn columns(&self, table: &str) -> Result<Vec<ColumnInfo>, DbError> {
+                        self.get::<Component>().columns(table.to_string())
+                    }
+
+                    pub fn copy_table(
+                        &self,
+                        src: &Database,
+                        table: &str,
+                        overwrite: bool,
+                    ) -> Result<u64, DbError> {
+                        self.get::<Component>()
+                            .copy_table(src, table.to_string(), overwrite)
+                    }
+
+                    pub fn upsert(
+                        &self,
+                        table: &str,
+                        key_columns: Vec<String>,
+                        columns: Vec<String>,
+                        rows: Vec<Vec<RecordValue>>,
+                    ) -> Result<UpsertCounts, DbError> {
+                        self.get::<Component>().upsert(
+                            table.to_string(),
+                            key_columns,
+                            columns,
+                            rows,
+                        )
+                    }
+
+                    pub fn subscribe(
+                        &self,
+                        sql: &str,
+                        params: Vec<RecordValue>,
+                    ) -> Result<Subscription, DbError> {
+                        self.get::<Component>().subscribe(sql.to_string(), params)
+                    }
+
+                    pub fn create_materialized_view(
+                        &self,
+                        name: &str,
+                        sql: &str,
+                        refresh: RefreshMode,
+                    ) -> Result<(), DbError> {
+                        self.get::<Component>().create_materialized_view(
+                            name.to_string(),
+                            sql.to_string(),
+                            refresh,
+                        )
+                    }
+
+                    pub fn refresh_view(&self, name: &str) -> Result<u64, DbError> {
+                        self.get::<Component>().refresh_view(name.to_string())
+                    }
+
+                    pub fn snapshot(&self) -> Result<u64, DbError> {
+                        self.get::<Component>().snapshot()
+                    }
+
+                    pub fn query_as_of(
+                        &self,
+                        sql: &str,
+                        snapshot_id: u64,
+                    ) -> Result<Vec<Vec<RecordValue>>, DbError> {
+                        self.get::<Component>()
+                            .query_as_of(sql.to_string(), snapshot_id)
+                    }
+
+                    pub fn release_snapshot(&self, snapshot_id: u64) -> bool {
+                        self.get::<Component>().release_snapshot(snapshot_id)
+                    }
+
+                    pub fn enable_audit(&self, table: &str) -> Result<(), DbError> {
+                        self.get::<Component>().enable_audit(table.to_string())
+                    }
+
+                    pub fn audit_history(
+                        &self,
+                        table: &str,
+                        rowid: i64,
+                    ) -> Result<Vec<AuditEntry>, DbError> {
+                        self.get::<Component>()
+                            .audit_history(table.to_string(), rowid)
+                    }
+
+                    pub fn set_compress_pages(&self, enabled: bool) -> Result<(), DbError> {
+                        self.get::<Component>().set_compress_pages(enabled)
+                    }
+
+                    pub fn set_readahead_pages(&self, pages: u32) -> Result<(), DbError> {
+                        self.get::<Component>().set_readahead_pages(pages)
+                    }
+
+                    pub fn set_coalesce_writes(&self, enabled: bool) -> Result<(), DbError> {
+                        self.get::<Component>().set_coalesce_writes(enabled)
+                    }
+
+                    pub fn set_temp_store(&self, store: TempStore) -> Result<(), DbError> {
+                        self.get::<Component>().set_temp_store(store)
+                    }
+
+                    pub fn export_ndjson(&self, table: &str) -> Result<NdjsonExporter, DbError> {
+                        self.get::<Component>().export_ndjson(table.to_string())
+                    }
+
+                    pub fn diff_schema(&self, other: &Database) -> Result<Vec<String>, DbError> {
+                        self.get::<Component>().diff_schema(other)
+                    }
+
+                    pub fn estimate(&self, sql: &str) -> Result<QueryEstimate, DbError> {
+                        self.get::<Component>().estimate(sql.to_string())
+                    }
+
+                    pub fn views(&self) -> Result<Vec<ViewInfo>, DbError> {
+                        self.get::<Component>().views()
+                    }
+
+                    pub fn schema_version(&self) -> Result<u32, DbError> {
+                        self.get::<Component>().schema_version()
+                    }
+
+                    pub fn begin_batched(
+                        &self,
+                        max_delay_ms: u32,
+                        max_ops: u32,
+                    ) -> Result<(), DbError> {
+                        self.get::<Component>().begin_batched(max_delay_ms, max_ops)
+                    }
+
+                    pub fn flush(&self) -> Result<u32, DbError> {
+                        self.get::<Component>().flush()
+                    }
+
+                    pub fn end_batched(&self) -> Result<u32, DbError> {
+                        self.get::<Component>().end_batched()
+                    }
+
+                    pub fn tick(&self) -> Result<TickWork, DbError> {
+                        self.get::<Component>().tick()
+                    }
+
+                    pub fn close(&self) -> Result<(), DbError> {
+                        self.get::<Component>().close()
+                    }
+                }
+
+                impl Statement {
+                    pub fn bind(&self, index: u32, value: RecordValue) -> Result<(), DbError> {
+                        self.get::<InnerStatement>().bind(index, value)
+                    }
+
+                    pub fn bind_json(&self, params: &str) -> Result<(), DbError> {
+                        self.get::<InnerStatement>().bind_json(params.to_string())
+                    }
+
+                    pub fn all(&self) -> Result<Vec<Vec<RecordValue>>, DbError> {
+                        self.get::<InnerStatement>().all()
+                    }
+
+                    pub fn run(&self) -> Result<Rows, DbError> {
+                        self.get::<InnerStatement>().run()
+                    }
+
+                    pub fn all_chunked(&self, max_rows: u32) -> Result<Chunk, DbError> {
+                        self.get::<InnerStatement>().all_chunked(max_rows)
+                    }
+
+                    pub fn keyset_page(
+                        &self,
+                        after: Option<Vec<RecordValue>>,
+                        limit: u32,
+                    ) -> Result<KeysetPage, DbError> {
+                        self.get::<InnerStatement>().keyset_page(after, limit)
+                    }
+
+                    pub fn column_names(&self) -> Vec<String> {
+                        self.get::<InnerStatement>().column_names()
+                    }
+
+                    pub fn column_decl_types(&self) -> Vec<Option<String>> {
+                        self.get::<InnerStatement>().column_decl_types()
+                    }
+
+                    pub fn all_with_names(&self) -> Result<ResultSet, DbError> {
+                        self.get::<InnerStatement>().all_with_names()
+                    }
+
+                    pub fn all_with_blob_encoding(
+                        &self,
+                        encoding: BlobEncoding,
+                    ) -> Result<Vec<Vec<RecordValue>>, DbError> {
+                        self.get::<InnerStatement>()
+                            .all_with_blob_encoding(encoding)
+                    }
+
+                    pub fn all_json(&self) -> Result<String, DbError> {
+                        self.get::<InnerStatement>().all_json()
+                    }
+
+                    pub fn all_proto(&self) -> Result<Vec<u8>, DbError> {
+                        self.get::<InnerStatement>().all_proto()
+                    }
+
+                    pub fn all_parquet(&self) -> Result<Vec<u8>, DbError> {
+                        self.get::<InnerStatement>().all_parquet()
+                    }
+
+                    pub fn last_run_stats(&self) -> RunStats {
+                        self.get::<InnerStatement>().last_run_stats()
+                    }
+
+                    pub fn is_readonly(&self) -> bool {
+                        self.get::<InnerStatement>().is_readonly()
+                    }
+
+                    pub fn kind(&self) -> StatementKind {
+                        self.get::<InnerStatement>().kind()
+                    }
+
+                    pub fn tables(&self) -> Result<Vec<String>, DbError> {
+                        self.get::<InnerStatement>().tables()
+                    }
+
+                    pub fn original_sql(&self) -> String {
+                        self.get::<InnerStatement>().original_sql()
+                    }
+
+                    pub fn expanded_sql(&self) -> String {
+                        self.get::<InnerStatement>().expanded_sql()
+                    }
+
+                    pub fn finalize(&self) -> Result<(), DbError> {
+                        self.get::<InnerStatement>().finalize()
+                    }
+                }
+
+                impl Rows {
+                    pub fn next(&self) -> Result<Option<Vec<RecordValue>>, DbError> {
+                        self.get::<InnerRows>().next()
+                    }
+
+                    pub fn next_chunk(&self, n: u32) -> Result<Vec<Vec<RecordValue>>, DbError> {
+                        self.get::<InnerRows>().next_chunk(n)
+                    }
+                }
+
+                impl BlobWriter {
+                    pub fn write(&self, chunk: Vec<u8>) -> Result<(), DbError> {
+                        self.get::<InnerBlobWriter>().write(chunk)
+                    }
+
+                    pub fn finish(&self) -> Result<(), DbError> {
+                        self.get::<InnerBlobWriter>().finish()
+                    }
+                }
+
+                impl Subscription {
+                    /// See `subscription.id` in the WIT; the id [crate::set_on_query_changed]'s
+                    /// callback is called with.
+                    pub fn id(&self) -> u64 {
+                        self.get::<InnerSubscription>().id()
+                    }
+
+                    pub fn latest(&self) -> Result<Vec<Vec<RecordValue>>, DbError> {
+                        self.get::<InnerSubscription>().latest()
+                    }
+                }
+
+                impl NdjsonExporter {
+                    pub fn next_chunk(&self, max_bytes: u32) -> Result<Option<String>, DbError> {
+                        self.get::<InnerNdjsonExporter>().next_chunk(max_bytes)
+                    }
+                }
+
+                impl Manager {
+                    /// A manager keeping tenant databases under `root`, see `manager` in the WIT.
+                    pub fn with_root(root: &str) -> Self {
+                        Self::new(InnerManager::new(root.to_string()))
+                    }
+
+                    pub fn open(&self, tenant_id: &str) -> Result<Database, DbError> {
+                        self.get::<InnerManager>().open(tenant_id.to_string())
+                    }
+
+                    pub fn list(&self) -> Vec<String> {
+                        self.get::<InnerManager>().list()
+                    }
+
+                    pub fn drop_tenant(&self, tenant_id: &str) -> Result<(), DbError> {
+                        self.get::<InnerManager>()
+                            .drop_tenant(tenant_id.to_string())
+                    }
+
+                    pub fn set_memory_quota(&self, bytes: u64) {
+                        self.get::<InnerManager>().set_memory_quota(bytes)
+                    }
+
+                    pub fn set_idle_close_ms(&self, ms: u32) {
+                        self.get::<InnerManager>().set_idle_close_ms(ms)
+                    }
+
+                    pub fn close_idle(&self) -> Result<Vec<String>, DbError> {
+                        self.get::<InnerManager>().close_idle()
+                    }
+                }
+            }
+
+            pub mod docs {
+                use super::limbo::DbError;
+                use crate::Component;
+
+                /// See `document` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct Document {
+                    pub id: String,
+                    pub json: String,
+                }
+
+                pub trait Guest {
+                    type Store: GuestStore;
+                }
+
+                pub trait GuestStore: 'static {
+                    fn new(path: String) -> Self;
+                    fn put(
+                        &self,
+                        collection: String,
+                        id: String,
+                        json: String,
+                    ) -> Result<(), DbError>;
+                    fn get(
+                        &self,
+                        collection: String,
+                        id: String,
+                    ) -> Result<Option<String>, DbError>;
+                    fn find(
+                        &self,
+                        collection: String,
+                        filter: String,
+                        limit: u32,
+                    ) -> Result<Vec<Document>, DbError>;
+                }
+
+                type InnerStore = <Component as Guest>::Store;
+
+                /// See `store` in the WIT, held directly as [super::kv::Store] is.
+                pub struct Store(InnerStore);
+
+                impl Store {
+                    /// Open the store in the database at `path`, see `store` in the WIT.
+                    pub fn open(path: &str) -> Self {
+                        Self(InnerStore::new(path.to_string()))
+                    }
+
+                    pub fn put(
+                        &self,
+                        collection: &str,
+                        id: &str,
+                        json: &str,
+                    ) -> Result<(), DbError> {
+                        self.0
+                            .put(collection.to_string(), id.to_string(), json.to_string())
+                    }
+
+                    pub fn get(
+                        &self,
+                        collection: &str,
+                        id: &str,
+                    ) -> Result<Option<String>, DbError> {
+                        self.0.get(collection.to_string(), id.to_string())
+                    }
+
+                    pub fn find(
+                        &self,
+                        collection: &str,
+                        filter: &str,
+                        limit: u32,
+                    ) -> Result<Vec<Document>, DbError> {
+                        self.0
+                            .find(collection.to_string(), filter.to_string(), limit)
+                    }
+                }
+            }
+
+            pub mod events {
+                use super::limbo::DbError;
+                use crate::Component;
+
+                /// See `event` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct Event {
+                    pub seq: u64,
+                    pub type_: String,
+                    pub payload: Vec<u8>,
+                }
+
+                pub trait Guest {
+                    type Store: GuestStore;
+                }
+
+                pub trait GuestStore: 'static {
+                    fn new(path: String) -> Self;
+                    fn append(
+                        &self,
+                        stream: String,
+                        type_: String,
+                        payload: Vec<u8>,
+                    ) -> Result<u64, DbError>;
+                    fn read(
+                        &self,
+                        stream: String,
+                        from_seq: u64,
+                        limit: u32,
+                    ) -> Result<Vec<Event>, DbError>;
+                }
+
+                type InnerStore = <Component as Guest>::Store;
+
+                /// See `store` in the WIT, held directly as [super::kv::Store] is.
+                pub struct Store(InnerStore);
+
+                impl Store {
+                    /// Open the store in the database at `path`, see `store` in the WIT.
+                    pub fn open(path: &str) -> Self {
+                        Self(InnerStore::new(path.to_string()))
+                    }
+
+                    pub fn append(
+                        &self,
+                        stream: &str,
+                        type_: &str,
+                        payload: Vec<u8>,
+                    ) -> Result<u64, DbError> {
+                        self.0
+                            .append(stream.to_string(), type_.to_string(), payload)
+                    }
+
+                    pub fn read(
+                        &self,
+                        stream: &str,
+                        from_seq: u64,
+                        limit: u32,
+                    ) -> Result<Vec<Event>, DbError> {
+                        self.0.read(stream.to_string(), from_seq, limit)
+                    }
+                }
+            }
+
+            pub mod sqlar {
+                use super::limbo::DbError;
+                use crate::Component;
+
+                /// See `entry` in the WIT.
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct Entry {
+                    pub name: String,
+                    pub mode: u32,
+                    pub mtime: i64,
+                    pub size: u64,
+                }
+
+                pub trait Guest {
+                    type Archive: GuestArchive;
+                }
+
+                pub trait GuestArchive: 'static {
+                    fn new(path: String) -> Self;
+                    fn add_file(
+                        &self,
+                        name: String,
+                        bytes: Vec<u8>,
+                        mode: u32,
+                    ) -> Result<(), DbError>;
+                    fn extract(&self, name: String) -> Result<Option<Vec<u8>>, DbError>;
+                    fn list(&self) -> Result<Vec<Entry>, DbError>;
+                }
+
+                type InnerArchive = <Component as Guest>::Archive;
+
+                /// See `archive` in the WIT, held directly as [super::kv::Store] is.
+                pub struct Archive(InnerArchive);
+
+                impl Archive {
+                    /// Open the archive in the database at `path`, see `archive` in the WIT.
+                    pub fn open(path: &str) -> Self {
+                        Self(InnerArchive::new(path.to_string()))
+                    }
+
+                    pub fn add_file(
+                        &self,
+                        name: &str,
+                        bytes: Vec<u8>,
+                        mode: u32,
+                    ) -> Result<(), DbError> {
+                        self.0.add_file(name.to_string(), bytes, mode)
+                    }
+
+                    pub fn extract(&self, name: &str) -> Result<Option<Vec<u8>>, DbError> {
+                        self.0.extract(name.to_string())
+                    }
+
+                    pub fn list(&self) -> Result<Vec<Entry>, DbError> {
+                        self.0.list()
+                    }
+                }
+            }
+
+            pub mod kv {
+                use super::limbo::DbError;
+                use crate::Component;
+
+                pub trait Guest {
+                    type Store: GuestStore;
+                }
+
+                pub trait GuestStore: 'static {
+                    fn new(path: String) -> Self;
+                    fn get(&self, key: String) -> Result<Option<Vec<u8>>, DbError>;
+                    fn set(&self, key: String, value: Vec<u8>) -> Result<(), DbError>;
+                    fn delete(&self, key: String) -> Result<bool, DbError>;
+                    fn scan(&self, prefix: String) -> Result<Vec<(String, Vec<u8>)>, DbError>;
+                }
+
+                type InnerStore = <Component as Guest>::Store;
+
+                /// See `store` in the WIT. Nothing hands a store back to the component, so
+                /// this holds the implementation directly rather than as a `resource!`.
+                pub struct Store(InnerStore);
+
+                impl Store {
+                    /// Open the store in the database at `path`, see `store` in the WIT.
+                    pub fn open(path: &str) -> Self {
+                        Self(InnerStore::new(path.to_string()))
+                    }
+
+                    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, DbError> {
+                        self.0.get(key.to_string())
+                    }
+
+                    pub fn set(&self, key: &str, value: Vec<u8>) -> Result<(), DbError> {
+                        self.0.set(key.to_string(), value)
+                    }
+
+                    pub fn delete(&self, key: &str) -> Result<bool, DbError> {
+                        self.0.delete(key.to_string())
+                    }
+
+                    pub fn scan(&self, prefix: &str) -> Result<Vec<(String, Vec<u8>)>, DbError> {
+                        self.0.scan(prefix.to_string())
+                    }
+                }
+            }
+        }
+    }
+}
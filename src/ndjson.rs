@@ -0,0 +1,51 @@
+//! Exporting a table as newline-delimited JSON, see `database.export-ndjson` in the WIT.
+use std::cell::RefCell;
+
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    DbError, GuestNdjsonExporter, JsonOptions,
+};
+use crate::{json, InnerRows};
+
+pub struct NdjsonExporter {
+    rows: InnerRows,
+    columns: Vec<String>,
+    /// The connection's JSON options when the export started.
+    options: JsonOptions,
+    /// A line that didn't fit in the last chunk, to start the next one.
+    carried: RefCell<Option<String>>,
+}
+
+impl NdjsonExporter {
+    pub(crate) fn new(rows: InnerRows, columns: Vec<String>, options: JsonOptions) -> Self {
+        Self {
+            rows,
+            columns,
+            options,
+            carried: RefCell::new(None),
+        }
+    }
+}
+
+impl GuestNdjsonExporter for NdjsonExporter {
+    fn next_chunk(&self, max_bytes: u32) -> Result<Option<String>, DbError> {
+        if max_bytes == 0 {
+            return Err(DbError::Misuse("max-bytes must be at least 1".to_string()));
+        }
+        let max_bytes = max_bytes as usize;
+        let mut chunk = self.carried.take().unwrap_or_default();
+        while chunk.len() < max_bytes {
+            let Some(row) = self.rows.step()? else {
+                break;
+            };
+            let mut line = json::row_object(&self.columns, row, &self.options).to_string();
+            line.push('\n');
+            // a chunk holds whole lines, and at least one however long it is
+            if !chunk.is_empty() && chunk.len() + line.len() > max_bytes {
+                self.carried.replace(Some(line));
+                break;
+            }
+            chunk.push_str(&line);
+        }
+        Ok((!chunk.is_empty()).then_some(chunk))
+    }
+}
@@ -0,0 +1,95 @@
+//! The values an operand can take, for the operators the guest works out itself before a
+//! statement runs because limbo has no way to call back into the guest for them: REGEXP, and
+//! with the `host-locale` feature, folding case and collating by the host's locale.
+use std::collections::BTreeMap;
+
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue};
+use crate::sql::{self, Operand};
+use crate::{error, quote_identifier, Component};
+
+/// The most distinct values of a column the guest reads for an operator. Each is written into
+/// the statement, so a column with more is refused rather than growing it without bound.
+pub(crate) const MAX_VALUES: usize = 10_000;
+
+/// The values `operand` of `sql` can take, given `params`, the values bound to its
+/// parameters: a literal's own, a parameter's bound value, or the distinct values of a column
+/// in each table `sql` reads or writes that has one. Without `params`, as when a statement is
+/// only being prepared, a parameter or column has none yet. None for anything else, or for a
+/// column of no such table. A column with more than [MAX_VALUES] is refused for `operator`.
+pub(crate) fn values(
+    db: &Component,
+    sql: &str,
+    operand: &Operand,
+    params: Option<&BTreeMap<usize, RecordValue>>,
+    operator: &str,
+) -> Result<Option<Vec<RecordValue>>, DbError> {
+    match (operand, params) {
+        (Operand::Literal(value), _) => Ok(Some(vec![value.clone()])),
+        (Operand::Parameter(index), Some(params)) => {
+            Ok(Some(params.get(index).cloned().into_iter().collect()))
+        }
+        (Operand::Column(name), Some(_)) => column_values(db, sql, name, operator),
+        (Operand::Parameter(_) | Operand::Column(_), None) => Ok(Some(vec![])),
+        (Operand::Other, _) => Ok(None),
+    }
+}
+
+/// The distinct values of the column `name` in the tables `sql` reads or writes that have
+/// it, or none if no table does.
+fn column_values(
+    db: &Component,
+    sql: &str,
+    name: &str,
+    operator: &str,
+) -> Result<Option<Vec<RecordValue>>, DbError> {
+    let mut tables = sql::referenced_tables(sql);
+    if let sql::Changes::Table(written) = sql::changes(sql) {
+        if !tables
+            .iter()
+            .any(|table| table.eq_ignore_ascii_case(&written))
+        {
+            tables.push(written);
+        }
+    }
+    let mut values = None;
+    for table in tables {
+        let info = db.query_all(format!("PRAGMA table_info({})", quote_identifier(&table)))?;
+        // each row is: cid, name, type, notnull, dflt_value, pk
+        let has_column = info.iter().any(|row| match &row[..] {
+            [_, RecordValue::Text(column), ..] => column.eq_ignore_ascii_case(name),
+            _ => false,
+        });
+        if !has_column {
+            continue;
+        }
+        // one more than can be taken, to tell a column that has too many
+        let select = format!(
+            "SELECT DISTINCT {} FROM {} LIMIT {}",
+            quote_identifier(name),
+            quote_identifier(&table),
+            MAX_VALUES + 1
+        );
+        let values = values.get_or_insert_with(Vec::new);
+        values.extend(
+            db.query_all(select)?
+                .into_iter()
+                .filter_map(|row| row.into_iter().next()),
+        );
+        if values.len() > MAX_VALUES {
+            return Err(error::unsupported(&format!(
+                "{} on {}, which has more than {} distinct values,",
+                operator, name, MAX_VALUES
+            )));
+        }
+    }
+    Ok(values)
+}
+
+/// An operand as a rewrite writes it, perhaps more than once: a `?` is numbered, so that
+/// repeating it doesn't number the parameters after it differently.
+pub(crate) fn written(sql: &str, operand: &Operand) -> String {
+    match operand {
+        Operand::Parameter(index) if sql == "?" => format!("?{}", index),
+        _ => sql.to_string(),
+    }
+}
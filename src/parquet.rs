@@ -0,0 +1,278 @@
+//! A small Parquet writer, used by `statement.all-parquet` with the `parquet` feature.
+//!
+//! It writes one row group with one uncompressed, PLAIN-encoded data page per column, every
+//! column optional. The metadata is written with the Thrift compact protocol by hand, as the
+//! parquet crate would be most of the component's size.
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue, ResultSet};
+
+const MAGIC: &[u8; 4] = b"PAR1";
+
+/// The Parquet type a column is written as, from the values it holds.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Int64,
+    Double,
+    /// A UTF-8 byte array.
+    Text,
+    Blob,
+}
+
+impl Kind {
+    /// The column's `Type` in the Parquet metadata.
+    fn physical_type(self) -> i32 {
+        match self {
+            Kind::Int64 => 2,
+            Kind::Double => 5,
+            Kind::Text | Kind::Blob => 6,
+        }
+    }
+}
+
+/// The kind of the column `name`, which holds `values`: integers stay integers, floats make
+/// the column doubles, and text or blobs can't mix with anything else. A column of nulls
+/// only is text.
+fn kind<'a>(name: &str, values: impl Iterator<Item = &'a RecordValue>) -> Result<Kind, DbError> {
+    let (mut ints, mut floats, mut texts, mut blobs) = (false, false, false, false);
+    for value in values {
+        match value {
+            RecordValue::Null => {}
+            RecordValue::Integer(_) => ints = true,
+            RecordValue::Float(_) => floats = true,
+            RecordValue::Text(_) | RecordValue::TextDecimal(_) => texts = true,
+            RecordValue::Blob(_) => blobs = true,
+        }
+    }
+    match (ints || floats, texts, blobs) {
+        (true, true, _) | (true, _, true) | (_, true, true) => Err(DbError::Misuse(format!(
+            "column {} holds values of different types, which a Parquet column can't; \
+             CAST it in the query",
+            name
+        ))),
+        (_, false, true) => Ok(Kind::Blob),
+        (true, ..) if floats => Ok(Kind::Double),
+        (true, ..) => Ok(Kind::Int64),
+        _ => Ok(Kind::Text),
+    }
+}
+
+/// The data page of one column: its definition levels, then its values, without a header.
+fn page(kind: Kind, values: impl Iterator<Item = RecordValue>) -> Vec<u8> {
+    let mut levels = vec![];
+    let mut plain = vec![];
+    for value in values {
+        levels.push(!matches!(value, RecordValue::Null));
+        match value {
+            RecordValue::Null => {}
+            RecordValue::Integer(i) if kind == Kind::Double => {
+                plain.extend((i as f64).to_le_bytes())
+            }
+            RecordValue::Integer(i) => plain.extend(i.to_le_bytes()),
+            RecordValue::Float(f) => plain.extend(f.to_le_bytes()),
+            RecordValue::Text(s) | RecordValue::TextDecimal(s) => {
+                plain.extend((s.len() as u32).to_le_bytes());
+                plain.extend(s.into_bytes());
+            }
+            RecordValue::Blob(b) => {
+                plain.extend((b.len() as u32).to_le_bytes());
+                plain.extend(b);
+            }
+        }
+    }
+
+    // the levels as runs of the RLE/bit-packing hybrid, each level one bit wide
+    let mut runs = vec![];
+    let mut i = 0;
+    while i < levels.len() {
+        let run = levels[i..].iter().take_while(|l| **l == levels[i]).count();
+        varint(&mut runs, (run as u64) << 1);
+        runs.push(levels[i] as u8);
+        i += run;
+    }
+    let mut page = (runs.len() as u32).to_le_bytes().to_vec();
+    page.extend(runs);
+    page.extend(plain);
+    page
+}
+
+/// `result_set` as a Parquet file.
+pub(crate) fn write(result_set: ResultSet) -> Result<Vec<u8>, DbError> {
+    let ResultSet { columns, rows } = result_set;
+    let kinds = columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| kind(name, rows.iter().map(|row| &row[i])))
+        .collect::<Result<Vec<_>, _>>()?;
+    let num_rows = rows.len() as i64;
+    let mut by_column: Vec<Vec<RecordValue>> = vec![Vec::with_capacity(rows.len()); columns.len()];
+    for row in rows {
+        for (i, value) in row.into_iter().enumerate() {
+            by_column[i].push(value);
+        }
+    }
+
+    let mut out = MAGIC.to_vec();
+    // each column's kind, offset and size in bytes
+    let mut chunks = vec![];
+    for (kind, values) in kinds.iter().zip(by_column) {
+        let data = page(*kind, values.into_iter());
+        let mut header = Thrift::default();
+        header.begin();
+        header.i32_field(1, 0); // DATA_PAGE
+        header.i32_field(2, data.len() as i32);
+        header.i32_field(3, data.len() as i32);
+        header.struct_field(5);
+        header.i32_field(1, num_rows as i32);
+        header.i32_field(2, 0); // PLAIN
+        header.i32_field(3, 3); // RLE
+        header.i32_field(4, 3);
+        header.end();
+        header.end();
+        let offset = out.len() as i64;
+        out.extend(&header.out);
+        out.extend(&data);
+        chunks.push((*kind, offset, (header.out.len() + data.len()) as i64));
+    }
+
+    let mut meta = Thrift::default();
+    meta.begin();
+    meta.i32_field(1, 1);
+    meta.list_field(2, STRUCT, columns.len() + 1);
+    meta.begin();
+    meta.binary_field(4, b"schema");
+    meta.i32_field(5, columns.len() as i32);
+    meta.end();
+    for (name, kind) in columns.iter().zip(&kinds) {
+        meta.begin();
+        meta.i32_field(1, kind.physical_type());
+        meta.i32_field(3, 1); // OPTIONAL
+        meta.binary_field(4, name.as_bytes());
+        if *kind == Kind::Text {
+            meta.i32_field(6, 0); // UTF8
+        }
+        meta.end();
+    }
+    meta.i64_field(3, num_rows);
+    meta.list_field(4, STRUCT, 1);
+    meta.begin();
+    meta.list_field(1, STRUCT, chunks.len());
+    for (name, (kind, offset, size)) in columns.iter().zip(&chunks) {
+        meta.begin();
+        meta.i64_field(2, *offset);
+        meta.struct_field(3);
+        meta.i32_field(1, kind.physical_type());
+        meta.list_field(2, I32, 2);
+        meta.i32_value(0); // PLAIN
+        meta.i32_value(3); // RLE
+        meta.list_field(3, BINARY, 1);
+        meta.binary_value(name.as_bytes());
+        meta.i32_field(4, 0); // UNCOMPRESSED
+        meta.i64_field(5, num_rows);
+        meta.i64_field(6, *size);
+        meta.i64_field(7, *size);
+        meta.i64_field(9, *offset);
+        meta.end();
+        meta.end();
+    }
+    meta.i64_field(2, chunks.iter().map(|(_, _, size)| size).sum());
+    meta.i64_field(3, num_rows);
+    meta.end();
+    meta.binary_field(6, b"wit-limbo");
+    meta.end();
+
+    out.extend(&meta.out);
+    out.extend((meta.out.len() as u32).to_le_bytes());
+    out.extend(MAGIC);
+    Ok(out)
+}
+
+// Thrift compact protocol types
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+fn varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+/// Structs written in the Thrift compact protocol.
+#[derive(Default)]
+struct Thrift {
+    out: Vec<u8>,
+    /// The id of the last field written in each struct being written, innermost last.
+    last_ids: Vec<i16>,
+}
+
+impl Thrift {
+    /// Start a struct, at the top level or as an element of a list.
+    fn begin(&mut self) {
+        self.last_ids.push(0);
+    }
+
+    fn end(&mut self) {
+        self.out.push(0);
+        self.last_ids.pop();
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last_ids.last_mut().expect("a field outside a struct");
+        match id - *last {
+            delta @ 1..=15 => self.out.push((delta as u8) << 4 | kind),
+            _ => {
+                self.out.push(kind);
+                varint(&mut self.out, zigzag(id as i64));
+            }
+        }
+        *last = id;
+    }
+
+    fn i32_field(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.i32_value(value);
+    }
+
+    fn i64_field(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        varint(&mut self.out, zigzag(value));
+    }
+
+    fn binary_field(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.binary_value(value);
+    }
+
+    /// Start a struct field, ended with [Thrift::end].
+    fn struct_field(&mut self, id: i16) {
+        self.field(id, STRUCT);
+        self.begin();
+    }
+
+    /// Start a list field of `len` elements of type `kind`, which are written next.
+    fn list_field(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, LIST);
+        if len < 15 {
+            self.out.push((len as u8) << 4 | kind);
+        } else {
+            self.out.push(0xf0 | kind);
+            varint(&mut self.out, len as u64);
+        }
+    }
+
+    fn i32_value(&mut self, value: i32) {
+        varint(&mut self.out, zigzag(value as i64));
+    }
+
+    fn binary_value(&mut self, value: &[u8]) {
+        varint(&mut self.out, value.len() as u64);
+        self.out.extend(value);
+    }
+}
@@ -0,0 +1,87 @@
+//! Reading a statement's plan from its EXPLAIN bytecode, for `database.estimate` and
+//! `statement.tables`.
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue};
+
+/// How a statement reads the tables it opens.
+#[derive(Default)]
+pub(crate) struct Plan {
+    /// Tables read from start to end, directly or through one of their indexes.
+    pub(crate) scanned: Vec<String>,
+    /// Tables only looked up by key.
+    pub(crate) searched: Vec<String>,
+}
+
+/// Opcodes that move a cursor to its first or last row, to walk every row from there.
+const SCANS: [&str; 2] = ["Rewind", "Last"];
+
+/// The plan of the program in `explain`, the rows of `EXPLAIN <sql>`, each of which is:
+/// addr, opcode, p1, p2, p3, p4, p5, comment. `tables` maps the root page of every table and
+/// index to the table it belongs to.
+pub(crate) fn read(
+    explain: &[Vec<RecordValue>],
+    tables: &[(i64, String)],
+) -> Result<Plan, DbError> {
+    use RecordValue as V;
+    // the table each cursor reads, by cursor number, and whether it is walked
+    let mut cursors: Vec<(i64, String, bool)> = vec![];
+    for row in explain {
+        let (opcode, p1, p2) = match &row[..] {
+            [_, V::Text(opcode), V::Integer(p1), V::Integer(p2), ..] => (opcode.as_str(), *p1, *p2),
+            _ => return Err(DbError::Other(format!("Unexpected EXPLAIN row: {:?}", row))),
+        };
+        if opcode == "OpenRead" {
+            if let Some((_, table)) = tables.iter().find(|(root, _)| *root == p2) {
+                cursors.push((p1, table.clone(), false));
+            }
+        } else if SCANS.contains(&opcode) {
+            if let Some(cursor) = cursors.iter_mut().find(|(cursor, _, _)| *cursor == p1) {
+                cursor.2 = true;
+            }
+        }
+    }
+
+    let mut plan = Plan::default();
+    for (_, table, scanned) in cursors {
+        let list = if scanned {
+            &mut plan.scanned
+        } else {
+            &mut plan.searched
+        };
+        if !list.contains(&table) {
+            list.push(table);
+        }
+    }
+    // a table scanned through one cursor is scanned, however else it is read
+    plan.searched.retain(|table| !plan.scanned.contains(table));
+    Ok(plan)
+}
+
+/// The tables the program in `explain` opens to read or write, in the order it first opens
+/// each. `tables` is as for [read], and root page 1 is `sqlite_schema`, which DDL writes.
+pub(crate) fn tables(
+    explain: &[Vec<RecordValue>],
+    tables: &[(i64, String)],
+) -> Result<Vec<String>, DbError> {
+    use RecordValue as V;
+    let mut opened: Vec<String> = vec![];
+    for row in explain {
+        let (opcode, p2) = match &row[..] {
+            [_, V::Text(opcode), _, V::Integer(p2), ..] => (opcode.as_str(), *p2),
+            _ => return Err(DbError::Other(format!("Unexpected EXPLAIN row: {:?}", row))),
+        };
+        if opcode != "OpenRead" && opcode != "OpenWrite" {
+            continue;
+        }
+        let table = match p2 {
+            1 => Some("sqlite_schema"),
+            root => tables
+                .iter()
+                .find(|(page, _)| *page == root)
+                .map(|(_, table)| table.as_str()),
+        };
+        if let Some(table) = table.filter(|table| !opened.iter().any(|t| t == table)) {
+            opened.push(table.to_string());
+        }
+    }
+    Ok(opened)
+}
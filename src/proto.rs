@@ -0,0 +1,75 @@
+//! Rust side of `proto/limbo.proto`, used by `statement.all-proto`.
+//!
+//! The messages are written out with the prost derives rather than generated by a build script,
+//! so building the component does not need `protoc`. Keep the tags in sync with the .proto file.
+use crate::bindings::exports::component::wit_limbo::limbo::{RecordValue, ResultSet};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Null {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Value {
+    #[prost(oneof = "value::Kind", tags = "1, 2, 3, 4, 5, 6")]
+    pub kind: Option<value::Kind>,
+}
+
+pub mod value {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Kind {
+        #[prost(message, tag = "1")]
+        Null(super::Null),
+        #[prost(sint64, tag = "2")]
+        Integer(i64),
+        #[prost(double, tag = "3")]
+        Float(f64),
+        #[prost(string, tag = "4")]
+        Text(String),
+        #[prost(bytes = "vec", tag = "5")]
+        Blob(Vec<u8>),
+        #[prost(string, tag = "6")]
+        TextDecimal(String),
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Row {
+    #[prost(message, repeated, tag = "1")]
+    pub values: Vec<Value>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct QueryResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub columns: Vec<String>,
+    #[prost(message, repeated, tag = "2")]
+    pub rows: Vec<Row>,
+}
+
+impl From<RecordValue> for Value {
+    fn from(value: RecordValue) -> Self {
+        let kind = match value {
+            RecordValue::Null => value::Kind::Null(Null {}),
+            RecordValue::Integer(i) => value::Kind::Integer(i),
+            RecordValue::Float(f) => value::Kind::Float(f),
+            RecordValue::Text(s) => value::Kind::Text(s),
+            RecordValue::Blob(b) => value::Kind::Blob(b),
+            RecordValue::TextDecimal(s) => value::Kind::TextDecimal(s),
+        };
+        Value { kind: Some(kind) }
+    }
+}
+
+impl From<ResultSet> for QueryResponse {
+    fn from(result_set: ResultSet) -> Self {
+        QueryResponse {
+            columns: result_set.columns,
+            rows: result_set
+                .rows
+                .into_iter()
+                .map(|row| Row {
+                    values: row.into_iter().map(Value::from).collect(),
+                })
+                .collect(),
+        }
+    }
+}
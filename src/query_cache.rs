@@ -0,0 +1,138 @@
+//! Rows of queries kept for when they run again, see `database.set-query-cache` in the WIT.
+//!
+//! Each connection keeps its own cache, as what a query returns depends on the connection's
+//! table filters and settings, and the database's connections share a list of their caches,
+//! so a write that any of them commits drops the rows read from the table it wrote from all
+//! of them. A write whose table isn't known, such as DDL, drops every cached row.
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::{Rc, Weak};
+
+use crate::bindings::exports::component::wit_limbo::limbo::RecordValue;
+use crate::sql::Changes;
+use crate::value_size;
+
+/// The query caches of the connections to one database.
+#[derive(Default)]
+pub(crate) struct QueryCaches {
+    live: RefCell<Vec<Weak<QueryCache>>>,
+}
+
+impl QueryCaches {
+    pub(crate) fn add(&self, cache: &Rc<QueryCache>) {
+        self.live.borrow_mut().push(Rc::downgrade(cache));
+    }
+
+    /// Drop the rows that committed `changes` could have changed, from every connection.
+    pub(crate) fn changed(&self, changes: &Changes) {
+        let mut live = self.live.borrow_mut();
+        live.retain(|cache| cache.strong_count() > 0);
+        for cache in live.iter().filter_map(Weak::upgrade) {
+            match changes {
+                Changes::Nothing | Changes::Begin | Changes::Commit | Changes::Rollback => {}
+                Changes::Table(table) => cache.forget_table(table),
+                Changes::Everything => {
+                    cache.clear();
+                }
+            }
+        }
+    }
+}
+
+/// The rows of one query, with what they were cached under.
+struct Entry {
+    /// The query's SQL with its parameters written in.
+    key: String,
+    /// The tables the query reads.
+    tables: Vec<String>,
+    rows: Vec<Vec<RecordValue>>,
+    bytes: u64,
+}
+
+/// The rows of a connection's queries, least recently used first.
+#[derive(Default)]
+pub(crate) struct QueryCache {
+    /// How many queries to keep the rows of, 0 for none.
+    max_entries: Cell<u32>,
+    /// How many bytes of rows to keep, 0 for no limit.
+    max_bytes: Cell<u64>,
+    entries: RefCell<VecDeque<Entry>>,
+    /// The bytes of rows in `entries`.
+    bytes: Cell<u64>,
+}
+
+impl QueryCache {
+    /// Keep the rows of up to `max_entries` queries and `max_bytes` bytes, dropping the least
+    /// recently used to fit.
+    pub(crate) fn set_limits(&self, max_entries: u32, max_bytes: u64) {
+        self.max_entries.set(max_entries);
+        self.max_bytes.set(max_bytes);
+        self.evict();
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.max_entries.get() > 0
+    }
+
+    /// The rows cached under `key`, which become the most recently used.
+    pub(crate) fn get(&self, key: &str) -> Option<Vec<Vec<RecordValue>>> {
+        let mut entries = self.entries.borrow_mut();
+        let at = entries.iter().position(|entry| entry.key == key)?;
+        let entry = entries.remove(at)?;
+        let rows = entry.rows.clone();
+        entries.push_back(entry);
+        Some(rows)
+    }
+
+    /// Cache `rows` under `key`, read from `tables`, unless they are larger than the cache.
+    pub(crate) fn insert(&self, key: String, tables: Vec<String>, rows: &[Vec<RecordValue>]) {
+        let bytes = rows.iter().flatten().map(value_size).sum::<u64>() + key.len() as u64;
+        let max_bytes = self.max_bytes.get();
+        if !self.is_enabled() || (max_bytes != 0 && bytes > max_bytes) {
+            return;
+        }
+        self.retain(|entry| entry.key != key);
+        self.entries.borrow_mut().push_back(Entry {
+            key,
+            tables,
+            rows: rows.to_vec(),
+            bytes,
+        });
+        self.bytes.set(self.bytes.get() + bytes);
+        self.evict();
+    }
+
+    /// Drop the rows of the queries that read `table`.
+    pub(crate) fn forget_table(&self, table: &str) {
+        self.retain(|entry| !entry.tables.iter().any(|t| t.eq_ignore_ascii_case(table)));
+    }
+
+    /// Drop every entry, returning whether there were any.
+    pub(crate) fn clear(&self) -> bool {
+        let had_entries = !self.entries.borrow().is_empty();
+        self.entries.borrow_mut().clear();
+        self.bytes.set(0);
+        had_entries
+    }
+
+    fn retain(&self, keep: impl Fn(&Entry) -> bool) {
+        let mut entries = self.entries.borrow_mut();
+        entries.retain(|entry| keep(entry));
+        self.bytes
+            .set(entries.iter().map(|entry| entry.bytes).sum());
+    }
+
+    /// Drop the least recently used rows until the cache is within its limits.
+    fn evict(&self) {
+        let mut entries = self.entries.borrow_mut();
+        let max_bytes = self.max_bytes.get();
+        while entries.len() > self.max_entries.get() as usize
+            || (max_bytes != 0 && self.bytes.get() > max_bytes)
+        {
+            let Some(entry) = entries.pop_front() else {
+                break;
+            };
+            self.bytes.set(self.bytes.get() - entry.bytes);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Reading ahead of sequential reads of a file, see `database.set-readahead-pages` in the WIT.
+//!
+//! Once a file has been read at consecutive offsets a few times, as a table scan reads the
+//! pages of a table stored in order, the next read asks the host for that many pages more in
+//! the same request, and the pages after it are served from what was read ahead.
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// The consecutive reads after which the next one reads ahead.
+const SEQUENTIAL: u32 = 2;
+
+/// Where the bytes of a read come from.
+pub(crate) enum Read {
+    /// They were read ahead.
+    Ahead(Vec<u8>),
+    /// The host, asked for this many bytes: those of the read, and more to read ahead.
+    Host(usize),
+}
+
+/// The reads of one file, and what was read ahead of them.
+pub(crate) struct Readahead {
+    /// How many reads' worth to read ahead by, 0 for none, shared with the database's other
+    /// files.
+    pages: Rc<Cell<u32>>,
+    /// Where the last read ended.
+    next: Cell<usize>,
+    /// How many reads in a row started where the one before ended.
+    run: Cell<u32>,
+    /// The bytes read ahead, and the offset they start at.
+    ahead: RefCell<(usize, Vec<u8>)>,
+    /// Counts the writes to the file, so bytes read before one aren't kept after it.
+    writes: Cell<u64>,
+}
+
+impl Readahead {
+    pub(crate) fn new(pages: Rc<Cell<u32>>) -> Self {
+        Self {
+            pages,
+            next: Cell::new(0),
+            run: Cell::new(0),
+            ahead: RefCell::new((0, vec![])),
+            writes: Cell::new(0),
+        }
+    }
+
+    /// Note a read of `len` bytes at `pos`, returning where to get them.
+    pub(crate) fn read(&self, pos: usize, len: usize) -> Read {
+        let run = if pos == self.next.get() {
+            self.run.get() + 1
+        } else {
+            0
+        };
+        self.run.set(run);
+        self.next.set(pos + len);
+
+        let ahead = self.ahead.borrow();
+        let (start, data) = &*ahead;
+        if pos >= *start && pos + len <= start + data.len() {
+            return Read::Ahead(data[pos - start..pos + len - start].to_vec());
+        }
+        match self.pages.get() {
+            pages if pages > 0 && run >= SEQUENTIAL => Read::Host(len * (1 + pages as usize)),
+            _ => Read::Host(len),
+        }
+    }
+
+    /// The number of writes so far, for [Readahead::keep].
+    pub(crate) fn writes(&self) -> u64 {
+        self.writes.get()
+    }
+
+    /// Keep `data`, the bytes read beyond a read that ended at `pos`, for the reads after it,
+    /// unless the file was written since `writes` were counted.
+    pub(crate) fn keep(&self, pos: usize, data: Vec<u8>, writes: u64) {
+        if !data.is_empty() && writes == self.writes.get() {
+            self.ahead.replace((pos, data));
+        }
+    }
+
+    /// Forget what was read ahead, if a write of `len` bytes at `pos` overlaps it.
+    pub(crate) fn written(&self, pos: usize, len: usize) {
+        self.writes.set(self.writes.get() + 1);
+        let mut ahead = self.ahead.borrow_mut();
+        let (start, data) = &*ahead;
+        if pos < start + data.len() && *start < pos + len {
+            *ahead = (0, vec![]);
+        }
+    }
+}
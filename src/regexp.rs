@@ -0,0 +1,117 @@
+//! The `REGEXP` operator, matched by the `regex` crate in the guest.
+//!
+//! Limbo doesn't evaluate REGEXP and has no way for the guest to give it a function to call,
+//! so the guest decides each `x REGEXP pattern` before its statement runs: it reads every
+//! value `x` can take, and rewrites the operator into `x IN (...)` of the ones the pattern
+//! matches anywhere in, as SQLite's regexp extension does. A column's values are read from
+//! each table the statement reads or writes that has a column of that name, and a prepared
+//! statement is prepared again at the start of each run, so it matches the rows as they are
+//! then. `x` can be a column, literal or parameter and the pattern a literal or parameter;
+//! anything else, or a column with more than [operands::MAX_VALUES] distinct values, is
+//! refused rather than matched differently. Built with the `regexp` feature.
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, RecordValue};
+use crate::sql::{self, Operand};
+use crate::{error, operands, Component};
+
+/// `sql` with each `x REGEXP pattern` rewritten into the values of `x` that match, given
+/// `params`, the values bound to its parameters. Without them, as when a statement is only
+/// being prepared, nothing matches yet, but a literal pattern is still checked.
+pub(crate) fn rewrite(
+    db: &Component,
+    sql: &str,
+    params: Option<&BTreeMap<usize, RecordValue>>,
+) -> Result<String, DbError> {
+    let mut out = sql.to_string();
+    for regexp in sql::regexps(sql)?.iter().rev() {
+        let regex = match &regexp.pattern.1 {
+            Operand::Literal(value) => compile(value)?,
+            Operand::Parameter(index) => match params.and_then(|params| params.get(index)) {
+                Some(value) => compile(value)?,
+                None => None,
+            },
+            Operand::Column(_) | Operand::Other => {
+                return Err(error::unsupported(
+                    "REGEXP with a pattern other than a literal or parameter",
+                ))
+            }
+        };
+        let values = match (&regexp.operand.1, &regex) {
+            (Operand::Other, _) => {
+                return Err(error::unsupported(
+                    "REGEXP on anything but a column, literal or parameter",
+                ))
+            }
+            // a NULL pattern matches nothing, so there's nothing to read
+            (_, None) => vec![],
+            (operand, Some(_)) => operands::values(db, sql, operand, params, "REGEXP")?
+                .ok_or_else(|| {
+                    DbError::Misuse(format!(
+                        "REGEXP on {}, which no table the statement names has a column of",
+                        regexp.operand.0
+                    ))
+                })?,
+        };
+        let mut matches = String::new();
+        for value in values {
+            if regex
+                .as_ref()
+                .zip(text(&value))
+                .is_some_and(|(regex, text)| regex.is_match(&text))
+            {
+                if !matches.is_empty() {
+                    matches.push_str(", ");
+                }
+                sql::push_literal(&mut matches, Some(&value));
+            }
+        }
+        let (x, pattern) = (
+            operands::written(regexp.operand.0, &regexp.operand.1),
+            operands::written(regexp.pattern.0, &regexp.pattern.1),
+        );
+        let matched = if matches.is_empty() {
+            "0".to_string()
+        } else {
+            format!("{} IN ({})", x, matches)
+        };
+        // NULL, as REGEXP gives, if either side is
+        out.replace_range(
+            regexp.span.clone(),
+            &format!(
+                "({}CASE WHEN {} IS NULL OR {} IS NULL THEN NULL ELSE {} END)",
+                if regexp.not { "NOT " } else { "" },
+                pattern,
+                x,
+                matched
+            ),
+        );
+    }
+    Ok(out)
+}
+
+/// The pattern `value`, or none for NULL.
+fn compile(value: &RecordValue) -> Result<Option<Regex>, DbError> {
+    text(value)
+        .map(|pattern| {
+            Regex::new(&pattern)
+                .map_err(|e| DbError::Misuse(format!("invalid REGEXP pattern: {}", e)))
+        })
+        .transpose()
+}
+
+/// The text of `value`: numbers as their text, and blobs as UTF-8. NULL has none, nor has a
+/// float that isn't finite, which has no literal to write it back with.
+fn text(value: &RecordValue) -> Option<Cow<'_, str>> {
+    match value {
+        RecordValue::Null => None,
+        RecordValue::Integer(i) => Some(Cow::Owned(i.to_string())),
+        RecordValue::Float(f) if f.is_finite() => Some(Cow::Owned(format!("{:?}", f))),
+        RecordValue::Float(_) => None,
+        RecordValue::Text(s) | RecordValue::TextDecimal(s) => Some(Cow::Borrowed(s)),
+        RecordValue::Blob(b) => Some(String::from_utf8_lossy(b)),
+    }
+}
@@ -0,0 +1,163 @@
+//! Comparing two schemas, for `database.diff-schema`.
+use crate::{quote_identifier, sql};
+
+/// A table, index, view or trigger, as `sqlite_schema` describes it.
+pub(crate) struct SchemaObject {
+    /// `table`, `index`, `view` or `trigger`.
+    pub(crate) kind: String,
+    pub(crate) name: String,
+    /// The table an index or trigger belongs to, or the name of a table or view.
+    pub(crate) table: String,
+    /// The statement that created it.
+    pub(crate) sql: String,
+}
+
+impl SchemaObject {
+    fn is(&self, kind: &str) -> bool {
+        self.kind == kind
+    }
+
+    /// Whether `self` and `other` are the same object, created the same way.
+    fn same_as(&self, other: &SchemaObject) -> bool {
+        sql::normalize(&self.sql) == sql::normalize(&other.sql)
+    }
+
+    fn drop_statement(&self) -> String {
+        format!(
+            "DROP {} IF EXISTS {};",
+            self.kind.to_ascii_uppercase(),
+            quote_identifier(&self.name)
+        )
+    }
+}
+
+/// The statements that change a database with schema `from` to have schema `to`.
+///
+/// Tables that only gained columns at the end are altered with ADD COLUMN. Any other change
+/// to a table rebuilds it: a new table is created, the columns both versions have are
+/// copied over, and it replaces the old one, which takes the old table's indexes and
+/// triggers with it, so those are created again.
+pub(crate) fn diff(from: &[SchemaObject], to: &[SchemaObject]) -> Vec<String> {
+    let mut tables = vec![];
+    let mut rebuilt: Vec<&str> = vec![];
+    for new in to.iter().filter(|object| object.is("table")) {
+        match find(from, new) {
+            None => tables.push(format!("{};", new.sql)),
+            Some(old) if old.same_as(new) => {}
+            Some(old) => match added_columns(&old.sql, &new.sql) {
+                Some(added) => tables.extend(added.into_iter().map(|column| {
+                    format!(
+                        "ALTER TABLE {} ADD COLUMN {};",
+                        quote_identifier(&new.name),
+                        column
+                    )
+                })),
+                None => {
+                    tables.extend(rebuild(&old.sql, &new.name, &new.sql));
+                    rebuilt.push(&new.name);
+                }
+            },
+        }
+    }
+    // rebuilding a table drops its indexes and triggers along with it
+    let on_rebuilt = |object: &SchemaObject| {
+        (object.is("index") || object.is("trigger"))
+            && rebuilt
+                .iter()
+                .any(|table| table.eq_ignore_ascii_case(&object.table))
+    };
+
+    // indexes, views and triggers that changed or went away are dropped before the tables
+    // change, and the new versions created after
+    let mut drops = vec![];
+    for kind in ["view", "trigger", "index"] {
+        for old in from.iter().filter(|object| object.is(kind)) {
+            let kept = find(to, old).is_some_and(|new| new.same_as(old));
+            if !kept && !on_rebuilt(old) {
+                drops.push(old.drop_statement());
+            }
+        }
+    }
+    for old in from.iter().filter(|object| object.is("table")) {
+        if find(to, old).is_none() {
+            drops.push(old.drop_statement());
+        }
+    }
+    let mut creates = vec![];
+    for kind in ["index", "view", "trigger"] {
+        for new in to.iter().filter(|object| object.is(kind)) {
+            let kept = find(from, new).is_some_and(|old| old.same_as(new));
+            if !kept || on_rebuilt(new) {
+                creates.push(format!("{};", new.sql));
+            }
+        }
+    }
+
+    drops.into_iter().chain(tables).chain(creates).collect()
+}
+
+/// The object in `objects` of the same kind and name as `object`.
+fn find<'a>(objects: &'a [SchemaObject], object: &SchemaObject) -> Option<&'a SchemaObject> {
+    objects
+        .iter()
+        .find(|other| other.kind == object.kind && other.name.eq_ignore_ascii_case(&object.name))
+}
+
+/// The definitions of the columns `new` adds at the end of `old`, or `None` if the table
+/// changed in any other way.
+fn added_columns<'a>(old: &str, new: &'a str) -> Option<Vec<&'a str>> {
+    let (old, _) = sql::definitions(old);
+    let (new, _) = sql::definitions(new);
+    if new.len() < old.len()
+        || old
+            .iter()
+            .zip(&new)
+            .any(|(old, new)| old.normalized != new.normalized)
+    {
+        return None;
+    }
+    new[old.len()..]
+        .iter()
+        .map(|definition| definition.column.as_ref().map(|_| definition.text))
+        .collect()
+}
+
+/// The statements that rebuild the table `old` as `name`, created by `new`.
+fn rebuild(old: &str, name: &str, new: &str) -> Vec<String> {
+    let (old_definitions, _) = sql::definitions(old);
+    let (new_definitions, open) = sql::definitions(new);
+    let temporary = format!("{}_new", name);
+    let copied: Vec<String> = new_definitions
+        .iter()
+        .filter_map(|definition| definition.column.as_ref())
+        .filter(|column| {
+            old_definitions.iter().any(|old| {
+                old.column
+                    .as_ref()
+                    .is_some_and(|old| old.eq_ignore_ascii_case(column))
+            })
+        })
+        .map(|column| quote_identifier(column))
+        .collect();
+    let copied = copied.join(", ");
+    vec![
+        format!(
+            "CREATE TABLE {} {};",
+            quote_identifier(&temporary),
+            &new[open..]
+        ),
+        format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {};",
+            quote_identifier(&temporary),
+            copied,
+            copied,
+            quote_identifier(name)
+        ),
+        format!("DROP TABLE {};", quote_identifier(name)),
+        format!(
+            "ALTER TABLE {} RENAME TO {};",
+            quote_identifier(&temporary),
+            quote_identifier(name)
+        ),
+    ]
+}
@@ -0,0 +1,55 @@
+//! The rows of `sqlite_schema`, kept per connection, see `database.schema-version` in the WIT.
+//!
+//! Looking up tables, indexes, views and triggers, as `columns`, `views`, `estimate` and
+//! the query cache do, reads the rows kept here rather than scanning `sqlite_schema` again.
+//! They are kept with the schema version they were read at, and read again once it changes,
+//! which DDL on any connection to the database, in this instance or not, does.
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A table, index, view or trigger, as a row of `sqlite_schema`.
+pub(crate) struct SchemaRow {
+    /// `table`, `index`, `view` or `trigger`.
+    pub(crate) kind: String,
+    pub(crate) name: String,
+    /// The table an index or trigger belongs to, or the name of a table or view.
+    pub(crate) table: String,
+    /// The root page of a table or index, 0 for views and triggers.
+    pub(crate) root: i64,
+    /// The statement that created it, `None` for automatic indexes.
+    pub(crate) sql: Option<String>,
+}
+
+impl SchemaRow {
+    /// Whether the database made it itself, e.g. `sqlite_sequence`, as
+    /// `name LIKE 'sqlite_%'` says.
+    pub(crate) fn is_internal(&self) -> bool {
+        self.name.len() > 6
+            && self
+                .name
+                .get(..6)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("sqlite"))
+    }
+}
+
+/// The schema as a connection last read it.
+#[derive(Default)]
+pub(crate) struct SchemaCache {
+    /// The rows in creation order, with the schema version they were read at.
+    rows: RefCell<Option<(u32, Rc<Vec<SchemaRow>>)>>,
+}
+
+impl SchemaCache {
+    /// The rows, if they were read at schema version `version`.
+    pub(crate) fn get(&self, version: u32) -> Option<Rc<Vec<SchemaRow>>> {
+        match &*self.rows.borrow() {
+            Some((read_at, rows)) if *read_at == version => Some(rows.clone()),
+            _ => None,
+        }
+    }
+
+    /// Keep `rows`, read at schema version `version`.
+    pub(crate) fn set(&self, version: u32, rows: Rc<Vec<SchemaRow>>) {
+        self.rows.replace(Some((version, rows)));
+    }
+}
@@ -0,0 +1,34 @@
+//! Copies of a database kept to query later, see `database.snapshot` in the WIT.
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+use crate::Component;
+
+/// The snapshots taken of one database, shared by its connections like its subscriptions.
+#[derive(Default)]
+pub(crate) struct Snapshots {
+    last_id: Cell<u64>,
+    taken: RefCell<HashMap<u64, Rc<Component>>>,
+}
+
+impl Snapshots {
+    /// Keep `copy`, returning the id to find it by.
+    pub(crate) fn add(&self, copy: Component) -> u64 {
+        let id = self.last_id.get() + 1;
+        self.last_id.set(id);
+        self.taken.borrow_mut().insert(id, Rc::new(copy));
+        id
+    }
+
+    pub(crate) fn get(&self, id: u64) -> Option<Rc<Component>> {
+        self.taken.borrow().get(&id).cloned()
+    }
+
+    /// Stop keeping the snapshot `id`, returning whether there was one.
+    pub(crate) fn release(&self, id: u64) -> bool {
+        self.taken.borrow_mut().remove(&id).is_some()
+    }
+}
@@ -0,0 +1,17 @@
+//! Tracing spans reported to the host, see `span-start` in the WIT.
+use crate::bindings::component::wit_limbo::host_trace::{span_end, span_start};
+
+/// A span that is open from [Span::start] until it is dropped.
+pub(crate) struct Span(u64);
+
+impl Span {
+    pub(crate) fn start(name: &str) -> Self {
+        Self(span_start(name))
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        span_end(self.0);
+    }
+}
@@ -0,0 +1,1767 @@
+//! Reading and rewriting SQL text, e.g. for `statement.expanded-sql` and `statement.keyset-page`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    ops::Range,
+};
+
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    DbError, GeneratedKind, RecordValue, SqlPosition, StatementKind,
+};
+use crate::error;
+
+/// `sql` with every parameter replaced by the literal bound to it, or NULL if none is.
+///
+/// Parameters are numbered as SQLite numbers them: `?NNN` is parameter NNN, and a bare `?` or
+/// each new `:name`, `@name` or `$name` is one more than the largest number used before it.
+/// String literals, quoted identifiers and comments are copied unchanged.
+pub(crate) fn expand(sql: &str, params: &BTreeMap<usize, RecordValue>) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    let mut last = 0;
+    let mut rest = sql;
+
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '\'' | '"' | '`' => quoted(rest, c),
+            '[' => quoted(rest, ']'),
+            '-' if rest.starts_with("--") => rest.find('\n').unwrap_or(rest.len()),
+            '/' if rest.starts_with("/*") => rest.find("*/").map_or(rest.len(), |end| end + 2),
+            '?' => {
+                let digits = rest[1..]
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len() - 1);
+                let index = match rest[1..1 + digits].parse() {
+                    Ok(index) => index,
+                    Err(_) => last + 1,
+                };
+                last = last.max(index);
+                push_literal(&mut out, params.get(&index));
+                rest = &rest[1 + digits..];
+                continue;
+            }
+            ':' | '@' | '$' if rest[1..].starts_with(is_name_char) => {
+                let end = rest[1..]
+                    .find(|c: char| !is_name_char(c))
+                    .map_or(rest.len(), |end| end + 1);
+                let index = *names.entry(&rest[..end]).or_insert_with(|| {
+                    last += 1;
+                    last
+                });
+                push_literal(&mut out, params.get(&index));
+                rest = &rest[end..];
+                continue;
+            }
+            c => c.len_utf8(),
+        };
+        out.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    out
+}
+
+/// The length of the quoted token at the start of `sql`, up to and including `close`, with
+/// any doubled quote inside it, which stands for the quote itself.
+fn quoted(sql: &str, close: char) -> usize {
+    let mut at = 1;
+    while let Some(end) = sql[at..].find(close) {
+        at += end + 1;
+        if close == ']' || !sql[at..].starts_with(close) {
+            return at;
+        }
+        at += 1;
+    }
+    sql.len()
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Append `value` as an SQL literal.
+pub(crate) fn push_literal(out: &mut String, value: Option<&RecordValue>) {
+    match value {
+        None | Some(RecordValue::Null) => out.push_str("NULL"),
+        Some(RecordValue::Integer(i)) => out.push_str(&i.to_string()),
+        // `{:?}` keeps the decimal point, so the literal reads back as a REAL
+        Some(RecordValue::Float(f)) => out.push_str(&format!("{:?}", f)),
+        Some(RecordValue::Text(s)) | Some(RecordValue::TextDecimal(s)) => {
+            out.push('\'');
+            out.push_str(&s.replace('\'', "''"));
+            out.push('\'');
+        }
+        Some(RecordValue::Blob(b)) => {
+            out.push_str("X'");
+            for byte in b {
+                out.push_str(&format!("{:02X}", byte));
+            }
+            out.push('\'');
+        }
+    }
+}
+
+/// The first statement of `sql`, with its `;` if it has one, and the SQL after it. Empty
+/// statements before it, only `;`, whitespace and comments, are part of it.
+pub(crate) fn split_first(sql: &str) -> (&str, &str) {
+    let end = tokens(sql)
+        .into_iter()
+        .skip_while(|(_, _, token)| *token == ";")
+        .find(|(_, depth, token)| *depth == 0 && *token == ";")
+        .map_or(sql.len(), |(at, _, _)| at + 1);
+    sql.split_at(end)
+}
+
+/// Where the first statement in `sql` starts, `None` if it holds none, only `;`, whitespace
+/// and comments.
+pub(crate) fn statement_start(sql: &str) -> Option<usize> {
+    tokens(sql)
+        .into_iter()
+        .find(|(_, _, token)| *token != ";")
+        .map(|(at, _, _)| at)
+}
+
+/// The position of the token at or after `offset`, a byte offset into `sql`, or of its end.
+pub(crate) fn position(sql: &str, offset: usize) -> SqlPosition {
+    let (offset, token) = tokens(sql)
+        .into_iter()
+        .find(|(at, _, token)| at + token.len() > offset)
+        .map_or((sql.len(), ""), |(at, _, token)| (at, token));
+    let before = &sql[..offset];
+    let line_start = before.rfind('\n').map_or(0, |at| at + 1);
+    SqlPosition {
+        line: before.matches('\n').count() as u32 + 1,
+        column: before[line_start..].chars().count() as u32 + 1,
+        offset: offset as u32,
+        token: token.to_string(),
+    }
+}
+
+/// Where in `sql` the parse error `message` from limbo points: at the token it says the error
+/// is near, the occurrence closest to the line and column it gives, else at that line and
+/// column, or at the end for an unexpected end of input.
+pub(crate) fn locate(sql: &str, message: &str) -> Option<SqlPosition> {
+    let reported = message.rsplit_once(" at (").and_then(|(_, rest)| {
+        let (line, column) = rest.split_once(')')?.0.split_once(", ")?;
+        let line_start: usize = sql
+            .split_inclusive('\n')
+            .take(line.parse::<usize>().ok()?.saturating_sub(1))
+            .map(str::len)
+            .sum();
+        Some(line_start + column.parse::<usize>().ok()?.saturating_sub(1))
+    });
+    let near = message
+        .split_once("near \"")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(near, _)| near)
+        .filter(|near| !near.is_empty());
+    if let Some(near) = near {
+        let target = reported.unwrap_or(0);
+        let found = tokens(sql)
+            .into_iter()
+            .filter(|(_, _, token)| token.eq_ignore_ascii_case(near))
+            .min_by_key(|(at, _, _)| at.abs_diff(target));
+        if let Some((at, _, _)) = found {
+            return Some(position(sql, at));
+        }
+    }
+    if message.contains("unexpected end of input") || message.contains("incomplete input") {
+        return Some(position(sql, sql.len()));
+    }
+    reported.map(|at| position(sql, at.min(sql.len())))
+}
+
+/// Whether `sql` is a CREATE TRIGGER statement, which limbo_core can't run yet.
+pub(crate) fn is_create_trigger(sql: &str) -> bool {
+    is_create(sql, "TRIGGER")
+}
+
+/// Whether `sql` is a CREATE VIEW statement.
+pub(crate) fn is_create_view(sql: &str) -> bool {
+    is_create(sql, "VIEW")
+}
+
+/// Whether `sql` creates a `kind` of object, e.g. `TRIGGER`, TEMP or not.
+fn is_create(sql: &str, kind: &str) -> bool {
+    let words: Vec<_> = top_level_words(sql)
+        .into_iter()
+        .take(3)
+        .map(|(_, word)| word.to_ascii_uppercase())
+        .collect();
+    let at = match words.get(1).map(String::as_str) {
+        Some("TEMP" | "TEMPORARY") => 2,
+        _ => 1,
+    };
+    words.first().is_some_and(|word| word == "CREATE")
+        && words.get(at).is_some_and(|word| word == kind)
+}
+
+/// Every name in `sql`, unquoted and lowercased, whatever it names, so that a view's or
+/// trigger's SQL can be told to name a table anywhere in it.
+pub(crate) fn named(sql: &str) -> Vec<String> {
+    tokens(sql)
+        .into_iter()
+        .filter(|(_, _, token)| {
+            token.starts_with(is_name_char) || token.starts_with(['"', '`', '['])
+        })
+        .map(|(_, _, token)| unquote(token).to_ascii_lowercase())
+        .collect()
+}
+
+/// The table a `CREATE TEMP TABLE` or `CREATE TEMPORARY TABLE` statement creates, with
+/// where its TEMP keyword is in `sql`, and where its name is, after `temp.` if it has that.
+pub(crate) fn create_temp_table(sql: &str) -> Option<(String, Range<usize>, Range<usize>)> {
+    let tokens = tokens(sql);
+    let word = |i: usize| {
+        tokens
+            .get(i)
+            .map(|(_, _, token)| token.to_ascii_uppercase())
+    };
+    if word(0)? != "CREATE" || !["TEMP", "TEMPORARY"].contains(&word(1)?.as_str()) {
+        return None;
+    }
+    if word(2)? != "TABLE" {
+        return None;
+    }
+    let start = match word(3).as_deref() {
+        Some("IF") => 6,
+        _ => 3,
+    };
+    let (temp_at, _, temp) = tokens[1];
+    let name = table_name(&tokens, start)?;
+    Some((name.0, temp_at..temp_at + temp.len(), name.1))
+}
+
+/// The table a `DROP TABLE` statement drops, with where its name is in `sql`.
+pub(crate) fn dropped_table(sql: &str) -> Option<(String, Range<usize>)> {
+    let tokens = tokens(sql);
+    let word = |i: usize| {
+        tokens
+            .get(i)
+            .map(|(_, _, token)| token.to_ascii_uppercase())
+    };
+    if word(0)? != "DROP" || word(1)? != "TABLE" {
+        return None;
+    }
+    let start = match word(2).as_deref() {
+        Some("IF") => 4,
+        _ => 2,
+    };
+    table_name(&tokens, start)
+}
+
+/// The table named by `tokens[start..]`, as `name` or `schema.name`, with where that is in
+/// the SQL.
+fn table_name(tokens: &[(usize, usize, &str)], start: usize) -> Option<(String, Range<usize>)> {
+    let end = match tokens.get(start + 1) {
+        Some((_, _, ".")) => start + 3,
+        _ => start + 1,
+    };
+    let (at, _, _) = *tokens.get(start)?;
+    let (last, _, name) = *tokens.get(end - 1)?;
+    if !name.starts_with(is_name_char) && !name.starts_with(['"', '`', '[']) {
+        return None;
+    }
+    Some((unquote(name), at..last + name.len()))
+}
+
+/// `sql` with the tables in `names`, by lowercase name, read and written under the names
+/// they map to, see `database.set-temp-store`. A table read without an alias is given its
+/// old name as one, so columns qualified by it still resolve.
+pub(crate) fn rename_tables(sql: &str, names: &BTreeMap<String, String>) -> String {
+    if names.is_empty() {
+        return sql.to_string();
+    }
+    let tokens = tokens(sql);
+    let renamed = |reference: &Range<usize>| {
+        names.get(&unquote(tokens[reference.end - 1].2).to_ascii_lowercase())
+    };
+    let mut renames = vec![];
+    if let Some(target) = write_target(&tokens) {
+        if let Some(name) = renamed(&target) {
+            renames.push((target, name.clone()));
+        }
+    }
+    for reference in table_references(&tokens) {
+        let Some(name) = renamed(&reference) else {
+            continue;
+        };
+        let name = match has_alias(&tokens, reference.end) {
+            true => name.clone(),
+            false => format!("{} AS {}", name, tokens[reference.end - 1].2),
+        };
+        renames.push((reference, name));
+    }
+    // DELETE's table is also read FROM, and is renamed as the table written
+    renames.sort_by_key(|(reference, _)| reference.start);
+    renames.dedup_by_key(|(reference, _)| reference.start);
+
+    let mut out = sql.to_string();
+    for (reference, name) in renames.into_iter().rev() {
+        let (start, _, _) = tokens[reference.start];
+        let (last, _, token) = tokens[reference.end - 1];
+        out.replace_range(start..last + token.len(), &name);
+    }
+    out
+}
+
+/// Whether `sql` leaves the database file unchanged, following `sqlite3_stmt_readonly`:
+/// queries, EXPLAIN and reading PRAGMAs are, and so are BEGIN, COMMIT, ROLLBACK, SAVEPOINT
+/// and RELEASE, which only change what the connection does with later statements.
+pub(crate) fn is_readonly(sql: &str) -> bool {
+    let tokens = tokens(sql);
+    let words: Vec<_> = tokens
+        .iter()
+        .filter(|(_, depth, token)| *depth == 0 && token.starts_with(is_name_char))
+        .map(|(_, _, token)| token.to_ascii_uppercase())
+        .collect();
+    let Some(first) = words.first() else {
+        return true;
+    };
+    match first.as_str() {
+        "SELECT" | "VALUES" | "EXPLAIN" | "BEGIN" | "COMMIT" | "END" | "ROLLBACK" | "SAVEPOINT"
+        | "RELEASE" => true,
+        // a CTE can lead into a write, which comes after the CTEs at the top level
+        "WITH" => !words
+            .iter()
+            .any(|word| ["INSERT", "REPLACE", "UPDATE", "DELETE"].contains(&word.as_str())),
+        // `PRAGMA name = value` sets, and these change the file whatever their arguments
+        "PRAGMA" => {
+            let name = words.get(1).map(String::as_str).unwrap_or_default();
+            !tokens.iter().any(|(_, _, token)| *token == "=")
+                && ![
+                    "WAL_CHECKPOINT",
+                    "OPTIMIZE",
+                    "INCREMENTAL_VACUUM",
+                    "SHRINK_MEMORY",
+                ]
+                .contains(&name)
+        }
+        _ => false,
+    }
+}
+
+/// What `sql` does, from its first keyword, or for WITH from the first keyword after the CTEs
+/// that starts a statement.
+pub(crate) fn kind(sql: &str) -> StatementKind {
+    const LEADS: [&str; 6] = ["SELECT", "VALUES", "INSERT", "REPLACE", "UPDATE", "DELETE"];
+    let words: Vec<_> = top_level_words(sql)
+        .into_iter()
+        .map(|(_, word)| word.to_ascii_uppercase())
+        .collect();
+    let mut words = words.iter().map(String::as_str);
+    let first = match words.next() {
+        Some("WITH") => words.find(|word| LEADS.contains(word)),
+        first => first,
+    };
+    match first {
+        Some("SELECT" | "VALUES") => StatementKind::Select,
+        Some("INSERT" | "REPLACE") => StatementKind::Insert,
+        Some("UPDATE") => StatementKind::Update,
+        Some("DELETE") => StatementKind::Delete,
+        Some("CREATE" | "DROP" | "ALTER") => StatementKind::Ddl,
+        Some("PRAGMA") => StatementKind::Pragma,
+        _ => StatementKind::Other,
+    }
+}
+
+/// Whether `sql` calls a function whose result doesn't follow from the tables it reads,
+/// such as `random()` or `datetime('now')`.
+pub(crate) fn is_volatile(sql: &str) -> bool {
+    const VOLATILE: [&str; 12] = [
+        "random",
+        "randomblob",
+        "uuid4",
+        "uuid7",
+        "ulid",
+        "changes",
+        "total_changes",
+        "last_insert_rowid",
+        "current_date",
+        "current_time",
+        "current_timestamp",
+        "'now'",
+    ];
+    tokens(sql).iter().any(|(_, _, token)| {
+        VOLATILE
+            .iter()
+            .any(|volatile| token.eq_ignore_ascii_case(volatile))
+    })
+}
+
+/// What defensive mode refuses in `sql`, if anything, see `database.set-defensive`: a write to
+/// the schema table or `sqlite_dbpage`, or setting a PRAGMA that can corrupt the database.
+pub(crate) fn defensive_refuses(sql: &str) -> Option<String> {
+    const TABLES: [&str; 5] = [
+        "sqlite_schema",
+        "sqlite_master",
+        "sqlite_temp_schema",
+        "sqlite_temp_master",
+        "sqlite_dbpage",
+    ];
+    let tokens = tokens(sql);
+    if let Some(target) = write_target(&tokens) {
+        let table = unquote(tokens[target.end - 1].2);
+        return TABLES
+            .iter()
+            .any(|t| table.eq_ignore_ascii_case(t))
+            .then(|| format!("writing {}", table));
+    }
+    if !tokens
+        .first()
+        .is_some_and(|(_, _, token)| token.eq_ignore_ascii_case("PRAGMA"))
+    {
+        return None;
+    }
+    // the name comes after the schema's, if there is one, and then `= value` or `(value)`
+    let name_at = match tokens.get(2) {
+        Some((_, _, ".")) => 3,
+        _ => 1,
+    };
+    let name = unquote(tokens.get(name_at)?.2).to_ascii_lowercase();
+    let sets = tokens
+        .get(name_at + 1)
+        .is_some_and(|(_, _, token)| *token == "=" || *token == "(");
+    let value = tokens
+        .get(name_at + 2)
+        .map(|(_, _, token)| unquote(token).trim_matches('\'').to_ascii_lowercase());
+    match name.as_str() {
+        "writable_schema" | "schema_version" if sets => Some(format!("setting {}", name)),
+        "journal_mode" if sets && value.as_deref() == Some("off") => {
+            Some("turning journal_mode off".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// How deeply parentheses nest in `sql`, the measure `limit-kind.expr-depth` bounds.
+pub(crate) fn depth(sql: &str) -> usize {
+    tokens(sql)
+        .iter()
+        .map(|(_, depth, _)| *depth)
+        .max()
+        .unwrap_or(0)
+}
+
+/// The columns of the table `sql` creates, or 0 if it doesn't create one with a list of
+/// column definitions.
+pub(crate) fn created_columns(sql: &str) -> usize {
+    let words = top_level_words(sql);
+    let is_create_table = words
+        .first()
+        .is_some_and(|(_, w)| w.eq_ignore_ascii_case("CREATE"))
+        && words
+            .iter()
+            .take(3)
+            .any(|(_, w)| w.eq_ignore_ascii_case("TABLE"));
+    // CREATE TABLE ... AS SELECT takes its columns from the query, which isn't counted
+    if !is_create_table || words.iter().any(|(_, w)| w.eq_ignore_ascii_case("AS")) {
+        return 0;
+    }
+    definitions(sql)
+        .0
+        .iter()
+        .filter(|definition| definition.column.is_some())
+        .count()
+}
+
+/// A query paginated by key, see `statement.keyset-page`.
+pub(crate) struct Keyset {
+    /// The query, limited to the rows after the cursor.
+    pub(crate) sql: String,
+    /// The names of the ORDER BY columns, whose values in the last row are the next cursor.
+    pub(crate) keys: Vec<String>,
+}
+
+/// Rewrite `sql`, which must end in an ORDER BY over plain columns, to return the `limit` rows
+/// that sort after the key values in `after`, or the first `limit` rows if it is `None`.
+///
+/// The condition on the keys goes into the query's own WHERE clause rather than around a
+/// subquery, so the keys must be columns the WHERE clause can see.
+pub(crate) fn keyset(
+    sql: &str,
+    after: Option<&[RecordValue]>,
+    limit: u32,
+) -> Result<Keyset, DbError> {
+    let sql = sql.trim_end().trim_end_matches(';').trim_end();
+    let words = top_level_words(sql);
+    let word_at = |i: usize| words.get(i).map(|(_, word)| word.to_ascii_uppercase());
+    if let Some((_, word)) = words.iter().find(|(_, word)| {
+        ["UNION", "INTERSECT", "EXCEPT"].contains(&word.to_ascii_uppercase().as_str())
+    }) {
+        return Err(DbError::Misuse(format!(
+            "keyset-page can't paginate a compound {}",
+            word
+        )));
+    }
+    let order = (0..words.len())
+        .rev()
+        .find(|&i| {
+            word_at(i).as_deref() == Some("ORDER") && word_at(i + 1).as_deref() == Some("BY")
+        })
+        .ok_or_else(|| {
+            DbError::Misuse("keyset-page needs the query to end in ORDER BY".to_string())
+        })?;
+    let (head, clause) = (&sql[..words[order].0], &sql[words[order + 1].0 + 2..]);
+
+    let mut keys = vec![];
+    let mut terms = vec![];
+    for term in clause.split(',') {
+        let mut parts = term.split_whitespace();
+        let column = parts.next().unwrap_or_default();
+        let descending = match parts.next().map(|word| word.to_ascii_uppercase()) {
+            None => false,
+            Some(word) if word == "ASC" => false,
+            Some(word) if word == "DESC" => true,
+            Some(word) => {
+                return Err(DbError::Misuse(format!(
+                    "keyset-page can't paginate with {} after ORDER BY, it adds its own LIMIT",
+                    word
+                )))
+            }
+        };
+        if column.is_empty() || column.contains('(') || parts.next().is_some() {
+            return Err(DbError::Misuse(format!(
+                "keyset-page needs ORDER BY plain columns, not {:?}",
+                term.trim()
+            )));
+        }
+        let name = column.rsplit('.').next().unwrap_or(column);
+        keys.push(unquote(name));
+        terms.push((column, descending));
+    }
+
+    let mut out = head.trim_end().to_string();
+    if let Some(after) = after {
+        if after.len() != terms.len() {
+            return Err(DbError::Misuse(format!(
+                "the cursor has {} values for {} ORDER BY columns",
+                after.len(),
+                terms.len()
+            )));
+        }
+        // (k1 > v1) OR (k1 = v1 AND k2 > v2) OR ..., with < for DESC columns
+        let mut condition = String::new();
+        for (i, (column, descending)) in terms.iter().enumerate() {
+            if i > 0 {
+                condition.push_str(" OR ");
+            }
+            condition.push('(');
+            for (equal, value) in terms[..i].iter().zip(after) {
+                condition.push_str(equal.0);
+                condition.push_str(" = ");
+                push_literal(&mut condition, Some(value));
+                condition.push_str(" AND ");
+            }
+            condition.push_str(column);
+            condition.push_str(if *descending { " < " } else { " > " });
+            push_literal(&mut condition, Some(&after[i]));
+            condition.push(')');
+        }
+        out = add_condition(&out, &condition).trim_end().to_string();
+    }
+    out.push_str(&format!(" ORDER BY {} LIMIT {}", clause.trim(), limit));
+    Ok(Keyset { sql: out, keys })
+}
+
+/// `sql` with `condition` ANDed into its WHERE clause, or given one if it has none.
+fn add_condition(sql: &str, condition: &str) -> String {
+    let words = top_level_words(sql);
+    let is = |word: &str, names: &[&str]| names.contains(&word.to_ascii_uppercase().as_str());
+    let clause_end = |from: usize| {
+        words[from..]
+            .iter()
+            .find(|(_, word)| {
+                is(
+                    word,
+                    &["GROUP", "HAVING", "WINDOW", "ORDER", "LIMIT", "RETURNING"],
+                )
+            })
+            .map_or(sql.len(), |(at, _)| *at)
+    };
+    match words.iter().position(|(_, word)| is(word, &["WHERE"])) {
+        Some(i) => {
+            let (start, end) = (words[i].0 + "WHERE".len(), clause_end(i));
+            format!(
+                "{} ({}) AND ({}) {}",
+                &sql[..start],
+                sql[start..end].trim(),
+                condition,
+                &sql[end..]
+            )
+        }
+        None => {
+            let end = clause_end(0);
+            format!(
+                "{} WHERE {} {}",
+                sql[..end].trim_end(),
+                condition,
+                &sql[end..]
+            )
+        }
+    }
+}
+
+/// The words outside parentheses, string literals, quoted identifiers and comments, with
+/// where each starts.
+fn top_level_words(sql: &str) -> Vec<(usize, &str)> {
+    tokens(sql)
+        .into_iter()
+        .filter(|(_, depth, token)| *depth == 0 && token.starts_with(is_name_char))
+        .map(|(at, _, token)| (at, token))
+        .collect()
+}
+
+/// The tokens of `sql` other than whitespace and comments, with where each starts and how
+/// deep inside parentheses it is. A token is a word, a quoted string or identifier, or a
+/// single other character.
+fn tokens(sql: &str) -> Vec<(usize, usize, &str)> {
+    let mut tokens = vec![];
+    let mut depth = 0usize;
+    let mut at = 0;
+    while let Some(c) = sql[at..].chars().next() {
+        let rest = &sql[at..];
+        let len = match c {
+            '\'' | '"' | '`' => quoted(rest, c),
+            '[' => quoted(rest, ']'),
+            '-' if rest.starts_with("--") => {
+                at += rest.find('\n').unwrap_or(rest.len());
+                continue;
+            }
+            '/' if rest.starts_with("/*") => {
+                at += rest.find("*/").map_or(rest.len(), |end| end + 2);
+                continue;
+            }
+            c if is_name_char(c) => rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len()),
+            c if c.is_whitespace() => {
+                at += c.len_utf8();
+                continue;
+            }
+            c => c.len_utf8(),
+        };
+        if c == ')' {
+            depth = depth.saturating_sub(1);
+        }
+        tokens.push((at, depth, &rest[..len]));
+        if c == '(' {
+            depth += 1;
+        }
+        at += len;
+    }
+    tokens
+}
+
+/// The tables `sql` reads from, i.e. named after FROM or JOIN, in the order they first
+/// appear, without duplicates. Table-valued functions and subqueries are left out.
+pub(crate) fn referenced_tables(sql: &str) -> Vec<String> {
+    let tokens = tokens(sql);
+    let mut tables: Vec<String> = vec![];
+    for reference in table_references(&tokens) {
+        let name = unquote(tokens[reference.end - 1].2);
+        if !tables.iter().any(|table| table.eq_ignore_ascii_case(&name)) {
+            tables.push(name);
+        }
+    }
+    tables
+}
+
+/// What running a statement can change, for deciding which subscriptions to re-run and
+/// which materialized views to refresh, see `database.subscribe`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Changes {
+    /// The statement leaves every table as it was.
+    Nothing,
+    /// The statement writes rows of this table only.
+    Table(String),
+    /// The statement may change any table: DDL, PRAGMAs, several statements at once, and
+    /// writes whose table can't be told.
+    Everything,
+    /// BEGIN, after which writes are part of a transaction.
+    Begin,
+    /// COMMIT or END, which makes the writes since BEGIN visible to other connections.
+    Commit,
+    /// ROLLBACK, which undoes the writes since BEGIN.
+    Rollback,
+}
+
+/// What running `sql` can change: the table an INSERT, REPLACE, UPDATE or DELETE names, the
+/// end of a transaction, or [Changes::Nothing] for the other statements that are
+/// [is_readonly].
+pub(crate) fn changes(sql: &str) -> Changes {
+    let tokens = tokens(sql);
+    // anything after a `;` is another statement
+    if tokens
+        .iter()
+        .position(|(_, depth, token)| *depth == 0 && *token == ";")
+        .is_some_and(|end| tokens[end + 1..].iter().any(|(_, _, token)| *token != ";"))
+    {
+        return Changes::Everything;
+    }
+    let words: Vec<_> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, depth, token))| *depth == 0 && token.starts_with(is_name_char))
+        .map(|(i, (_, _, token))| (i, token.to_ascii_uppercase()))
+        .collect();
+    let Some((_, first)) = words.first() else {
+        return Changes::Nothing;
+    };
+    match first.as_str() {
+        "BEGIN" => return Changes::Begin,
+        "COMMIT" | "END" => return Changes::Commit,
+        // ROLLBACK TO a savepoint undoes some writes but stays in the transaction
+        "ROLLBACK" if words.iter().any(|(_, word)| word == "TO") => return Changes::Everything,
+        "ROLLBACK" => return Changes::Rollback,
+        _ => {}
+    }
+    if is_readonly(sql) {
+        return Changes::Nothing;
+    }
+    match write_target(&tokens) {
+        Some(target) => Changes::Table(unquote(tokens[target.end - 1].2)),
+        None => Changes::Everything,
+    }
+}
+
+/// The tokens naming the table an INSERT, REPLACE, UPDATE or DELETE in `tokens` writes, as a
+/// range of indexes that ends with the table's name, after its schema's if it has one.
+fn write_target(tokens: &[(usize, usize, &str)]) -> Option<Range<usize>> {
+    let words: Vec<_> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, depth, token))| *depth == 0 && token.starts_with(is_name_char))
+        .map(|(i, (_, _, token))| (i, token.to_ascii_uppercase()))
+        .collect();
+    let (_, first) = words.first()?;
+    // the table follows INTO, the first word after UPDATE (and any OR clause), or FROM
+    let before_table = match first.as_str() {
+        "INSERT" | "REPLACE" => words.iter().find(|(_, word)| word == "INTO"),
+        "DELETE" => words.iter().find(|(_, word)| word == "FROM"),
+        "UPDATE" => match words.get(1) {
+            Some((_, word)) if word == "OR" => words.get(2),
+            _ => words.first(),
+        },
+        _ => None,
+    };
+    let &(i, _) = before_table?;
+    // `schema.table` names the table last
+    let end = match tokens.get(i + 2) {
+        Some((_, _, ".")) => i + 4,
+        _ => i + 2,
+    };
+    match tokens.get(end - 1) {
+        Some((_, _, name))
+            if name.starts_with(is_name_char) || name.starts_with(['"', '`', '[']) =>
+        {
+            Some(i + 1..end)
+        }
+        _ => None,
+    }
+}
+
+/// The tokens naming each table after FROM or JOIN in `tokens`, as a range of indexes that
+/// ends with the table's name, after its schema's if it has one.
+fn table_references(tokens: &[(usize, usize, &str)]) -> Vec<Range<usize>> {
+    let mut references = vec![];
+    // the depths at which a FROM list is open, where a comma starts another table
+    let mut from_lists: Vec<usize> = vec![];
+    let mut expect_table = false;
+    for (i, &(_, depth, token)) in tokens.iter().enumerate() {
+        let upper = token.to_ascii_uppercase();
+        if token == ")" {
+            while from_lists.last().is_some_and(|&open| open > depth) {
+                from_lists.pop();
+            }
+        }
+        if expect_table {
+            expect_table = false;
+            let is_name = token.starts_with(is_name_char) || token.starts_with(['"', '`', '[']);
+            // `schema.table` names the table last, and `name(` is a table-valued function
+            let end = match tokens.get(i + 1) {
+                Some((_, _, ".")) => i + 3,
+                _ => i + 1,
+            };
+            if is_name && end <= tokens.len() && !matches!(tokens.get(end), Some((_, _, "("))) {
+                references.push(i..end);
+            }
+            continue;
+        }
+        match upper.as_str() {
+            "FROM" => {
+                from_lists.push(depth);
+                expect_table = true;
+            }
+            "JOIN" => expect_table = true,
+            "," if from_lists.last() == Some(&depth) => expect_table = true,
+            "WHERE" | "GROUP" | "HAVING" | "ORDER" | "LIMIT" | "WINDOW" | "UNION" | "INTERSECT"
+            | "EXCEPT" | "ON" | "USING"
+                if from_lists.last() == Some(&depth) =>
+            {
+                from_lists.pop();
+            }
+            _ => {}
+        }
+    }
+    references
+}
+
+/// Keywords that can follow a table in FROM, so are not its alias.
+const AFTER_TABLE: [&str; 22] = [
+    "WHERE",
+    "JOIN",
+    "ON",
+    "USING",
+    "GROUP",
+    "HAVING",
+    "ORDER",
+    "LIMIT",
+    "WINDOW",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "INNER",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "CROSS",
+    "NATURAL",
+    "OUTER",
+    "RETURNING",
+    "SET",
+    "INDEXED",
+];
+
+/// Whether the table named by the tokens before `after` in `tokens` is given an alias.
+fn has_alias(tokens: &[(usize, usize, &str)], after: usize) -> bool {
+    match tokens
+        .get(after)
+        .map(|(_, _, token)| token.to_ascii_uppercase())
+    {
+        Some(word) => {
+            word == "AS"
+                || ((word.starts_with(is_name_char) || word.starts_with(['"', '`', '[']))
+                    && !AFTER_TABLE.contains(&word.as_str()))
+        }
+        None => false,
+    }
+}
+
+/// `sql` with the rows of each table in `filters` limited to those matching its predicate,
+/// see `database.set-table-filter`. `filters` maps lowercase table names to predicates.
+///
+/// Each table read after FROM or JOIN is replaced by a subquery of its matching rows under
+/// the table's name, UPDATE and DELETE get the predicate added to their WHERE clause, and so
+/// does each `ON CONFLICT ... DO UPDATE` of an INSERT, with the predicate also holding of the
+/// row as its SET clause leaves it, see [write_condition]. REPLACE into a filtered table is
+/// refused, as it could delete a row the predicate doesn't match.
+pub(crate) fn apply_filters(
+    sql: &str,
+    filters: &BTreeMap<String, String>,
+) -> Result<String, DbError> {
+    if filters.is_empty() {
+        return Ok(sql.to_string());
+    }
+    let tokens = tokens(sql);
+    let predicate = |i: usize| filters.get(&unquote(tokens[i].2).to_ascii_lowercase());
+    let word = |i: usize| {
+        tokens
+            .get(i)
+            .map(|(_, _, token)| token.to_ascii_uppercase())
+    };
+
+    // (start, end, text) to replace sql[start..end] with
+    let mut edits = vec![];
+    if let Some(written) = write_target(&tokens) {
+        let name = written.end - 1;
+        if let Some(predicate) = predicate(name) {
+            let replaces = word(0).as_deref() == Some("REPLACE")
+                || (word(1).as_deref() == Some("OR") && word(2).as_deref() == Some("REPLACE"));
+            if replaces {
+                return Err(DbError::Misuse(format!(
+                    "REPLACE into {} is refused, as the table is filtered",
+                    unquote(tokens[name].2)
+                )));
+            }
+            if word(0).as_deref() == Some("INSERT") {
+                edits.extend(do_update_conditions(sql, &tokens, predicate)?);
+            }
+        }
+    }
+
+    // the table UPDATE or DELETE writes, which is filtered in place
+    let target = match word(0).as_deref() {
+        Some("DELETE") => Some(2),
+        Some("UPDATE") => (1..tokens.len()).find(|&i| {
+            let word = word(i).unwrap_or_default();
+            !["OR", "ROLLBACK", "ABORT", "REPLACE", "FAIL", "IGNORE"].contains(&word.as_str())
+        }),
+        _ => None,
+    };
+    let target_end = target.map(|i| match tokens.get(i + 1) {
+        Some((_, _, ".")) => i + 3,
+        _ => i + 1,
+    });
+
+    for reference in table_references(&tokens) {
+        if Some(reference.end) == target_end {
+            continue;
+        }
+        let Some(predicate) = predicate(reference.end - 1) else {
+            continue;
+        };
+        let (start, _, _) = tokens[reference.start];
+        let (last, _, name) = tokens[reference.end - 1];
+        let mut subquery = format!(
+            "(SELECT * FROM {} WHERE ({}))",
+            &sql[start..last + name.len()],
+            predicate
+        );
+        if !has_alias(&tokens, reference.end) {
+            subquery.push_str(" AS ");
+            subquery.push_str(name);
+        }
+        edits.push((start, last + name.len(), subquery));
+    }
+    edits.sort_by_key(|(start, end, _)| (*start, *end));
+    let mut out = sql.to_string();
+    for (start, end, text) in edits.into_iter().rev() {
+        out.replace_range(start..end, &text);
+    }
+    Ok(match target_end.and_then(|end| predicate(end - 1)) {
+        Some(predicate) => {
+            let set = (0..tokens.len())
+                .find(|&i| tokens[i].1 == 0 && tokens[i].2.eq_ignore_ascii_case("SET"));
+            add_condition(&out, &write_condition(sql, &tokens, set, predicate)?)
+        }
+        None => out,
+    })
+}
+
+/// The condition for writing a row of a table filtered by `predicate`: that the predicate
+/// holds of the row, and, if the statement has a SET clause at token `set`, of the row as the
+/// clause leaves it too, so an UPDATE can't move a row out of the filter. A row value,
+/// `SET (a, b) = ...`, is refused, as the columns it sets can't be told.
+fn write_condition(
+    sql: &str,
+    tokens: &[(usize, usize, &str)],
+    set: Option<usize>,
+    predicate: &str,
+) -> Result<String, DbError> {
+    let Some(set) = set else {
+        return Ok(format!("({})", predicate));
+    };
+    let depth = tokens[set].1;
+    let ends = ["WHERE", "FROM", "RETURNING", "ORDER", "LIMIT", "ON", ";"];
+    let end = (set + 1..tokens.len())
+        .find(|&i| {
+            tokens[i].1 == depth && ends.contains(&tokens[i].2.to_ascii_uppercase().as_str())
+        })
+        .unwrap_or(tokens.len());
+    let mut assignments = vec![];
+    let mut at = set + 1;
+    while at < end {
+        let next = (at..end)
+            .find(|&i| tokens[i].1 == depth && tokens[i].2 == ",")
+            .unwrap_or(end);
+        if (at + 2..next).is_empty() || tokens[at + 1].2 != "=" || tokens[at].2 == "(" {
+            return Err(DbError::Misuse(
+                "SET of a row value in a filtered table is refused, as the columns it sets \
+                 can't be told"
+                    .to_string(),
+            ));
+        }
+        let (last, _, token) = tokens[next - 1];
+        assignments.push((
+            unquote(tokens[at].2),
+            &sql[tokens[at + 2].0..last + token.len()],
+        ));
+        at = next + 1;
+    }
+    let after = after_assignments(predicate, &assignments);
+    Ok(if after == predicate {
+        format!("({})", predicate)
+    } else {
+        format!("({}) AND ({})", predicate, after)
+    })
+}
+
+/// `predicate` as it holds of a row once `assignments` have set its columns: each column it
+/// names, qualified or not, is replaced by the value it is set to, which is written in terms
+/// of the row as it was, so the result can be checked before the row is written.
+fn after_assignments(predicate: &str, assignments: &[(String, &str)]) -> String {
+    let tokens = tokens(predicate);
+    let mut out = String::with_capacity(predicate.len());
+    let mut at = 0;
+    for (i, &(start, _, token)) in tokens.iter().enumerate() {
+        let is_name = token.starts_with(is_name_char) || token.starts_with(['"', '`', '[']);
+        // a qualifier, or a function's name
+        if !is_name || matches!(tokens.get(i + 1), Some((_, _, "." | "("))) {
+            continue;
+        }
+        let column = unquote(token);
+        let Some((_, value)) = assignments
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&column))
+        else {
+            continue;
+        };
+        let start = match i.checked_sub(2) {
+            Some(qualifier) if tokens[i - 1].2 == "." => tokens[qualifier].0,
+            _ => start,
+        };
+        out.push_str(&predicate[at..start]);
+        out.push('(');
+        out.push_str(value);
+        out.push(')');
+        at = tokens[i].0 + token.len();
+    }
+    out.push_str(&predicate[at..]);
+    out
+}
+
+/// The edits adding `predicate` to the WHERE clause of each `DO UPDATE` in the INSERT `sql`,
+/// of `tokens`, so it only updates the rows the predicate matches, and leaves them matching
+/// it, see [write_condition]. Each clause runs to the next `ON CONFLICT`, RETURNING or the end
+/// of the statement.
+fn do_update_conditions(
+    sql: &str,
+    tokens: &[(usize, usize, &str)],
+    predicate: &str,
+) -> Result<Vec<(usize, usize, String)>, DbError> {
+    let is = |i: usize, word: &str| {
+        tokens
+            .get(i)
+            .is_some_and(|(_, depth, token)| *depth == 0 && token.eq_ignore_ascii_case(word))
+    };
+    let end = |i: usize| tokens[i].0 + tokens[i].2.len();
+    let mut edits = vec![];
+    for at in (0..tokens.len()).filter(|&i| is(i, "DO") && is(i + 1, "UPDATE")) {
+        let clause_end = (at + 2..tokens.len())
+            .find(|&i| (is(i, "ON") && is(i + 1, "CONFLICT")) || is(i, "RETURNING") || is(i, ";"))
+            .unwrap_or(tokens.len());
+        let set = is(at + 2, "SET").then_some(at + 2);
+        let condition = write_condition(sql, tokens, set, predicate)?;
+        match (at + 2..clause_end).find(|&i| is(i, "WHERE")) {
+            Some(at) => {
+                let (start, _, _) = tokens[at + 1];
+                edits.push((start, start, "(".to_string()));
+                edits.push((
+                    end(clause_end - 1),
+                    end(clause_end - 1),
+                    format!(") AND {}", condition),
+                ));
+            }
+            None => edits.push((
+                end(clause_end - 1),
+                end(clause_end - 1),
+                format!(" WHERE {}", condition),
+            )),
+        }
+    }
+    Ok(edits)
+}
+
+/// The words directly before a parenthesis that don't call a function with it.
+const NOT_FUNCTIONS: &[&str] = &[
+    "AND", "BY", "ELSE", "EXISTS", "HAVING", "IN", "NOT", "ON", "OR", "SELECT", "THEN", "WHEN",
+    "WHERE",
+];
+
+/// `sql` with LIKE and `COLLATE NOCASE` folding case beyond ASCII, see
+/// `database.set-unicode-case`.
+///
+/// Both operands of each LIKE become `lower(...)` of themselves, and an operand with
+/// `COLLATE NOCASE` becomes `lower(...)` of itself instead, along with what it is compared to.
+/// DDL is left as it is, so the collations columns are declared with don't change.
+pub(crate) fn fold_case(sql: &str) -> String {
+    match fold_case_with(sql, |_, _| Ok::<_, Infallible>(None)) {
+        Ok(folded) => folded,
+        Err(never) => match never {},
+    }
+}
+
+/// `sql` folded as [fold_case] folds it, but with each operand `fold` gives some SQL for,
+/// given how it is written and what it is, replaced by that instead of `lower(...)` of it.
+pub(crate) fn fold_case_with<E>(
+    sql: &str,
+    mut fold: impl FnMut(&str, &Operand) -> Result<Option<String>, E>,
+) -> Result<String, E> {
+    let tokens = tokens(sql);
+    let is = |i: usize, word: &str| {
+        tokens
+            .get(i)
+            .is_some_and(|(_, _, token)| token.eq_ignore_ascii_case(word))
+    };
+    if ["CREATE", "ALTER", "DROP"].iter().any(|word| is(0, word)) {
+        return Ok(sql.to_string());
+    }
+    let mut folds = vec![];
+    let mut collations = vec![];
+    for i in 0..tokens.len() {
+        if is(i, "LIKE") {
+            let end = if i > 0 && is(i - 1, "NOT") { i - 1 } else { i };
+            folds.extend(operand_before(&tokens, end));
+            folds.extend(operand_after(&tokens, i + 1));
+        } else if is(i, "COLLATE") && is(i + 1, "NOCASE") {
+            let Some(operand) = operand_before(&tokens, i) else {
+                continue;
+            };
+            if let Some(at) = comparison_before(&tokens, operand.start) {
+                folds.extend(operand_before(&tokens, at));
+            }
+            if let Some(at) = comparison_after(&tokens, i + 2) {
+                folds.extend(operand_after(&tokens, at));
+            }
+            folds.push(operand);
+            collations.push(i - 1..i + 2);
+        }
+    }
+    folds.sort_by_key(|fold| (fold.start, fold.end));
+    folds.dedup();
+
+    // (start, end, text) to replace sql[start..end] with
+    let end = |i: usize| tokens[i].0 + tokens[i].2.len();
+    let parameters = parameters(sql);
+    let mut edits = vec![];
+    for range in folds {
+        // an operand's own COLLATE clause is no part of what is folded
+        let folded = if range.len() > 2 && is(range.end - 2, "COLLATE") {
+            range.start..range.end - 2
+        } else {
+            range.clone()
+        };
+        let (written, what) = operand(sql, &tokens, &parameters, folded.clone(), None);
+        let start = tokens[range.start].0;
+        match fold(written, &what)? {
+            Some(replacement) => edits.push((start, end(folded.end - 1), replacement)),
+            None => {
+                edits.push((start, start, "lower(".to_string()));
+                edits.push((end(range.end - 1), end(range.end - 1), ")".to_string()));
+            }
+        }
+    }
+    for collation in collations {
+        edits.push((end(collation.start), end(collation.end - 1), String::new()));
+    }
+    edits.sort_by_key(|(start, end, _)| (*start, *end));
+    let mut out = String::with_capacity(sql.len() + edits.len() * 3);
+    let mut at = 0;
+    for (start, end, text) in edits {
+        out.push_str(&sql[at.min(start)..start]);
+        out.push_str(&text);
+        at = at.max(end);
+    }
+    out.push_str(&sql[at..]);
+    Ok(out)
+}
+
+/// The collations limbo has, which [collations] leaves to it.
+#[cfg(all(feature = "host-locale", not(feature = "native")))]
+const COLLATIONS: [&str; 3] = ["BINARY", "NOCASE", "RTRIM"];
+
+/// A `COLLATE name` with a collation limbo doesn't have, such as a locale's, that
+/// [collations] finds.
+#[cfg(all(feature = "host-locale", not(feature = "native")))]
+pub(crate) struct Collation<'s> {
+    /// The name of the collation, unquoted, e.g. `de-DE`.
+    pub(crate) name: String,
+    /// The operands it applies to, with the bytes each spans: the one it follows, whose
+    /// bytes include the COLLATE clause, and the one it is compared to, if it is.
+    pub(crate) operands: Vec<(Range<usize>, &'s str, Operand)>,
+}
+
+/// Each `COLLATE name` in `sql` with a collation limbo doesn't have, in order. DDL is left
+/// out, as [fold_case] leaves it.
+#[cfg(all(feature = "host-locale", not(feature = "native")))]
+pub(crate) fn collations(sql: &str) -> Vec<Collation<'_>> {
+    let tokens = tokens(sql);
+    let is = |i: usize, word: &str| {
+        tokens
+            .get(i)
+            .is_some_and(|(_, _, token)| token.eq_ignore_ascii_case(word))
+    };
+    if ["CREATE", "ALTER", "DROP"].iter().any(|word| is(0, word)) {
+        return vec![];
+    }
+    let parameters = parameters(sql);
+    let end = |i: usize| tokens[i].0 + tokens[i].2.len();
+    let mut collations = vec![];
+    for i in 0..tokens.len().saturating_sub(1) {
+        if !is(i, "COLLATE") || COLLATIONS.iter().any(|name| is(i + 1, name)) {
+            continue;
+        }
+        let Some(collated) = operand_before(&tokens, i) else {
+            continue;
+        };
+        let mut compared = vec![];
+        if let Some(at) = comparison_before(&tokens, collated.start) {
+            compared.extend(operand_before(&tokens, at));
+        }
+        if let Some(at) = comparison_after(&tokens, i + 2) {
+            compared.extend(operand_after(&tokens, at));
+        }
+        let (written, what) = operand(sql, &tokens, &parameters, collated.clone(), None);
+        let mut operands = vec![(tokens[collated.start].0..end(i + 1), written, what)];
+        for range in compared {
+            let (written, what) = operand(sql, &tokens, &parameters, range.clone(), None);
+            operands.push((tokens[range.start].0..end(range.end - 1), written, what));
+        }
+        collations.push(Collation {
+            name: unquote(tokens[i + 1].2),
+            operands,
+        });
+    }
+    collations
+}
+
+/// The tokens of the operand that ends just before token `end`: a name, literal, parameter,
+/// function call or parenthesized expression, with any COLLATE clause of its own.
+fn operand_before(tokens: &[(usize, usize, &str)], end: usize) -> Option<Range<usize>> {
+    let mut start = end.checked_sub(1)?;
+    if start >= 2 && tokens[start - 1].2.eq_ignore_ascii_case("COLLATE") {
+        start -= 2;
+    }
+    let (_, depth, token) = tokens[start];
+    if token == ")" {
+        start = (0..start)
+            .rev()
+            .find(|&i| tokens[i].1 == depth && tokens[i].2 == "(")?;
+        if start > 0 && is_call(tokens, start - 1) {
+            start -= 1;
+        }
+    } else if token.starts_with(is_name_char) || token.starts_with(['\'', '"', '`', '[']) {
+        while start >= 2 && tokens[start - 1].2 == "." {
+            start -= 2;
+        }
+        if start > 0
+            && adjacent(tokens, start - 1)
+            && ["?", ":", "@", "$"].contains(&tokens[start - 1].2)
+        {
+            start -= 1;
+        }
+    } else if token != "?" {
+        return None;
+    }
+    Some(start..end)
+}
+
+/// The tokens of the operand that starts at token `start`, as [operand_before] reads them.
+fn operand_after(tokens: &[(usize, usize, &str)], start: usize) -> Option<Range<usize>> {
+    let (_, _, token) = *tokens.get(start)?;
+    let mut end = start + 1;
+    let mut open = None;
+    if ["?", ":", "@", "$"].contains(&token) {
+        if end < tokens.len() && adjacent(tokens, start) && tokens[end].2.starts_with(is_name_char)
+        {
+            end += 1;
+        }
+    } else if token.starts_with(is_name_char) || token.starts_with(['\'', '"', '`', '[']) {
+        while end + 1 < tokens.len() && tokens[end].2 == "." {
+            end += 2;
+        }
+        if end < tokens.len() && tokens[end].2 == "(" && is_call(tokens, end - 1) {
+            open = Some(end);
+        }
+    } else if token == "(" {
+        open = Some(start);
+    } else {
+        return None;
+    }
+    if let Some(open) = open {
+        let depth = tokens[open].1;
+        end = (open + 1..tokens.len()).find(|&i| tokens[i].1 == depth && tokens[i].2 == ")")? + 1;
+    }
+    Some(start..end)
+}
+
+/// Whether token `i` is the name of a function called with the parenthesis after it.
+fn is_call(tokens: &[(usize, usize, &str)], i: usize) -> bool {
+    let name = tokens[i].2;
+    name.starts_with(is_name_char)
+        && adjacent(tokens, i)
+        && !NOT_FUNCTIONS.contains(&name.to_ascii_uppercase().as_str())
+}
+
+/// Whether token `i` runs straight into the one after it, with no space in between.
+fn adjacent(tokens: &[(usize, usize, &str)], i: usize) -> bool {
+    tokens
+        .get(i + 1)
+        .is_some_and(|next| tokens[i].0 + tokens[i].2.len() == next.0)
+}
+
+/// Where the comparison operator that ends just before token `end` starts, if one does.
+fn comparison_before(tokens: &[(usize, usize, &str)], end: usize) -> Option<usize> {
+    let start = (0..end)
+        .rev()
+        .take_while(|&i| ["=", "<", ">", "!"].contains(&tokens[i].2))
+        .last()?;
+    Some(start)
+}
+
+/// Where the operand after the comparison operator starting at token `start` starts, if a
+/// comparison operator starts there.
+fn comparison_after(tokens: &[(usize, usize, &str)], start: usize) -> Option<usize> {
+    let end = (start..tokens.len())
+        .find(|&i| !["=", "<", ">", "!"].contains(&tokens[i].2))
+        .unwrap_or(tokens.len());
+    (end > start).then_some(end)
+}
+
+/// An `x REGEXP pattern` [regexps] finds.
+#[cfg_attr(not(feature = "regexp"), allow(dead_code))]
+pub(crate) struct Regexp<'s> {
+    /// The bytes from `x` to the end of the pattern.
+    pub(crate) span: Range<usize>,
+    /// Whether it is `x NOT REGEXP pattern`.
+    pub(crate) not: bool,
+    /// `x`, as it is written and what it is.
+    pub(crate) operand: (&'s str, Operand),
+    /// The pattern, as it is written and what it is.
+    pub(crate) pattern: (&'s str, Operand),
+}
+
+/// What an operand of an operator the guest works out itself is, see [regexps],
+/// [fold_case_with] and [collations].
+#[cfg_attr(
+    not(any(
+        feature = "regexp",
+        all(feature = "host-locale", not(feature = "native"))
+    )),
+    allow(dead_code)
+)]
+pub(crate) enum Operand {
+    /// A string or integer literal, or NULL.
+    Literal(RecordValue),
+    /// The parameter with this number.
+    Parameter(usize),
+    /// A column, by its name without any table it is qualified with.
+    Column(String),
+    /// Anything else, such as a function call or an expression.
+    Other,
+}
+
+/// Operators that bind more tightly than comparisons, so an operand next to one is only part
+/// of the operand of the comparison.
+const TIGHTER: [&str; 7] = ["|", "+", "-", "*", "/", "%", "&"];
+
+/// The operand at tokens `range`, as it is written and what it is. Next to an operator at
+/// token `next_to` that binds more tightly, it is only part of an operand, so
+/// [Operand::Other].
+fn operand<'s>(
+    sql: &'s str,
+    tokens: &[(usize, usize, &str)],
+    parameters: &[(Range<usize>, usize)],
+    range: Range<usize>,
+    next_to: Option<usize>,
+) -> (&'s str, Operand) {
+    let (start, _, _) = tokens[range.start];
+    let (last, _, token) = tokens[range.end - 1];
+    let span = start..last + token.len();
+    let words = &tokens[range];
+    let literal = match words {
+        [(_, _, token)] => literal_value(token),
+        _ => None,
+    };
+    // `name`, or `table.name`, quoted or not
+    let is_name = |token: &str| {
+        token.starts_with(|c: char| is_name_char(c) && !c.is_ascii_digit())
+            || token.starts_with(['"', '`', '['])
+    };
+    let is_column = words.len() % 2 == 1
+        && words.iter().step_by(2).all(|(_, _, token)| is_name(token))
+        && words
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|(_, _, token)| *token == ".");
+    let parameter = parameters.iter().find(|(range, _)| *range == span);
+    let operand = if next_to.is_some_and(|i| TIGHTER.contains(&tokens[i].2)) {
+        Operand::Other
+    } else if let Some(&(_, index)) = parameter {
+        Operand::Parameter(index)
+    } else if let Some(value) = literal {
+        Operand::Literal(value)
+    } else if is_column {
+        Operand::Column(unquote(token))
+    } else {
+        Operand::Other
+    };
+    (&sql[span], operand)
+}
+
+/// Each `x REGEXP pattern` in `sql`, in order, or the error for one whose `x` can't be told.
+pub(crate) fn regexps(sql: &str) -> Result<Vec<Regexp<'_>>, DbError> {
+    let tokens = tokens(sql);
+    let parameters = parameters(sql);
+    let mut regexps = vec![];
+    for i in 0..tokens.len() {
+        if !tokens[i].2.eq_ignore_ascii_case("REGEXP") {
+            continue;
+        }
+        let not = i > 0 && tokens[i - 1].2.eq_ignore_ascii_case("NOT");
+        let x = operand_before(&tokens, if not { i - 1 } else { i });
+        let (Some(x), Some(pattern)) = (x, operand_after(&tokens, i + 1)) else {
+            return Err(error::unsupported("REGEXP after or before this operand"));
+        };
+        let (last, _, token) = tokens[pattern.end - 1];
+        regexps.push(Regexp {
+            span: tokens[x.start].0..last + token.len(),
+            not,
+            operand: operand(sql, &tokens, &parameters, x.clone(), x.start.checked_sub(1)),
+            pattern: operand(
+                sql,
+                &tokens,
+                &parameters,
+                pattern.clone(),
+                (pattern.end < tokens.len()).then_some(pattern.end),
+            ),
+        });
+    }
+    Ok(regexps)
+}
+
+/// `sql` with each call to `uuid4()`, `uuid7()` or `ulid()` replaced by an expression that
+/// generates one from `randomblob` and `random`, which draw on `host-random`, so every row
+/// gets a new one. The time in a UUIDv7 or ULID is `now_ms`, when the statement is prepared,
+/// so they are refused in DDL, whose statements are run again long after.
+pub(crate) fn id_functions(sql: &str, now_ms: u64) -> Result<String, DbError> {
+    let tokens = tokens(sql);
+    let is_ddl = tokens.first().is_some_and(|(_, _, token)| {
+        ["CREATE", "ALTER"]
+            .iter()
+            .any(|word| token.eq_ignore_ascii_case(word))
+    });
+    // the last 12 hex digits of a UUID, after the version and variant
+    let random_hex = "substr(hex(randomblob(2)), 2) || '-' || \
+                      substr('89ab', 1 + (random() & 3), 1) || \
+                      substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6))";
+    let mut out = sql.to_string();
+    for i in (0..tokens.len()).rev() {
+        let call = tokens.get(i + 1).map(|(_, _, token)| *token) == Some("(")
+            && tokens.get(i + 2).map(|(_, _, token)| *token) == Some(")");
+        if !call {
+            continue;
+        }
+        let function = tokens[i].2.to_ascii_lowercase();
+        let timed = matches!(function.as_str(), "uuid7" | "ulid");
+        if timed && is_ddl {
+            return Err(error::unsupported(&format!("{}() in DDL", function)));
+        }
+        let expression = match function.as_str() {
+            "uuid4" => format!(
+                "lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || {})",
+                random_hex
+            ),
+            "uuid7" => {
+                let time = format!("{:012x}", now_ms & 0xFFFF_FFFF_FFFF);
+                format!("'{}-{}-7' || lower({})", &time[..8], &time[8..], random_hex)
+            }
+            "ulid" => {
+                const CROCKFORD: &str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+                let time: String = (0..10)
+                    .rev()
+                    .map(|digit| {
+                        CROCKFORD.as_bytes()[(now_ms >> (digit * 5)) as usize & 31] as char
+                    })
+                    .collect();
+                let random = format!("substr('{}', 1 + (random() & 31), 1)", CROCKFORD);
+                format!("'{}' || {}", time, vec![random; 16].join(" || "))
+            }
+            _ => continue,
+        };
+        let (start, _, _) = tokens[i];
+        let (close, _, _) = tokens[i + 2];
+        out.replace_range(start..close + 1, &format!("({})", expression));
+    }
+    Ok(out)
+}
+
+/// The argument of a call [calls] finds.
+#[cfg(feature = "hash-functions")]
+pub(crate) enum Argument<'s> {
+    /// A string, blob or integer literal, or NULL.
+    Literal(RecordValue),
+    /// The parameter with this number, written as it is in the SQL.
+    Parameter(usize, &'s str),
+    /// Anything else, such as a column or more than one argument.
+    Other,
+}
+
+/// The calls in `sql` of the functions `names`, with the bytes each spans, the name of its
+/// function in lowercase, and its argument.
+#[cfg(feature = "hash-functions")]
+pub(crate) fn calls<'s>(sql: &'s str, names: &[&str]) -> Vec<(Range<usize>, String, Argument<'s>)> {
+    let tokens = tokens(sql);
+    let parameters = parameters(sql);
+    let mut calls = vec![];
+    for (i, &(start, depth, name)) in tokens.iter().enumerate() {
+        let name = name.to_ascii_lowercase();
+        if !names.contains(&name.as_str()) || tokens.get(i + 1).map(|t| t.2) != Some("(") {
+            continue;
+        }
+        let Some(close) =
+            (i + 2..tokens.len()).find(|&j| tokens[j].1 == depth && tokens[j].2 == ")")
+        else {
+            continue;
+        };
+        let argument = &tokens[i + 2..close];
+        let span = match (argument.first(), argument.last()) {
+            (Some(&(from, _, _)), Some(&(last, _, token))) => from..last + token.len(),
+            _ => 0..0,
+        };
+        let parameter = parameters.iter().find(|(range, _)| *range == span);
+        let argument = match (parameter, argument) {
+            (Some(&(_, index)), _) => Argument::Parameter(index, &sql[span]),
+            (None, [(_, _, token)]) => {
+                literal_value(token).map_or(Argument::Other, Argument::Literal)
+            }
+            (None, [(at, _, x), (blob, _, token)])
+                if x.eq_ignore_ascii_case("x") && at + 1 == *blob =>
+            {
+                literal_value(token)
+                    .and_then(|value| match value {
+                        RecordValue::Text(hex) => decode_hex(&hex).map(RecordValue::Blob),
+                        _ => None,
+                    })
+                    .map_or(Argument::Other, Argument::Literal)
+            }
+            _ => Argument::Other,
+        };
+        calls.push((start..tokens[close].0 + 1, name, argument));
+    }
+    calls
+}
+
+/// The value of the single-token literal `token`: a string or integer, or NULL.
+fn literal_value(token: &str) -> Option<RecordValue> {
+    if token.eq_ignore_ascii_case("NULL") {
+        return Some(RecordValue::Null);
+    }
+    if token.len() >= 2 && token.starts_with('\'') && token.ends_with('\'') {
+        return Some(RecordValue::Text(
+            token[1..token.len() - 1].replace("''", "'"),
+        ));
+    }
+    token.parse().ok().map(RecordValue::Integer)
+}
+
+#[cfg(feature = "hash-functions")]
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Where each parameter is in `sql`, with its number, numbered as [expand] numbers them.
+pub(crate) fn parameters(sql: &str) -> Vec<(Range<usize>, usize)> {
+    let tokens = tokens(sql);
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    let mut last = 0;
+    let mut parameters = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let (at, _, token) = tokens[i];
+        // the name or number written straight after `?`, `:`, `@` or `$`
+        let suffix = tokens
+            .get(i + 1)
+            .filter(|(next, _, word)| *next == at + token.len() && word.starts_with(is_name_char))
+            .map(|(_, _, word)| *word);
+        let (index, len) = match (token, suffix) {
+            ("?", Some(digits)) if digits.bytes().all(|b| b.is_ascii_digit()) => {
+                (digits.parse().unwrap_or(last + 1), 2)
+            }
+            ("?", _) => (last + 1, 1),
+            (":" | "@" | "$", Some(name)) => {
+                let name = &sql[at..at + 1 + name.len()];
+                (*names.entry(name).or_insert(last + 1), 2)
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        let (end, _, word) = tokens[i + len - 1];
+        last = last.max(index);
+        parameters.push((at..end + word.len(), index));
+        i += len;
+    }
+    parameters
+}
+
+/// The generated columns of the table `create_table` makes, by name.
+///
+/// A column is generated if its definition has `AS (...)`, optionally after
+/// `GENERATED ALWAYS`, and is stored only if `STORED` follows.
+pub(crate) fn generated_columns(create_table: &str) -> Vec<(String, GeneratedKind)> {
+    let tokens = tokens(create_table);
+    let mut generated = vec![];
+    for definition in column_list(&tokens) {
+        let word = |i: usize| {
+            definition
+                .get(i)
+                .map(|(_, _, token)| token.to_ascii_uppercase())
+        };
+        let Some(as_at) = (0..definition.len()).find(|&i| {
+            definition[i].1 == 1
+                && word(i).as_deref() == Some("AS")
+                && word(i + 1).as_deref() == Some("(")
+        }) else {
+            continue;
+        };
+        let close = (as_at + 2..definition.len())
+            .find(|&i| definition[i].1 == 1 && definition[i].2 == ")")
+            .unwrap_or(definition.len());
+        let kind = match word(close + 1).as_deref() {
+            Some("STORED") => GeneratedKind::Stored,
+            _ => GeneratedKind::Virtual,
+        };
+        generated.push((unquote(definition[0].2), kind));
+    }
+    generated
+}
+
+/// A foreign key of a table, from a REFERENCES clause.
+pub(crate) struct ForeignKey {
+    /// The columns of the table that refer to the parent.
+    pub(crate) columns: Vec<String>,
+    /// The table the key refers to.
+    pub(crate) parent: String,
+    /// The columns of the parent referred to, empty for its primary key.
+    pub(crate) parent_columns: Vec<String>,
+}
+
+/// The foreign keys of the table `create_table` makes, in the order they are declared: a
+/// column's `REFERENCES parent (column)`, or a table constraint's
+/// `FOREIGN KEY (columns) REFERENCES parent (columns)`.
+pub(crate) fn foreign_keys(create_table: &str) -> Vec<ForeignKey> {
+    let tokens = tokens(create_table);
+    let mut keys = vec![];
+    for definition in column_list(&tokens) {
+        let is_word = |i: usize, word: &str| {
+            definition
+                .get(i)
+                .is_some_and(|(_, depth, token)| *depth == 1 && token.eq_ignore_ascii_case(word))
+        };
+        let Some(references) = (0..definition.len()).find(|&i| is_word(i, "REFERENCES")) else {
+            continue;
+        };
+        let Some((_, _, parent)) = definition.get(references + 1) else {
+            continue;
+        };
+        let columns = match (0..references).find(|&i| is_word(i, "FOREIGN")) {
+            Some(foreign) => (foreign..references)
+                .find(|&i| is_word(i, "("))
+                .map_or(vec![], |open| names(definition, open)),
+            None => vec![unquote(definition[0].2)],
+        };
+        let parent_columns = if is_word(references + 2, "(") {
+            names(definition, references + 2)
+        } else {
+            vec![]
+        };
+        keys.push(ForeignKey {
+            columns,
+            parent: unquote(parent),
+            parent_columns,
+        });
+    }
+    keys
+}
+
+/// The names listed in the parentheses opening at `tokens[open]`, e.g. `(a, b COLLATE x)`,
+/// each the first token of its item.
+fn names(tokens: &[(usize, usize, &str)], open: usize) -> Vec<String> {
+    let depth = tokens[open].1;
+    let end = tokens[open + 1..]
+        .iter()
+        .position(|(_, at, _)| *at == depth)
+        .map_or(tokens.len(), |end| open + 1 + end);
+    tokens[open + 1..end]
+        .split(|(_, at, token)| *at == depth + 1 && *token == ",")
+        .filter_map(|item| item.first())
+        .map(|(_, _, name)| unquote(name))
+        .collect()
+}
+
+/// A column definition or table constraint from a CREATE TABLE statement.
+pub(crate) struct Definition<'a> {
+    /// The column's name, `None` for a table constraint.
+    pub(crate) column: Option<String>,
+    /// The definition as written.
+    pub(crate) text: &'a str,
+    /// The definition's tokens separated by single spaces, to compare definitions by.
+    pub(crate) normalized: String,
+}
+
+/// The column definitions and table constraints of the table `create_table` makes, in order,
+/// and where the parenthesized list of them starts.
+pub(crate) fn definitions(create_table: &str) -> (Vec<Definition<'_>>, usize) {
+    let tokens = tokens(create_table);
+    let open = tokens
+        .iter()
+        .find(|(_, _, token)| *token == "(")
+        .map_or(create_table.len(), |(at, _, _)| *at);
+    let definitions = column_list(&tokens)
+        .into_iter()
+        .map(|definition| {
+            let (start, _, _) = definition[0];
+            let (last, _, token) = definition[definition.len() - 1];
+            let first = definition[0].2.to_ascii_uppercase();
+            let is_constraint =
+                ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"].contains(&first.as_str());
+            Definition {
+                column: (!is_constraint).then(|| unquote(definition[0].2)),
+                text: &create_table[start..last + token.len()],
+                normalized: join(definition),
+            }
+        })
+        .collect();
+    (definitions, open)
+}
+
+/// `sql` with its whitespace and comments normalized away, to compare statements by.
+pub(crate) fn normalize(sql: &str) -> String {
+    join(&tokens(sql))
+}
+
+/// `tokens` separated by single spaces.
+fn join(tokens: &[(usize, usize, &str)]) -> String {
+    let tokens: Vec<_> = tokens.iter().map(|(_, _, token)| *token).collect();
+    tokens.join(" ")
+}
+
+/// The tokens of each column definition or table constraint in the first parenthesized
+/// list of `tokens`, which are those of a CREATE TABLE statement.
+fn column_list<'t, 's>(
+    tokens: &'t [(usize, usize, &'s str)],
+) -> Vec<&'t [(usize, usize, &'s str)]> {
+    let Some(open) = tokens.iter().position(|(_, _, token)| *token == "(") else {
+        return vec![];
+    };
+    // the list ends at the first token back outside all parentheses
+    let end = tokens[open + 1..]
+        .iter()
+        .position(|(_, depth, _)| *depth == 0)
+        .map_or(tokens.len(), |end| open + 1 + end);
+    tokens[open + 1..end]
+        .split(|(_, depth, token)| *depth == 1 && *token == ",")
+        .filter(|definition| !definition.is_empty())
+        .collect()
+}
+
+/// `name` without the quotes around it, if it has any.
+fn unquote(name: &str) -> String {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next_back()) {
+        (Some(open @ ('"' | '`')), Some(close)) if open == close => {
+            let quote = open.to_string();
+            chars.as_str().replace(&quote.repeat(2), &quote)
+        }
+        (Some('['), Some(']')) => chars.as_str().to_string(),
+        _ => name.to_string(),
+    }
+}
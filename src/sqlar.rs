@@ -0,0 +1,147 @@
+//! Files kept in an SQLite archive table, see the `sqlar` interface in the WIT.
+use std::cell::Cell;
+
+use crate::bindings::component::wit_limbo::host_clock::now;
+use crate::bindings::exports::component::wit_limbo::limbo::{DbError, GuestDatabase, RecordValue};
+use crate::bindings::exports::component::wit_limbo::sqlar::{Entry, Guest, GuestArchive};
+use crate::Component;
+
+/// The table of the standard layout, which the sqlite3 shell's `-A` options read and write.
+const TABLE: &str = "sqlar";
+
+pub struct Archive {
+    db: Component,
+    /// Whether [TABLE] is known to exist.
+    created: Cell<bool>,
+}
+
+impl Guest for Component {
+    type Archive = Archive;
+}
+
+impl Archive {
+    /// Run `sql` with `params` bound in order, creating the table first if needed.
+    fn query(&self, sql: &str, params: Vec<RecordValue>) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        if !self.created.get() {
+            self.db.exec(format!(
+                "CREATE TABLE IF NOT EXISTS {} \
+                 (name TEXT PRIMARY KEY, mode INT, mtime INT, sz INT, data BLOB)",
+                TABLE
+            ))?;
+            self.created.set(true);
+        }
+        self.db.query_with(sql, params)
+    }
+}
+
+/// `bytes` as they are stored: zlib-compressed where that makes them smaller.
+#[cfg(feature = "sqlar-compression")]
+fn compress(bytes: Vec<u8>) -> Vec<u8> {
+    let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&bytes, 6);
+    if compressed.len() < bytes.len() {
+        compressed
+    } else {
+        bytes
+    }
+}
+
+#[cfg(not(feature = "sqlar-compression"))]
+fn compress(bytes: Vec<u8>) -> Vec<u8> {
+    bytes
+}
+
+/// The `size` bytes stored as `data`, which were compressed unless they are that size.
+fn decompress(name: &str, data: Vec<u8>, size: usize) -> Result<Vec<u8>, DbError> {
+    if data.len() == size {
+        return Ok(data);
+    }
+    #[cfg(feature = "sqlar-compression")]
+    {
+        miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(&data, size)
+            .ok()
+            .filter(|bytes| bytes.len() == size)
+            .ok_or_else(|| DbError::Corrupt(format!("{} in {} doesn't decompress", name, TABLE)))
+    }
+    #[cfg(not(feature = "sqlar-compression"))]
+    Err(DbError::Misuse(format!(
+        "{} is compressed, which needs the component built with the sqlar-compression feature",
+        name
+    )))
+}
+
+impl GuestArchive for Archive {
+    fn new(path: String) -> Self {
+        Self {
+            db: Component::new(path),
+            created: Cell::new(false),
+        }
+    }
+
+    fn add_file(&self, name: String, bytes: Vec<u8>, mode: u32) -> Result<(), DbError> {
+        let size = bytes.len() as i64;
+        let sql = format!(
+            "INSERT INTO {} (name, mode, mtime, sz, data) VALUES (?, ?, ?, ?, ?) \
+             ON CONFLICT (name) DO UPDATE SET mode = excluded.mode, mtime = excluded.mtime, \
+             sz = excluded.sz, data = excluded.data",
+            TABLE
+        );
+        self.query(
+            &sql,
+            vec![
+                RecordValue::Text(name),
+                RecordValue::Integer(mode as i64),
+                RecordValue::Integer((now() / 1_000_000) as i64),
+                RecordValue::Integer(size),
+                RecordValue::Blob(compress(bytes)),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn extract(&self, name: String) -> Result<Option<Vec<u8>>, DbError> {
+        let sql = format!("SELECT sz, data FROM {} WHERE name = ?", TABLE);
+        let rows = self.query(&sql, vec![RecordValue::Text(name.clone())])?;
+        let Some(row) = rows.into_iter().next() else {
+            return Ok(None);
+        };
+        let mut row = row.into_iter();
+        match (row.next(), row.next()) {
+            (Some(RecordValue::Integer(size)), Some(RecordValue::Blob(data))) if size >= 0 => {
+                decompress(&name, data, size as usize).map(Some)
+            }
+            // other tools store an empty file's data as null, and a directory's too
+            (Some(RecordValue::Integer(_)), Some(RecordValue::Null)) => Ok(Some(vec![])),
+            other => Err(DbError::Corrupt(format!(
+                "{} has an unexpected row: {:?}",
+                TABLE, other
+            ))),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<Entry>, DbError> {
+        let sql = format!("SELECT name, mode, mtime, sz FROM {} ORDER BY name", TABLE);
+        self.query(&sql, vec![])?
+            .into_iter()
+            .map(|row| {
+                let mut row = row.into_iter();
+                match (row.next(), row.next(), row.next(), row.next()) {
+                    (
+                        Some(RecordValue::Text(name)),
+                        Some(RecordValue::Integer(mode)),
+                        Some(RecordValue::Integer(mtime)),
+                        Some(RecordValue::Integer(size)),
+                    ) => Ok(Entry {
+                        name,
+                        mode: mode as u32,
+                        mtime,
+                        size: size.max(0) as u64,
+                    }),
+                    other => Err(DbError::Corrupt(format!(
+                        "{} has an unexpected row: {:?}",
+                        TABLE, other
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
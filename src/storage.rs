@@ -0,0 +1,323 @@
+//! Where a database lives, chosen by the scheme of the path it is opened with:
+//!
+//! - `:memory:` is [MemoryStorage], gone when the last connection closes
+//! - `kv:<name>` is [KvStorage], blocks in the host's `host-kv` store
+//! - `file:<path>`, or any other path, is [FsStorage], a file through `host-fs`; a `file:`
+//!   URI may end in `?mode=ro`, `rw` or `rwc`, see [uri_params]
+//!
+//! Built with the `in-memory` feature, only `:memory:` is, and the component imports neither
+//! `host-fs`, `host-io` nor `host-kv`; any other path fails with
+//! [Unsupported](std::io::ErrorKind::Unsupported).
+//!
+//! Each [StorageBackend] provides the [IO] the database file and its WAL are opened on.
+//!
+//! A database that doesn't exist is created, or not, as the `open-flags` it is opened with
+//! say. Opening one that is missing without `create`, or one that exists with `exclusive`,
+//! fails with an IO error of kind [NotFound](std::io::ErrorKind::NotFound) or
+//! [AlreadyExists](std::io::ErrorKind::AlreadyExists), and a URI with a parameter that isn't
+//! supported with [InvalidInput](std::io::ErrorKind::InvalidInput), which all become
+//! `db-error::cannot-open`.
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::Arc,
+};
+
+use limbo_core::{
+    maybe_init_database_file, BufferPool, Database, LimboError, MemoryIO, OpenFlags as IoFlags,
+    Pager, Result, WalFile, WalFileShared, IO,
+};
+
+use crate::bindings::exports::component::wit_limbo::limbo::OpenFlags;
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+use crate::{host_fs::HostFsIO, host_kv::HostKvIO};
+
+fn cannot_open(kind: std::io::ErrorKind, message: String) -> LimboError {
+    LimboError::IOError(std::io::Error::new(kind, message))
+}
+
+pub trait StorageBackend {
+    /// The scheme this backend is chosen by, e.g. `kv`.
+    fn scheme(&self) -> &'static str;
+
+    /// The IO the database file and its WAL are read and written through.
+    fn io(&self) -> Arc<dyn IO>;
+
+    /// Whether the backend compresses what it stores, for `database.set-compress-pages`, or
+    /// `None` if it can't.
+    fn compress_pages(&self) -> Option<Rc<Cell<bool>>> {
+        None
+    }
+
+    /// How many reads' worth its files read ahead by once reads are sequential, for
+    /// `database.set-readahead-pages`, or `None` if they can't.
+    fn readahead_pages(&self) -> Option<Rc<Cell<u32>>> {
+        None
+    }
+
+    /// Whether its files hold writes until they are synced, for
+    /// `database.set-coalesce-writes`, or `None` if they can't.
+    fn coalesce_writes(&self) -> Option<Rc<Cell<bool>>> {
+        None
+    }
+
+    /// The path of the WAL for the database at `path`.
+    fn wal_path(&self, path: &str) -> String {
+        format!("{}-wal", path)
+    }
+
+    /// Open the database at `path` with its WAL, creating the database if it is missing and
+    /// `flags` allow, and the WAL if it is missing.
+    fn open(&self, path: &str, flags: OpenFlags) -> Result<Arc<Database>> {
+        use std::io::ErrorKind;
+
+        let io = self.io();
+        let file = match io.open_file(path, IoFlags::None, false) {
+            Ok(_) if flags.contains(OpenFlags::EXCLUSIVE) => {
+                return Err(cannot_open(
+                    ErrorKind::AlreadyExists,
+                    format!("{} already exists", path),
+                ))
+            }
+            Ok(file) => file,
+            Err(_) if flags.contains(OpenFlags::CREATE) => {
+                io.open_file(path, IoFlags::Create, false)?
+            }
+            Err(e) => {
+                return Err(cannot_open(
+                    ErrorKind::NotFound,
+                    format!("{} doesn't exist: {}", path, e),
+                ))
+            }
+        };
+
+        maybe_init_database_file(&file, &io)?;
+        let page_io = Rc::new(DatabaseStorage::new(file));
+        let db_header = Pager::begin_open(page_io.clone())?;
+
+        // ensure db header is there
+        io.run_once()?;
+
+        let page_size = db_header.borrow().page_size;
+
+        let wal_shared = WalFileShared::open_shared(&io, &self.wal_path(path), page_size)?;
+        let buffer_pool = Rc::new(BufferPool::new(page_size as usize));
+        let wal = Rc::new(RefCell::new(WalFile::new(
+            io.clone(),
+            page_size as usize,
+            wal_shared.clone(),
+            buffer_pool.clone(),
+        )));
+
+        Database::open(io, page_io, wal, wal_shared, buffer_pool)
+    }
+}
+
+/// Split the query string off a `file:` URI, returning the path with `flags` as its
+/// parameters change them. Only `mode` is taken, as SQLite takes it: `ro` opens read-only,
+/// `rw` read-write without creating, and `rwc` creates the database if it is missing. Any
+/// other parameter or mode is refused rather than ignored, so a URI never opens the database
+/// differently from how it says.
+pub fn uri_params(uri: &str, mut flags: OpenFlags) -> Result<(&str, OpenFlags)> {
+    let Some((path, query)) = uri.strip_prefix("file:").and_then(|_| uri.split_once('?')) else {
+        return Ok((uri, flags));
+    };
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let invalid = || {
+            cannot_open(
+                std::io::ErrorKind::InvalidInput,
+                format!("{}: unsupported URI parameter {}", path, param),
+            )
+        };
+        let (name, value) = param.split_once('=').ok_or_else(invalid)?;
+        if name != "mode" {
+            return Err(invalid());
+        }
+        flags.remove(OpenFlags::CREATE | OpenFlags::READ_ONLY);
+        match value {
+            "ro" => flags.insert(OpenFlags::READ_ONLY),
+            "rw" => {}
+            "rwc" => flags.insert(OpenFlags::CREATE),
+            _ => return Err(invalid()),
+        }
+    }
+    Ok((path, flags))
+}
+
+/// Choose the backend for `uri`, returning it with the path within it.
+pub fn from_uri(uri: &str) -> Result<(Box<dyn StorageBackend>, &str)> {
+    if uri == ":memory:" {
+        return Ok((Box::new(MemoryStorage::new()?), uri));
+    }
+    host_storage(uri)
+}
+
+/// The backend the host provides for `uri`, which isn't `:memory:`.
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+fn host_storage(uri: &str) -> Result<(Box<dyn StorageBackend>, &str)> {
+    if let Some(name) = uri.strip_prefix("kv:") {
+        return Ok((Box::new(KvStorage::new()?), name));
+    }
+    // anything else is a file provided by the host
+    let path = uri.strip_prefix("file:").unwrap_or(uri);
+    Ok((Box::new(FsStorage::new()?), path))
+}
+
+/// Built with `in-memory`, the host provides no storage.
+#[cfg(all(feature = "in-memory", not(feature = "native")))]
+fn host_storage(uri: &str) -> Result<(Box<dyn StorageBackend>, &str)> {
+    Err(cannot_open(
+        std::io::ErrorKind::Unsupported,
+        format!("{}: only :memory: databases are built in", uri),
+    ))
+}
+
+/// An in-memory database, for `:memory:`.
+pub struct MemoryStorage {
+    io: Arc<dyn IO>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            io: Arc::new(MemoryIO::new()?),
+        })
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn scheme(&self) -> &'static str {
+        "memory"
+    }
+
+    fn io(&self) -> Arc<dyn IO> {
+        self.io.clone()
+    }
+}
+
+/// A database stored in files through the `host-fs` import, for `file:` and plain paths.
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+pub struct FsStorage {
+    io: Arc<dyn IO>,
+    readahead_pages: Rc<Cell<u32>>,
+    coalesce_writes: Rc<Cell<bool>>,
+}
+
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+impl FsStorage {
+    pub fn new() -> Result<Self> {
+        let readahead_pages = Rc::new(Cell::new(0));
+        let coalesce_writes = Rc::new(Cell::new(false));
+        Ok(Self {
+            io: Arc::new(HostFsIO::new(
+                readahead_pages.clone(),
+                coalesce_writes.clone(),
+            )?),
+            readahead_pages,
+            coalesce_writes,
+        })
+    }
+}
+
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+impl StorageBackend for FsStorage {
+    fn scheme(&self) -> &'static str {
+        "file"
+    }
+
+    fn io(&self) -> Arc<dyn IO> {
+        self.io.clone()
+    }
+
+    fn readahead_pages(&self) -> Option<Rc<Cell<u32>>> {
+        Some(self.readahead_pages.clone())
+    }
+
+    fn coalesce_writes(&self) -> Option<Rc<Cell<bool>>> {
+        Some(self.coalesce_writes.clone())
+    }
+}
+
+/// A database stored as blocks through the `host-kv` import, for `kv:` paths.
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+pub struct KvStorage {
+    io: Arc<dyn IO>,
+    compress: Rc<Cell<bool>>,
+    coalesce_writes: Rc<Cell<bool>>,
+}
+
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+impl KvStorage {
+    pub fn new() -> Result<Self> {
+        let compress = Rc::new(Cell::new(false));
+        let coalesce_writes = Rc::new(Cell::new(false));
+        Ok(Self {
+            io: Arc::new(HostKvIO::new(compress.clone(), coalesce_writes.clone())?),
+            compress,
+            coalesce_writes,
+        })
+    }
+}
+
+#[cfg(not(all(feature = "in-memory", not(feature = "native"))))]
+impl StorageBackend for KvStorage {
+    fn scheme(&self) -> &'static str {
+        "kv"
+    }
+
+    fn io(&self) -> Arc<dyn IO> {
+        self.io.clone()
+    }
+
+    fn compress_pages(&self) -> Option<Rc<Cell<bool>>> {
+        Some(self.compress.clone())
+    }
+
+    fn coalesce_writes(&self) -> Option<Rc<Cell<bool>>> {
+        Some(self.coalesce_writes.clone())
+    }
+}
+
+/// Pages of the database file, read and written on the backend's [IO].
+pub struct DatabaseStorage {
+    file: Rc<dyn limbo_core::File>,
+}
+
+impl DatabaseStorage {
+    pub fn new(file: Rc<dyn limbo_core::File>) -> Self {
+        Self { file }
+    }
+}
+
+impl limbo_core::DatabaseStorage for DatabaseStorage {
+    fn read_page(&self, page_idx: usize, c: limbo_core::Completion) -> Result<()> {
+        let r = match c {
+            limbo_core::Completion::Read(ref r) => r,
+            _ => unreachable!(),
+        };
+        let size = r.buf().len();
+        assert!(page_idx > 0);
+        if !(512..=65536).contains(&size) || size & (size - 1) != 0 {
+            return Err(limbo_core::LimboError::NotADB);
+        }
+        let pos = (page_idx - 1) * size;
+        self.file.pread(pos, c)?;
+        Ok(())
+    }
+
+    fn write_page(
+        &self,
+        page_idx: usize,
+        buffer: Rc<std::cell::RefCell<limbo_core::Buffer>>,
+        c: limbo_core::Completion,
+    ) -> Result<()> {
+        let size = buffer.borrow().len();
+        let pos = (page_idx - 1) * size;
+        self.file.pwrite(pos, buffer, c)?;
+        Ok(())
+    }
+
+    fn sync(&self, c: limbo_core::Completion) -> Result<()> {
+        self.file.sync(c)
+    }
+}
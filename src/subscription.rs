@@ -0,0 +1,133 @@
+//! Queries re-run after writes to the tables they read, see `database.subscribe` in the WIT.
+use std::{
+    cell::{Cell, RefCell},
+    rc::{Rc, Weak},
+};
+
+use crate::bindings::component::wit_limbo::host_subscriptions::on_query_changed;
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    DbError, GuestSubscription, RecordValue,
+};
+use crate::sql::Changes;
+use crate::Component;
+
+thread_local! {
+    /// The id of the next subscription, counted across every database so the host can tell
+    /// them apart from the id alone.
+    static NEXT_ID: Cell<u64> = const { Cell::new(1) };
+}
+
+/// The subscriptions to one database, shared by its connections like its metrics, so a write
+/// on any of them re-runs the queries it could change.
+#[derive(Default)]
+pub(crate) struct Subscriptions {
+    live: RefCell<Vec<Weak<Query>>>,
+}
+
+impl Subscriptions {
+    /// Re-run the subscribed queries `changes` could have changed, and tell the host about
+    /// those whose rows are now different.
+    pub(crate) fn notify(&self, changes: &Changes) {
+        if matches!(changes, Changes::Nothing | Changes::Begin) {
+            return;
+        }
+        // taken out of `live`, so re-runs and the host's calls back in can subscribe
+        let queries: Vec<Rc<Query>> = {
+            let mut live = self.live.borrow_mut();
+            live.retain(|query| query.strong_count() > 0);
+            live.iter().filter_map(Weak::upgrade).collect()
+        };
+        for query in queries {
+            if let Changes::Table(table) = changes {
+                if !query.tables.iter().any(|t| t.eq_ignore_ascii_case(table)) {
+                    continue;
+                }
+            }
+            if query.refresh() {
+                on_query_changed(query.id);
+            }
+        }
+    }
+}
+
+/// A subscribed query, and what it returned when last run.
+struct Query {
+    id: u64,
+    /// The connection that subscribed, so the query sees what it would see.
+    db: Component,
+    sql: String,
+    params: Vec<RecordValue>,
+    /// The tables the query reads, including those of the views it reads.
+    tables: Vec<String>,
+    latest: RefCell<Result<Vec<Vec<RecordValue>>, DbError>>,
+}
+
+impl Query {
+    /// Run the query again, returning whether its result changed.
+    fn refresh(&self) -> bool {
+        let rows = self.db.query_with(&self.sql, self.params.clone());
+        let changed = match (&rows, &*self.latest.borrow()) {
+            (Ok(rows), Ok(latest)) => !same_rows(rows, latest),
+            (Err(e), Err(latest)) => format!("{:?}", e) != format!("{:?}", latest),
+            _ => true,
+        };
+        if changed {
+            self.latest.replace(rows);
+        }
+        changed
+    }
+}
+
+/// Whether `a` and `b` hold exactly the same values. NaN floats are the same as themselves, so
+/// a result holding one doesn't count as changed every time it is run.
+fn same_rows(a: &[Vec<RecordValue>], b: &[Vec<RecordValue>]) -> bool {
+    use RecordValue as V;
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|pair| match pair {
+                    (V::Null, V::Null) => true,
+                    (V::Integer(a), V::Integer(b)) => a == b,
+                    (V::Float(a), V::Float(b)) => a.to_bits() == b.to_bits(),
+                    (V::Text(a), V::Text(b)) | (V::TextDecimal(a), V::TextDecimal(b)) => a == b,
+                    (V::Blob(a), V::Blob(b)) => a == b,
+                    _ => false,
+                })
+        })
+}
+
+pub struct Subscription(Rc<Query>);
+
+impl Subscription {
+    /// Subscribe `db`'s connection to `sql` with `params` bound in order, reading `tables`,
+    /// and run it for the first time.
+    pub(crate) fn new(
+        db: Component,
+        sql: String,
+        params: Vec<RecordValue>,
+        tables: Vec<String>,
+    ) -> Result<Self, DbError> {
+        let latest = db.query_with(&sql, params.clone())?;
+        let query = Rc::new(Query {
+            id: NEXT_ID.with(|next| next.replace(next.get() + 1)),
+            db,
+            sql,
+            params,
+            tables,
+            latest: RefCell::new(Ok(latest)),
+        });
+        let subscriptions = &query.db.state.subscriptions;
+        subscriptions.live.borrow_mut().push(Rc::downgrade(&query));
+        Ok(Self(query))
+    }
+}
+
+impl GuestSubscription for Subscription {
+    fn id(&self) -> u64 {
+        self.0.id
+    }
+
+    fn latest(&self) -> Result<Vec<Vec<RecordValue>>, DbError> {
+        self.0.latest.borrow().clone()
+    }
+}
@@ -0,0 +1,142 @@
+//! Temporary tables, see `database.set-temp-store` in the WIT.
+//!
+//! Limbo has no temp schema, so the guest keeps each connection's temporary tables itself:
+//! in an in-memory database of the connection's own, or in the database's storage under
+//! names of the connection's own. `CREATE TEMP TABLE` and the statements naming a temporary
+//! table are rewritten to use the table where it is kept.
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use crate::bindings::exports::component::wit_limbo::limbo::{
+    DbError, GuestDatabase, OpenFlags, RecordValue, TempStore,
+};
+use crate::{quote_identifier, sql, Component};
+
+/// What the names of the tables kept in the database's storage start with.
+const PREFIX: &str = "wit_limbo_temp_";
+
+thread_local! {
+    /// The id of the next connection's [TempTables], which makes the names they store tables
+    /// under their own.
+    static NEXT_ID: Cell<u64> = Cell::new(0);
+}
+
+/// Where to run a statement.
+pub(crate) enum Route {
+    /// On the connection, as this SQL.
+    Main(String),
+    /// On the in-memory database of temporary tables, as this SQL.
+    Memory(String),
+}
+
+/// The temporary tables of one connection.
+pub(crate) struct TempTables {
+    store: Cell<TempStore>,
+    /// The tables by lowercase name, with the quoted name each is kept under.
+    tables: RefCell<BTreeMap<String, String>>,
+    /// The database of [TempStore::Memory], opened when first needed.
+    memory: RefCell<Option<Component>>,
+    id: u64,
+}
+
+impl TempTables {
+    pub(crate) fn new() -> Self {
+        Self {
+            store: Cell::new(TempStore::Memory),
+            tables: RefCell::new(BTreeMap::new()),
+            memory: RefCell::new(None),
+            id: NEXT_ID.with(|id| id.replace(id.get() + 1)),
+        }
+    }
+
+    /// Keep the tables created from now on in `store`, which only changes while there are
+    /// none.
+    pub(crate) fn set_store(&self, store: TempStore) -> Result<(), DbError> {
+        if store != self.store.get() && !self.tables.borrow().is_empty() {
+            return Err(DbError::Misuse(
+                "the temp store can't change while temporary tables exist".to_string(),
+            ));
+        }
+        self.store.set(store);
+        Ok(())
+    }
+
+    /// Where to run `sql`, and as what.
+    pub(crate) fn route(&self, sql: &str) -> Route {
+        let mut sql = sql.to_string();
+        let mut tables = self.tables.borrow_mut();
+        if let Some((name, temp, at)) = sql::create_temp_table(&sql) {
+            let kept = match self.store.get() {
+                TempStore::Memory => quote_identifier(&name),
+                TempStore::Backend => quote_identifier(&format!("{}{}_{}", PREFIX, self.id, name)),
+            };
+            // a plain CREATE TABLE under the name it is kept as, the name coming after TEMP
+            sql.replace_range(at, &kept);
+            sql.replace_range(temp, "");
+            tables.insert(name.to_ascii_lowercase(), kept);
+            return self.on_store(sql);
+        }
+        if let Some((name, at)) = sql::dropped_table(&sql) {
+            if let Some(kept) = tables.remove(&name.to_ascii_lowercase()) {
+                sql.replace_range(at, &kept);
+                return self.on_store(sql);
+            }
+        }
+
+        let mut named = sql::referenced_tables(&sql);
+        if let sql::Changes::Table(table) = sql::changes(&sql) {
+            named.push(table);
+        }
+        let is_temp = |table: &String| tables.contains_key(&table.to_ascii_lowercase());
+        if !named.iter().any(is_temp) {
+            return Route::Main(sql);
+        }
+        self.on_store(sql::rename_tables(&sql, &tables))
+    }
+
+    fn on_store(&self, sql: String) -> Route {
+        match self.store.get() {
+            TempStore::Memory => Route::Memory(sql),
+            TempStore::Backend => Route::Main(sql),
+        }
+    }
+
+    /// Run `f` on the in-memory database of temporary tables.
+    pub(crate) fn in_memory<T>(&self, f: impl FnOnce(&Component) -> T) -> Result<T, DbError> {
+        let mut memory = self.memory.borrow_mut();
+        if memory.is_none() {
+            memory.replace(Component::open_uri(":memory:", OpenFlags::CREATE)?);
+        }
+        Ok(f(memory.as_ref().unwrap()))
+    }
+
+    /// Drop the tables, as closing the connection does.
+    pub(crate) fn clear(&self, db: &Component) -> Result<(), DbError> {
+        let tables = std::mem::take(&mut *self.tables.borrow_mut());
+        if self.store.get() == TempStore::Backend {
+            for kept in tables.values() {
+                db.exec(format!("DROP TABLE IF EXISTS {}", kept))?;
+            }
+        }
+        self.memory.replace(None);
+        Ok(())
+    }
+}
+
+/// Drop the tables kept in `db`'s storage by connections that ended without closing, when it
+/// is first opened in this instance.
+pub(crate) fn drop_stale(db: &Component) -> Result<(), DbError> {
+    let stale = db.query_with(
+        "SELECT name FROM sqlite_schema WHERE type = 'table' AND substr(name, 1, ?) = ?",
+        vec![
+            RecordValue::Integer(PREFIX.len() as i64),
+            RecordValue::Text(PREFIX.to_string()),
+        ],
+    )?;
+    for row in stale {
+        if let [RecordValue::Text(name)] = &row[..] {
+            db.exec(format!("DROP TABLE {}", quote_identifier(name)))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Coalescing the writes to a file until it is synced, see `database.set-coalesce-writes` in
+//! the WIT.
+//!
+//! While coalescing is on, each write is copied into a buffer of runs of bytes, merged with the
+//! runs it overlaps or touches, and completes straight away. Syncing the file writes each run
+//! with one host call, in order, so the page writes of a bulk insert or a checkpoint, and the
+//! frame headers and pages appended to the WAL by a commit, cost a few calls rather than one
+//! or more each. Reads see the buffered bytes over what the host returns.
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// The bytes held before the runs are written without waiting for a sync.
+const LIMIT: usize = 4 << 20;
+
+/// The writes to one file not yet passed to the host.
+pub(crate) struct WriteBack {
+    /// Whether writes are coalesced, shared with the database's other files.
+    enabled: Rc<Cell<bool>>,
+    /// The buffered bytes by the offset they start at. Runs never overlap or touch.
+    runs: RefCell<BTreeMap<usize, Vec<u8>>>,
+    /// The bytes in `runs`.
+    held: Cell<usize>,
+}
+
+impl WriteBack {
+    pub(crate) fn new(enabled: Rc<Cell<bool>>) -> Self {
+        Self {
+            enabled,
+            runs: RefCell::new(BTreeMap::new()),
+            held: Cell::new(0),
+        }
+    }
+
+    /// Buffer `data`, written at `pos`, returning false if coalescing is off and the caller
+    /// should write it, after [WriteBack::take] and writing what is buffered.
+    pub(crate) fn write(&self, pos: usize, data: &[u8]) -> bool {
+        if !self.enabled.get() {
+            return false;
+        }
+        let mut runs = self.runs.borrow_mut();
+        let end = pos + data.len();
+        let touching: Vec<usize> = runs
+            .range(..=end)
+            .filter(|(start, run)| *start + run.len() >= pos)
+            .map(|(start, _)| *start)
+            .collect();
+        let start = touching.first().map_or(pos, |first| pos.min(*first));
+        let mut merged = vec![];
+        for at in touching {
+            let run = runs.remove(&at).unwrap();
+            self.held.set(self.held.get() - run.len());
+            let offset = at - start;
+            if merged.len() < offset + run.len() {
+                merged.resize(offset + run.len(), 0);
+            }
+            merged[offset..offset + run.len()].copy_from_slice(&run);
+        }
+        let offset = pos - start;
+        if merged.len() < offset + data.len() {
+            merged.resize(offset + data.len(), 0);
+        }
+        merged[offset..offset + data.len()].copy_from_slice(data);
+        self.held.set(self.held.get() + merged.len());
+        runs.insert(start, merged);
+        true
+    }
+
+    /// Whether so much is buffered that it should be written now.
+    pub(crate) fn is_full(&self) -> bool {
+        self.held.get() >= LIMIT
+    }
+
+    /// The buffered runs, in order of offset, to be written now.
+    pub(crate) fn take(&self) -> Vec<(usize, Vec<u8>)> {
+        self.held.set(0);
+        std::mem::take(&mut *self.runs.borrow_mut())
+            .into_iter()
+            .collect()
+    }
+
+    /// Copy the buffered bytes over `buf`, which was read at `pos`.
+    pub(crate) fn overlay(&self, pos: usize, buf: &mut [u8]) {
+        let end = pos + buf.len();
+        for (start, run) in self.runs.borrow().range(..end) {
+            let (from, to) = ((*start).max(pos), (start + run.len()).min(end));
+            if from < to {
+                buf[from - pos..to - pos].copy_from_slice(&run[from - start..to - start]);
+            }
+        }
+    }
+
+    /// Where the buffered bytes end, which may be past the end of the file.
+    pub(crate) fn end(&self) -> usize {
+        self.runs
+            .borrow()
+            .last_key_value()
+            .map_or(0, |(start, run)| start + run.len())
+    }
+}
@@ -0,0 +1,270 @@
+//! Crash-recovery tests: a host fails or tears a storage operation of a transaction through
+//! `host-faults`, the instance is dropped as a crash would, and the database, reopened from
+//! what was stored, must hold either all of the transaction or none of it.
+//!
+//! Build the component for the `example-fault-injection` world first, then run them:
+//!
+//! ```sh
+//! cargo xtask build-component --features fault-injection
+//! cargo test --features fault-injection --test fault_injection
+//! ```
+#![cfg(feature = "fault-injection")]
+
+mod bindgen {
+    wasmtime::component::bindgen!({ world: "example-fault-injection" });
+}
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store};
+use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiView};
+
+use bindgen::component::wit_limbo::{
+    host_clock, host_faults, host_fs, host_io, host_kv, host_locale, host_log, host_progress,
+    host_random, host_subscriptions, host_trace,
+};
+use bindgen::exports::component::wit_limbo::limbo::RecordValue;
+
+type Error = Box<dyn std::error::Error>;
+
+struct FaultCtx {
+    table: ResourceTable,
+    ctx: WasiCtx,
+    kv: HashMap<String, Vec<u8>>,
+    /// The storage operations asked about so far.
+    ops: u64,
+    /// The operation to inject a fault into, and the fault.
+    fault: Option<(u64, host_faults::Fault)>,
+}
+
+impl WasiView for FaultCtx {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+impl host_faults::Host for FaultCtx {
+    fn inject(&mut self, _path: String, _op: host_faults::StorageOp, n: u64) -> host_faults::Fault {
+        self.ops = n;
+        match self.fault {
+            Some((at, fault)) if at == n => fault,
+            _ => host_faults::Fault::None,
+        }
+    }
+}
+
+impl host_random::Host for FaultCtx {
+    fn random_byte(&mut self) -> u8 {
+        rand::random::<u8>()
+    }
+}
+
+impl host_log::Host for FaultCtx {
+    fn log(&mut self, message: String) {
+        eprintln!("{}", message);
+    }
+}
+
+impl host_clock::Host for FaultCtx {
+    fn sleep(&mut self, ms: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+    }
+
+    fn now(&mut self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_micros() as u64)
+    }
+}
+
+impl host_trace::Host for FaultCtx {
+    fn span_start(&mut self, _name: String) -> u64 {
+        0
+    }
+
+    fn span_end(&mut self, _id: u64) {}
+}
+
+impl host_subscriptions::Host for FaultCtx {
+    fn on_query_changed(&mut self, _subscription_id: u64) {}
+}
+
+impl host_progress::Host for FaultCtx {
+    fn on_progress(&mut self, _operation: String, _done: u64, _total: u64) {}
+}
+
+impl host_locale::Host for FaultCtx {
+    fn to_lower(&mut self, text: String) -> String {
+        text.to_lowercase()
+    }
+
+    fn collate(&mut self, _locale: String, a: String, b: String) -> i8 {
+        a.cmp(&b) as i8
+    }
+}
+
+/// These tests only use `kv:` databases, so the file system is never touched.
+impl host_fs::Host for FaultCtx {
+    fn open(&mut self, path: String, _create: bool) -> Result<u32, String> {
+        Err(format!("host-fs is not available in this test: {}", path))
+    }
+
+    fn read(&mut self, fd: u32, _offset: u64, _len: u32) -> Result<Vec<u8>, String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+
+    fn write(&mut self, fd: u32, _offset: u64, _data: Vec<u8>) -> Result<(), String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+
+    fn sync(&mut self, fd: u32) -> Result<(), String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+
+    fn size(&mut self, fd: u32) -> Result<u64, String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+}
+
+impl host_io::Host for FaultCtx {
+    fn submit_io(&mut self, _request: host_io::IoRequest) -> u64 {
+        0
+    }
+
+    fn poll_io(&mut self, id: u64) -> Option<Result<Vec<u8>, String>> {
+        Some(Err(format!("no read {}", id)))
+    }
+}
+
+impl host_kv::Host for FaultCtx {
+    fn get(&mut self, key: String) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.kv.get(&key).cloned())
+    }
+
+    fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), String> {
+        self.kv.insert(key, value);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+/// Instantiate the built component in a fresh store, storing `kv:` databases in `kv`.
+fn instantiate(
+    kv: HashMap<String, Vec<u8>>,
+) -> Result<(Store<FaultCtx>, bindgen::ExampleFaultInjection), Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    if !wasm_path.exists() {
+        return Err(format!(
+            "{} not found, build it with `cargo xtask build-component --features fault-injection`",
+            wasm_path.display()
+        )
+        .into());
+    }
+
+    let mut config = Config::new();
+    config.cache_config_load_default()?;
+    config.wasm_component_model(true);
+
+    let engine = Engine::new(&config)?;
+    let component = Component::from_file(&engine, &wasm_path)?;
+
+    let mut linker = Linker::new(&engine);
+    bindgen::ExampleFaultInjection::add_to_linker(&mut linker, |state: &mut FaultCtx| state)?;
+    wasmtime_wasi::add_to_linker_sync(&mut linker)?;
+
+    let state = FaultCtx {
+        table: ResourceTable::new(),
+        ctx: WasiCtxBuilder::new().inherit_stdout().build(),
+        kv,
+        ops: 0,
+        fault: None,
+    };
+    let mut store = Store::new(&engine, state);
+    let bindings = bindgen::ExampleFaultInjection::instantiate(&mut store, &component, &linker)?;
+    Ok((store, bindings))
+}
+
+#[test]
+fn test_recovers_from_faults() -> Result<(), Error> {
+    let committed = {
+        let (mut store, bindings) = instantiate(HashMap::new())?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, "kv:crash")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (2), (3);",
+        )??;
+        store.into_data().kv
+    };
+
+    for fault in [host_faults::Fault::Fail, host_faults::Fault::ShortWrite] {
+        // each operation of the transaction in turn, until one past its last
+        for k in 1.. {
+            let (mut store, bindings) = instantiate(committed.clone())?;
+            let database = bindings.component_wit_limbo_limbo().database();
+            let db = database.call_constructor(&mut store, "kv:crash")?;
+            let at = store.data().ops + k;
+            store.data_mut().fault = Some((at, fault));
+            let result = database.call_exec(
+                &mut store,
+                db,
+                "BEGIN; INSERT INTO t VALUES (4), (5), (6); COMMIT;",
+            );
+            let hit = store.data().ops >= at;
+            if hit {
+                assert!(
+                    !matches!(result, Ok(Ok(_))),
+                    "{:?} at operation {} wasn't reported",
+                    fault,
+                    k
+                );
+            }
+            // crash, dropping the instance without closing
+            let stored = store.into_data().kv;
+
+            let (mut store, bindings) = instantiate(stored)?;
+            let database = bindings.component_wit_limbo_limbo().database();
+            let db = database.call_constructor(&mut store, "kv:crash")?;
+            let rows = database.call_exec(&mut store, db, "SELECT n FROM t ORDER BY n;")??;
+            let expected = |n: i64| (1..=n).map(|n| vec![RecordValue::Integer(n)]).collect();
+            assert!(
+                rows == expected(3) || rows == expected(6),
+                "{:?} at operation {} left {:?}",
+                fault,
+                k,
+                rows
+            );
+            database.call_exec(&mut store, db, "INSERT INTO t VALUES (7);")??;
+
+            if !hit {
+                assert_eq!(rows, expected(6));
+                break;
+            }
+        }
+    }
+    Ok(())
+}
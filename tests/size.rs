@@ -0,0 +1,42 @@
+//! Keeps the `release-small` component under a size budget, since browsers download all of it.
+//!
+//! Build it first with `cargo xtask build-small` (or `just build-small`).
+use std::path::{Path, PathBuf};
+
+/// The largest the component may be, in bytes. Lower it as the component shrinks.
+const BUDGET: u64 = 3 * 1024 * 1024;
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn test_small_component_size() {
+    let wasm_path =
+        workspace_dir().join("target/wasm32-unknown-unknown/release-small/wit_limbo.wasm");
+    let size = std::fs::metadata(&wasm_path)
+        .unwrap_or_else(|e| {
+            panic!(
+                "{}: {}, run `cargo xtask build-small` first",
+                wasm_path.display(),
+                e
+            )
+        })
+        .len();
+
+    assert!(
+        size <= BUDGET,
+        "component is {} bytes, over the {} byte budget",
+        size,
+        BUDGET
+    );
+}
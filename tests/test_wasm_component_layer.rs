@@ -31,10 +31,14 @@ fn test_wasm_component_layer_instance() {
     // log with timstamp
     eprintln!("{} [TestLog] test_instantiate_instance", chrono::Utc::now());
 
-    // get the target/wasm32-wasi/debug/CARGO_PKG_NAME.wasm file
+    // get the target/wasm32-unknown-unknown/release/CARGO_PKG_NAME_in_memory.wasm file, built
+    // by `just build-in-memory`, which only opens `:memory:` databases and so imports no storage
     let pkg_name = std::env::var("CARGO_PKG_NAME").unwrap().replace('-', "_");
     let workspace = workspace_dir();
-    let wasm_path = format!("target/wasm32-unknown-unknown/release/{}.wasm", pkg_name);
+    let wasm_path = format!(
+        "target/wasm32-unknown-unknown/release/{}_in_memory.wasm",
+        pkg_name
+    );
     let wasm_path = workspace.join(wasm_path);
 
     //let bytes: &[u8] =
@@ -62,11 +66,30 @@ fn test_wasm_component_layer_instance() {
     // Create a linker that will be used to resolve the component's imports, if any.
     let mut linker = Linker::default();
 
-    let host_interface = linker
-        .define_instance("component:wit-limbo/host".try_into().unwrap())
+    let host_random = linker
+        .define_instance("component:wit-limbo/host-random@0.2.0".try_into().unwrap())
         .unwrap();
 
-    host_interface
+    host_random
+        .define_func(
+            "random-byte",
+            Func::new(
+                &mut store,
+                FuncType::new([], [ValueType::U8]),
+                move |_store, _params, results| {
+                    let random = rand::random::<u8>();
+                    results[0] = Value::U8(random);
+                    Ok(())
+                },
+            ),
+        )
+        .unwrap();
+
+    let host_log = linker
+        .define_instance("component:wit-limbo/host-log@0.2.0".try_into().unwrap())
+        .unwrap();
+
+    host_log
         .define_func(
             "log",
             Func::new(
@@ -82,22 +105,113 @@ fn test_wasm_component_layer_instance() {
         )
         .unwrap();
 
-    // func "random-byte" is defined in the host interface
-    host_interface
+    let host_clock = linker
+        .define_instance("component:wit-limbo/host-clock@0.2.0".try_into().unwrap())
+        .unwrap();
+
+    host_clock
         .define_func(
-            "random-byte",
+            "sleep",
             Func::new(
                 &mut store,
-                FuncType::new([], [ValueType::U8]),
+                FuncType::new([ValueType::U32], []),
+                move |_store, params, _results| {
+                    if let Value::U32(ms) = params[0] {
+                        std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+                    }
+                    Ok(())
+                },
+            ),
+        )
+        .unwrap();
+
+    host_clock
+        .define_func(
+            "now",
+            Func::new(
+                &mut store,
+                FuncType::new([], [ValueType::U64]),
                 move |_store, _params, results| {
-                    let random = rand::random::<u8>();
-                    results[0] = Value::U8(random);
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map_or(0, |elapsed| elapsed.as_micros() as u64);
+                    results[0] = Value::U64(now);
+                    Ok(())
+                },
+            ),
+        )
+        .unwrap();
+
+    // spans aren't traced in this test
+    let host_trace = linker
+        .define_instance("component:wit-limbo/host-trace@0.2.0".try_into().unwrap())
+        .unwrap();
+
+    host_trace
+        .define_func(
+            "span-start",
+            Func::new(
+                &mut store,
+                FuncType::new([ValueType::String], [ValueType::U64]),
+                move |_store, _params, results| {
+                    results[0] = Value::U64(0);
                     Ok(())
                 },
             ),
         )
         .unwrap();
 
+    host_trace
+        .define_func(
+            "span-end",
+            Func::new(
+                &mut store,
+                FuncType::new([ValueType::U64], []),
+                move |_store, _params, _results| Ok(()),
+            ),
+        )
+        .unwrap();
+
+    // nothing subscribes in this test
+    let host_subscriptions = linker
+        .define_instance(
+            "component:wit-limbo/host-subscriptions@0.2.0"
+                .try_into()
+                .unwrap(),
+        )
+        .unwrap();
+
+    host_subscriptions
+        .define_func(
+            "on-query-changed",
+            Func::new(
+                &mut store,
+                FuncType::new([ValueType::U64], []),
+                move |_store, _params, _results| Ok(()),
+            ),
+        )
+        .unwrap();
+
+    // nothing runs long enough to report progress in this test
+    let host_progress = linker
+        .define_instance(
+            "component:wit-limbo/host-progress@0.2.0"
+                .try_into()
+                .unwrap(),
+        )
+        .unwrap();
+
+    host_progress
+        .define_func(
+            "on-progress",
+            Func::new(
+                &mut store,
+                FuncType::new([ValueType::String, ValueType::U64, ValueType::U64], []),
+                move |_store, _params, _results| Ok(()),
+            ),
+        )
+        .unwrap();
+
     // Instantiate the component with the linker and store.
     let instance = linker.instantiate(&mut store, &component).unwrap();
 
@@ -106,7 +220,7 @@ fn test_wasm_component_layer_instance() {
 
     // Get the interface that the interface exports.
     let interface = exports
-        .instance(&"component:wit-limbo/limbo".try_into().unwrap())
+        .instance(&"component:wit-limbo/limbo@0.2.0".try_into().unwrap())
         .unwrap();
 
     // Call the resource constructor for 'bar' using a direct function call
@@ -150,7 +264,7 @@ fn test_wasm_component_layer_instance() {
     let method_database_exec = interface.func("[method]database.exec").unwrap();
 
     method_database_exec
-        .call(&mut store, &exec_arguments, &mut [])
+        .call(&mut store, &exec_arguments, &mut [Value::Bool(false)])
         .unwrap();
 
     // Insert user into the database
@@ -163,7 +277,7 @@ fn test_wasm_component_layer_instance() {
 
     // Call the method, mutate the results
     method_database_exec
-        .call(&mut store, &exec_arguments, &mut [])
+        .call(&mut store, &exec_arguments, &mut [Value::Bool(false)])
         .unwrap();
 
     // Get the `value` method of the `bar` resource
@@ -183,8 +297,12 @@ fn test_wasm_component_layer_instance() {
         .call(&mut store, &prepare_arguments, &mut results)
         .unwrap();
 
+    // prepare returns result<statement, db-error>
     let statement_resource = match results[0] {
-        Value::Own(ref resource) => resource.clone(),
+        Value::Result(ref result) => match &**result {
+            Ok(Some(Value::Own(resource))) => resource.clone(),
+            other => panic!("Expected statement, found {:?}", other),
+        },
         _ => panic!("Unexpected result type"),
     };
 
@@ -212,8 +330,11 @@ fn test_wasm_component_layer_instance() {
     );
 
     let list = match results[0] {
-        Value::List(ref list) => list.clone(),
-        _ => panic!("Expected List, found Unexpected result type"),
+        Value::Result(ref result) => match &**result {
+            Ok(Some(Value::List(list))) => list.clone(),
+            other => panic!("Expected List, found {:?}", other),
+        },
+        _ => panic!("Expected Result, found Unexpected result type"),
     };
 
     println!("[ResultLog]");
@@ -253,6 +374,7 @@ fn test_wasm_component_layer_instance() {
             VariantCase::new("float", Some(ValueType::F64)),
             VariantCase::new("text", Some(ValueType::String)),
             VariantCase::new("blob", Some(ValueType::List(ListType::new(ValueType::U8)))),
+            VariantCase::new("text-decimal", Some(ValueType::String)),
         ],
     )
     .unwrap();
@@ -6,19 +6,40 @@ mod bindgen {
 }
 
 use std::{
+    collections::HashMap,
     env,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
-use wasmtime::component::{Component, Linker};
+use wasmtime::component::{Component, Linker, ResourceAny};
 use wasmtime::{Config, Engine, Store};
 use wasmtime_wasi::{ResourceTable, WasiCtx, WasiCtxBuilder, WasiView};
+use wasmtime_wasi_http::{WasiHttpCtx, WasiHttpView};
 
-use bindgen::{component::wit_limbo::host, exports::component::wit_limbo::limbo::RecordValue};
+use bindgen::{
+    component::wit_limbo::{
+        host_clock, host_fs, host_io, host_kv, host_locale, host_log, host_progress, host_random,
+        host_subscriptions, host_trace,
+    },
+    exports::component::wit_limbo::limbo::{
+        Affinity, DbError, LimitKind, OpenFlags, ParseError, RecordValue, StatementKind, TempStore,
+    },
+};
 
 struct MyCtx {
     table: ResourceTable,
     ctx: WasiCtx,
+    http: WasiHttpCtx,
+    kv: HashMap<String, Vec<u8>>,
+    /// The names of the spans started so far, and whether each has ended.
+    spans: Vec<(String, bool)>,
+    /// The ids `on-query-changed` has been called with, in order.
+    changed: Vec<u64>,
+    /// What `on-progress` has been called with, in order.
+    progress: Vec<(String, u64, u64)>,
+    /// The results of the reads submitted through `host-io` and not yet polled, by id.
+    reads: HashMap<u64, Result<Vec<u8>, String>>,
+    last_read_id: u64,
 }
 
 impl WasiView for MyCtx {
@@ -31,16 +52,138 @@ impl WasiView for MyCtx {
     }
 }
 
-impl host::Host for MyCtx {
+impl WasiHttpView for MyCtx {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+}
+
+impl host_random::Host for MyCtx {
     fn random_byte(&mut self) -> u8 {
         rand::random::<u8>()
     }
+}
 
+impl host_log::Host for MyCtx {
     fn log(&mut self, message: String) {
         eprintln!("{}", message);
     }
 }
 
+impl host_clock::Host for MyCtx {
+    fn sleep(&mut self, ms: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+    }
+
+    fn now(&mut self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_micros() as u64)
+    }
+}
+
+impl host_trace::Host for MyCtx {
+    fn span_start(&mut self, name: String) -> u64 {
+        self.spans.push((name, false));
+        self.spans.len() as u64 - 1
+    }
+
+    fn span_end(&mut self, id: u64) {
+        self.spans[id as usize].1 = true;
+    }
+}
+
+impl host_subscriptions::Host for MyCtx {
+    fn on_query_changed(&mut self, subscription_id: u64) {
+        self.changed.push(subscription_id);
+    }
+}
+
+impl host_progress::Host for MyCtx {
+    fn on_progress(&mut self, operation: String, done: u64, total: u64) {
+        self.progress.push((operation, done, total));
+    }
+}
+
+/// A toy locale: `tr` lowercases a dotless `I` to `ı` wherever case is folded, and `sv`
+/// sorts `å`, `ä` and `ö` after `z`; anything else is compared lowercased.
+impl host_locale::Host for MyCtx {
+    fn to_lower(&mut self, text: String) -> String {
+        text.replace('I', "ı").to_lowercase()
+    }
+
+    fn collate(&mut self, locale: String, a: String, b: String) -> i8 {
+        let key = |text: &str| -> Vec<u32> {
+            text.to_lowercase()
+                .chars()
+                .map(|c| match (locale.as_str(), c) {
+                    ("sv", 'å') => 'z' as u32 + 1,
+                    ("sv", 'ä') => 'z' as u32 + 2,
+                    ("sv", 'ö') => 'z' as u32 + 3,
+                    _ => c as u32,
+                })
+                .collect()
+        };
+        key(&a).cmp(&key(&b)) as i8
+    }
+}
+
+/// These tests only use `:memory:` databases, so the file system is never touched.
+impl host_fs::Host for MyCtx {
+    fn open(&mut self, path: String, _create: bool) -> Result<u32, String> {
+        Err(format!("host-fs is not available in this test: {}", path))
+    }
+
+    fn read(&mut self, fd: u32, _offset: u64, _len: u32) -> Result<Vec<u8>, String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+
+    fn write(&mut self, fd: u32, _offset: u64, _data: Vec<u8>) -> Result<(), String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+
+    fn sync(&mut self, fd: u32) -> Result<(), String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+
+    fn size(&mut self, fd: u32) -> Result<u64, String> {
+        Err(format!("bad file descriptor {}", fd))
+    }
+}
+
+impl host_io::Host for MyCtx {
+    /// Reads right away, keeping the result until it is polled.
+    fn submit_io(&mut self, request: host_io::IoRequest) -> u64 {
+        let result = host_fs::Host::read(self, request.fd, request.offset, request.len);
+        self.last_read_id += 1;
+        self.reads.insert(self.last_read_id, result);
+        self.last_read_id
+    }
+
+    fn poll_io(&mut self, id: u64) -> Option<Result<Vec<u8>, String>> {
+        self.reads.remove(&id)
+    }
+}
+
+impl host_kv::Host for MyCtx {
+    fn get(&mut self, key: String) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.kv.get(&key).cloned())
+    }
+
+    fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), String> {
+        self.kv.insert(key, value);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum TestError {
     /// From String
@@ -58,6 +201,10 @@ pub enum TestError {
     /// From io
     #[error("IO: {0}")]
     Io(#[from] std::io::Error),
+
+    /// From the component's `db-error`
+    #[error("Database: {0:?}")]
+    Db(#[from] DbError),
 }
 
 impl From<String> for TestError {
@@ -79,6 +226,58 @@ pub fn workspace_dir() -> PathBuf {
     cargo_path.parent().unwrap().to_path_buf()
 }
 
+/// Load the built component and instantiate the `example` world in a fresh store.
+pub fn instantiate() -> Result<(Store<MyCtx>, bindgen::Example), TestError> {
+    let (mut store, component, linker) = load()?;
+    let bindings = bindgen::Example::instantiate(&mut store, &component, &linker)?;
+
+    Ok((store, bindings))
+}
+
+/// The component, with a store and a linker defining everything it may import, including
+/// `wasi:http` for a component built with the `http` feature.
+pub fn load() -> Result<(Store<MyCtx>, Component, Linker<MyCtx>), TestError> {
+    let pkg_name = std::env::var("CARGO_PKG_NAME")?.replace('-', "_");
+    let wasm_path = workspace_dir().join(format!(
+        "target/wasm32-unknown-unknown/release/{}.wasm",
+        pkg_name
+    ));
+    if !wasm_path.exists() {
+        return Err(format!(
+            "{} not found, build it with `cargo xtask build-component`",
+            wasm_path.display()
+        )
+        .into());
+    }
+
+    let mut config = Config::new();
+    config.cache_config_load_default()?;
+    config.wasm_component_model(true);
+
+    let engine = Engine::new(&config)?;
+    let component = Component::from_file(&engine, &wasm_path)?;
+
+    let mut linker = Linker::new(&engine);
+    bindgen::Example::add_to_linker(&mut linker, |state: &mut MyCtx| state)?;
+    wasmtime_wasi::add_to_linker_sync(&mut linker)?;
+    wasmtime_wasi_http::add_only_http_to_linker_sync(&mut linker)?;
+
+    let state = MyCtx {
+        table: ResourceTable::new(),
+        ctx: WasiCtxBuilder::new().inherit_stdout().build(),
+        http: WasiHttpCtx::new(),
+        kv: HashMap::new(),
+        spans: Vec::new(),
+        changed: Vec::new(),
+        progress: Vec::new(),
+        reads: HashMap::new(),
+        last_read_id: 0,
+    };
+    let store = Store::new(&engine, state);
+
+    Ok((store, component, linker))
+}
+
 #[cfg(test)]
 mod aggregate_peerpiper_tests {
 
@@ -183,7 +382,16 @@ mod aggregate_peerpiper_tests {
 
         let table = ResourceTable::new();
         let wasi: WasiCtx = WasiCtxBuilder::new().inherit_stdout().args(&[""]).build();
-        let state = MyCtx { table, ctx: wasi };
+        let state = MyCtx {
+            table,
+            ctx: wasi,
+            kv: HashMap::new(),
+            spans: Vec::new(),
+            changed: Vec::new(),
+            progress: Vec::new(),
+            reads: HashMap::new(),
+            last_read_id: 0,
+        };
         let mut store = Store::new(&engine, state);
 
         let bindings = bindgen::Example::instantiate(&mut store, &component, &linker)?;
@@ -206,7 +414,7 @@ mod aggregate_peerpiper_tests {
             &mut store,
             resource_constructor,
             &sql,
-        )?;
+        )??;
 
         let sql = "INSERT INTO users (name) VALUES ('Alice');".to_string();
 
@@ -215,19 +423,19 @@ mod aggregate_peerpiper_tests {
             &mut store,
             resource_constructor,
             &sql,
-        )?;
+        )??;
 
         let sql_table_metadata = "PRAGMA table_info(users)".to_string();
 
         let statement = bindings
             .component_wit_limbo_limbo()
             .database()
-            .call_prepare(&mut store, resource_constructor, &sql_table_metadata)?;
+            .call_prepare(&mut store, resource_constructor, &sql_table_metadata)??;
 
         let mut headers = bindings
             .component_wit_limbo_limbo()
             .statement()
-            .call_all(&mut store, statement)?;
+            .call_all(&mut store, statement)??;
 
         eprintln!("\n\n{:?}\n\n", headers);
 
@@ -235,13 +443,13 @@ mod aggregate_peerpiper_tests {
         let statement = bindings
             .component_wit_limbo_limbo()
             .database()
-            .call_prepare(&mut store, resource_constructor, &sql)?;
+            .call_prepare(&mut store, resource_constructor, &sql)??;
 
         // call all using the statement result
         let rows = bindings
             .component_wit_limbo_limbo()
             .statement()
-            .call_all(&mut store, statement)?;
+            .call_all(&mut store, statement)??;
 
         println!("[ResultLog]");
         println!(" └ database");
@@ -289,4 +497,3498 @@ mod aggregate_peerpiper_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_clone_handle_shares_storage() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+        )??;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "INSERT INTO users (name) VALUES ('Alice');",
+        )??;
+
+        // the second handle has its own connection over the same storage
+        let handle = limbo.database().call_clone_handle(&mut store, db)?;
+        let statement =
+            limbo
+                .database()
+                .call_prepare(&mut store, handle, "SELECT name FROM users;")??;
+        let rows = limbo.statement().call_all(&mut store, statement)??;
+
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(&rows[0][..], [RecordValue::Text(name)] if name == "Alice"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_flags() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        // a missing database isn't created without `create`
+        let result = database.call_open_with(&mut store, "kv:flags", OpenFlags::empty())?;
+        assert!(
+            matches!(result, Err(DbError::CannotOpen(_))),
+            "{:?}",
+            result
+        );
+        assert!(!store.data().kv.keys().any(|key| key.starts_with("flags")));
+
+        let db = database.call_open_with(&mut store, "kv:flags", OpenFlags::CREATE)??;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (x); INSERT INTO t VALUES (1);",
+        )??;
+
+        let result = database.call_open_with(
+            &mut store,
+            "kv:flags",
+            OpenFlags::CREATE | OpenFlags::EXCLUSIVE,
+        )?;
+        assert!(
+            matches!(result, Err(DbError::CannotOpen(_))),
+            "{:?}",
+            result
+        );
+
+        // a read-only connection reads, but refuses writes
+        let reader = database.call_open_with(&mut store, "kv:flags", OpenFlags::READ_ONLY)??;
+        let rows = database.call_exec(&mut store, reader, "SELECT x FROM t;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(1)]]);
+        let result = database.call_exec(&mut store, reader, "INSERT INTO t VALUES (2);")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let result = database.call_prepare(&mut store, reader, "DELETE FROM t;")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // as do upsert and insert-blob, which write without SQL from the host
+        let x = ["x".to_string()];
+        let rows = [vec![RecordValue::Integer(2)]];
+        let result = database.call_upsert(&mut store, reader, "t", &x, &x, &rows)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let result = database.call_insert_blob(&mut store, reader, "t", "x", 1, 1)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let rows = database.call_exec(&mut store, reader, "SELECT x FROM t;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(1)]]);
+
+        let result = database.call_open_with(
+            &mut store,
+            "kv:other",
+            OpenFlags::CREATE | OpenFlags::READ_ONLY,
+        )?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_constructor_failure() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        // rather than trapping, the database comes back closed, and every call says why
+        let db = database.call_constructor(&mut store, "file:t.db?cache=shared")?;
+        let last = database.call_last_error(&mut store, db)?;
+        assert!(matches!(last, Some(DbError::CannotOpen(_))), "{:?}", last);
+        let result = database.call_exec(&mut store, db, "SELECT 1;")?;
+        assert!(
+            matches!(result, Err(DbError::CannotOpen(_))),
+            "{:?}",
+            result
+        );
+        let other = database.call_clone_handle(&mut store, db)?;
+        let result = database.call_prepare(&mut store, other, "SELECT 1;")?;
+        assert!(
+            matches!(result, Err(DbError::CannotOpen(_))),
+            "{:?}",
+            result
+        );
+
+        // the instance is still usable
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let rows = database.call_exec(&mut store, db, "SELECT 1;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(1)]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_temp_tables() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, "kv:temp")?;
+        let other = database.call_clone_handle(&mut store, db)?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT); \
+             INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob');",
+        )??;
+
+        // kept in memory, seen by this connection only
+        database.call_exec(&mut store, db, "CREATE TEMP TABLE scratch (x);")??;
+        database.call_exec(&mut store, db, "INSERT INTO temp.scratch VALUES (1), (2);")??;
+        let rows = database.call_exec(&mut store, db, "SELECT count(*) FROM scratch;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(2)]]);
+        let result = database.call_exec(&mut store, other, "SELECT * FROM scratch;")?;
+        assert!(result.is_err(), "{:?}", result);
+        let schema = "SELECT name FROM sqlite_schema WHERE type = 'table' ORDER BY name;";
+        let rows = database.call_exec(&mut store, db, schema)??;
+        assert_eq!(rows, vec![vec![RecordValue::Text("users".to_string())]]);
+
+        let result = database.call_set_temp_store(&mut store, db, TempStore::Backend)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        database.call_exec(&mut store, db, "DROP TABLE scratch;")??;
+        database.call_set_temp_store(&mut store, db, TempStore::Backend)??;
+
+        // kept in storage, so it can be joined with the database's tables
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TEMPORARY TABLE picked (user_id INTEGER);",
+        )??;
+        database.call_exec(&mut store, db, "INSERT INTO picked VALUES (2);")??;
+        let rows = database.call_exec(
+            &mut store,
+            db,
+            "SELECT users.name FROM users JOIN picked ON picked.user_id = users.id;",
+        )??;
+        assert_eq!(rows, vec![vec![RecordValue::Text("Bob".to_string())]]);
+        let result = database.call_exec(&mut store, other, "SELECT * FROM picked;")?;
+        assert!(result.is_err(), "{:?}", result);
+
+        // and dropped on close
+        database.call_close(&mut store, db)??;
+        let rows = database.call_exec(&mut store, other, schema)??;
+        assert_eq!(rows, vec![vec![RecordValue::Text("users".to_string())]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_tail() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let db = database.call_constructor(&mut store, "kv:scripts")?;
+
+        // exec runs every statement of a script, returning their rows in order
+        let rows = database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (x INTEGER); \
+             INSERT INTO t VALUES (1) RETURNING x; \
+             INSERT INTO t VALUES (2) RETURNING x;",
+        )??;
+        assert_eq!(
+            rows,
+            vec![vec![RecordValue::Integer(1)], vec![RecordValue::Integer(2)]]
+        );
+
+        // prepare refuses a script rather than running its first statement alone
+        let result = database.call_prepare(&mut store, db, "SELECT 1; SELECT 2;")?;
+        assert!(
+            matches!(&result, Err(DbError::Parse(e)) if e.message.contains("byte 10")),
+            "{:?}",
+            result
+        );
+        let statement = database.call_prepare(&mut store, db, "SELECT 1; -- done\n")??;
+        let rows = limbo.statement().call_all(&mut store, statement)??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(1)]]);
+
+        // prepare-tail steps through it a statement at a time
+        let mut sql = "SELECT count(*) FROM t; SELECT max(x) FROM t; ;".to_string();
+        let mut results = vec![];
+        while !sql
+            .trim_matches(|c: char| c == ';' || c.is_whitespace())
+            .is_empty()
+        {
+            let prepared = database.call_prepare_tail(&mut store, db, &sql)??;
+            results.extend(
+                limbo
+                    .statement()
+                    .call_all(&mut store, prepared.statement)??,
+            );
+            sql = prepared.tail;
+        }
+        assert_eq!(
+            results,
+            vec![vec![RecordValue::Integer(2)], vec![RecordValue::Integer(2)]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_error_position() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (x);")??;
+
+        fn position<T>(result: Result<T, DbError>) -> (u32, u32, u32, String) {
+            match result {
+                Err(DbError::Parse(ParseError {
+                    position: Some(position),
+                    ..
+                })) => (
+                    position.line,
+                    position.column,
+                    position.offset,
+                    position.token,
+                ),
+                other => panic!("{:?}", other.map(|_| ())),
+            }
+        }
+        let result = database.call_prepare(&mut store, db, "SELEKT x FROM t")?;
+        assert_eq!(position(result), (1, 1, 0, "SELEKT".to_string()));
+
+        // in a script, the position is in the whole script
+        let script = "INSERT INTO t VALUES (1);\n-- then\n  SELEKT x FROM t;";
+        let result = database.call_exec(&mut store, db, script)?;
+        assert_eq!(position(result), (3, 3, 36, "SELEKT".to_string()));
+
+        // with no token to point at, it is the end of the SQL
+        let result = database.call_prepare(&mut store, db, "SELECT x FROM")?;
+        assert_eq!(position(result), (1, 14, 13, String::new()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_connections() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+
+        // opening the same path twice gives two connections to one database
+        let writer = database.call_constructor(&mut store, "kv:shared")?;
+        let other = database.call_constructor(&mut store, "kv:shared")?;
+
+        database.call_exec(
+            &mut store,
+            writer,
+            "CREATE TABLE counter (id INTEGER PRIMARY KEY, n INTEGER NOT NULL);",
+        )??;
+        database.call_exec(&mut store, writer, "INSERT INTO counter (n) VALUES (1);")??;
+
+        let count = |store: &mut Store<MyCtx>, db: ResourceAny| -> Result<i64, TestError> {
+            let rows = database.call_exec(store, db, "SELECT count(*) FROM counter;")??;
+            match &rows[..] {
+                [row] => match &row[..] {
+                    [RecordValue::Integer(n)] => Ok(*n),
+                    other => Err(format!("unexpected row {:?}", other).into()),
+                },
+                other => Err(format!("unexpected rows {:?}", other).into()),
+            }
+        };
+        assert_eq!(count(&mut store, other)?, 1);
+
+        // while one connection is in a write transaction, the other can read but not write
+        database.call_exec(&mut store, writer, "BEGIN;")??;
+        database.call_exec(&mut store, writer, "INSERT INTO counter (n) VALUES (2);")??;
+
+        let result =
+            database.call_exec(&mut store, other, "INSERT INTO counter (n) VALUES (3);")?;
+        assert!(matches!(result, Err(DbError::Busy(_))), "{:?}", result);
+        assert_eq!(count(&mut store, other)?, 1);
+
+        // once it commits, the other connection sees the write and may write itself
+        database.call_exec(&mut store, writer, "COMMIT;")??;
+        assert_eq!(count(&mut store, other)?, 2);
+        database.call_exec(&mut store, other, "INSERT INTO counter (n) VALUES (3);")??;
+
+        // interleaved writes from both connections all land
+        for n in 4..10 {
+            let db = if n % 2 == 0 { writer } else { other };
+            database.call_exec(
+                &mut store,
+                db,
+                &format!("INSERT INTO counter (n) VALUES ({});", n),
+            )??;
+        }
+        assert_eq!(count(&mut store, writer)?, 9);
+        assert_eq!(count(&mut store, other)?, 9);
+
+        let rows = database.call_exec(&mut store, writer, "SELECT sum(n) FROM counter;")??;
+        assert!(matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Integer(45)])));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_busy_timeout() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let writer = database.call_constructor(&mut store, "kv:timeout")?;
+        let other = database.call_constructor(&mut store, "kv:timeout")?;
+        database.call_exec(&mut store, writer, "CREATE TABLE t (n INTEGER);")??;
+        database.call_set_busy_timeout(&mut store, other, 50)?;
+
+        // nothing can commit while `other` waits in this instance, so it still ends up busy,
+        // but only after sleeping out its timeout
+        database.call_exec(&mut store, writer, "BEGIN;")??;
+        database.call_exec(&mut store, writer, "INSERT INTO t VALUES (1);")??;
+        let start = std::time::Instant::now();
+        let result = database.call_exec(&mut store, other, "INSERT INTO t VALUES (2);")?;
+        assert!(matches!(result, Err(DbError::Busy(_))), "{:?}", result);
+        assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+
+        database.call_exec(&mut store, writer, "COMMIT;")??;
+        database.call_exec(&mut store, other, "INSERT INTO t VALUES (2);")??;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_close_and_finalize() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo
+            .database()
+            .call_constructor(&mut store, "kv:closing")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+        )??;
+
+        let statement = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT * FROM users;")??;
+        limbo.statement().call_finalize(&mut store, statement)?;
+        let result = limbo.statement().call_all(&mut store, statement)?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+        let result = limbo.statement().call_finalize(&mut store, statement)?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+
+        limbo.database().call_close(&mut store, db)??;
+        let result = limbo.database().call_exec(&mut store, db, "SELECT 1;")?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+        let result = limbo.database().call_close(&mut store, db)?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+        db.resource_drop(&mut store)?;
+
+        // the checkpointed database opens again with its schema
+        let db = limbo
+            .database()
+            .call_constructor(&mut store, "kv:closing")?;
+        let rows = limbo
+            .database()
+            .call_exec(&mut store, db, "SELECT count(*) FROM users;")??;
+        assert!(matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Integer(0)])));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_run() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (2), (3), (4), (5);",
+        )??;
+        let statement = limbo.statement();
+        let stmt =
+            limbo
+                .database()
+                .call_prepare(&mut store, db, "SELECT n FROM t WHERE n > ?;")??;
+
+        let ints = |rows: Vec<Vec<RecordValue>>| -> Vec<i64> {
+            rows.into_iter()
+                .map(|row| match row[..] {
+                    [RecordValue::Integer(n)] => n,
+                    _ => panic!("unexpected row {:?}", row),
+                })
+                .collect()
+        };
+
+        // rows are stepped as they are fetched, one at a time or in chunks
+        statement.call_bind(&mut store, stmt, 1, &RecordValue::Integer(1))??;
+        let rows = statement.call_run(&mut store, stmt)??;
+        let row = limbo.rows().call_next(&mut store, rows)??;
+        assert_eq!(row, Some(vec![RecordValue::Integer(2)]));
+        let chunk = limbo.rows().call_next_chunk(&mut store, rows, 2)??;
+        assert_eq!(ints(chunk), [3, 4]);
+        let chunk = limbo.rows().call_next_chunk(&mut store, rows, 10)??;
+        assert_eq!(ints(chunk), [5]);
+        assert_eq!(limbo.rows().call_next(&mut store, rows)??, None);
+        rows.resource_drop(&mut store)?;
+
+        // the same statement runs again with new bindings
+        statement.call_bind(&mut store, stmt, 1, &RecordValue::Integer(3))??;
+        let rows = statement.call_run(&mut store, stmt)??;
+        let chunk = limbo.rows().call_next_chunk(&mut store, rows, 10)??;
+        assert_eq!(ints(chunk), [4, 5]);
+        rows.resource_drop(&mut store)?;
+
+        // a new run ends the rows of the last one, even part way through
+        statement.call_bind(&mut store, stmt, 1, &RecordValue::Integer(0))??;
+        let first = statement.call_run(&mut store, stmt)??;
+        assert!(limbo.rows().call_next(&mut store, first)??.is_some());
+        statement.call_bind(&mut store, stmt, 1, &RecordValue::Integer(0))??;
+        let second = statement.call_run(&mut store, stmt)??;
+        let result = limbo.rows().call_next(&mut store, first)?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+        let chunk = limbo.rows().call_next_chunk(&mut store, second, 10)??;
+        assert_eq!(ints(chunk), [1, 2, 3, 4, 5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_decimal() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let statement = limbo.statement();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE amounts (id INTEGER PRIMARY KEY, amount TEXT);",
+        )??;
+        let insert = limbo.database().call_prepare(
+            &mut store,
+            db,
+            "INSERT INTO amounts (amount) VALUES (?);",
+        )??;
+
+        // decimals beyond `s64` and `f64` read back digit for digit
+        let decimals = [
+            "18446744073709551615",
+            "-170141183460469231731687303715884105728",
+            "1234.5600",
+            "0.1000000000000000055511151231257827",
+            "+7",
+        ];
+        for decimal in decimals {
+            let value = RecordValue::TextDecimal(decimal.to_string());
+            statement.call_bind(&mut store, insert, 1, &value)??;
+            statement.call_all(&mut store, insert)??;
+        }
+        let rows = limbo.database().call_exec(
+            &mut store,
+            db,
+            "SELECT amount FROM amounts ORDER BY id;",
+        )??;
+        let expected: Vec<_> = decimals
+            .iter()
+            .map(|decimal| vec![RecordValue::Text(decimal.to_string())])
+            .collect();
+        assert_eq!(rows, expected);
+
+        // anything but a plain decimal number is refused, and nothing is written
+        for invalid in ["", "abc", "1e10", "1.", ".5", "--1", "1,000", " 1", "NaN"] {
+            let value = RecordValue::TextDecimal(invalid.to_string());
+            let result = statement.call_bind(&mut store, insert, 1, &value)?;
+            assert!(
+                matches!(result, Err(DbError::Misuse(_))),
+                "{:?}: {:?}",
+                invalid,
+                result
+            );
+        }
+        let result = limbo.database().call_upsert(
+            &mut store,
+            db,
+            "amounts",
+            &["id".to_string()],
+            &["id".to_string(), "amount".to_string()],
+            &[vec![
+                RecordValue::Integer(9),
+                RecordValue::TextDecimal("12.3.4".to_string()),
+            ]],
+        )?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let rows =
+            limbo
+                .database()
+                .call_exec(&mut store, db, "SELECT count(*) FROM amounts;")??;
+        assert_eq!(
+            rows,
+            vec![vec![RecordValue::Integer(decimals.len() as i64)]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_consistency() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let statement = limbo.statement();
+
+        let reader = database.call_constructor(&mut store, "kv:consistent")?;
+        let writer = database.call_constructor(&mut store, "kv:consistent")?;
+        database.call_exec(
+            &mut store,
+            writer,
+            "CREATE TABLE t (n INTEGER PRIMARY KEY); INSERT INTO t VALUES (1), (2), (3), (4), (5);",
+        )??;
+        let ints = |rows: Vec<Vec<RecordValue>>| -> Vec<i64> {
+            rows.into_iter()
+                .map(|row| match row[..] {
+                    [RecordValue::Integer(n)] => n,
+                    _ => panic!("unexpected row {:?}", row),
+                })
+                .collect()
+        };
+
+        // a run part way through returns the rows as they were when it started, whatever
+        // the other connection writes in between
+        let stmt = database.call_prepare(&mut store, reader, "SELECT n FROM t ORDER BY n;")??;
+        let rows = statement.call_run(&mut store, stmt)??;
+        let chunk = limbo.rows().call_next_chunk(&mut store, rows, 2)??;
+        assert_eq!(ints(chunk), [1, 2]);
+        database.call_exec(&mut store, writer, "DELETE FROM t WHERE n = 4;")??;
+        database.call_exec(&mut store, writer, "INSERT INTO t VALUES (6);")??;
+        database.call_exec(&mut store, writer, "UPDATE t SET n = 0 WHERE n = 3;")??;
+        let chunk = limbo.rows().call_next_chunk(&mut store, rows, 10)??;
+        assert_eq!(ints(chunk), [3, 4, 5]);
+        assert_eq!(limbo.rows().call_next(&mut store, rows)??, None);
+        rows.resource_drop(&mut store)?;
+
+        // the next run sees the writes
+        let rows = statement.call_all(&mut store, stmt)??;
+        assert_eq!(ints(rows), [0, 1, 2, 5, 6]);
+
+        // as do the chunks of all-chunked
+        let chunk = statement.call_all_chunked(&mut store, stmt, 2)??;
+        assert_eq!(ints(chunk.rows), [0, 1]);
+        database.call_exec(&mut store, writer, "DELETE FROM t;")??;
+        let chunk = statement.call_all_chunked(&mut store, stmt, 10)??;
+        assert!(chunk.done);
+        assert_eq!(ints(chunk.rows), [2, 5, 6]);
+        let chunk = statement.call_all_chunked(&mut store, stmt, 10)??;
+        assert!(chunk.rows.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_chunked() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (2), (3), (4), (5);",
+        )??;
+        let statement = limbo.statement();
+        let stmt = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT n FROM t;")??;
+
+        // the guest keeps the cursor between calls until a chunk comes back done
+        let mut seen = vec![];
+        loop {
+            let chunk = statement.call_all_chunked(&mut store, stmt, 2)??;
+            assert!(chunk.rows.len() <= 2);
+            seen.extend(chunk.rows);
+            if chunk.done {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), 5);
+        assert_eq!(seen[4], [RecordValue::Integer(5)]);
+
+        // the next call starts another run
+        let chunk = statement.call_all_chunked(&mut store, stmt, 10)??;
+        assert!(chunk.done);
+        assert_eq!(chunk.rows.len(), 5);
+
+        let result = statement.call_all_chunked(&mut store, stmt, 0)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_with_names() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+        )??;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "INSERT INTO users (name) VALUES ('Alice');",
+        )??;
+
+        let statement =
+            limbo
+                .database()
+                .call_prepare(&mut store, db, "SELECT id, name FROM users;")??;
+        let result_set = limbo
+            .statement()
+            .call_all_with_names(&mut store, statement)??;
+
+        assert_eq!(result_set.columns, vec!["id", "name"]);
+        assert_eq!(result_set.affinities, [Affinity::Integer, Affinity::Text]);
+
+        let name = result_set
+            .columns
+            .iter()
+            .position(|column| column == "name")
+            .unwrap();
+        assert!(matches!(&result_set.rows[0][name], RecordValue::Text(name) if name == "Alice"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel_running() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (2), (3);",
+        )??;
+        let stmt = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT n FROM t;")??;
+        let rows = limbo.statement().call_run(&mut store, stmt)??;
+        assert!(limbo.rows().call_next(&mut store, rows)??.is_some());
+        let chunk = limbo.statement().call_all_chunked(&mut store, stmt, 1)?;
+        assert!(matches!(chunk, Ok(ref chunk) if !chunk.done), "{:?}", chunk);
+
+        // both runs part way through are interrupted
+        limbo.database().call_cancel_running(&mut store, db)?;
+        let result = limbo.statement().call_all_chunked(&mut store, stmt, 1)?;
+        assert!(
+            matches!(result, Err(DbError::Interrupted(_))),
+            "{:?}",
+            result
+        );
+
+        let other = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT n FROM t;")??;
+        let rows = limbo.statement().call_run(&mut store, other)??;
+        assert!(limbo.rows().call_next(&mut store, rows)??.is_some());
+        limbo.database().call_cancel_running(&mut store, db)?;
+        let result = limbo.rows().call_next(&mut store, rows)?;
+        assert!(
+            matches!(result, Err(DbError::Interrupted(_))),
+            "{:?}",
+            result
+        );
+        assert_eq!(limbo.rows().call_next(&mut store, rows)??, None);
+
+        // runs started afterwards are unaffected
+        let rows = limbo.statement().call_all(&mut store, other)??;
+        assert_eq!(rows.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_run_stats() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1), (2), (3);",
+        )??;
+        let stmt = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT n FROM t;")??;
+
+        let stats = limbo.statement().call_last_run_stats(&mut store, stmt)?;
+        assert_eq!((stats.vm_steps, stats.rows_produced), (0, 0));
+
+        limbo.statement().call_all(&mut store, stmt)??;
+        let stats = limbo.statement().call_last_run_stats(&mut store, stmt)?;
+        assert_eq!(stats.rows_produced, 3);
+        // one step per row, plus the one that finds the end
+        assert!(stats.vm_steps >= 4, "{:?}", stats);
+
+        // stats add up over every call of a run, and start over with the next
+        let rows = limbo.statement().call_run(&mut store, stmt)??;
+        limbo.rows().call_next(&mut store, rows)??;
+        let stats = limbo.statement().call_last_run_stats(&mut store, stmt)?;
+        assert_eq!(stats.rows_produced, 1);
+        limbo.rows().call_next_chunk(&mut store, rows, 10)??;
+        let stats = limbo.statement().call_last_run_stats(&mut store, stmt)?;
+        assert_eq!(stats.rows_produced, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_prometheus() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let db = database.call_constructor(&mut store, "kv:metrics")?;
+        // a second open of the same path joins the first, and shares its metrics
+        let other = database.call_constructor(&mut store, "kv:metrics")?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+        database.call_exec(&mut store, other, "INSERT INTO t VALUES (1), (2);")??;
+        database.call_exec(&mut store, db, "SELECT n FROM t;")??;
+
+        let metrics = database.call_metrics_prometheus(&mut store, other)?;
+        let counter = |name: &str| {
+            metrics
+                .lines()
+                .find_map(|line| line.strip_prefix(&format!("{} ", name)))
+                .map(|value| value.parse::<u64>().unwrap())
+        };
+        assert_eq!(counter("limbo_queries_total"), Some(3), "{}", metrics);
+        assert_eq!(counter("limbo_rows_read_total"), Some(2), "{}", metrics);
+        assert_eq!(counter("limbo_busy_retries_total"), Some(0), "{}", metrics);
+        assert_eq!(counter("limbo_cache_hits_total"), Some(1), "{}", metrics);
+        assert!(metrics.contains("# TYPE limbo_queries_total counter"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_error() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let statement = limbo.statement();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        assert!(database.call_last_error(&mut store, db)?.is_none());
+        assert_eq!(database.call_last_error_code(&mut store, db)?, None);
+
+        // a failure is kept until the next one, whatever succeeds in between
+        database.call_exec(&mut store, db, "SELEC 1;")?.unwrap_err();
+        database.call_exec(&mut store, db, "CREATE TABLE t (id INTEGER PRIMARY KEY);")??;
+        let last = database.call_last_error(&mut store, db)?;
+        assert!(matches!(last, Some(DbError::Parse(_))), "{:?}", last);
+        assert_eq!(database.call_last_error_code(&mut store, db)?, Some(1));
+
+        // constraint failures get their extended code
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (1);")??;
+        let result = database.call_exec(&mut store, db, "INSERT INTO t VALUES (1);")?;
+        assert!(result.is_err());
+        assert_eq!(database.call_last_error_code(&mut store, db)?, Some(2067));
+
+        // and so do the statements prepared on the connection
+        let stmt = database.call_prepare(&mut store, db, "SELECT id FROM t")??;
+        statement.call_finalize(&mut store, stmt)??;
+        let result = statement.call_all(&mut store, stmt)?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+        let last = database.call_last_error(&mut store, db)?;
+        assert!(matches!(last, Some(DbError::Closed(_))), "{:?}", last);
+        assert_eq!(database.call_last_error_code(&mut store, db)?, Some(21));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_cache() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+
+        let db = database.call_constructor(&mut store, "kv:cached")?;
+        let other = database.call_clone_handle(&mut store, db)?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER); CREATE TABLE u (n INTEGER); \
+             INSERT INTO t VALUES (1), (2);",
+        )??;
+        database.call_set_query_cache(&mut store, db, 16, 1 << 20)?;
+
+        let hits = |store: &mut Store<MyCtx>| -> Result<u64, TestError> {
+            let metrics = database.call_metrics_prometheus(&mut *store, db)?;
+            Ok(metrics
+                .lines()
+                .find_map(|line| line.strip_prefix("limbo_query_cache_hits_total "))
+                .map_or(0, |value| value.parse().unwrap()))
+        };
+        let count = |store: &mut Store<MyCtx>, n: i64| -> Result<_, TestError> {
+            let sql = "SELECT count(*) FROM t WHERE n >= ?";
+            let statement = database.call_prepare(&mut *store, db, sql)??;
+            let value = RecordValue::Integer(n);
+            limbo
+                .statement()
+                .call_bind(&mut *store, statement, 1, &value)??;
+            Ok(limbo.statement().call_all(&mut *store, statement)??)
+        };
+
+        // the same query with the same parameters is answered from the cache
+        assert_eq!(count(&mut store, 1)?, vec![vec![RecordValue::Integer(2)]]);
+        assert_eq!(count(&mut store, 1)?, vec![vec![RecordValue::Integer(2)]]);
+        assert_eq!(hits(&mut store)?, 1);
+        assert_eq!(count(&mut store, 2)?, vec![vec![RecordValue::Integer(1)]]);
+        assert_eq!(hits(&mut store)?, 1);
+
+        // a write to another table keeps it, and one to the table it reads drops it
+        database.call_exec(&mut store, other, "INSERT INTO u VALUES (3);")??;
+        count(&mut store, 1)?;
+        assert_eq!(hits(&mut store)?, 2);
+        database.call_exec(&mut store, other, "INSERT INTO t VALUES (3);")??;
+        assert_eq!(count(&mut store, 1)?, vec![vec![RecordValue::Integer(3)]]);
+        assert_eq!(hits(&mut store)?, 2);
+
+        // turning it off stops caching
+        database.call_set_query_cache(&mut store, db, 0, 0)?;
+        count(&mut store, 1)?;
+        count(&mut store, 1)?;
+        assert_eq!(hits(&mut store)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spans() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, "kv:spans")?;
+        limbo
+            .database()
+            .call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+        let stmt = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT n FROM t;")??;
+        limbo.statement().call_all(&mut store, stmt)??;
+        limbo.database().call_close(&mut store, db)??;
+
+        let spans = &store.data().spans;
+        let names: Vec<&str> = spans.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "limbo.prepare",
+                "limbo.step",
+                "limbo.prepare",
+                "limbo.step",
+                "limbo.checkpoint"
+            ]
+        );
+        assert!(spans.iter().all(|(_, ended)| *ended));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_soft_heap_limit() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let other = database.call_constructor(&mut store, ":memory:")?;
+        for db in [db, other] {
+            database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+            database.call_exec(&mut store, db, "INSERT INTO t VALUES (1), (2);")??;
+        }
+
+        // the heap is already far past one byte, so collecting rows fails rather than aborting,
+        // on every database in the instance
+        assert_eq!(limbo.call_set_soft_heap_limit(&mut store, 1)?, 0);
+        for db in [db, other] {
+            let result = database.call_exec(&mut store, db, "SELECT n FROM t;")?;
+            assert!(
+                matches!(result, Err(DbError::OutOfMemory(_))),
+                "{:?}",
+                result
+            );
+        }
+
+        assert_eq!(limbo.call_set_soft_heap_limit(&mut store, 0)?, 1);
+        let rows = database.call_exec(&mut store, db, "SELECT n FROM t;")??;
+        assert_eq!(rows.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_limits() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (a, b, c);")??;
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (1, 2, 3);")??;
+
+        let limits = database.call_limits(&mut store, db)?;
+        assert_eq!(
+            (limits.sql_length, limits.column, limits.expr_depth),
+            (1_000_000_000, 2000, 1000)
+        );
+        assert_eq!(
+            database.call_set_limit(&mut store, db, LimitKind::SqlLength, 30)?,
+            1_000_000_000
+        );
+        database.call_set_limit(&mut store, db, LimitKind::Column, 2)?;
+        database.call_set_limit(&mut store, db, LimitKind::ExprDepth, 2)?;
+        assert_eq!(database.call_limits(&mut store, db)?.column, 2);
+
+        for sql in [
+            "SELECT a FROM t WHERE b = 'a longer string'",
+            "SELECT a, b, c FROM t",
+            "CREATE TABLE u (x, y, z)",
+            "SELECT (((1)))",
+        ] {
+            let result = database.call_exec(&mut store, db, sql)?;
+            assert!(
+                matches!(result, Err(DbError::LimitExceeded(_))),
+                "{}: {:?}",
+                sql,
+                result
+            );
+            let result = database.call_prepare(&mut store, db, sql)?;
+            assert!(matches!(result, Err(DbError::LimitExceeded(_))), "{}", sql);
+        }
+
+        // statements within the limits run as before
+        let rows = database.call_exec(&mut store, db, "SELECT a, (b) FROM t;")??;
+        assert_eq!(rows.len(), 1);
+
+        database.call_set_limit(&mut store, db, LimitKind::Column, 0)?;
+        let rows = database.call_exec(&mut store, db, "SELECT a, b, c FROM t;")??;
+        assert_eq!(rows[0].len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_manager() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let manager = limbo.manager();
+
+        let tenants = manager.call_constructor(&mut store, "kv:tenants")?;
+        let alice = manager.call_open(&mut store, tenants, "alice")??;
+        let bob = manager.call_open(&mut store, tenants, "bob")??;
+        database.call_exec(&mut store, alice, "CREATE TABLE t (n INTEGER);")??;
+        database.call_exec(&mut store, alice, "INSERT INTO t VALUES (1), (2);")??;
+
+        // each tenant has a database of its own
+        let result = database.call_exec(&mut store, bob, "SELECT n FROM t;")?;
+        assert!(result.is_err(), "{:?}", result);
+        assert_eq!(
+            manager.call_list(&mut store, tenants)?,
+            vec!["alice".to_string(), "bob".to_string()]
+        );
+
+        let result = manager.call_open(&mut store, tenants, "../x")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // the quota holds for databases opened after it is set
+        manager.call_set_memory_quota(&mut store, tenants, 8)?;
+        let limited = manager.call_open(&mut store, tenants, "alice")??;
+        let result = database.call_exec(&mut store, limited, "SELECT n FROM t;")?;
+        assert!(
+            matches!(result, Err(DbError::OutOfMemory(_))),
+            "{:?}",
+            result
+        );
+        let rows = database.call_exec(&mut store, alice, "SELECT n FROM t;")??;
+        assert_eq!(rows.len(), 2);
+
+        manager.call_drop_tenant(&mut store, tenants, "alice")??;
+        assert_eq!(
+            manager.call_list(&mut store, tenants)?,
+            vec!["bob".to_string()]
+        );
+        let result = manager.call_drop_tenant(&mut store, tenants, "alice")?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+
+        // its storage is kept, so opening it again finds the data
+        let alice = manager.call_open(&mut store, tenants, "alice")??;
+        let rows = database.call_exec(&mut store, alice, "SELECT n FROM t;")??;
+        assert_eq!(rows.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_idle_close() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let manager = limbo.manager();
+
+        let tenants = manager.call_constructor(&mut store, "kv:idle")?;
+        let alice = manager.call_open(&mut store, tenants, "alice")??;
+        database.call_exec(&mut store, alice, "CREATE TABLE t (n INTEGER);")??;
+        database.call_exec(&mut store, alice, "INSERT INTO t VALUES (1);")??;
+        assert!(manager.call_close_idle(&mut store, tenants)??.is_empty());
+
+        manager.call_set_idle_close_ms(&mut store, tenants, 200)?;
+        assert!(manager.call_close_idle(&mut store, tenants)??.is_empty());
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        assert_eq!(
+            manager.call_close_idle(&mut store, tenants)??,
+            vec!["alice".to_string()]
+        );
+        assert_eq!(
+            manager.call_list(&mut store, tenants)?,
+            vec!["alice".to_string()]
+        );
+
+        // the connection reconnects on its next call, finding the checkpointed data
+        let rows = database.call_exec(&mut store, alice, "SELECT n FROM t;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(1)]]);
+        assert!(manager.call_close_idle(&mut store, tenants)??.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyset_page() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let stmt = limbo.statement();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY, day INTEGER, title TEXT);",
+        )??;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "INSERT INTO posts VALUES (1, 1, 'a'), (2, 2, 'b'), (3, 2, 'c'), (4, 3, 'd'), (5, 3, 'e');",
+        )??;
+
+        let sql = "SELECT id, day FROM posts WHERE title <> ? ORDER BY day DESC, id;";
+        let statement = limbo.database().call_prepare(&mut store, db, sql)??;
+        stmt.call_bind(&mut store, statement, 1, &RecordValue::Text("e".into()))??;
+
+        let mut ids = vec![];
+        let mut after = None;
+        loop {
+            let page = stmt.call_keyset_page(&mut store, statement, after.as_deref(), 2)??;
+            assert!(page.rows.len() <= 2);
+            ids.extend(page.rows.iter().map(|row| row[0].clone()));
+            match page.next {
+                Some(next) => after = Some(next),
+                None => break,
+            }
+        }
+        let expected: Vec<_> = [4, 2, 3, 1].into_iter().map(RecordValue::Integer).collect();
+        assert_eq!(ids, expected);
+
+        let unordered =
+            limbo
+                .database()
+                .call_prepare(&mut store, db, "SELECT id FROM posts;")??;
+        let result = stmt.call_keyset_page(&mut store, unordered, None, 2)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        let unselected = limbo.database().call_prepare(
+            &mut store,
+            db,
+            "SELECT title FROM posts ORDER BY id;",
+        )??;
+        let result = stmt.call_keyset_page(&mut store, unselected, None, 2)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // each page is rewritten as the statement was, ID functions and table filters included
+        limbo
+            .database()
+            .call_set_table_filter(&mut store, db, "posts", "day > 1")??;
+        let sql = "SELECT id, uuid4() AS tag FROM posts ORDER BY id;";
+        let statement = limbo.database().call_prepare(&mut store, db, sql)??;
+        let page = stmt.call_keyset_page(&mut store, statement, None, 10)??;
+        let ids: Vec<_> = page.rows.iter().map(|row| row[0].clone()).collect();
+        let expected: Vec<_> = [2, 3, 4, 5].into_iter().map(RecordValue::Integer).collect();
+        assert_eq!(ids, expected);
+        assert!(page
+            .rows
+            .iter()
+            .all(|row| matches!(&row[1], RecordValue::Text(tag) if tag.len() == 36)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_triggers() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+        database.call_exec(&mut store, db, "CREATE TABLE log (n INTEGER);")??;
+
+        // CREATE TRIGGER fails rather than being accepted and never firing
+        let create = "/* audit */ create temp trigger t_log AFTER INSERT ON t \
+            BEGIN INSERT INTO log VALUES (new.n); END;";
+        let result = database.call_exec(&mut store, db, create)?;
+        assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+        let result = database.call_prepare(&mut store, db, create)?;
+        assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+
+        assert!(database.call_triggers(&mut store, db, None)??.is_empty());
+        // the table is named whatever its case, as in SQL
+        for table in ["t", "T"] {
+            assert!(database
+                .call_triggers(&mut store, db, Some(table))??
+                .is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_columns() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT 'none', \
+                note);",
+        )??;
+
+        let columns = database.call_columns(&mut store, db, "items")??;
+        let names: Vec<_> = columns.iter().map(|column| column.name.as_str()).collect();
+        assert_eq!(names, ["id", "name", "note"]);
+        assert_eq!(columns[0].decl_type.as_deref(), Some("INTEGER"));
+        assert_eq!(columns[0].primary_key, 1);
+        assert!(columns[1].not_null);
+        // the expression as written, quotes and all
+        assert_eq!(columns[1].default_expr.as_deref(), Some("'none'"));
+        assert_eq!(columns[2].decl_type, None);
+        assert!(columns.iter().all(|column| column.generated.is_none()));
+        // the table is named whatever its case, as in SQL
+        let columns = database.call_columns(&mut store, db, "Items")??;
+        assert_eq!(columns.len(), 3);
+
+        let result = database.call_columns(&mut store, db, "missing")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_table() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let src = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            src,
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT, data BLOB);",
+        )??;
+        database.call_exec(
+            &mut store,
+            src,
+            "INSERT INTO items VALUES (1, 'a', x'00ff'), (2, NULL, NULL), (3, 'c', x'');",
+        )??;
+
+        let dest = database.call_constructor(&mut store, ":memory:")?;
+        assert_eq!(
+            database.call_copy_table(&mut store, dest, src, "items", false)??,
+            3
+        );
+        let select = "SELECT id, name, data FROM items ORDER BY id";
+        assert_eq!(
+            database.call_exec(&mut store, dest, select)??,
+            database.call_exec(&mut store, src, select)??
+        );
+
+        let result = database.call_copy_table(&mut store, dest, src, "items", false)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        database.call_exec(&mut store, src, "DELETE FROM items WHERE id > 1")??;
+        assert_eq!(
+            database.call_copy_table(&mut store, dest, src, "items", true)??,
+            1
+        );
+
+        let result = database.call_copy_table(&mut store, dest, src, "missing", true)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_schema() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let local = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            local,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+        )??;
+        database.call_exec(&mut store, local, "CREATE TABLE old (x INTEGER);")??;
+
+        let latest = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            latest,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT);",
+        )??;
+        database.call_exec(&mut store, latest, "CREATE TABLE posts (id INTEGER);")??;
+
+        let statements = database.call_diff_schema(&mut store, local, latest)??;
+        assert_eq!(statements.len(), 3, "{:?}", statements);
+        assert_eq!(statements[0], "DROP TABLE IF EXISTS \"old\";");
+        assert!(
+            statements[1].starts_with("ALTER TABLE \"users\" ADD COLUMN email"),
+            "{:?}",
+            statements
+        );
+        assert!(statements[2].contains("posts"), "{:?}", statements);
+
+        // the same schema needs no changes
+        assert!(database
+            .call_diff_schema(&mut store, latest, latest)??
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+        )??;
+        database.call_exec(
+            &mut store,
+            db,
+            "INSERT INTO users VALUES (1, 'a'), (2, 'b'), (3, 'c');",
+        )??;
+
+        let scan =
+            database.call_estimate(&mut store, db, "SELECT * FROM users WHERE name = 'b'")??;
+        assert!(scan.full_scan);
+        assert_eq!(scan.scanned_tables, vec!["users".to_string()]);
+        assert_eq!(scan.estimated_rows, 3);
+
+        let lookup =
+            database.call_estimate(&mut store, db, "SELECT * FROM users WHERE id = 2")??;
+        assert!(!lookup.full_scan);
+        assert_eq!(lookup.searched_tables, vec!["users".to_string()]);
+        assert_eq!(lookup.estimated_rows, 1);
+
+        // nothing was run
+        let rows = database.call_exec(&mut store, db, "SELECT count(*) FROM users")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(3)]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_filter() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, owner INTEGER, body TEXT);",
+        )??;
+        database.call_exec(
+            &mut store,
+            db,
+            "INSERT INTO notes VALUES (1, 1, 'mine'), (2, 2, 'theirs'), (3, 1, 'also mine');",
+        )??;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE VIEW bodies AS SELECT body FROM notes;",
+        )??;
+        database.call_exec(&mut store, db, "CREATE VIEW later AS SELECT * FROM bodies;")??;
+
+        database.call_set_table_filter(&mut store, db, "notes", "owner = 1")??;
+        // a view runs SQL of its own, which the filter can't reach
+        for sql in [
+            "SELECT body FROM bodies",
+            "SELECT * FROM later",
+            "CREATE VIEW mine AS SELECT * FROM \"Notes\"",
+        ] {
+            let result = database.call_exec(&mut store, db, sql)?;
+            assert!(
+                matches!(result, Err(DbError::Misuse(_))),
+                "{}: {:?}",
+                sql,
+                result
+            );
+        }
+        let rows =
+            database.call_exec(&mut store, db, "SELECT n.id FROM notes n ORDER BY n.id")??;
+        assert_eq!(
+            rows,
+            vec![vec![RecordValue::Integer(1)], vec![RecordValue::Integer(3)]]
+        );
+        database.call_exec(&mut store, db, "UPDATE notes SET body = 'edited'")??;
+        database.call_exec(&mut store, db, "DELETE FROM notes WHERE id > 2")??;
+        // a row can't be moved out of the filter, where it could no longer be seen
+        database.call_exec(&mut store, db, "UPDATE notes SET owner = 2 WHERE id = 1")??;
+        let result =
+            database.call_exec(&mut store, db, "UPDATE notes SET (owner, body) = (2, 'x')")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        let result =
+            database.call_set_table_filter(&mut store, db, "notes", "no_such_column = 1")?;
+        assert!(result.is_err(), "{:?}", result);
+        database.call_set_table_filter(&mut store, db, "notes", "owner = 1")??;
+
+        // nor can a write that reaches the other owner's row some other way change it
+        database.call_exec(
+            &mut store,
+            db,
+            "INSERT INTO notes VALUES (2, 1, 'taken') \
+             ON CONFLICT (id) DO UPDATE SET owner = 1, body = excluded.body",
+        )??;
+        for sql in [
+            "REPLACE INTO notes VALUES (2, 1, 'taken')",
+            "INSERT OR REPLACE INTO notes VALUES (2, 1, 'taken')",
+        ] {
+            let result = database.call_exec(&mut store, db, sql)?;
+            assert!(
+                matches!(result, Err(DbError::Misuse(_))),
+                "{}: {:?}",
+                sql,
+                result
+            );
+        }
+        let key = ["id".to_string()];
+        let columns = ["id", "owner", "body"].map(String::from);
+        let rows = [vec![
+            RecordValue::Integer(2),
+            RecordValue::Integer(1),
+            RecordValue::Text("taken".into()),
+        ]];
+        database.call_upsert(&mut store, db, "notes", &key, &columns, &rows)??;
+        let writer = bindings.component_wit_limbo_limbo().blob_writer();
+        let blob = database.call_insert_blob(&mut store, db, "notes", "body", 2, 1)??;
+        writer.call_write(&mut store, blob, &[0])??;
+        writer.call_finish(&mut store, blob)??;
+        blob.resource_drop(&mut store)?;
+
+        // without the filter, the other owner's row is untouched
+        database.call_set_table_filter(&mut store, db, "notes", "")??;
+        let rows = database.call_exec(&mut store, db, "SELECT body FROM notes ORDER BY id")??;
+        assert_eq!(
+            rows,
+            vec![
+                vec![RecordValue::Text("edited".into())],
+                vec![RecordValue::Text("theirs".into())],
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_defensive() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+        database.call_set_defensive(&mut store, db, true)?;
+
+        for sql in [
+            "UPDATE sqlite_master SET sql = 'CREATE TABLE t (m)' WHERE name = 't'",
+            "DELETE FROM main.\"sqlite_schema\"",
+            "PRAGMA writable_schema = ON",
+            "PRAGMA main.schema_version = 7",
+            "PRAGMA journal_mode = 'off'",
+        ] {
+            let result = database.call_exec(&mut store, db, sql)?;
+            assert!(
+                matches!(result, Err(DbError::Misuse(_))),
+                "{}: {:?}",
+                sql,
+                result
+            );
+            let result = database.call_prepare(&mut store, db, sql)?;
+            assert!(result.is_err(), "{}", sql);
+        }
+        // upsert and insert-blob are refused the same way
+        let name = ["name".to_string()];
+        let rows = [vec![RecordValue::Text("t".to_string())]];
+        let result = database.call_upsert(&mut store, db, "sqlite_schema", &name, &name, &rows)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let result = database.call_insert_blob(&mut store, db, "sqlite_schema", "sql", 1, 1)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // everything else runs as before
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (1);")??;
+        database.call_exec(&mut store, db, "PRAGMA writable_schema;")??;
+        let rows = database.call_exec(&mut store, db, "SELECT name FROM sqlite_schema;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Text("t".to_string())]]);
+
+        database.call_set_defensive(&mut store, db, false)?;
+        database.call_exec(&mut store, db, "PRAGMA schema_version = 7;")??;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unicode_case() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (name TEXT); \
+             INSERT INTO t VALUES ('ÉCOLE'), ('école'), ('Ecole');",
+        )??;
+        let names = |rows: Vec<Vec<RecordValue>>| -> Vec<String> {
+            rows.into_iter()
+                .map(|row| match &row[0] {
+                    RecordValue::Text(name) => name.clone(),
+                    value => panic!("{:?}", value),
+                })
+                .collect()
+        };
+        let like = "SELECT name FROM t WHERE name LIKE 'éc%' ORDER BY rowid;";
+
+        // only ASCII letters fold by default
+        let rows = database.call_exec(&mut store, db, like)??;
+        assert_eq!(names(rows), ["école"]);
+
+        database.call_set_unicode_case(&mut store, db, true)?;
+        let rows = database.call_exec(&mut store, db, like)??;
+        assert_eq!(names(rows), ["ÉCOLE", "école"]);
+        let rows = database.call_exec(
+            &mut store,
+            db,
+            "SELECT name FROM t WHERE name NOT LIKE 'ÉC%' ORDER BY rowid;",
+        )??;
+        assert_eq!(names(rows), ["Ecole"]);
+        let rows = database.call_exec(
+            &mut store,
+            db,
+            "SELECT name FROM t WHERE t.name = 'École' COLLATE NOCASE ORDER BY rowid;",
+        )??;
+        assert_eq!(names(rows), ["ÉCOLE", "école"]);
+
+        // prepared statements are rewritten too
+        let statement = database.call_prepare(&mut store, db, like)??;
+        let rows = bindings
+            .component_wit_limbo_limbo()
+            .statement()
+            .call_all(&mut store, statement)??;
+        assert_eq!(names(rows), ["ÉCOLE", "école"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_regexp() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        if !limbo
+            .call_capabilities(&mut store)?
+            .iter()
+            .any(|c| c == "regexp")
+        {
+            // refused rather than left to limbo without the `regexp` feature
+            let result = database.call_exec(&mut store, db, "SELECT 'a' REGEXP 'a'")?;
+            assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+            return Ok(());
+        }
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (name TEXT); \
+             INSERT INTO t VALUES ('abc'), ('a.c'), ('x1'), ('It''s'), ('*');",
+        )??;
+        let names = |store: &mut Store<MyCtx>, condition: &str| -> Result<Vec<String>, TestError> {
+            let sql = format!("SELECT name FROM t WHERE {} ORDER BY rowid;", condition);
+            let rows = database.call_exec(store, db, &sql)??;
+            Ok(rows
+                .into_iter()
+                .map(|row| match &row[0] {
+                    RecordValue::Text(name) => name.clone(),
+                    value => panic!("{:?}", value),
+                })
+                .collect())
+        };
+
+        assert_eq!(names(&mut store, "name REGEXP 'b'")?, ["abc"]);
+        assert_eq!(names(&mut store, "name REGEXP '^a.c$'")?, ["abc", "a.c"]);
+        assert_eq!(names(&mut store, r"name REGEXP 'a\.c'")?, ["a.c"]);
+        assert_eq!(
+            names(&mut store, r"name REGEXP '\d$|^I.*s$'")?,
+            ["x1", "It's"]
+        );
+        assert_eq!(names(&mut store, "name NOT REGEXP '^a|1'")?, ["It's", "*"]);
+        assert_eq!(names(&mut store, "t.name REGEXP '''s'")?, ["It's"]);
+        assert_eq!(names(&mut store, r"name REGEXP '\*'")?, ["*"]);
+        assert_eq!(
+            names(&mut store, r"name REGEXP '^(ab|x)\w+$'")?,
+            ["abc", "x1"]
+        );
+        assert_eq!(
+            names(&mut store, r"name REGEXP '^[a-z]\.?c{1,2}$'")?,
+            ["a.c"]
+        );
+        assert_eq!(names(&mut store, "name REGEXP '(?i)IT'")?, ["It's"]);
+
+        // a bound pattern, matched against the rows as they are at each run
+        let statement = bindings.component_wit_limbo_limbo().statement();
+        let sql = "SELECT name FROM t WHERE name REGEXP ? ORDER BY rowid";
+        let stmt = database.call_prepare(&mut store, db, sql)??;
+        let text = |s: &str| RecordValue::Text(s.to_string());
+        statement.call_bind(&mut store, stmt, 1, &text("^a"))??;
+        let rows = statement.call_all(&mut store, stmt)??;
+        assert_eq!(rows, [[text("abc")], [text("a.c")]]);
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES ('apple')")??;
+        let rows = statement.call_all(&mut store, stmt)??;
+        assert_eq!(rows, [[text("abc")], [text("a.c")], [text("apple")]]);
+        statement.call_bind(&mut store, stmt, 1, &text(r"\d"))??;
+        let rows = statement.call_all(&mut store, stmt)??;
+        assert_eq!(rows, [[text("x1")]]);
+
+        // a bound value, and NULL on either side
+        let sql = r"SELECT ? REGEXP '^\d+$', ?2 REGEXP NULL";
+        let stmt = database.call_prepare(&mut store, db, sql)??;
+        statement.call_bind(&mut store, stmt, 2, &text("x"))??;
+        for (value, matched) in [("123", 1), ("12a", 0)] {
+            statement.call_bind(&mut store, stmt, 1, &text(value))??;
+            let rows = statement.call_all(&mut store, stmt)??;
+            assert_eq!(rows, [[RecordValue::Integer(matched), RecordValue::Null]]);
+        }
+        statement.call_bind(&mut store, stmt, 1, &RecordValue::Null)??;
+        let rows = statement.call_all(&mut store, stmt)??;
+        assert_eq!(rows, [[RecordValue::Null, RecordValue::Null]]);
+
+        // writes match against the table they write
+        database.call_exec(&mut store, db, "DELETE FROM t WHERE name REGEXP '^a'")??;
+        assert_eq!(names(&mut store, "1")?, ["x1", "It's", "*"]);
+
+        let result = database.call_exec(&mut store, db, "SELECT 'a' REGEXP '('")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        // a REGEXP the guest can't read the values of is refused, not matched differently
+        for condition in [
+            "lower(name) REGEXP 'a'",
+            "name REGEXP name",
+            "'a' || name REGEXP 'a'",
+        ] {
+            let sql = format!("SELECT name FROM t WHERE {};", condition);
+            let result = database.call_exec(&mut store, db, &sql)?;
+            assert!(matches!(result, Err(DbError::Other(_))), "{}", condition);
+        }
+
+        // a column with more distinct values than the guest writes into a statement is refused
+        let values: Vec<_> = (0..=10_000).map(|i| format!("('{}')", i)).collect();
+        let sql = format!(
+            "CREATE TABLE big (n TEXT); INSERT INTO big VALUES {};",
+            values.join(", ")
+        );
+        database.call_exec(&mut store, db, &sql)??;
+        let result = database.call_exec(&mut store, db, "SELECT n FROM big WHERE n REGEXP '^1'")?;
+        assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+        database.call_exec(&mut store, db, "DELETE FROM big WHERE n = '0'")??;
+        let rows =
+            database.call_exec(&mut store, db, "SELECT n FROM big WHERE n REGEXP '^9999$'")??;
+        assert_eq!(rows, [[RecordValue::Text("9999".to_string())]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_locale() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        if !limbo
+            .call_capabilities(&mut store)?
+            .iter()
+            .any(|c| c == "host-locale")
+        {
+            // only imported by a component built with `just test-locale`
+            return Ok(());
+        }
+        let database = limbo.database();
+        let statement = limbo.statement();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (name TEXT); \
+             INSERT INTO t VALUES ('ö'), ('z'), ('a'), ('å'), ('Z'), ('Istanbul'), ('istanbul');",
+        )??;
+        let names = |store: &mut Store<MyCtx>, sql: &str| -> Result<Vec<String>, TestError> {
+            let rows = database.call_exec(store, db, sql)??;
+            Ok(rows
+                .into_iter()
+                .map(|row| match &row[0] {
+                    RecordValue::Text(name) => name.clone(),
+                    value => panic!("{:?}", value),
+                })
+                .collect())
+        };
+
+        // folded by the host's `to-lower`, which lowercases `I` to `ı`
+        database.call_set_unicode_case(&mut store, db, true)?;
+        let like = "SELECT name FROM t WHERE name LIKE 'ı%' ORDER BY rowid;";
+        assert_eq!(names(&mut store, like)?, ["Istanbul"]);
+        let like = "SELECT name FROM t WHERE name LIKE 'i%' ORDER BY rowid;";
+        assert_eq!(names(&mut store, like)?, ["istanbul"]);
+        database.call_set_unicode_case(&mut store, db, false)?;
+
+        // sorted and compared as the host's `sv` sorts, `å` and `ö` after `z`
+        let sorted =
+            "SELECT name FROM t WHERE length(name) = 1 ORDER BY name COLLATE \"sv\", rowid;";
+        assert_eq!(names(&mut store, sorted)?, ["a", "z", "Z", "å", "ö"]);
+        let after = "SELECT name FROM t WHERE name COLLATE \"sv\" > 'z' ORDER BY rowid;";
+        assert_eq!(names(&mut store, after)?, ["ö", "å"]);
+
+        // a bound value, ranked against the rows as they are at each run
+        let sql = "SELECT name FROM t WHERE name COLLATE \"sv\" < ? ORDER BY rowid";
+        let stmt = database.call_prepare(&mut store, db, sql)??;
+        let text = |s: &str| RecordValue::Text(s.to_string());
+        statement.call_bind(&mut store, stmt, 1, &text("b"))??;
+        let rows = statement.call_all(&mut store, stmt)??;
+        assert_eq!(rows, [[text("a")]]);
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES ('A')")??;
+        let rows = statement.call_all(&mut store, stmt)??;
+        assert_eq!(rows, [[text("a")], [text("A")]]);
+
+        // a collation the guest can't read the values of is refused, not sorted differently
+        let sql = "SELECT name FROM t ORDER BY lower(name) COLLATE \"sv\";";
+        let result = database.call_exec(&mut store, db, sql)?;
+        assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+        // as is one holding a number, which has no place in the host's order
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (1)")??;
+        let sql = "SELECT name FROM t ORDER BY name COLLATE \"sv\";";
+        let result = database.call_exec(&mut store, db, sql)?;
+        assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_math_functions() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+
+        // limbo_core has SQLite's math functions built in
+        let checks = [
+            "sqrt(16) = 4",
+            "pow(2, 10) = 1024",
+            "power(3, 2) = 9",
+            "exp(0) = 1",
+            "ln(1) = 0",
+            "log(100) = 2",
+            "log(2, 8) = 3",
+            "log10(1000) = 3",
+            "log2(1024) = 10",
+            "abs(cos(0) - 1) < 1e-12",
+            "abs(sin(pi() / 2) - 1) < 1e-12",
+            "abs(atan2(1, 1) * 4 - pi()) < 1e-12",
+            "abs(degrees(pi()) - 180) < 1e-12",
+            "ceil(1.2) = 2",
+            "floor(-1.5) = -2",
+            "trunc(-1.5) = -1",
+            "mod(7, 3) = 1",
+            "sqrt(-1) IS NULL",
+        ];
+        let sql = format!("SELECT {};", checks.join(", "));
+        let rows = database.call_exec(&mut store, db, &sql)??;
+        assert_eq!(rows[0].len(), checks.len());
+        for (check, value) in checks.iter().zip(&rows[0]) {
+            assert_eq!(*value, RecordValue::Integer(1), "{}", check);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_id_functions() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER, v4 TEXT, v7 TEXT, u TEXT); \
+             INSERT INTO t VALUES (1, uuid4(), uuid7(), ulid()), (2, UUID4(), uuid7(), ulid());",
+        )??;
+        let rows = database.call_exec(&mut store, db, "SELECT v4, v7, u FROM t ORDER BY n;")??;
+        let text = |value: &RecordValue| match value {
+            RecordValue::Text(text) => text.clone(),
+            value => panic!("{:?}", value),
+        };
+        let hex = |s: &str| {
+            s.chars()
+                .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+        };
+        for row in &rows {
+            let (v4, v7, ulid) = (text(&row[0]), text(&row[1]), text(&row[2]));
+            for (uuid, version) in [(&v4, '4'), (&v7, '7')] {
+                let groups: Vec<&str> = uuid.split('-').collect();
+                let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+                assert_eq!(lengths, [8, 4, 4, 4, 12], "{}", uuid);
+                assert!(groups.iter().all(|group| hex(group)), "{}", uuid);
+                assert!(groups[2].starts_with(version), "{}", uuid);
+                assert!(groups[3].starts_with(['8', '9', 'a', 'b']), "{}", uuid);
+            }
+            assert_eq!(ulid.len(), 26);
+            assert!(ulid
+                .chars()
+                .all(|c| c.is_ascii_digit() || (c.is_ascii_uppercase() && !"ILOU".contains(c))));
+        }
+        // every row gets its own
+        assert_ne!(rows[0], rows[1]);
+
+        // the time in a UUIDv7 is in milliseconds since the Unix epoch
+        let now_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let v7 = text(&rows[1][1]).replace('-', "");
+        let time = u64::from_str_radix(&v7[..12], 16).unwrap();
+        assert!(
+            time <= now_ms && now_ms - time < 60_000,
+            "{} {}",
+            time,
+            now_ms
+        );
+
+        // as DDL runs long after it is written, only uuid4() may be in it
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE d (id TEXT DEFAULT (uuid4()), n INTEGER);",
+        )??;
+        let result =
+            database.call_exec(&mut store, db, "CREATE TABLE e (id TEXT DEFAULT (ulid()));")?;
+        assert!(matches!(result, Err(DbError::Other(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_functions() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        if !limbo
+            .call_capabilities(&mut store)?
+            .iter()
+            .any(|c| c == "hash-functions")
+        {
+            return Ok(());
+        }
+        let database = limbo.database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let sha256_abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let blake3_abc = "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85";
+
+        let rows = database.call_exec(
+            &mut store,
+            db,
+            "SELECT lower(hex(sha256('abc'))), lower(hex(blake3(X'616263'))), base64('abc'), \
+             sha256(NULL);",
+        )??;
+        assert_eq!(
+            rows,
+            vec![vec![
+                RecordValue::Text(sha256_abc.to_string()),
+                RecordValue::Text(blake3_abc.to_string()),
+                RecordValue::Text("YWJj".to_string()),
+                RecordValue::Null,
+            ]]
+        );
+
+        // content addressing: a parameter is hashed as it is bound
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE blobs (hash BLOB PRIMARY KEY, data BLOB);",
+        )??;
+        let statement = database.call_prepare(
+            &mut store,
+            db,
+            "INSERT INTO blobs VALUES (blake3(?1), ?2);",
+        )??;
+        let data = RecordValue::Blob(b"abc".to_vec());
+        limbo
+            .statement()
+            .call_bind(&mut store, statement, 1, &data)??;
+        limbo
+            .statement()
+            .call_bind(&mut store, statement, 2, &data)??;
+        limbo.statement().call_all(&mut store, statement)??;
+        let rows =
+            database.call_exec(&mut store, db, "SELECT lower(hex(hash)), data FROM blobs;")??;
+        assert_eq!(rows[0][0], RecordValue::Text(blake3_abc.to_string()));
+        assert_eq!(rows[0][1], data);
+
+        // only what is known before the statement runs can be hashed
+        for sql in ["SELECT sha256(data) FROM blobs;", "SELECT sha256(?1), ?1;"] {
+            let result = database.call_prepare(&mut store, db, sql)?;
+            assert!(result.is_err(), "{}", sql);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_views() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER, name TEXT);",
+        )??;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE posts (id INTEGER, user_id INTEGER);",
+        )??;
+        let create = "CREATE VIEW feed AS SELECT u.name, p.id FROM users u \
+            JOIN \"posts\" p ON p.user_id = u.id WHERE u.name <> 'FROM x'";
+        database.call_exec(&mut store, db, create)??;
+
+        let views = database.call_views(&mut store, db)??;
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].name, "feed");
+        assert!(views[0]
+            .sql
+            .as_deref()
+            .is_some_and(|sql| sql.contains("JOIN")));
+        assert_eq!(
+            views[0].tables,
+            vec!["users".to_string(), "posts".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_version() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let other = database.call_clone_handle(&mut store, db)?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+        let version = database.call_schema_version(&mut store, db)??;
+        assert_eq!(database.call_columns(&mut store, db, "t")??.len(), 1);
+        assert!(database.call_views(&mut store, db)??.is_empty());
+
+        // writing rows leaves the schema, and the version, as they were
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (1);")??;
+        assert_eq!(database.call_schema_version(&mut store, db)??, version);
+
+        // DDL on another connection changes the version, so this one reads the schema again
+        database.call_exec(&mut store, other, "CREATE VIEW v AS SELECT n FROM t;")??;
+        database.call_exec(&mut store, other, "ALTER TABLE t ADD COLUMN m TEXT;")??;
+        assert_ne!(database.call_schema_version(&mut store, db)??, version);
+        let views = database.call_views(&mut store, db)??;
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].name, "v");
+        assert_eq!(database.call_columns(&mut store, db, "t")??.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_foreign_key_check() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT); \
+             CREATE TABLE teams (code TEXT, season INTEGER, PRIMARY KEY (code, season)); \
+             CREATE TABLE posts (id INTEGER PRIMARY KEY, author INTEGER REFERENCES users, \
+                 team TEXT, season INTEGER, FOREIGN KEY (team, season) REFERENCES teams); \
+             INSERT INTO users VALUES (1, 'Alice'); \
+             INSERT INTO teams VALUES ('red', 2024); \
+             INSERT INTO posts VALUES (1, 1, 'red', 2024), (2, NULL, 'red', NULL);",
+        )??;
+        assert!(database
+            .call_foreign_key_check(&mut store, db, None)??
+            .is_empty());
+
+        // a row whose key is missing from the parent is reported, one whose key has a NULL isn't
+        database.call_exec(
+            &mut store,
+            db,
+            "INSERT INTO posts VALUES (3, 7, 'red', 2024), (4, 1, 'blue', 2024);",
+        )??;
+        let violations = database.call_foreign_key_check(&mut store, db, Some("posts"))??;
+        let found: Vec<_> = violations
+            .iter()
+            .map(|v| (v.table.as_str(), v.rowid, v.parent.as_str(), v.fkid))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("posts", Some(3), "users", 0),
+                ("posts", Some(4), "teams", 1)
+            ]
+        );
+        assert!(database
+            .call_foreign_key_check(&mut store, db, Some("users"))??
+            .is_empty());
+        // table names match whatever their case, as they do in SQL
+        let violations = database.call_foreign_key_check(&mut store, db, Some("Posts"))??;
+        assert_eq!(violations.len(), 2, "{:?}", violations);
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE likes (post INTEGER REFERENCES POSTS); INSERT INTO likes VALUES (1);",
+        )??;
+        assert!(database
+            .call_foreign_key_check(&mut store, db, Some("likes"))??
+            .is_empty());
+
+        let result = database.call_foreign_key_check(&mut store, db, Some("nope"))?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // limbo can't enforce them, so they can only be left off
+        database.call_set_foreign_keys(&mut store, db, false)??;
+        let result = database.call_set_foreign_keys(&mut store, db, true)?;
+        assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_integrity_check() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, "kv:integrity")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER, s TEXT); CREATE TABLE u (b BLOB); \
+             INSERT INTO t VALUES (1, 'one'), (2, 'two'); INSERT INTO u VALUES (x'00ff');",
+        )??;
+
+        // a sound database has nothing wrong with it, and each table is reported as checked
+        assert!(database
+            .call_integrity_check(&mut store, db, 0)??
+            .is_empty());
+        let operation = "integrity-check".to_string();
+        assert_eq!(
+            store.data().progress,
+            vec![
+                (operation.clone(), 0, 2),
+                (operation.clone(), 1, 2),
+                (operation, 2, 2)
+            ]
+        );
+        let root = database.call_exec(
+            &mut store,
+            db,
+            "SELECT rootpage FROM sqlite_schema WHERE name = 't';",
+        )??;
+        let [RecordValue::Integer(root)] = root[0][..] else {
+            panic!("no root page for t: {:?}", root);
+        };
+        // closing checkpoints the pages into the database file
+        database.call_close(&mut store, db)??;
+
+        // the page type of t's root page, in a copy of the file opened by another instance
+        let mut kv = store.data().kv.clone();
+        let block = kv
+            .get_mut(&format!("integrity/{}", root - 1))
+            .expect("t's root page is stored");
+        block[0] = 0xff;
+        let (mut store, bindings) = instantiate()?;
+        store.data_mut().kv = kv;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, "kv:integrity")?;
+        let errors = database.call_integrity_check(&mut store, db, 0)??;
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("table t: "), "{:?}", errors);
+        assert!(database.call_integrity_check(&mut store, db, 1)??.len() <= 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_readonly() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+
+        let select = database.call_prepare(&mut store, db, "SELECT n FROM t WHERE n > ?")??;
+        assert!(limbo.statement().call_is_readonly(&mut store, select)?);
+        let insert = database.call_prepare(&mut store, db, "INSERT INTO t VALUES (?)")??;
+        assert!(!limbo.statement().call_is_readonly(&mut store, insert)?);
+
+        for (sql, readonly) in [
+            ("  /* hi */ select 1", true),
+            ("BEGIN", true),
+            ("PRAGMA foreign_keys", true),
+            ("PRAGMA foreign_keys = ON", false),
+            ("WITH x AS (SELECT 1) SELECT * FROM x", true),
+            ("WITH x AS (SELECT 1) INSERT INTO t SELECT * FROM x", false),
+            ("DELETE FROM t", false),
+            ("CREATE TABLE u (n)", false),
+        ] {
+            assert_eq!(
+                database.call_is_readonly_sql(&mut store, db, sql)?,
+                readonly,
+                "{}",
+                sql
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_kind_and_tables() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT); \
+             CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT); \
+             CREATE INDEX posts_user ON posts (user_id);",
+        )??;
+
+        for (sql, kind, tables) in [
+            (
+                "SELECT title FROM posts JOIN users ON users.id = posts.user_id",
+                StatementKind::Select,
+                &["posts", "users"][..],
+            ),
+            (
+                "WITH x AS (SELECT 1) INSERT INTO users (name) SELECT 'a' FROM x",
+                StatementKind::Insert,
+                &["users"],
+            ),
+            (
+                "UPDATE posts SET title = ? WHERE user_id = 1",
+                StatementKind::Update,
+                &["posts"],
+            ),
+            (
+                "DELETE FROM users WHERE id = 1",
+                StatementKind::Delete,
+                &["users"],
+            ),
+            (
+                "CREATE TABLE tags (name TEXT)",
+                StatementKind::Ddl,
+                &["sqlite_schema"],
+            ),
+            ("PRAGMA table_info(users)", StatementKind::Pragma, &[]),
+            ("BEGIN", StatementKind::Other, &[]),
+        ] {
+            let statement = database.call_prepare(&mut store, db, sql)??;
+            assert_eq!(
+                limbo.statement().call_kind(&mut store, statement)?,
+                kind,
+                "{}",
+                sql
+            );
+            let mut found = limbo.statement().call_tables(&mut store, statement)??;
+            found.sort();
+            assert_eq!(found, tables, "{}", sql);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capabilities() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let capabilities = bindings
+            .component_wit_limbo_limbo()
+            .call_capabilities(&mut store)?;
+
+        for capability in ["database.exec", "statement.keyset-page", "manager.open"] {
+            assert!(
+                capabilities.iter().any(|c| c == capability),
+                "{} missing from {:?}",
+                capability,
+                capabilities
+            );
+        }
+        assert!(!capabilities.iter().any(|c| c == "wasi-http"));
+
+        Ok(())
+    }
+
+    /// Send `method path` with `body` through the component's `wasi:http/incoming-handler`,
+    /// returning the status and the JSON it responds with.
+    fn http_request(
+        store: &mut Store<MyCtx>,
+        proxy: &wasmtime_wasi_http::bindings::sync::Proxy,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> Result<(u16, serde_json::Value), TestError> {
+        use http_body_util::{BodyExt, Full};
+        use wasmtime_wasi_http::bindings::http::types::Scheme;
+
+        let body = Full::new(bytes::Bytes::from(body.to_string()))
+            .map_err(|never| -> hyper::Error { match never {} });
+        let request = hyper::Request::builder()
+            .method(method)
+            .uri(format!("http://localhost{}", path))
+            .body(body)
+            .map_err(|e| e.to_string())?;
+        let request = store
+            .data_mut()
+            .new_incoming_request(Scheme::Http, request)?;
+        let (sender, mut receiver) = tokio::sync::oneshot::channel();
+        let out = store.data_mut().new_response_outparam(sender)?;
+        proxy
+            .wasi_http_incoming_handler()
+            .call_handle(&mut *store, request, out)?;
+
+        let response = receiver
+            .try_recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("{:?}", e))?;
+        let status = response.status().as_u16();
+        let body = tokio::runtime::Runtime::new()?
+            .block_on(response.into_body().collect())
+            .map_err(|e| format!("{:?}", e))?
+            .to_bytes();
+        let json = serde_json::from_slice(&body).map_err(|e| e.to_string())?;
+        Ok((status, json))
+    }
+
+    #[test]
+    fn test_http_handler() -> wasmtime::Result<(), TestError> {
+        use serde_json::json;
+
+        let (mut store, bindings) = instantiate()?;
+        let capabilities = bindings
+            .component_wit_limbo_limbo()
+            .call_capabilities(&mut store)?;
+        if !capabilities.iter().any(|c| c == "wasi-http") {
+            // only exported by a component built with `just build-http`
+            return Ok(());
+        }
+        let (mut store, component, linker) = load()?;
+        let proxy = wasmtime_wasi_http::bindings::sync::Proxy::instantiate(
+            &mut store, &component, &linker,
+        )?;
+        let mut post = |body: &str| http_request(&mut store, &proxy, "POST", "/query", body);
+
+        // statements run against one database for as long as the instance lives
+        let create = json!({
+            "sql": "CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT, score REAL, data BLOB)",
+        });
+        assert_eq!(post(&create.to_string())?.0, 200);
+        let insert = json!({
+            "sql": "INSERT INTO t VALUES (?, ?, ?, x'0102'), (2, NULL, NULL, NULL)",
+            "params": [1, "Alice", 2.5],
+        });
+        assert_eq!(post(&insert.to_string())?.0, 200);
+
+        // rows come back as arrays of JSON values, under the names of their columns
+        let select = json!({
+            "sql": "SELECT id, name AS who, score, data FROM t WHERE id >= ? ORDER BY id",
+            "params": [1],
+        });
+        let (status, body) = post(&select.to_string())?;
+        assert_eq!(status, 200);
+        assert_eq!(
+            body,
+            json!({
+                "columns": ["id", "who", "score", "data"],
+                "rows": [[1, "Alice", 2.5, [1, 2]], [2, null, null, null]],
+            })
+        );
+
+        // a request the handler can't run is a 400, with the error as JSON
+        for body in [
+            "not json",
+            r#"{"params": []}"#,
+            r#"{"sql": 1}"#,
+            r#"{"sql": "SELECT ?", "params": {"a": 1}}"#,
+            r#"{"sql": "SELECT ?", "params": [[1]]}"#,
+            r#"{"sql": "SELEC 1"}"#,
+            r#"{"sql": "SELECT * FROM missing"}"#,
+        ] {
+            let (status, response) = post(body)?;
+            assert_eq!(status, 400, "{}", body);
+            assert!(response["error"].is_string(), "{}: {}", body, response);
+        }
+
+        // anything but POST /query is a 404
+        for (method, path) in [("GET", "/query"), ("POST", "/"), ("POST", "/query/x")] {
+            let (status, response) = http_request(&mut store, &proxy, method, path, "{}")?;
+            assert_eq!(status, 404, "{} {}", method, path);
+            assert!(response["error"].is_string(), "{}", response);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expanded_sql() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        let sql = "SELECT ?, ?, '?', ?, ? -- ?";
+        let statement = limbo.database().call_prepare(&mut store, db, sql)??;
+        let stmt = limbo.statement();
+        stmt.call_bind(&mut store, statement, 1, &RecordValue::Integer(42))??;
+        stmt.call_bind(
+            &mut store,
+            statement,
+            2,
+            &RecordValue::Text("O'Brien".into()),
+        )??;
+        stmt.call_bind(
+            &mut store,
+            statement,
+            4,
+            &RecordValue::Blob(vec![0xca, 0xfe]),
+        )??;
+
+        assert_eq!(stmt.call_original_sql(&mut store, statement)?, sql);
+        // string literals and comments are left alone, and unbound parameters are NULL
+        assert_eq!(
+            stmt.call_expanded_sql(&mut store, statement)?,
+            "SELECT 42, 'O''Brien', '?', X'CAFE', NULL -- ?"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_json() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        let stmt = limbo.statement();
+
+        let positional = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT ?, ?, ?, ?")??;
+        stmt.call_bind_json(&mut store, positional, r#"[1, 2.5, "three", true]"#)??;
+        assert_eq!(
+            stmt.call_all(&mut store, positional)??,
+            vec![vec![
+                RecordValue::Integer(1),
+                RecordValue::Float(2.5),
+                RecordValue::Text("three".into()),
+                RecordValue::Integer(1),
+            ]]
+        );
+
+        let named = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT :a, @b")??;
+        stmt.call_bind_json(&mut store, named, r#"{"b": null, ":a": "x"}"#)??;
+        assert_eq!(
+            stmt.call_all(&mut store, named)??,
+            vec![vec![RecordValue::Text("x".into()), RecordValue::Null]]
+        );
+
+        for params in [r#"[[1]]"#, r#"{"c": 1}"#, r#"{"a": {}}"#, "42", "not json"] {
+            let result = stmt.call_bind_json(&mut store, named, params)?;
+            assert!(
+                matches!(result, Err(DbError::Misuse(_))),
+                "{}: {:?}",
+                params,
+                result
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_json() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (n INTEGER, s TEXT, b BLOB); INSERT INTO t VALUES (1, 'a', x'0102'), (2, NULL, NULL);",
+        )??;
+
+        let json =
+            database.call_query_json(&mut store, db, "SELECT * FROM t WHERE n >= ?", "[1]")??;
+        assert_eq!(
+            json,
+            r#"{"columns":["n","s","b"],"rows":[[1,"a",[1,2]],[2,null,null]]}"#
+        );
+        let json = database.call_query_json(
+            &mut store,
+            db,
+            "SELECT s FROM t WHERE n = :n",
+            r#"{"n": 1}"#,
+        )??;
+        assert_eq!(json, r#"{"columns":["s"],"rows":[["a"]]}"#);
+        let json = database.call_query_json(&mut store, db, "SELECT count(*) FROM t", "")??;
+        assert_eq!(json, r#"{"columns":["count(*)"],"rows":[[2]]}"#);
+
+        let result = database.call_query_json(&mut store, db, "SELECT ?", "[[1]]")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let result = database.call_query_json(&mut store, db, "SELEKT 1", "")?;
+        assert!(matches!(result, Err(DbError::Parse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_options() -> wasmtime::Result<(), TestError> {
+        use bindgen::exports::component::wit_limbo::limbo::{BlobEncoding, JsonOptions};
+
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let sql = "SELECT 9007199254740993, 9007199254740991, 3.14159265, x'fbff00'";
+
+        let json = database.call_query_json(&mut store, db, sql, "")??;
+        assert!(
+            json.ends_with(r#"[[9007199254740993,9007199254740991,3.14159265,[251,255,0]]]}"#),
+            "{}",
+            json
+        );
+
+        for (blob_encoding, blob) in [
+            (BlobEncoding::Base64, r#""+/8A""#),
+            (BlobEncoding::Hex, r#""fbff00""#),
+        ] {
+            let options = JsonOptions {
+                big_integers_as_strings: true,
+                float_precision: Some(3),
+                blob_encoding,
+            };
+            database.call_set_json_options(&mut store, db, &options)?;
+            let json = database.call_query_json(&mut store, db, sql, "")??;
+            let expected = format!(r#"[["9007199254740993",9007199254740991,3.14,{}]]}}"#, blob);
+            assert!(json.ends_with(&expected), "{}", json);
+        }
+
+        // all-json follows the same options
+        let statement = database.call_prepare(&mut store, db, "SELECT x'00'")??;
+        let json = bindings
+            .component_wit_limbo_limbo()
+            .statement()
+            .call_all_json(&mut store, statement)??;
+        assert!(json.ends_with(r#"[["00"]]}"#), "{}", json);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_blob() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let writer = limbo.blob_writer();
+
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE files (id INTEGER PRIMARY KEY, data BLOB); \
+             INSERT INTO files VALUES (1, NULL);",
+        )??;
+
+        // the chunks are written into the row once the writer is finished
+        let blob = database.call_insert_blob(&mut store, db, "files", "data", 1, 6)??;
+        writer.call_write(&mut store, blob, &[1, 2, 3])??;
+        writer.call_write(&mut store, blob, &[4, 5, 6])??;
+        writer.call_finish(&mut store, blob)??;
+        let rows = database.call_exec(&mut store, db, "SELECT data FROM files WHERE id = 1;")??;
+        assert_eq!(rows, [[RecordValue::Blob(vec![1, 2, 3, 4, 5, 6])]]);
+        let result = writer.call_finish(&mut store, blob)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        blob.resource_drop(&mut store)?;
+
+        // more or fewer bytes than the writer was made for are refused, leaving the row alone
+        let blob = database.call_insert_blob(&mut store, db, "files", "data", 1, 4)??;
+        let result = writer.call_write(&mut store, blob, &[1, 2, 3, 4, 5])?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        writer.call_write(&mut store, blob, &[7, 7])??;
+        let result = writer.call_finish(&mut store, blob)?;
+        assert!(
+            matches!(&result, Err(DbError::Misuse(m)) if m.contains("expected 4 bytes, got 2")),
+            "{:?}",
+            result
+        );
+        blob.resource_drop(&mut store)?;
+        let rows = database.call_exec(&mut store, db, "SELECT length(data) FROM files;")??;
+        assert_eq!(rows, [[RecordValue::Integer(6)]]);
+
+        // a length the instance has no room for is an error rather than a trap
+        let result = database.call_insert_blob(&mut store, db, "files", "data", 1, u64::MAX)?;
+        assert!(
+            matches!(result, Err(DbError::OutOfMemory(_))),
+            "{:?}",
+            result
+        );
+        let result = database.call_insert_blob(&mut store, db, "files", "data", 1, 1 << 31)?;
+        assert!(
+            matches!(result, Err(DbError::OutOfMemory(_))),
+            "{:?}",
+            result
+        );
+
+        // a closed database says so when the writer is made
+        database.call_close(&mut store, db)??;
+        let result = database.call_insert_blob(&mut store, db, "files", "data", 1, 1)?;
+        assert!(matches!(result, Err(DbError::Closed(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_with_blob_encoding() -> wasmtime::Result<(), TestError> {
+        use bindgen::exports::component::wit_limbo::limbo::BlobEncoding;
+
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        let statement = limbo.database().call_prepare(
+            &mut store,
+            db,
+            "SELECT x'fbff00', x'', 'text', NULL",
+        )??;
+
+        for (encoding, blob, empty) in [
+            (
+                BlobEncoding::Bytes,
+                RecordValue::Blob(vec![0xfb, 0xff, 0x00]),
+                RecordValue::Blob(vec![]),
+            ),
+            (
+                BlobEncoding::Base64,
+                RecordValue::Text("+/8A".into()),
+                RecordValue::Text("".into()),
+            ),
+            (
+                BlobEncoding::Hex,
+                RecordValue::Text("fbff00".into()),
+                RecordValue::Text("".into()),
+            ),
+        ] {
+            let rows = limbo
+                .statement()
+                .call_all_with_blob_encoding(&mut store, statement, encoding)??;
+            assert_eq!(
+                rows,
+                vec![vec![
+                    blob,
+                    empty,
+                    RecordValue::Text("text".into()),
+                    RecordValue::Null
+                ]]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batched_writes() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let other = database.call_clone_handle(&mut store, db)?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+        let count = |store: &mut Store<MyCtx>, db| -> Result<RecordValue, TestError> {
+            Ok(database.call_exec(store, db, "SELECT count(*) FROM t")??[0][0].clone())
+        };
+
+        database.call_begin_batched(&mut store, db, 0, 3)?;
+        let result = database.call_begin_batched(&mut store, db, 0, 3)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // queued until the third write fills the batch
+        for n in 0..2 {
+            let sql = format!("INSERT INTO t VALUES ({});", n);
+            assert!(database.call_exec(&mut store, db, &sql)??.is_empty());
+        }
+        assert_eq!(count(&mut store, other)?, RecordValue::Integer(0));
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (2);")??;
+        assert_eq!(count(&mut store, other)?, RecordValue::Integer(3));
+
+        // a read on the batching connection commits what is queued first
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (3);")??;
+        assert_eq!(count(&mut store, db)?, RecordValue::Integer(4));
+
+        // a failing write rolls back the whole batch
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (4);")??;
+        database.call_exec(&mut store, db, "INSERT INTO missing VALUES (5);")??;
+        assert!(database.call_flush(&mut store, db)?.is_err());
+        assert_eq!(count(&mut store, other)?, RecordValue::Integer(4));
+
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (6);")??;
+        assert_eq!(database.call_end_batched(&mut store, db)??, 1);
+        assert_eq!(database.call_flush(&mut store, db)??, 0);
+        assert_eq!(count(&mut store, other)?, RecordValue::Integer(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tick() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let other = database.call_clone_handle(&mut store, db)?;
+        database.call_exec(&mut store, db, "CREATE TABLE t (n INTEGER);")??;
+
+        // what was committed is checkpointed once, by whichever connection ticks first
+        let work = database.call_tick(&mut store, other)??;
+        assert!(work.checkpointed);
+        let work = database.call_tick(&mut store, db)??;
+        assert_eq!(
+            (work.flushed, work.checkpointed, work.trimmed),
+            (0, false, false)
+        );
+
+        // a batch is committed once its first write has waited long enough
+        database.call_begin_batched(&mut store, db, 200, 0)?;
+        database.call_exec(&mut store, db, "INSERT INTO t VALUES (1);")??;
+        assert_eq!(database.call_tick(&mut store, db)??.flushed, 0);
+        std::thread::sleep(std::time::Duration::from_millis(250));
+        let work = database.call_tick(&mut store, db)??;
+        assert_eq!((work.flushed, work.checkpointed), (1, true));
+        let rows = database.call_exec(&mut store, other, "SELECT n FROM t;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(1)]]);
+        database.call_end_batched(&mut store, db)??;
+
+        // cached rows are dropped once the heap is past half the soft heap limit
+        database.call_set_query_cache(&mut store, db, 8, 0)?;
+        database.call_query_json(&mut store, db, "SELECT n FROM t", "")??;
+        assert!(!database.call_tick(&mut store, db)??.trimmed);
+        limbo.call_set_soft_heap_limit(&mut store, 1)?;
+        let work = database.call_tick(&mut store, db);
+        limbo.call_set_soft_heap_limit(&mut store, 0)?;
+        assert!(work??.trimmed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let subscription = limbo.subscription();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE todos (id INTEGER PRIMARY KEY, title TEXT); CREATE TABLE other (x);",
+        )??;
+
+        let todos = database.call_subscribe(
+            &mut store,
+            db,
+            "SELECT title FROM todos WHERE id > ?",
+            &[RecordValue::Integer(1)],
+        )??;
+        let id = subscription.call_id(&mut store, todos)?;
+        assert!(subscription.call_latest(&mut store, todos)??.is_empty());
+
+        // the first row doesn't match, so the rows don't change
+        database.call_exec(&mut store, db, "INSERT INTO todos (title) VALUES ('a')")??;
+        assert!(store.data().changed.is_empty());
+
+        // writes from another connection count too
+        let other = database.call_clone_handle(&mut store, db)?;
+        database.call_exec(&mut store, other, "INSERT INTO todos (title) VALUES ('b')")??;
+        assert_eq!(store.data().changed, [id]);
+        let rows = subscription.call_latest(&mut store, todos)??;
+        assert!(
+            matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Text(t)] if t == "b"))
+        );
+
+        // tables the query doesn't read are left alone
+        database.call_exec(&mut store, db, "INSERT INTO other VALUES (1)")??;
+        assert_eq!(store.data().changed, [id]);
+
+        let statement = database.call_prepare(
+            &mut store,
+            db,
+            "UPDATE todos SET title = 'c' WHERE id = 2",
+        )??;
+        limbo.statement().call_all(&mut store, statement)??;
+        assert_eq!(store.data().changed, [id, id]);
+        let rows = subscription.call_latest(&mut store, todos)??;
+        assert!(
+            matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Text(t)] if t == "c"))
+        );
+
+        let result = database.call_subscribe(&mut store, db, "DELETE FROM todos", &[])?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // dropping the subscription ends it
+        todos.resource_drop(&mut store)?;
+        database.call_exec(&mut store, db, "DELETE FROM todos")??;
+        assert_eq!(store.data().changed, [id, id]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_materialized_view() -> wasmtime::Result<(), TestError> {
+        use bindgen::exports::component::wit_limbo::limbo::RefreshMode;
+
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE orders (customer TEXT, total INTEGER); \
+             INSERT INTO orders VALUES ('a', 1), ('a', 2), ('b', 5);",
+        )??;
+        let sql = "SELECT customer, sum(total) AS spent FROM orders GROUP BY customer";
+        database.call_create_materialized_view(
+            &mut store,
+            db,
+            "spent_manual",
+            sql,
+            RefreshMode::Manual,
+        )??;
+        database.call_create_materialized_view(
+            &mut store,
+            db,
+            "spent",
+            sql,
+            RefreshMode::OnCommit,
+        )??;
+        let spent = |store: &mut Store<MyCtx>, view: &str| -> Result<Vec<i64>, TestError> {
+            let sql = format!("SELECT spent FROM {} ORDER BY customer", view);
+            let rows = database.call_exec(&mut *store, db, &sql)??;
+            Ok(rows
+                .iter()
+                .map(|row| match &row[..] {
+                    [RecordValue::Integer(spent)] => *spent,
+                    other => panic!("unexpected row {:?}", other),
+                })
+                .collect())
+        };
+        assert_eq!(spent(&mut store, "spent_manual")?, [3, 5]);
+        assert_eq!(spent(&mut store, "spent")?, [3, 5]);
+
+        // an on-commit view follows each write, a manual one waits for refresh-view
+        database.call_exec(&mut store, db, "INSERT INTO orders VALUES ('b', 1)")??;
+        assert_eq!(spent(&mut store, "spent")?, [3, 6]);
+        assert_eq!(spent(&mut store, "spent_manual")?, [3, 5]);
+        let rows = database.call_refresh_view(&mut store, db, "spent_manual")??;
+        assert_eq!(rows, 2);
+        assert_eq!(spent(&mut store, "spent_manual")?, [3, 6]);
+
+        // writes in a transaction are seen once it commits
+        database.call_exec(&mut store, db, "BEGIN")??;
+        database.call_exec(&mut store, db, "INSERT INTO orders VALUES ('c', 4)")??;
+        assert_eq!(spent(&mut store, "spent")?, [3, 6]);
+        database.call_exec(&mut store, db, "COMMIT")??;
+        assert_eq!(spent(&mut store, "spent")?, [3, 6, 4]);
+
+        let result = database.call_create_materialized_view(
+            &mut store,
+            db,
+            "spent",
+            sql,
+            RefreshMode::Manual,
+        )?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let result = database.call_refresh_view(&mut store, db, "orders")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_audit() -> wasmtime::Result<(), TestError> {
+        use bindgen::exports::component::wit_limbo::limbo::AuditOp;
+
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE accounts (id INTEGER PRIMARY KEY, owner TEXT, balance INTEGER)",
+        )??;
+        database.call_enable_audit(&mut store, db, "accounts")??;
+
+        let epoch_micros = || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_micros() as u64)
+        };
+        let before = epoch_micros();
+        database.call_exec(&mut store, db, "INSERT INTO accounts VALUES (1, 'a', 10)")??;
+        let statement = database.call_prepare(
+            &mut store,
+            db,
+            "UPDATE accounts SET balance = 5 WHERE id = 1",
+        )??;
+        bindings
+            .component_wit_limbo_limbo()
+            .statement()
+            .call_all(&mut store, statement)??;
+        // a write that leaves the row as it was records nothing
+        database.call_exec(&mut store, db, "UPDATE accounts SET balance = 5")??;
+        database.call_exec(&mut store, db, "DELETE FROM accounts")??;
+
+        let history = database.call_audit_history(&mut store, db, "ACCOUNTS", 1)??;
+        let ops: Vec<_> = history.iter().map(|entry| entry.op).collect();
+        assert!(
+            matches!(ops[..], [AuditOp::Insert, AuditOp::Update, AuditOp::Delete]),
+            "{:?}",
+            ops
+        );
+        let json = |text: &Option<String>| -> serde_json::Value {
+            serde_json::from_str(text.as_deref().unwrap()).unwrap()
+        };
+        assert_eq!(history[0].old, None);
+        assert_eq!(
+            json(&history[0].new),
+            serde_json::json!({"id": 1, "owner": "a", "balance": 10})
+        );
+        assert_eq!(json(&history[1].old)["balance"], 10);
+        assert_eq!(json(&history[1].new)["balance"], 5);
+        assert_eq!(json(&history[2].old)["balance"], 5);
+        assert_eq!(history[2].new, None);
+        assert!(history.windows(2).all(|w| w[0].at <= w[1].at));
+        // stamped by the wall clock, not by time since some other point
+        let after = epoch_micros();
+        assert!(
+            history
+                .iter()
+                .all(|entry| (before..=after).contains(&entry.at)),
+            "{:?} outside {}..={}",
+            history.iter().map(|entry| entry.at).collect::<Vec<_>>(),
+            before,
+            after
+        );
+
+        let result = database.call_audit_history(&mut store, db, "other", 1)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let result = database.call_enable_audit(&mut store, db, "other")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_pages() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let compress = limbo
+            .call_capabilities(&mut store)?
+            .iter()
+            .any(|c| c == "database.set-compress-pages");
+
+        let memory = limbo.database().call_constructor(&mut store, ":memory:")?;
+        let result = limbo
+            .database()
+            .call_set_compress_pages(&mut store, memory, true)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        let db = limbo.database().call_constructor(&mut store, "kv:packed")?;
+        let result = limbo
+            .database()
+            .call_set_compress_pages(&mut store, db, true)?;
+        if !compress {
+            assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+            return Ok(());
+        }
+        result?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE notes (body TEXT); INSERT INTO notes VALUES (hex(zeroblob(1000)));",
+        )??;
+        // written blocks compress to well under their 4096 bytes
+        let stored = &store.data().kv;
+        assert!(stored.iter().any(|(key, value)| key.starts_with("packed")
+            && !key.ends_with("/size")
+            && value.len() < 1024));
+
+        let rows =
+            limbo
+                .database()
+                .call_exec(&mut store, db, "SELECT length(body) FROM notes;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(2000)]]);
+
+        // what was compressed stays readable once compression is off
+        limbo
+            .database()
+            .call_set_compress_pages(&mut store, db, false)??;
+        limbo
+            .database()
+            .call_exec(&mut store, db, "INSERT INTO notes VALUES ('b');")??;
+        let rows = limbo
+            .database()
+            .call_exec(&mut store, db, "SELECT count(*) FROM notes;")??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(2)]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_coalesce_writes() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+
+        let db = database.call_constructor(&mut store, "kv:held")?;
+        database.call_set_coalesce_writes(&mut store, db, true)??;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE notes (body TEXT); \
+             INSERT INTO notes VALUES (hex(zeroblob(3000))); \
+             INSERT INTO notes VALUES ('b');",
+        )??;
+        // the commits synced the WAL, storing what was held
+        let stored = &store.data().kv;
+        assert!(stored.keys().any(|key| key.starts_with("held-wal/")));
+
+        let rows = database.call_exec(
+            &mut store,
+            db,
+            "SELECT count(*), max(length(body)) FROM notes;",
+        )??;
+        assert_eq!(
+            rows,
+            vec![vec![RecordValue::Integer(2), RecordValue::Integer(6000)]]
+        );
+
+        let memory = database.call_constructor(&mut store, ":memory:")?;
+        let result = database.call_set_coalesce_writes(&mut store, memory, true)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_ndjson() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let database = limbo.database();
+        let exporter = limbo.ndjson_exporter();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT); \
+             INSERT INTO items VALUES (1, 'a'), (2, 'b'), (3, 'a much longer name');",
+        )??;
+
+        let export = database.call_export_ndjson(&mut store, db, "items")??;
+        let first = r#"{"id":1,"name":"a"}"#.to_string() + "\n";
+        let second = r#"{"id":2,"name":"b"}"#.to_string() + "\n";
+        // two lines fit in the first chunk, and the longer third is returned on its own
+        let max_bytes = (first.len() + second.len()) as u32;
+        let chunk = exporter.call_next_chunk(&mut store, export, max_bytes)??;
+        assert_eq!(chunk, Some(first + &second));
+        let chunk = exporter.call_next_chunk(&mut store, export, 1)??;
+        assert_eq!(
+            chunk.as_deref(),
+            Some("{\"id\":3,\"name\":\"a much longer name\"}\n")
+        );
+        assert_eq!(exporter.call_next_chunk(&mut store, export, 1024)??, None);
+        let result = exporter.call_next_chunk(&mut store, export, 0)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        let result = database.call_export_ndjson(&mut store, db, "missing")?;
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_as_of() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE notes (body TEXT); CREATE VIEW bodies AS SELECT body FROM notes; \
+             INSERT INTO notes VALUES ('old');",
+        )??;
+        let before = database.call_snapshot(&mut store, db)??;
+        database.call_exec(&mut store, db, "UPDATE notes SET body = 'new'")??;
+
+        let rows =
+            database.call_query_as_of(&mut store, db, "SELECT body FROM bodies", before)??;
+        assert!(
+            matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Text(t)] if t == "old"))
+        );
+        let rows = database.call_exec(&mut store, db, "SELECT body FROM notes")??;
+        assert!(
+            matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Text(t)] if t == "new"))
+        );
+
+        let result = database.call_query_as_of(&mut store, db, "DELETE FROM notes", before)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // a snapshot holds every row, whatever filter was set when it was taken, and is read
+        // through the filter set when it is queried
+        database.call_exec(&mut store, db, "INSERT INTO notes VALUES ('hidden')")??;
+        database.call_set_table_filter(&mut store, db, "notes", "body <> 'hidden'")??;
+        let filtered = database.call_snapshot(&mut store, db)??;
+        let count = "SELECT count(*) FROM notes";
+        let rows = database.call_query_as_of(&mut store, db, count, filtered)??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(1)]]);
+        database.call_set_table_filter(&mut store, db, "notes", "")??;
+        let rows = database.call_query_as_of(&mut store, db, count, filtered)??;
+        assert_eq!(rows, vec![vec![RecordValue::Integer(2)]]);
+
+        assert!(database.call_release_snapshot(&mut store, db, before)?);
+        assert!(!database.call_release_snapshot(&mut store, db, before)?);
+        let result = database.call_query_as_of(&mut store, db, "SELECT 1", before)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upsert() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        database.call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, visits INTEGER); \
+             INSERT INTO users VALUES (1, 'Alice', 1);",
+        )??;
+
+        let columns = ["id", "name", "visits"].map(String::from).to_vec();
+        let rows = vec![
+            vec![
+                RecordValue::Integer(1),
+                RecordValue::Text("Alice".into()),
+                RecordValue::Integer(2),
+            ],
+            vec![
+                RecordValue::Integer(2),
+                RecordValue::Text("Bob".into()),
+                RecordValue::Integer(1),
+            ],
+        ];
+        let counts = database.call_upsert(
+            &mut store,
+            db,
+            "users",
+            &["id".to_string()],
+            &columns,
+            &rows,
+        )??;
+        assert_eq!((counts.inserted, counts.updated), (1, 1));
+        assert_eq!(
+            database.call_exec(&mut store, db, "SELECT id, visits FROM users ORDER BY id")??,
+            vec![
+                vec![RecordValue::Integer(1), RecordValue::Integer(2)],
+                vec![RecordValue::Integer(2), RecordValue::Integer(1)],
+            ]
+        );
+
+        let keys = ["missing".to_string()];
+        let result = database.call_upsert(&mut store, db, "users", &keys, &columns, &rows)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let short = vec![vec![RecordValue::Integer(3)]];
+        let result = database.call_upsert(
+            &mut store,
+            db,
+            "users",
+            &["id".to_string()],
+            &columns,
+            &short,
+        )?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kv_store() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let kv = bindings.component_wit_limbo_kv().store();
+        let kv_store = kv.call_constructor(&mut store, ":memory:")?;
+
+        assert_eq!(kv.call_get(&mut store, kv_store, "a")??, None);
+        kv.call_set(&mut store, kv_store, "user:1", b"alice")??;
+        kv.call_set(&mut store, kv_store, "user:2", b"bob")??;
+        kv.call_set(&mut store, kv_store, "session:1", b"x")??;
+        kv.call_set(&mut store, kv_store, "user:1", b"alice2")??;
+        assert_eq!(
+            kv.call_get(&mut store, kv_store, "user:1")??,
+            Some(b"alice2".to_vec())
+        );
+
+        assert_eq!(
+            kv.call_scan(&mut store, kv_store, "user:")??,
+            vec![
+                ("user:1".to_string(), b"alice2".to_vec()),
+                ("user:2".to_string(), b"bob".to_vec()),
+            ]
+        );
+        assert_eq!(kv.call_scan(&mut store, kv_store, "")??.len(), 3);
+
+        assert!(kv.call_delete(&mut store, kv_store, "user:2")??);
+        assert!(!kv.call_delete(&mut store, kv_store, "user:2")??);
+        assert_eq!(kv.call_get(&mut store, kv_store, "user:2")??, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_doc_store() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let docs = bindings.component_wit_limbo_docs().store();
+        let doc_store = docs.call_constructor(&mut store, ":memory:")?;
+
+        docs.call_put(
+            &mut store,
+            doc_store,
+            "todos",
+            "1",
+            r#"{"title": "a", "done": false, "owner": 7}"#,
+        )??;
+        docs.call_put(
+            &mut store,
+            doc_store,
+            "todos",
+            "2",
+            r#"{"title": "b", "done": true, "owner": 7}"#,
+        )??;
+        docs.call_put(
+            &mut store,
+            doc_store,
+            "todos",
+            "3",
+            r#"{"title": "c", "done": false, "owner": 8}"#,
+        )??;
+        docs.call_put(&mut store, doc_store, "notes", "1", r#"{"owner": 7}"#)??;
+        // replacing a document re-indexes it
+        docs.call_put(
+            &mut store,
+            doc_store,
+            "todos",
+            "2",
+            r#"{"title": "b", "done": false, "owner": 7}"#,
+        )??;
+
+        assert_eq!(
+            docs.call_get(&mut store, doc_store, "notes", "1")??,
+            Some(r#"{"owner": 7}"#.to_string())
+        );
+        assert_eq!(docs.call_get(&mut store, doc_store, "notes", "2")??, None);
+
+        let ids = |found: Vec<bindgen::exports::component::wit_limbo::docs::Document>| {
+            found.into_iter().map(|doc| doc.id).collect::<Vec<_>>()
+        };
+        let found = docs.call_find(
+            &mut store,
+            doc_store,
+            "todos",
+            r#"{"owner": 7, "done": false}"#,
+            10,
+        )??;
+        assert_eq!(ids(found), ["1", "2"]);
+        let found = docs.call_find(&mut store, doc_store, "todos", "{}", 2)??;
+        assert_eq!(ids(found), ["1", "2"]);
+
+        let result = docs.call_put(&mut store, doc_store, "todos", "4", "[1]")?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+        let result = docs.call_find(&mut store, doc_store, "todos", r#"{"tags": []}"#, 10)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_event_store() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let events = bindings.component_wit_limbo_events().store();
+        let event_store = events.call_constructor(&mut store, ":memory:")?;
+
+        assert!(events
+            .call_read(&mut store, event_store, "cart-1", 1, 10)??
+            .is_empty());
+        assert_eq!(
+            events.call_append(&mut store, event_store, "cart-1", "created", b"{}")??,
+            1
+        );
+        assert_eq!(
+            events.call_append(&mut store, event_store, "cart-2", "created", b"{}")??,
+            1
+        );
+        assert_eq!(
+            events.call_append(&mut store, event_store, "cart-1", "item-added", b"a")??,
+            2
+        );
+        assert_eq!(
+            events.call_append(&mut store, event_store, "cart-1", "item-added", b"b")??,
+            3
+        );
+
+        let read = events.call_read(&mut store, event_store, "cart-1", 2, 10)??;
+        assert_eq!(
+            read.iter()
+                .map(|event| (event.seq, event.type_.as_str(), event.payload.as_slice()))
+                .collect::<Vec<_>>(),
+            vec![(2, "item-added", &b"a"[..]), (3, "item-added", &b"b"[..])]
+        );
+        let read = events.call_read(&mut store, event_store, "cart-1", 1, 1)??;
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].type_, "created");
+        assert!(events
+            .call_read(&mut store, event_store, "cart-1", 4, 10)??
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sqlar() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let archives = bindings.component_wit_limbo_sqlar().archive();
+        let archive = archives.call_constructor(&mut store, ":memory:")?;
+
+        let epoch_secs = || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs() as i64)
+        };
+        let before = epoch_secs();
+        let page = b"<html>".repeat(100);
+        archives.call_add_file(&mut store, archive, "index.html", &page, 0o100644)??;
+        archives.call_add_file(&mut store, archive, "app.js", b"old", 0o100644)??;
+        archives.call_add_file(&mut store, archive, "app.js", b"new", 0o100755)??;
+
+        assert_eq!(
+            archives.call_extract(&mut store, archive, "index.html")??,
+            Some(page.clone())
+        );
+        assert_eq!(
+            archives.call_extract(&mut store, archive, "app.js")??,
+            Some(b"new".to_vec())
+        );
+        assert_eq!(
+            archives.call_extract(&mut store, archive, "missing")??,
+            None
+        );
+
+        let entries = archives.call_list(&mut store, archive)??;
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.name.as_str(), entry.mode, entry.size))
+                .collect::<Vec<_>>(),
+            vec![
+                ("app.js", 0o100755, 3),
+                ("index.html", 0o100644, page.len() as u64)
+            ]
+        );
+        // stamped in Unix time, which other sqlar readers show as a date
+        let after = epoch_secs();
+        for entry in &entries {
+            assert!((before..=after).contains(&entry.mtime), "{}", entry.mtime);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_finite_floats() -> wasmtime::Result<(), TestError> {
+        use bindgen::exports::component::wit_limbo::limbo::NonFiniteFloats;
+
+        let (mut store, bindings) = instantiate()?;
+        let database = bindings.component_wit_limbo_limbo().database();
+        let db = database.call_constructor(&mut store, ":memory:")?;
+        let sql = "SELECT 1e999, -1e999, 1.5";
+
+        let rows = database.call_exec(&mut store, db, sql)??;
+        assert!(matches!(rows[0][0], RecordValue::Float(f) if f == f64::INFINITY));
+        let json = database.call_query_json(&mut store, db, sql, "")??;
+        assert!(json.ends_with("[[null,null,1.5]]}"), "{}", json);
+
+        database.call_set_non_finite_floats(&mut store, db, NonFiniteFloats::Null)?;
+        assert_eq!(
+            database.call_exec(&mut store, db, sql)??,
+            vec![vec![
+                RecordValue::Null,
+                RecordValue::Null,
+                RecordValue::Float(1.5)
+            ]]
+        );
+
+        database.call_set_non_finite_floats(&mut store, db, NonFiniteFloats::Text)?;
+        let json = database.call_query_json(&mut store, db, sql, "")??;
+        assert!(
+            json.ends_with(r#"[["Infinity","-Infinity",1.5]]}"#),
+            "{}",
+            json
+        );
+
+        database.call_set_non_finite_floats(&mut store, db, NonFiniteFloats::Error)?;
+        let result = database.call_exec(&mut store, db, sql)?;
+        assert!(matches!(result, Err(DbError::Other(_))), "{:?}", result);
+        // finite results are untouched
+        assert_eq!(
+            database.call_exec(&mut store, db, "SELECT 1.5")??,
+            vec![vec![RecordValue::Float(1.5)]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_decl_types() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE t (a BIGINT, b VARCHAR(10), c DOUBLE, d BLOB, e DECIMAL(10, 2));",
+        )??;
+        let statement = limbo.database().call_prepare(
+            &mut store,
+            db,
+            "SELECT a, b, c, d, e, a + 1 AS f FROM t;",
+        )??;
+
+        let decl_types = limbo
+            .statement()
+            .call_column_decl_types(&mut store, statement)?;
+        assert_eq!(
+            decl_types,
+            [
+                Some("BIGINT".to_string()),
+                Some("VARCHAR(10)".to_string()),
+                Some("DOUBLE".to_string()),
+                Some("BLOB".to_string()),
+                Some("DECIMAL(10, 2)".to_string()),
+                None,
+            ]
+        );
+
+        let result_set = limbo
+            .statement()
+            .call_all_with_names(&mut store, statement)??;
+        assert_eq!(
+            result_set.affinities,
+            [
+                Affinity::Integer,
+                Affinity::Text,
+                Affinity::Real,
+                Affinity::Blob,
+                Affinity::Numeric,
+                Affinity::Blob,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_proto() -> wasmtime::Result<(), TestError> {
+        /// Just the columns of `QueryResponse` from proto/limbo.proto, the rows are skipped.
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct QueryResponse {
+            #[prost(string, repeated, tag = "1")]
+            columns: Vec<String>,
+        }
+
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        let statement =
+            limbo
+                .database()
+                .call_prepare(&mut store, db, "SELECT 1 AS id, 'Alice' AS name;")??;
+        let bytes = limbo.statement().call_all_proto(&mut store, statement)??;
+
+        let response = <QueryResponse as prost::Message>::decode(&bytes[..]).unwrap();
+        assert_eq!(response.columns, vec!["id", "name"]);
+        assert!(bytes.windows(5).any(|window| window == b"Alice"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_parquet() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+        let parquet = limbo
+            .call_capabilities(&mut store)?
+            .iter()
+            .any(|c| c == "statement.all-parquet");
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        let statement = limbo.database().call_prepare(
+            &mut store,
+            db,
+            "SELECT 1 AS id, 'Alice' AS name, 2.5 AS score;",
+        )??;
+        let result = limbo.statement().call_all_parquet(&mut store, statement)?;
+        if !parquet {
+            assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+            return Ok(());
+        }
+        let bytes = result?;
+        assert_eq!(&bytes[..4], b"PAR1");
+        assert_eq!(&bytes[bytes.len() - 4..], b"PAR1");
+        // the footer ends with the length of the metadata, which names the columns
+        let footer = bytes.len() - 8;
+        let len = u32::from_le_bytes(bytes[footer..footer + 4].try_into().unwrap()) as usize;
+        let metadata = &bytes[footer - len..footer];
+        for name in [&b"id"[..], b"name", b"score"] {
+            assert!(metadata.windows(name.len()).any(|window| window == name));
+        }
+        assert!(bytes.windows(5).any(|window| window == b"Alice"));
+
+        let statement =
+            limbo
+                .database()
+                .call_prepare(&mut store, db, "SELECT 1 UNION ALL SELECT 'text'")??;
+        let result = limbo.statement().call_all_parquet(&mut store, statement)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors_are_returned() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+
+        // malformed SQL is an error, not a trap
+        let result = limbo
+            .database()
+            .call_exec(&mut store, db, "SELEKT * FROM nowhere;")?;
+        assert!(matches!(result, Err(DbError::Parse(_))), "{:?}", result);
+
+        let statement = limbo
+            .database()
+            .call_prepare(&mut store, db, "SELECT ?;")??;
+        let result = limbo
+            .statement()
+            .call_bind(&mut store, statement, 0, &RecordValue::Null)?;
+        assert!(matches!(result, Err(DbError::Misuse(_))), "{:?}", result);
+
+        // and the instance is still usable afterwards
+        let rows = limbo.database().call_exec(&mut store, db, "SELECT 1;")??;
+        assert!(matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Integer(1)])));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exec_returning() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo.database().call_constructor(&mut store, ":memory:")?;
+        let rows = limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+        )??;
+        assert!(rows.is_empty());
+
+        let rows = limbo.database().call_exec(
+            &mut store,
+            db,
+            "INSERT INTO users (name) VALUES ('Alice') RETURNING id;",
+        )??;
+        assert!(matches!(&rows[..], [row] if matches!(&row[..], [RecordValue::Integer(1)])));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_kv_database() -> wasmtime::Result<(), TestError> {
+        let (mut store, bindings) = instantiate()?;
+        let limbo = bindings.component_wit_limbo_limbo();
+
+        let db = limbo
+            .database()
+            .call_constructor(&mut store, "kv:test.db")?;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+        )??;
+        limbo.database().call_exec(
+            &mut store,
+            db,
+            "INSERT INTO users (name) VALUES ('Alice');",
+        )??;
+
+        // pages went to the host's key-value store
+        assert!(store.data().kv.contains_key("test.db/size"));
+
+        let statement =
+            limbo
+                .database()
+                .call_prepare(&mut store, db, "SELECT name FROM users;")??;
+        let rows = limbo.statement().call_all(&mut store, statement)??;
+        assert!(matches!(&rows[0][..], [RecordValue::Text(name)] if name == "Alice"));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,189 @@
+//! The shell's dot commands, and printing query results.
+use wit_limbo_host::{Database, Row, Value};
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+pub enum Flow {
+    Continue,
+    Exit,
+}
+
+const HELP: &str = "\
+.dump ?TABLE?          Render the database (or TABLE) as SQL
+.exit                  Exit this program
+.help                  Show this message
+.import FILE TABLE     Import CSV data from FILE into TABLE
+.quit                  Exit this program
+.schema ?TABLE?        Show the CREATE statements
+.tables                List the tables";
+
+/// Run a dot command, like `.tables`.
+pub fn run(db: &mut Database, line: &str) -> Result<Flow> {
+    let args: Vec<&str> = line.split_whitespace().collect();
+    match args.as_slice() {
+        [".exit"] | [".quit"] => return Ok(Flow::Exit),
+        [".help"] => println!("{}", HELP),
+        [".tables"] => {
+            let rows = db.query(
+                "SELECT name FROM sqlite_schema WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+                &[],
+            )?;
+            let names: Vec<String> = rows.iter().map(|row| display(&row.values()[0])).collect();
+            println!("{}", names.join("  "));
+        }
+        [".schema"] => {
+            for sql in schema(db, None)? {
+                println!("{};", sql);
+            }
+        }
+        [".schema", table] => {
+            for sql in schema(db, Some(table))? {
+                println!("{};", sql);
+            }
+        }
+        [".import", file, table] => {
+            let count = import_csv(db, file, table)?;
+            println!("Imported {} rows into {}", count, table);
+        }
+        [".dump"] => dump(db, None)?,
+        [".dump", table] => dump(db, Some(table))?,
+        _ => return Err(format!("unknown command or invalid arguments: {}", line).into()),
+    }
+    Ok(Flow::Continue)
+}
+
+/// Run SQL and print any rows it returns, with a header.
+pub fn query(db: &mut Database, sql: &str) -> Result<()> {
+    let rows = db.query(sql, &[])?;
+    if let Some(first) = rows.first() {
+        println!("{}", first.columns().join(" | "));
+    }
+    for row in &rows {
+        println!("{}", format_row(row));
+    }
+    Ok(())
+}
+
+fn format_row(row: &Row) -> String {
+    row.values()
+        .iter()
+        .map(display)
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Text(s) | Value::TextDecimal(s) => s.clone(),
+        Value::Blob(b) => String::from_utf8_lossy(b).into_owned(),
+    }
+}
+
+/// Render a value as an SQL literal.
+fn literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => format!("{:?}", f),
+        Value::Text(s) => format!("'{}'", s.replace('\'', "''")),
+        Value::TextDecimal(s) => s.clone(),
+        Value::Blob(b) => {
+            let hex: String = b.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("X'{}'", hex)
+        }
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn schema(db: &mut Database, table: Option<&str>) -> Result<Vec<String>> {
+    let rows = match table {
+        Some(table) => db.query(
+            "SELECT sql FROM sqlite_schema WHERE tbl_name = ? AND sql IS NOT NULL",
+            &[Value::Text(table.to_string())],
+        )?,
+        None => db.query(
+            "SELECT sql FROM sqlite_schema WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%'",
+            &[],
+        )?,
+    };
+    Ok(rows.iter().map(|row| display(&row.values()[0])).collect())
+}
+
+fn dump(db: &mut Database, table: Option<&str>) -> Result<()> {
+    println!("BEGIN TRANSACTION;");
+    let tables = match table {
+        Some(table) => vec![table.to_string()],
+        None => db
+            .query(
+                "SELECT name FROM sqlite_schema WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+                &[],
+            )?
+            .iter()
+            .map(|row| display(&row.values()[0]))
+            .collect(),
+    };
+    for table in tables {
+        for sql in schema(db, Some(&table))? {
+            println!("{};", sql);
+        }
+        let rows = db.query(&format!("SELECT * FROM {}", quote_identifier(&table)), &[])?;
+        for row in rows {
+            let values: Vec<String> = row.values().iter().map(literal).collect();
+            println!(
+                "INSERT INTO {} VALUES({});",
+                quote_identifier(&table),
+                values.join(",")
+            );
+        }
+    }
+    println!("COMMIT;");
+    Ok(())
+}
+
+/// Insert every record of the CSV `file` into `table`, creating it from the header if needed.
+fn import_csv(db: &mut Database, file: &str, table: &str) -> Result<usize> {
+    let mut reader = csv::Reader::from_path(file)?;
+    let headers = reader.headers()?.clone();
+
+    let exists = !db
+        .query(
+            "SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = ?",
+            &[Value::Text(table.to_string())],
+        )?
+        .is_empty();
+    if !exists {
+        let columns: Vec<String> = headers
+            .iter()
+            .map(|h| format!("{} TEXT", quote_identifier(h)))
+            .collect();
+        db.exec(&format!(
+            "CREATE TABLE {} ({});",
+            quote_identifier(table),
+            columns.join(", ")
+        ))?;
+    }
+
+    let placeholders = vec!["?"; headers.len()].join(", ");
+    let sql = format!(
+        "INSERT INTO {} VALUES ({})",
+        quote_identifier(table),
+        placeholders
+    );
+
+    let mut count = 0;
+    for record in reader.records() {
+        let params: Vec<Value> = record?
+            .iter()
+            .map(|field| Value::Text(field.to_string()))
+            .collect();
+        db.query(&sql, &params)?;
+        count += 1;
+    }
+    Ok(count)
+}
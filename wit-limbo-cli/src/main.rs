@@ -0,0 +1,85 @@
+//! Interactive SQL shell for the wit-limbo component, and a reference host for the WIT.
+//!
+//! ```sh
+//! wit-limbo [--wasm path/to/wit_limbo.wasm] [database]
+//! ```
+//!
+//! Besides SQL, it understands the sqlite3-style dot commands listed by `.help`.
+mod commands;
+
+use std::path::PathBuf;
+
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use wasmtime::component::Component;
+use wit_limbo_host::Database;
+
+const DEFAULT_WASM: &str = "target/wasm32-unknown-unknown/release/wit_limbo.wasm";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut wasm = PathBuf::from(DEFAULT_WASM);
+    let mut path = ":memory:".to_string();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wasm" => wasm = args.next().ok_or("--wasm needs a path")?.into(),
+            "-h" | "--help" => {
+                println!("usage: wit-limbo [--wasm path/to/wit_limbo.wasm] [database]");
+                return Ok(());
+            }
+            _ => path = arg,
+        }
+    }
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, &wasm)?;
+    let mut db = Database::open(&engine, &component, &path)?;
+
+    println!("wit-limbo shell, connected to {}", path);
+    println!("Enter \".help\" for usage hints.");
+
+    let mut editor = DefaultEditor::new()?;
+    // SQL may span lines, so gather input until the statement is terminated
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() {
+            "limbo> "
+        } else {
+            "   ...> "
+        };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                buffer.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+
+        if buffer.is_empty() && line.trim_start().starts_with('.') {
+            editor.add_history_entry(line.as_str())?;
+            match commands::run(&mut db, line.trim()) {
+                Ok(commands::Flow::Continue) => {}
+                Ok(commands::Flow::Exit) => break,
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            continue;
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if !buffer.trim_end().ends_with(';') {
+            continue;
+        }
+
+        let sql = std::mem::take(&mut buffer);
+        editor.add_history_entry(sql.trim())?;
+        if let Err(e) = commands::query(&mut db, &sql) {
+            eprintln!("Error: {}", e);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+/// Run the shell on a `:memory:` database with `input` as what is typed, returning what it
+/// prints.
+fn shell(input: &str) -> String {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wit-limbo"))
+        .arg("--wasm")
+        .arg(wasm_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("Error"), "{}", stderr);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The SQL a `.dump` printed, from BEGIN to COMMIT.
+fn dumped(output: &str) -> &str {
+    let start = output.find("BEGIN TRANSACTION;").expect(output);
+    let end = output[start..].find("COMMIT;").expect(output) + start + "COMMIT;".len();
+    &output[start..end]
+}
+
+#[test]
+fn test_dump_and_import_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let csv = dir.path().join("people.csv");
+    std::fs::write(&csv, "name,age\nAlice,30\n\"O'Brien, Pat\",41\n").unwrap();
+
+    let output = shell(&format!(
+        "CREATE TABLE items (id INTEGER PRIMARY KEY, label TEXT, price REAL, data BLOB);\n\
+         INSERT INTO items VALUES (1, 'it''s', 1.5, X'00FF'), (2, NULL, NULL, NULL);\n\
+         .import {} people\n\
+         .dump\n",
+        csv.display()
+    ));
+    assert!(output.contains("Imported 2 rows into people"), "{}", output);
+    let dump = dumped(&output);
+    for line in [
+        "INSERT INTO \"items\" VALUES(1,'it''s',1.5,X'00FF');",
+        "INSERT INTO \"items\" VALUES(2,NULL,NULL,NULL);",
+        "INSERT INTO \"people\" VALUES('Alice','30');",
+        "INSERT INTO \"people\" VALUES('O''Brien, Pat','41');",
+    ] {
+        assert!(
+            dump.lines().any(|dumped| dumped == line),
+            "{}\n{}",
+            line,
+            dump
+        );
+    }
+
+    // loading the dump into a new database and dumping that gives the same SQL back
+    let reloaded = shell(&format!("{}\n.dump\n", dump));
+    assert_eq!(dumped(&reloaded), dump);
+
+    // `.dump TABLE` only dumps that table
+    let output = shell(&format!("{}\n.dump people\n", dump));
+    let people = dumped(&output);
+    assert!(people.contains("CREATE TABLE \"people\""), "{}", people);
+    assert!(!people.contains("items"), "{}", people);
+}
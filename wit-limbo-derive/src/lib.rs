@@ -0,0 +1,66 @@
+//! `#[derive(FromRow)]` for [wit-limbo-host](https://docs.rs/wit-limbo-host), enable it with
+//! the `derive` feature there rather than depending on this crate directly.
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Implement `FromRow` for a struct with named fields, reading each field from the column of
+/// the same name. Use `#[limbo(rename = "column")]` when the column is named differently.
+#[proc_macro_derive(FromRow, attributes(limbo))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FromRow needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FromRow can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut reads = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let mut column = ident.to_string();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("limbo"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    column = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"column\"`"))
+                }
+            })?;
+        }
+        reads.push(quote! { #ident: row.try_get_by_name(#column)? });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::wit_limbo_host::FromRow for #name #ty_generics #where_clause {
+            fn from_row(row: &::wit_limbo_host::Row) -> ::std::result::Result<Self, ::wit_limbo_host::Error> {
+                Ok(Self { #(#reads),* })
+            }
+        }
+    })
+}
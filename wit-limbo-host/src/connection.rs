@@ -0,0 +1,61 @@
+//! [Connection], the operations every backend supports, so code written against it runs
+//! unchanged in the sandboxed component ([Database]) or, with the `native` feature,
+//! on limbo_core in-process ([NativeDatabase](crate::native::NativeDatabase)).
+//!
+//! ```ignore
+//! fn count_users(db: &mut impl Connection) -> Result<i64, Error> {
+//!     db.query_row("SELECT count(*) FROM users", params![], |row| row.try_get(0))
+//! }
+//! ```
+use crate::{Database, Error, FromRow, Row, Value};
+
+pub trait Connection {
+    /// Execute a SQL statement, discarding any rows it produces.
+    fn exec(&mut self, sql: &str) -> Result<(), Error>;
+
+    /// Run `sql` with `params` bound in order, and collect the resulting rows.
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>, Error>;
+
+    /// Execute a SQL statement with `params` bound in order, discarding any rows it produces.
+    fn execute(&mut self, sql: &str, params: &[Value]) -> Result<(), Error> {
+        self.query(sql, params)?;
+        Ok(())
+    }
+
+    /// Map each row of the result with `f`.
+    fn query_map<T, F>(&mut self, sql: &str, params: &[Value], f: F) -> Result<Vec<T>, Error>
+    where
+        F: FnMut(&Row) -> Result<T, Error>,
+    {
+        self.query(sql, params)?.iter().map(f).collect()
+    }
+
+    /// Map the first row of the result with `f`, failing with [Error::QueryReturnedNoRows]
+    /// if there is none.
+    fn query_row<T, F>(&mut self, sql: &str, params: &[Value], f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Row) -> Result<T, Error>,
+    {
+        let rows = self.query(sql, params)?;
+        f(rows.first().ok_or(Error::QueryReturnedNoRows)?)
+    }
+
+    /// Convert each row of the result into a `T`, see [FromRow].
+    fn query_as<T: FromRow>(&mut self, sql: &str, params: &[Value]) -> Result<Vec<T>, Error> {
+        self.query_map(sql, params, T::from_row)
+    }
+}
+
+impl Connection for Database {
+    fn exec(&mut self, sql: &str) -> Result<(), Error> {
+        Database::exec(self, sql)
+    }
+
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>, Error> {
+        Database::query(self, sql, params)
+    }
+
+    fn execute(&mut self, sql: &str, params: &[Value]) -> Result<(), Error> {
+        Database::execute(self, sql, params)
+    }
+}
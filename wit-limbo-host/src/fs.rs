@@ -0,0 +1,90 @@
+//! `host-fs` backed by the local file system, for databases that aren't `:memory:`, and
+//! `host-io` reading the same files.
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+/// Files opened by the component, indexed by file descriptor.
+#[derive(Default)]
+pub struct HostFs {
+    /// Directory that database paths are relative to, the working directory if none.
+    root: Option<PathBuf>,
+    files: Vec<File>,
+    /// The results of the reads submitted through `host-io` and not yet polled, by id.
+    done: HashMap<u64, Result<Vec<u8>, String>>,
+    last_id: u64,
+}
+
+impl HostFs {
+    /// Resolve database paths relative to `root`.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn open(&mut self, path: &str, create: bool) -> Result<u32, String> {
+        let path = match &self.root {
+            Some(root) => root.join(path),
+            None => PathBuf::from(path),
+        };
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(create)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.files.push(file);
+        Ok(self.files.len() as u32 - 1)
+    }
+
+    pub fn read(&mut self, fd: u32, offset: u64, len: u32) -> Result<Vec<u8>, String> {
+        let file = self.file(fd)?;
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| e.to_string())?;
+        let mut buf = Vec::with_capacity(len as usize);
+        file.take(len as u64)
+            .read_to_end(&mut buf)
+            .map_err(|e| e.to_string())?;
+        Ok(buf)
+    }
+
+    /// Start a read for `host-io.submit-io`. Files are read synchronously, so it is done
+    /// before this returns, and [HostFs::poll] returns it straight away.
+    pub fn submit(&mut self, fd: u32, offset: u64, len: u32) -> u64 {
+        let result = self.read(fd, offset, len);
+        self.last_id += 1;
+        self.done.insert(self.last_id, result);
+        self.last_id
+    }
+
+    /// The result of the read `id`, for `host-io.poll-io`.
+    pub fn poll(&mut self, id: u64) -> Option<Result<Vec<u8>, String>> {
+        self.done.remove(&id)
+    }
+
+    pub fn write(&mut self, fd: u32, offset: u64, data: &[u8]) -> Result<(), String> {
+        let file = self.file(fd)?;
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| e.to_string())?;
+        file.write_all(data).map_err(|e| e.to_string())
+    }
+
+    pub fn sync(&mut self, fd: u32) -> Result<(), String> {
+        self.file(fd)?.sync_data().map_err(|e| e.to_string())
+    }
+
+    pub fn size(&mut self, fd: u32) -> Result<u64, String> {
+        let metadata = self.file(fd)?.metadata().map_err(|e| e.to_string())?;
+        Ok(metadata.len())
+    }
+
+    fn file(&mut self, fd: u32) -> Result<&mut File, String> {
+        self.files
+            .get_mut(fd as usize)
+            .ok_or_else(|| format!("bad file descriptor {}", fd))
+    }
+}
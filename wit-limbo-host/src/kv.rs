@@ -0,0 +1,36 @@
+//! `host-kv` kept in memory, for databases opened with a `kv:` path.
+use std::collections::HashMap;
+
+/// Key-value storage for the component, shared by every `kv:` database in the instance.
+///
+/// This keeps everything in memory; hosts that need durability can persist [HostKv::entries]
+/// and restore them with [HostKv::from_entries].
+#[derive(Default, Clone)]
+pub struct HostKv {
+    entries: HashMap<String, Vec<u8>>,
+}
+
+impl HostKv {
+    /// Restore previously saved entries.
+    pub fn from_entries(entries: HashMap<String, Vec<u8>>) -> Self {
+        Self { entries }
+    }
+
+    /// Every entry written so far.
+    pub fn entries(&self) -> &HashMap<String, Vec<u8>> {
+        &self.entries
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.entries.get(key).cloned())
+    }
+
+    pub fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), String> {
+        self.entries.insert(key, value);
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
@@ -0,0 +1,832 @@
+//! Runtime agnostic host helper built on [wasm_component_layer].
+//!
+//! [linker] defines the `host` imports for you and [LimboInstance] wraps the exported
+//! resources, converting to and from the same [Value] and [Row] types as the wasmtime
+//! [Database](crate::Database), so there's no `Value::Variant` plumbing left to hand-roll.
+//!
+//! ```no_run
+//! use wit_limbo_host::{layer_host::LimboInstance, Value};
+//!
+//! let engine = wasm_component_layer::Engine::new(wasmi_runtime_layer::Engine::default());
+//! let bytes = std::fs::read("wit_limbo.wasm").unwrap();
+//!
+//! let mut instance = LimboInstance::new(&engine, &bytes)?;
+//! let db = instance.open(":memory:")?;
+//! instance.exec(&db, "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);")?;
+//! let rows = instance.query(&db, "SELECT * FROM users WHERE id = ?", &[Value::Integer(1)])?;
+//! # Ok::<(), wit_limbo_host::Error>(())
+//! ```
+use std::sync::{Arc, Mutex};
+
+use wasm_component_layer::{
+    AsContextMut, Component, Engine, Flags, Func, FuncType, Instance, Linker, List, ListType,
+    OptionType, OptionValue, Record, RecordType, ResourceOwn, ResultType, ResultValue, Store,
+    Value as LayerValue, ValueType, Variant,
+};
+use wasm_runtime_layer::backend::WasmEngine;
+
+use crate::{DbError, Error, HostFs, HostKv, ParseError, Row, SqlPosition, Value};
+
+const HOST_RANDOM_INTERFACE: &str = "component:wit-limbo/host-random@0.2.0";
+const HOST_LOG_INTERFACE: &str = "component:wit-limbo/host-log@0.2.0";
+const HOST_CLOCK_INTERFACE: &str = "component:wit-limbo/host-clock@0.2.0";
+const HOST_TRACE_INTERFACE: &str = "component:wit-limbo/host-trace@0.2.0";
+const HOST_SUBSCRIPTIONS_INTERFACE: &str = "component:wit-limbo/host-subscriptions@0.2.0";
+const HOST_PROGRESS_INTERFACE: &str = "component:wit-limbo/host-progress@0.2.0";
+const HOST_LOCALE_INTERFACE: &str = "component:wit-limbo/host-locale@0.2.0";
+const HOST_FS_INTERFACE: &str = "component:wit-limbo/host-fs@0.2.0";
+const HOST_IO_INTERFACE: &str = "component:wit-limbo/host-io@0.2.0";
+const HOST_KV_INTERFACE: &str = "component:wit-limbo/host-kv@0.2.0";
+const LIMBO_INTERFACE: &str = "component:wit-limbo/limbo@0.2.0";
+
+/// Create a [Linker] with all of the component's imports already defined, `host-fs` and
+/// `host-io` backed by `fs`, and `host-kv` kept in memory.
+pub fn linker<T, E: WasmEngine>(store: &mut Store<T, E>, fs: HostFs) -> Result<Linker, Error> {
+    let mut linker = Linker::default();
+
+    let host_random = linker.define_instance(HOST_RANDOM_INTERFACE.try_into()?)?;
+
+    host_random.define_func(
+        "random-byte",
+        Func::new(
+            &mut *store,
+            FuncType::new([], [ValueType::U8]),
+            move |_store, _params, results| {
+                results[0] = LayerValue::U8(rand::random::<u8>());
+                Ok(())
+            },
+        ),
+    )?;
+
+    let host_log = linker.define_instance(HOST_LOG_INTERFACE.try_into()?)?;
+
+    host_log.define_func(
+        "log",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::String], []),
+            move |_store, params, _results| {
+                if let LayerValue::String(s) = &params[0] {
+                    eprintln!("{}", s);
+                }
+                Ok(())
+            },
+        ),
+    )?;
+
+    let host_clock = linker.define_instance(HOST_CLOCK_INTERFACE.try_into()?)?;
+
+    host_clock.define_func(
+        "sleep",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::U32], []),
+            move |_store, params, _results| {
+                if let LayerValue::U32(ms) = params[0] {
+                    std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+                }
+                Ok(())
+            },
+        ),
+    )?;
+
+    host_clock.define_func(
+        "now",
+        Func::new(
+            &mut *store,
+            FuncType::new([], [ValueType::U64]),
+            move |_store, _params, results| {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_micros() as u64);
+                results[0] = LayerValue::U64(now);
+                Ok(())
+            },
+        ),
+    )?;
+
+    // spans aren't traced
+    let host_trace = linker.define_instance(HOST_TRACE_INTERFACE.try_into()?)?;
+
+    host_trace.define_func(
+        "span-start",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::String], [ValueType::U64]),
+            move |_store, _params, results| {
+                results[0] = LayerValue::U64(0);
+                Ok(())
+            },
+        ),
+    )?;
+
+    host_trace.define_func(
+        "span-end",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::U64], []),
+            move |_store, _params, _results| Ok(()),
+        ),
+    )?;
+
+    // nothing subscribes through [LimboInstance]
+    let host_subscriptions = linker.define_instance(HOST_SUBSCRIPTIONS_INTERFACE.try_into()?)?;
+
+    host_subscriptions.define_func(
+        "on-query-changed",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::U64], []),
+            move |_store, _params, _results| Ok(()),
+        ),
+    )?;
+
+    // no long call is run through [LimboInstance]
+    let host_progress = linker.define_instance(HOST_PROGRESS_INTERFACE.try_into()?)?;
+
+    host_progress.define_func(
+        "on-progress",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::String, ValueType::U64, ValueType::U64], []),
+            move |_store, _params, _results| Ok(()),
+        ),
+    )?;
+
+    // no locale data is linked in, so text is folded and sorted by Unicode alone
+    let host_locale = linker.define_instance(HOST_LOCALE_INTERFACE.try_into()?)?;
+
+    host_locale.define_func(
+        "to-lower",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::String], [ValueType::String]),
+            move |_store, params, results| {
+                if let LayerValue::String(s) = &params[0] {
+                    results[0] = LayerValue::String(s.to_lowercase().into());
+                }
+                Ok(())
+            },
+        ),
+    )?;
+
+    host_locale.define_func(
+        "collate",
+        Func::new(
+            &mut *store,
+            FuncType::new(
+                [ValueType::String, ValueType::String, ValueType::String],
+                [ValueType::S8],
+            ),
+            move |_store, params, results| {
+                if let (LayerValue::String(a), LayerValue::String(b)) = (&params[1], &params[2]) {
+                    results[0] = LayerValue::S8(a.to_lowercase().cmp(&b.to_lowercase()) as i8);
+                }
+                Ok(())
+            },
+        ),
+    )?;
+
+    define_host_fs(&mut linker, store, fs)?;
+    define_host_kv(&mut linker, store, HostKv::default())?;
+
+    Ok(linker)
+}
+
+/// Lower a `result<T, string>` returned by one of the `host-fs` or `host-kv` functions.
+fn storage_result(
+    ok: Option<ValueType>,
+    result: Result<Option<LayerValue>, String>,
+) -> Result<LayerValue, Error> {
+    let ty = ResultType::new(ok, Some(ValueType::String));
+    let value = result.map_err(|e| Some(LayerValue::String(e.into())));
+    Ok(LayerValue::Result(ResultValue::new(ty, value)?))
+}
+
+fn define_host_fs<T, E: WasmEngine>(
+    linker: &mut Linker,
+    store: &mut Store<T, E>,
+    fs: HostFs,
+) -> Result<(), Error> {
+    let fs = Arc::new(Mutex::new(fs));
+    let host_fs = linker.define_instance(HOST_FS_INTERFACE.try_into()?)?;
+    let result_ty =
+        |ok: Option<ValueType>| ValueType::Result(ResultType::new(ok, Some(ValueType::String)));
+
+    let open_fs = fs.clone();
+    host_fs.define_func(
+        "open",
+        Func::new(
+            &mut *store,
+            FuncType::new(
+                [ValueType::String, ValueType::Bool],
+                [result_ty(Some(ValueType::U32))],
+            ),
+            move |_store, params, results| {
+                let (LayerValue::String(path), LayerValue::Bool(create)) = (&params[0], &params[1])
+                else {
+                    anyhow::bail!("host-fs.open: unexpected params {:?}", params);
+                };
+                let fd = open_fs.lock().unwrap().open(path, *create);
+                results[0] =
+                    storage_result(Some(ValueType::U32), fd.map(|fd| Some(LayerValue::U32(fd))))?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    let read_fs = fs.clone();
+    host_fs.define_func(
+        "read",
+        Func::new(
+            &mut *store,
+            FuncType::new(
+                [ValueType::U32, ValueType::U64, ValueType::U32],
+                [result_ty(Some(ValueType::List(ListType::new(
+                    ValueType::U8,
+                ))))],
+            ),
+            move |_store, params, results| {
+                let (LayerValue::U32(fd), LayerValue::U64(offset), LayerValue::U32(len)) =
+                    (&params[0], &params[1], &params[2])
+                else {
+                    anyhow::bail!("host-fs.read: unexpected params {:?}", params);
+                };
+                let data = read_fs.lock().unwrap().read(*fd, *offset, *len);
+                let data = match data {
+                    Ok(data) => Ok(Some(LayerValue::List(List::new(
+                        ListType::new(ValueType::U8),
+                        data.into_iter().map(LayerValue::U8),
+                    )?))),
+                    Err(e) => Err(e),
+                };
+                results[0] =
+                    storage_result(Some(ValueType::List(ListType::new(ValueType::U8))), data)?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    let write_fs = fs.clone();
+    host_fs.define_func(
+        "write",
+        Func::new(
+            &mut *store,
+            FuncType::new(
+                [
+                    ValueType::U32,
+                    ValueType::U64,
+                    ValueType::List(ListType::new(ValueType::U8)),
+                ],
+                [result_ty(None)],
+            ),
+            move |_store, params, results| {
+                let (LayerValue::U32(fd), LayerValue::U64(offset), LayerValue::List(data)) =
+                    (&params[0], &params[1], &params[2])
+                else {
+                    anyhow::bail!("host-fs.write: unexpected params {:?}", params);
+                };
+                let data = data
+                    .iter()
+                    .map(|byte| match byte {
+                        LayerValue::U8(byte) => Ok(byte),
+                        other => Err(anyhow::anyhow!("expected u8, found {:?}", other)),
+                    })
+                    .collect::<anyhow::Result<Vec<u8>>>()?;
+                let written = write_fs.lock().unwrap().write(*fd, *offset, &data);
+                results[0] = storage_result(None, written.map(|_| None))?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    let sync_fs = fs.clone();
+    host_fs.define_func(
+        "sync",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::U32], [result_ty(None)]),
+            move |_store, params, results| {
+                let LayerValue::U32(fd) = &params[0] else {
+                    anyhow::bail!("host-fs.sync: unexpected params {:?}", params);
+                };
+                let synced = sync_fs.lock().unwrap().sync(*fd);
+                results[0] = storage_result(None, synced.map(|_| None))?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    let size_fs = fs.clone();
+    host_fs.define_func(
+        "size",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::U32], [result_ty(Some(ValueType::U64))]),
+            move |_store, params, results| {
+                let LayerValue::U32(fd) = &params[0] else {
+                    anyhow::bail!("host-fs.size: unexpected params {:?}", params);
+                };
+                let size = size_fs.lock().unwrap().size(*fd);
+                results[0] = storage_result(
+                    Some(ValueType::U64),
+                    size.map(|size| Some(LayerValue::U64(size))),
+                )?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    define_host_io(linker, store, fs)
+}
+
+/// Define `host-io`, reading the files `fs` has open.
+fn define_host_io<T, E: WasmEngine>(
+    linker: &mut Linker,
+    store: &mut Store<T, E>,
+    fs: Arc<Mutex<HostFs>>,
+) -> Result<(), Error> {
+    let host_io = linker.define_instance(HOST_IO_INTERFACE.try_into()?)?;
+    let request_ty = RecordType::new(
+        None,
+        [
+            ("fd", ValueType::U32),
+            ("offset", ValueType::U64),
+            ("len", ValueType::U32),
+        ],
+    )?;
+    let bytes_ty = ValueType::List(ListType::new(ValueType::U8));
+    let result_ty = ResultType::new(Some(bytes_ty), Some(ValueType::String));
+    let option_ty = OptionType::new(ValueType::Result(result_ty.clone()));
+
+    let submit_fs = fs.clone();
+    host_io.define_func(
+        "submit-io",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::Record(request_ty)], [ValueType::U64]),
+            move |_store, params, results| {
+                let LayerValue::Record(request) = &params[0] else {
+                    anyhow::bail!("host-io.submit-io: unexpected params {:?}", params);
+                };
+                let (
+                    Some(LayerValue::U32(fd)),
+                    Some(LayerValue::U64(offset)),
+                    Some(LayerValue::U32(len)),
+                ) = (
+                    request.field("fd"),
+                    request.field("offset"),
+                    request.field("len"),
+                )
+                else {
+                    anyhow::bail!("host-io.submit-io: unexpected request {:?}", request);
+                };
+                let id = submit_fs.lock().unwrap().submit(fd, offset, len);
+                results[0] = LayerValue::U64(id);
+                Ok(())
+            },
+        ),
+    )?;
+
+    host_io.define_func(
+        "poll-io",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::U64], [ValueType::Option(option_ty.clone())]),
+            move |_store, params, results| {
+                let LayerValue::U64(id) = &params[0] else {
+                    anyhow::bail!("host-io.poll-io: unexpected params {:?}", params);
+                };
+                let result = match fs.lock().unwrap().poll(*id) {
+                    Some(result) => {
+                        let result = match result {
+                            Ok(data) => Ok(Some(LayerValue::List(List::new(
+                                ListType::new(ValueType::U8),
+                                data.into_iter().map(LayerValue::U8),
+                            )?))),
+                            Err(e) => Err(Some(LayerValue::String(e.into()))),
+                        };
+                        Some(LayerValue::Result(ResultValue::new(
+                            result_ty.clone(),
+                            result,
+                        )?))
+                    }
+                    None => None,
+                };
+                results[0] = LayerValue::Option(OptionValue::new(option_ty.clone(), result)?);
+                Ok(())
+            },
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// A `database` resource owned by a [LimboInstance].
+pub struct LayerDatabase(ResourceOwn);
+
+/// An instantiated component with typed access to its `limbo` exports.
+pub struct LimboInstance<E: WasmEngine> {
+    store: Store<(), E>,
+    instance: Instance,
+}
+
+impl<E: WasmEngine> LimboInstance<E> {
+    /// Parse and instantiate the component `bytes` with the `host` imports linked in,
+    /// and database files opened relative to the working directory.
+    pub fn new(engine: &Engine<E>, bytes: &[u8]) -> Result<Self, Error> {
+        Self::new_with(engine, bytes, HostFs::default())
+    }
+
+    /// Like [LimboInstance::new], with database files opened through `fs`.
+    pub fn new_with(engine: &Engine<E>, bytes: &[u8], fs: HostFs) -> Result<Self, Error> {
+        let mut store = Store::new(engine, ());
+        let component = Component::new(engine, bytes)?;
+        let instance = linker(&mut store, fs)?.instantiate(&mut store, &component)?;
+        Ok(Self { store, instance })
+    }
+
+    /// Open the database at `path`, creating it if it doesn't exist.
+    pub fn open(&mut self, path: &str) -> Result<LayerDatabase, Error> {
+        // open-with rather than the constructor, which returns a database that failed to
+        // open closed
+        let open_with = self.func("[static]database.open-with")?;
+        let flags = match open_with.ty().params().get(1) {
+            Some(ValueType::Flags(ty)) => {
+                let mut flags = Flags::new(ty.clone());
+                flags.set("create", true);
+                LayerValue::Flags(flags)
+            }
+            other => return Err(Error::Unexpected(format!("open-flags: {:?}", other))),
+        };
+        let mut results = [LayerValue::Bool(false)];
+        open_with.call(
+            &mut self.store,
+            &[LayerValue::String(path.into()), flags],
+            &mut results,
+        )?;
+        match ok(&results[0])? {
+            Some(LayerValue::Own(resource)) => Ok(LayerDatabase(resource)),
+            Some(other) => Err(unexpected("database resource", &other)),
+            None => Err(Error::Unexpected("open-with returned no database".into())),
+        }
+    }
+
+    /// Execute a SQL statement, discarding any rows it produces.
+    pub fn exec(&mut self, db: &LayerDatabase, sql: &str) -> Result<(), Error> {
+        let db = db.0.borrow(self.store.as_context_mut())?;
+        let mut results = [LayerValue::Bool(false)];
+        self.func("[method]database.exec")?.call(
+            &mut self.store,
+            &[LayerValue::Borrow(db), LayerValue::String(sql.into())],
+            &mut results,
+        )?;
+        ok(&results[0])?;
+        Ok(())
+    }
+
+    /// Checkpoint the WAL, release the connection and drop `db`.
+    pub fn close(&mut self, db: LayerDatabase) -> Result<(), Error> {
+        let borrowed_db = db.0.borrow(self.store.as_context_mut())?;
+        let mut results = [LayerValue::Bool(false)];
+        let result = self
+            .func("[method]database.close")?
+            .call(
+                &mut self.store,
+                &[LayerValue::Borrow(borrowed_db)],
+                &mut results,
+            )
+            .map_err(Error::from)
+            .and_then(|()| ok(&results[0]));
+        db.0.drop(&mut self.store)?;
+        result.map(|_| ())
+    }
+
+    /// Run `sql` with `params` bound in order, and collect the resulting rows.
+    pub fn query(
+        &mut self,
+        db: &LayerDatabase,
+        sql: &str,
+        params: &[Value],
+    ) -> Result<Vec<Row>, Error> {
+        let borrowed_db = db.0.borrow(self.store.as_context_mut())?;
+        let mut results = [LayerValue::Bool(false)];
+        self.func("[method]database.prepare")?.call(
+            &mut self.store,
+            &[
+                LayerValue::Borrow(borrowed_db),
+                LayerValue::String(sql.into()),
+            ],
+            &mut results,
+        )?;
+        let statement = match ok(&results[0])? {
+            Some(LayerValue::Own(resource)) => resource,
+            other => {
+                return Err(Error::Unexpected(format!(
+                    "statement resource: {:?}",
+                    other
+                )))
+            }
+        };
+
+        let result = self.bind_and_fetch(&statement, params);
+        statement.drop(&mut self.store)?;
+        result
+    }
+
+    /// Bind `params` to `statement` in order, and fetch all of its rows.
+    fn bind_and_fetch(
+        &mut self,
+        statement: &ResourceOwn,
+        params: &[Value],
+    ) -> Result<Vec<Row>, Error> {
+        // take the record-value type from the component, rather than hardcoding it here
+        let bind = self.func("[method]statement.bind")?;
+        let record_value_ty = match bind.ty().params().get(2) {
+            Some(ValueType::Variant(ty)) => ty.clone(),
+            _ => return Err(Error::Unexpected("statement.bind signature".to_string())),
+        };
+
+        for (i, param) in params.iter().enumerate() {
+            let (case, payload) = match param {
+                Value::Null => (0, None),
+                Value::Integer(i) => (1, Some(LayerValue::S64(*i))),
+                Value::Float(f) => (2, Some(LayerValue::F64(*f))),
+                Value::Text(s) => (3, Some(LayerValue::String(s.as_str().into()))),
+                Value::Blob(b) => (
+                    4,
+                    Some(LayerValue::List(List::new(
+                        ListType::new(ValueType::U8),
+                        b.iter().copied().map(LayerValue::U8),
+                    )?)),
+                ),
+                Value::TextDecimal(s) => (5, Some(LayerValue::String(s.as_str().into()))),
+            };
+            let borrowed_stmt = statement.borrow(self.store.as_context_mut())?;
+            let mut results = [LayerValue::Bool(false)];
+            bind.call(
+                &mut self.store,
+                &[
+                    LayerValue::Borrow(borrowed_stmt),
+                    LayerValue::U32(i as u32 + 1),
+                    LayerValue::Variant(Variant::new(record_value_ty.clone(), case, payload)?),
+                ],
+                &mut results,
+            )?;
+            ok(&results[0])?;
+        }
+
+        let borrowed_stmt = statement.borrow(self.store.as_context_mut())?;
+        let mut results = [LayerValue::Bool(false)];
+        self.func("[method]statement.all-with-names")?.call(
+            &mut self.store,
+            &[LayerValue::Borrow(borrowed_stmt)],
+            &mut results,
+        )?;
+
+        let record = match ok(&results[0])? {
+            Some(LayerValue::Record(record)) => record,
+            other => return Err(Error::Unexpected(format!("result-set: {:?}", other))),
+        };
+
+        let columns: Arc<[String]> = match record.field("columns") {
+            Some(LayerValue::List(list)) => list
+                .iter()
+                .map(|column| match column {
+                    LayerValue::String(s) => Ok(s.to_string()),
+                    other => Err(unexpected("column name", &other)),
+                })
+                .collect::<Result<_, _>>()?,
+            _ => return Err(Error::Unexpected("result-set.columns".to_string())),
+        };
+
+        let rows = match record.field("rows") {
+            Some(LayerValue::List(rows)) => rows,
+            _ => return Err(Error::Unexpected("result-set.rows".to_string())),
+        };
+
+        rows.iter()
+            .map(|row| {
+                let values = match row {
+                    LayerValue::List(values) => values
+                        .iter()
+                        .map(|value| to_value(&value))
+                        .collect::<Result<_, _>>()?,
+                    other => return Err(unexpected("row", &other)),
+                };
+                Ok(Row {
+                    columns: columns.clone(),
+                    values,
+                })
+            })
+            .collect()
+    }
+
+    fn func(&self, name: &str) -> Result<Func, Error> {
+        self.instance
+            .exports()
+            .instance(&LIMBO_INTERFACE.try_into()?)
+            .and_then(|interface| interface.func(name))
+            .ok_or_else(|| Error::Unexpected(format!("missing export {}", name)))
+    }
+}
+
+/// Unwrap a lifted `result<T, db-error>`, turning the error case into [Error::Db].
+fn ok(value: &LayerValue) -> Result<Option<LayerValue>, Error> {
+    let result = match value {
+        LayerValue::Result(result) => result,
+        other => return Err(unexpected("result", other)),
+    };
+    match &**result {
+        Ok(value) => Ok(value.clone()),
+        Err(Some(LayerValue::Variant(variant))) => {
+            let case = variant.ty().cases()[variant.discriminant()].name();
+            let message = match variant.value() {
+                Some(LayerValue::String(message)) => message.to_string(),
+                Some(LayerValue::Record(error)) if case == "parse" => {
+                    return Err(Error::Db(DbError::Parse(parse_error(&error)?)));
+                }
+                other => return Err(Error::Unexpected(format!("db-error: {:?}", other))),
+            };
+            Err(Error::Db(match case {
+                "corrupt" => DbError::Corrupt(message),
+                "io" => DbError::Io(message),
+                "misuse" => DbError::Misuse(message),
+                "busy" => DbError::Busy(message),
+                "closed" => DbError::Closed(message),
+                "interrupted" => DbError::Interrupted(message),
+                "out-of-memory" => DbError::OutOfMemory(message),
+                "limit-exceeded" => DbError::LimitExceeded(message),
+                "cannot-open" => DbError::CannotOpen(message),
+                _ => DbError::Other(message),
+            }))
+        }
+        Err(other) => Err(Error::Unexpected(format!("db-error: {:?}", other))),
+    }
+}
+
+/// Convert a lifted `parse-error` into a [ParseError].
+fn parse_error(record: &Record) -> Result<ParseError, Error> {
+    let message = match record.field("message") {
+        Some(LayerValue::String(message)) => message.to_string(),
+        other => {
+            return Err(Error::Unexpected(format!(
+                "parse-error.message: {:?}",
+                other
+            )))
+        }
+    };
+    let position = match record.field("position") {
+        Some(LayerValue::Option(position)) => match &*position {
+            Some(LayerValue::Record(position)) => {
+                let (
+                    Some(LayerValue::U32(line)),
+                    Some(LayerValue::U32(column)),
+                    Some(LayerValue::U32(offset)),
+                    Some(LayerValue::String(token)),
+                ) = (
+                    position.field("line"),
+                    position.field("column"),
+                    position.field("offset"),
+                    position.field("token"),
+                )
+                else {
+                    return Err(Error::Unexpected(format!("sql-position: {:?}", position)));
+                };
+                Some(SqlPosition {
+                    line,
+                    column,
+                    offset,
+                    token: token.to_string(),
+                })
+            }
+            None => None,
+            other => return Err(Error::Unexpected(format!("sql-position: {:?}", other))),
+        },
+        other => {
+            return Err(Error::Unexpected(format!(
+                "parse-error.position: {:?}",
+                other
+            )))
+        }
+    };
+    Ok(ParseError { message, position })
+}
+
+/// Convert a lifted `record-value` into a [Value], by case name.
+fn to_value(value: &LayerValue) -> Result<Value, Error> {
+    let variant = match value {
+        LayerValue::Variant(variant) => variant,
+        other => return Err(unexpected("record-value", other)),
+    };
+    let case = variant.ty().cases()[variant.discriminant()].name();
+    Ok(match (case, variant.value()) {
+        ("null", None) => Value::Null,
+        ("integer", Some(LayerValue::S64(i))) => Value::Integer(i),
+        ("float", Some(LayerValue::F64(f))) => Value::Float(f),
+        ("text", Some(LayerValue::String(s))) => Value::Text(s.to_string()),
+        ("text-decimal", Some(LayerValue::String(s))) => Value::TextDecimal(s.to_string()),
+        ("blob", Some(LayerValue::List(list))) => Value::Blob(
+            list.iter()
+                .map(|byte| match byte {
+                    LayerValue::U8(byte) => Ok(byte),
+                    other => Err(unexpected("blob byte", &other)),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        _ => return Err(unexpected("record-value", value)),
+    })
+}
+
+fn unexpected(expected: &str, found: &LayerValue) -> Error {
+    Error::Unexpected(format!("expected {}, found {:?}", expected, found))
+}
+
+fn define_host_kv<T, E: WasmEngine>(
+    linker: &mut Linker,
+    store: &mut Store<T, E>,
+    kv: HostKv,
+) -> Result<(), Error> {
+    let kv = Arc::new(Mutex::new(kv));
+    let host_kv = linker.define_instance(HOST_KV_INTERFACE.try_into()?)?;
+    let bytes_ty = ValueType::List(ListType::new(ValueType::U8));
+    let option_bytes_ty = ValueType::Option(OptionType::new(bytes_ty.clone()));
+    let result_ty =
+        |ok: Option<ValueType>| ValueType::Result(ResultType::new(ok, Some(ValueType::String)));
+
+    let get_kv = kv.clone();
+    let get_ty = option_bytes_ty.clone();
+    host_kv.define_func(
+        "get",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::String], [result_ty(Some(option_bytes_ty))]),
+            move |_store, params, results| {
+                let LayerValue::String(key) = &params[0] else {
+                    anyhow::bail!("host-kv.get: unexpected params {:?}", params);
+                };
+                let value = get_kv.lock().unwrap().get(key);
+                let value = match value {
+                    Ok(value) => {
+                        let value = value
+                            .map(|value| {
+                                List::new(
+                                    ListType::new(ValueType::U8),
+                                    value.into_iter().map(LayerValue::U8),
+                                )
+                                .map(LayerValue::List)
+                            })
+                            .transpose()?;
+                        Ok(Some(LayerValue::Option(OptionValue::new(
+                            OptionType::new(ValueType::List(ListType::new(ValueType::U8))),
+                            value,
+                        )?)))
+                    }
+                    Err(e) => Err(e),
+                };
+                results[0] = storage_result(Some(get_ty.clone()), value)?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    let set_kv = kv.clone();
+    host_kv.define_func(
+        "set",
+        Func::new(
+            &mut *store,
+            FuncType::new([ValueType::String, bytes_ty], [result_ty(None)]),
+            move |_store, params, results| {
+                let (LayerValue::String(key), LayerValue::List(value)) = (&params[0], &params[1])
+                else {
+                    anyhow::bail!("host-kv.set: unexpected params {:?}", params);
+                };
+                let value = value
+                    .iter()
+                    .map(|byte| match byte {
+                        LayerValue::U8(byte) => Ok(byte),
+                        other => Err(anyhow::anyhow!("expected u8, found {:?}", other)),
+                    })
+                    .collect::<anyhow::Result<Vec<u8>>>()?;
+                let set = set_kv.lock().unwrap().set(key.to_string(), value);
+                results[0] = storage_result(None, set.map(|_| None))?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    host_kv.define_func(
+        "flush",
+        Func::new(
+            &mut *store,
+            FuncType::new([], [result_ty(None)]),
+            move |_store, _params, results| {
+                let flushed = kv.lock().unwrap().flush();
+                results[0] = storage_result(None, flushed.map(|_| None))?;
+                Ok(())
+            },
+        ),
+    )?;
+
+    Ok(())
+}
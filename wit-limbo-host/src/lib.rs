@@ -0,0 +1,703 @@
+//! Typed host wrapper around the wit-limbo component for [wasmtime] hosts.
+//!
+//! This hides the resource handles, store management and binding calls you can see in the
+//! wasmtime test, so that using the component looks like using any other database crate:
+//!
+//! ```no_run
+//! use wit_limbo_host::{Database, Value};
+//!
+//! let engine = wit_limbo_host::engine()?;
+//! let component = wasmtime::component::Component::from_file(&engine, "wit_limbo.wasm")?;
+//!
+//! let mut db = Database::open(&engine, &component, ":memory:")?;
+//! db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);")?;
+//! db.exec("INSERT INTO users (name) VALUES ('Alice');")?;
+//!
+//! let rows = db.query("SELECT * FROM users WHERE id = ?", &[Value::Integer(1)])?;
+//! assert_eq!(rows[0].get_by_name("name"), Some(&Value::Text("Alice".into())));
+//!
+//! // or with plain Rust types
+//! db.execute("INSERT INTO users (name) VALUES (?)", wit_limbo_host::params!["Bob"])?;
+//! let name: String = db.query_row(
+//!     "SELECT name FROM users WHERE id = ?",
+//!     wit_limbo_host::params![2],
+//!     |row| row.try_get(0),
+//! )?;
+//! assert_eq!(name, "Bob");
+//! # Ok::<(), wit_limbo_host::Error>(())
+//! ```
+mod connection;
+#[cfg(feature = "serde")]
+pub mod de;
+mod fs;
+mod kv;
+#[cfg(feature = "layer")]
+pub mod layer_host;
+#[cfg(feature = "native")]
+pub mod native;
+#[cfg(feature = "pgwire")]
+pub mod pgwire;
+#[cfg(feature = "pool")]
+pub mod pool;
+mod shared;
+#[cfg(feature = "sqlite3_compat")]
+pub mod sqlite3_compat;
+mod value;
+
+mod bindgen {
+    wasmtime::component::bindgen!({
+        path: "../wit",
+        world: "example",
+        additional_derives: [PartialEq],
+    });
+}
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use thiserror::Error;
+use wasmtime::component::{Component, Linker, ResourceAny};
+use wasmtime::{Config, Engine, Store};
+
+use bindgen::component::wit_limbo::{
+    host_clock, host_fs, host_io, host_kv, host_locale, host_log, host_progress, host_random,
+    host_subscriptions, host_trace,
+};
+
+pub use connection::Connection;
+pub use fs::HostFs;
+pub use kv::HostKv;
+pub use shared::SharedDatabase;
+pub use value::{FromRow, FromValue, ToValue};
+#[cfg(feature = "derive")]
+pub use wit_limbo_derive::FromRow;
+
+pub use bindgen::exports::component::wit_limbo::limbo::{
+    DbError, LimitKind, OpenFlags, ParseError, RecordValue as Value, SqlPosition,
+};
+
+use bindgen::exports::component::wit_limbo::limbo::Guest;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    /// From the wasm runtime, wasmtime or [wasm_component_layer]
+    #[error("Runtime: {0}")]
+    Runtime(#[from] wasmtime::Error),
+
+    /// The database returned an error
+    #[error("Database: {0:?}")]
+    Db(#[from] DbError),
+
+    /// The component returned something other than what its WIT describes
+    #[error("Unexpected value: {0}")]
+    Unexpected(String),
+
+    /// [Database::query_row] found no rows
+    #[error("Query returned no rows")]
+    QueryReturnedNoRows,
+
+    /// A column was read by a name the result does not have
+    #[error("No such column: {0}")]
+    InvalidColumnName(String),
+
+    /// A column was read by an index the result does not have
+    #[error("No column at index {0}")]
+    InvalidColumnIndex(usize),
+
+    /// A value could not be converted to the requested Rust type
+    #[error("Invalid type: {0}")]
+    InvalidType(String),
+
+    /// A row could not be deserialized, see [de]
+    #[cfg(feature = "serde")]
+    #[error("Deserialize: {0}")]
+    Deserialize(String),
+
+    /// The [pool] was dropped, or the instance running a call panicked
+    #[cfg(feature = "pool")]
+    #[error("Pool stopped")]
+    PoolStopped,
+
+    /// A [pool] of more than one instance opened a file it could write
+    #[cfg(feature = "pool")]
+    #[error("Pool of {0} instances opened a file that isn't read-only")]
+    PoolWritesFile(usize),
+}
+
+/// Host state backing the imports of the component.
+#[derive(Default)]
+pub struct HostState {
+    fs: HostFs,
+    kv: HostKv,
+}
+
+impl host_random::Host for HostState {
+    fn random_byte(&mut self) -> u8 {
+        rand::random::<u8>()
+    }
+}
+
+impl host_log::Host for HostState {
+    fn log(&mut self, message: String) {
+        eprintln!("{}", message);
+    }
+}
+
+impl host_clock::Host for HostState {
+    fn sleep(&mut self, ms: u32) {
+        std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+    }
+
+    fn now(&mut self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_micros() as u64)
+    }
+}
+
+impl host_trace::Host for HostState {
+    /// Spans aren't traced.
+    fn span_start(&mut self, _name: String) -> u64 {
+        0
+    }
+
+    fn span_end(&mut self, _id: u64) {}
+}
+
+impl host_subscriptions::Host for HostState {
+    /// Nothing subscribes through [Connection].
+    fn on_query_changed(&mut self, _subscription_id: u64) {}
+}
+
+impl host_progress::Host for HostState {
+    /// No long call is run through [Connection].
+    fn on_progress(&mut self, _operation: String, _done: u64, _total: u64) {}
+}
+
+/// No locale data is linked in, so text is folded and sorted by Unicode alone, whatever the
+/// locale.
+impl host_locale::Host for HostState {
+    fn to_lower(&mut self, text: String) -> String {
+        text.to_lowercase()
+    }
+
+    fn collate(&mut self, _locale: String, a: String, b: String) -> i8 {
+        a.to_lowercase().cmp(&b.to_lowercase()) as i8
+    }
+}
+
+impl host_fs::Host for HostState {
+    fn open(&mut self, path: String, create: bool) -> Result<u32, String> {
+        self.fs.open(&path, create)
+    }
+
+    fn read(&mut self, fd: u32, offset: u64, len: u32) -> Result<Vec<u8>, String> {
+        self.fs.read(fd, offset, len)
+    }
+
+    fn write(&mut self, fd: u32, offset: u64, data: Vec<u8>) -> Result<(), String> {
+        self.fs.write(fd, offset, &data)
+    }
+
+    fn sync(&mut self, fd: u32) -> Result<(), String> {
+        self.fs.sync(fd)
+    }
+
+    fn size(&mut self, fd: u32) -> Result<u64, String> {
+        self.fs.size(fd)
+    }
+}
+
+impl host_io::Host for HostState {
+    fn submit_io(&mut self, request: host_io::IoRequest) -> u64 {
+        self.fs.submit(request.fd, request.offset, request.len)
+    }
+
+    fn poll_io(&mut self, id: u64) -> Option<Result<Vec<u8>, String>> {
+        self.fs.poll(id)
+    }
+}
+
+impl host_kv::Host for HostState {
+    fn get(&mut self, key: String) -> Result<Option<Vec<u8>>, String> {
+        self.kv.get(&key)
+    }
+
+    fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), String> {
+        self.kv.set(key, value)
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.kv.flush()
+    }
+}
+
+/// Create an [Engine] configured for running the component.
+pub fn engine() -> Result<Engine, Error> {
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    Ok(Engine::new(&config)?)
+}
+
+/// A row returned by [Database::query].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    columns: Arc<[String]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// The column names, in the same order as [Row::values].
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The values in this row.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Get a value by column index.
+    pub fn get(&self, idx: usize) -> Option<&Value> {
+        self.values.get(idx)
+    }
+
+    /// Get a value by column name.
+    pub fn get_by_name(&self, name: &str) -> Option<&Value> {
+        let idx = self.columns.iter().position(|column| column == name)?;
+        self.values.get(idx)
+    }
+
+    /// Get a value by column index, converted to `T`.
+    pub fn try_get<T: FromValue>(&self, idx: usize) -> Result<T, Error> {
+        let value = self.get(idx).ok_or(Error::InvalidColumnIndex(idx))?;
+        T::from_value(value)
+    }
+
+    /// Get a value by column name, converted to `T`.
+    pub fn try_get_by_name<T: FromValue>(&self, name: &str) -> Result<T, Error> {
+        let value = self
+            .get_by_name(name)
+            .ok_or_else(|| Error::InvalidColumnName(name.to_string()))?;
+        T::from_value(value)
+    }
+}
+
+/// `rows` with `columns` as their column names.
+fn with_columns(columns: Arc<[String]>, rows: Vec<Vec<Value>>) -> Vec<Row> {
+    rows.into_iter()
+        .map(|values| Row {
+            columns: columns.clone(),
+            values,
+        })
+        .collect()
+}
+
+/// Whether `database.open-with` opens `path`, with `flags`, as a file it may write: not
+/// `:memory:` or `kv:`, nor read-only, by the `mode` of a `file:` URI or else by `flags`.
+fn writes_file(path: &str, flags: OpenFlags) -> bool {
+    if path == ":memory:" || path.starts_with("kv:") {
+        return false;
+    }
+    let mode = path
+        .strip_prefix("file:")
+        .and_then(|uri| uri.split_once('?'))
+        .and_then(|(_, query)| {
+            query
+                .split('&')
+                .filter_map(|param| param.strip_prefix("mode="))
+                .last()
+        });
+    match mode {
+        Some(mode) => mode != "ro",
+        None => !flags.contains(OpenFlags::READ_ONLY),
+    }
+}
+
+/// A database running inside its own instance of the component.
+///
+/// Calls take `&mut self`; to share one between threads, wrap it in a [SharedDatabase].
+pub struct Database {
+    store: Store<HostState>,
+    bindings: bindgen::Example,
+    db: ResourceAny,
+    /// The statements [Database::cached_query] keeps, most recently used first.
+    statements: VecDeque<CachedStatement>,
+    /// How many statements [Database::cached_query] keeps.
+    statement_cache_capacity: usize,
+    /// Whether the database is a file this connection may write, which the file's locks,
+    /// kept inside this instance, don't guard against other instances.
+    writes_file: bool,
+}
+
+/// A statement kept by [Database::cached_query].
+struct CachedStatement {
+    sql: String,
+    statement: ResourceAny,
+    /// How many parameters were bound when it last ran.
+    bound: usize,
+}
+
+/// How many statements [Database::cached_query] keeps until
+/// [Database::set_statement_cache_capacity] is called, as rusqlite's `prepare_cached` does.
+const DEFAULT_STATEMENT_CACHE_CAPACITY: usize = 16;
+
+impl Database {
+    /// Instantiate `component` and open the database at `path`.
+    ///
+    /// `:memory:` is an in-memory database, `kv:<name>` is kept in this instance's [HostKv],
+    /// and any other path is a file relative to the working directory.
+    /// Use [Database::open_with] to choose where files live.
+    pub fn open(engine: &Engine, component: &Component, path: &str) -> Result<Self, Error> {
+        Self::open_with(engine, component, path, HostFs::default())
+    }
+
+    /// Like [Database::open], with database files opened through `fs`.
+    pub fn open_with(
+        engine: &Engine,
+        component: &Component,
+        path: &str,
+        fs: HostFs,
+    ) -> Result<Self, Error> {
+        // rather than the constructor, which returns a database that failed to open closed
+        Self::open_with_flags(engine, component, path, fs, OpenFlags::CREATE)
+    }
+
+    /// Like [Database::open_with], opening the database as `flags` say: without
+    /// [OpenFlags::CREATE] a missing database fails with [DbError::CannotOpen] rather than
+    /// being created.
+    pub fn open_with_flags(
+        engine: &Engine,
+        component: &Component,
+        path: &str,
+        fs: HostFs,
+        flags: OpenFlags,
+    ) -> Result<Self, Error> {
+        let (mut store, bindings) = Self::instantiate(engine, component, fs)?;
+        let db = bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_open_with(&mut store, path, flags)??;
+        Ok(Self::new(store, bindings, db, writes_file(path, flags)))
+    }
+
+    fn new(
+        store: Store<HostState>,
+        bindings: bindgen::Example,
+        db: ResourceAny,
+        writes_file: bool,
+    ) -> Self {
+        Self {
+            store,
+            bindings,
+            db,
+            statements: VecDeque::new(),
+            statement_cache_capacity: DEFAULT_STATEMENT_CACHE_CAPACITY,
+            writes_file,
+        }
+    }
+
+    /// Whether the database is a file this connection may write, so one that no other
+    /// instance should open, other than read-only, while it is open.
+    pub fn writes_file(&self) -> bool {
+        self.writes_file
+    }
+
+    /// A new instance of `component`, with database files opened through `fs`.
+    fn instantiate(
+        engine: &Engine,
+        component: &Component,
+        fs: HostFs,
+    ) -> Result<(Store<HostState>, bindgen::Example), Error> {
+        let mut linker = Linker::new(engine);
+        bindgen::Example::add_to_linker(&mut linker, |state: &mut HostState| state)?;
+
+        let mut store = Store::new(
+            engine,
+            HostState {
+                fs,
+                kv: HostKv::default(),
+            },
+        );
+        let bindings = bindgen::Example::instantiate(&mut store, component, &linker)?;
+        Ok((store, bindings))
+    }
+
+    /// Execute a SQL statement, discarding any rows it produces.
+    pub fn exec(&mut self, sql: &str) -> Result<(), Error> {
+        self.bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_exec(&mut self.store, self.db, sql)??;
+        Ok(())
+    }
+
+    /// Wait up to `ms` milliseconds for another connection's lock before a statement fails
+    /// with [DbError::Busy], like `sqlite3_busy_timeout`. 0, the default, fails straight away.
+    pub fn set_busy_timeout(&mut self, ms: u32) -> Result<(), Error> {
+        self.bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_set_busy_timeout(&mut self.store, self.db, ms)?;
+        Ok(())
+    }
+
+    /// Stop the runs on this connection that are part way through, which then fail with
+    /// [DbError::Interrupted].
+    pub fn cancel_running(&mut self) -> Result<(), Error> {
+        self.bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_cancel_running(&mut self.store, self.db)?;
+        Ok(())
+    }
+
+    /// Fail statements with [DbError::OutOfMemory] once the component's heap grows past
+    /// `bytes`, rather than aborting the instance, and return the limit before. 0 means no
+    /// limit. The limit is on the whole instance, not this connection, so it applies to every
+    /// database opened in the same store.
+    pub fn set_soft_heap_limit(&mut self, bytes: u64) -> Result<u64, Error> {
+        Ok(self
+            .bindings
+            .component_wit_limbo_limbo()
+            .call_set_soft_heap_limit(&mut self.store, bytes)?)
+    }
+
+    /// Limit the statements the connection prepares, failing those beyond it with
+    /// [DbError::LimitExceeded], and return the limit's previous value. 0 means no limit.
+    pub fn set_limit(&mut self, kind: LimitKind, value: u32) -> Result<u32, Error> {
+        Ok(self
+            .bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_set_limit(&mut self.store, self.db, kind, value)?)
+    }
+
+    /// Read `pages` pages ahead once reads of the database's files are sequential, so large
+    /// scans ask the [HostFs] for a batch of pages at a time. 0, the default, reads nothing
+    /// ahead. Fails with [DbError::Misuse] for `:memory:` and `kv:` databases.
+    pub fn set_readahead_pages(&mut self, pages: u32) -> Result<(), Error> {
+        self.bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_set_readahead_pages(&mut self.store, self.db, pages)??;
+        Ok(())
+    }
+
+    /// Hold the database's writes until they are synced, so a commit passes each run of
+    /// adjacent pages to the [HostFs] in one write. Fails with [DbError::Misuse] for
+    /// `:memory:` databases.
+    pub fn set_coalesce_writes(&mut self, enabled: bool) -> Result<(), Error> {
+        self.bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_set_coalesce_writes(&mut self.store, self.db, enabled)??;
+        Ok(())
+    }
+
+    /// The database's counters in the Prometheus text format, to serve from `/metrics`.
+    pub fn metrics_prometheus(&mut self) -> Result<String, Error> {
+        Ok(self
+            .bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_metrics_prometheus(&mut self.store, self.db)?)
+    }
+
+    /// The error the most recent failed call on the connection returned, if any has failed.
+    pub fn last_error(&mut self) -> Result<Option<DbError>, Error> {
+        Ok(self
+            .bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_last_error(&mut self.store, self.db)?)
+    }
+
+    /// The SQLite extended result code of [Database::last_error], e.g. 2067
+    /// (`SQLITE_CONSTRAINT_UNIQUE`) for a duplicate key.
+    pub fn last_error_code(&mut self) -> Result<Option<u32>, Error> {
+        Ok(self
+            .bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_last_error_code(&mut self.store, self.db)?)
+    }
+
+    /// Checkpoint the WAL and release the connection. Every later call returns
+    /// [DbError::Closed]; dropping the [Database] without closing it skips the checkpoint.
+    pub fn close(&mut self) -> Result<(), Error> {
+        self.trim_statements(0)?;
+        self.bindings
+            .component_wit_limbo_limbo()
+            .database()
+            .call_close(&mut self.store, self.db)??;
+        Ok(())
+    }
+
+    /// Run `sql` with `params` bound in order, and collect the resulting rows.
+    pub fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>, Error> {
+        let (columns, rows) = self.query_columns(sql, params)?;
+        Ok(with_columns(columns, rows))
+    }
+
+    /// Like [Database::query], reusing the statement an earlier call prepared for `sql`
+    /// rather than preparing it again, which is most of the cost of a small query.
+    ///
+    /// The most recently used statements are kept, up to
+    /// [Database::set_statement_cache_capacity]. A statement that fails is dropped rather
+    /// than kept, so the next call prepares it afresh, e.g. after the schema changes.
+    pub fn cached_query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>, Error> {
+        let limbo = self.bindings.component_wit_limbo_limbo();
+        let cached = match self.statements.iter().position(|cached| cached.sql == sql) {
+            Some(i) => self.statements.remove(i).unwrap(),
+            None => CachedStatement {
+                sql: sql.to_string(),
+                statement: limbo
+                    .database()
+                    .call_prepare(&mut self.store, self.db, sql)??,
+                bound: 0,
+            },
+        };
+        // parameters the last run bound and this one doesn't are NULL, as when freshly
+        // prepared
+        let nulls = cached.bound.saturating_sub(params.len());
+        let result = params
+            .iter()
+            .chain(std::iter::repeat(&Value::Null).take(nulls))
+            .enumerate()
+            .try_for_each(|(i, param)| {
+                Ok::<_, Error>(limbo.statement().call_bind(
+                    &mut self.store,
+                    cached.statement,
+                    i as u32 + 1,
+                    param,
+                )??)
+            })
+            .and_then(|()| {
+                Ok(limbo
+                    .statement()
+                    .call_all_with_names(&mut self.store, cached.statement)??)
+            });
+        let result_set = match result {
+            Ok(result_set) => result_set,
+            Err(e) => {
+                cached.statement.resource_drop(&mut self.store)?;
+                return Err(e);
+            }
+        };
+        self.statements.push_front(CachedStatement {
+            bound: params.len(),
+            ..cached
+        });
+        self.trim_statements(self.statement_cache_capacity)?;
+        Ok(with_columns(result_set.columns.into(), result_set.rows))
+    }
+
+    /// Keep up to `capacity` statements for [Database::cached_query], dropping the least
+    /// recently used beyond it. 0 keeps none.
+    pub fn set_statement_cache_capacity(&mut self, capacity: usize) -> Result<(), Error> {
+        self.statement_cache_capacity = capacity;
+        self.trim_statements(capacity)
+    }
+
+    /// Drop the least recently used of the cached statements beyond the first `keep`.
+    fn trim_statements(&mut self, keep: usize) -> Result<(), Error> {
+        while self.statements.len() > keep {
+            let cached = self.statements.pop_back().unwrap();
+            cached.statement.resource_drop(&mut self.store)?;
+        }
+        Ok(())
+    }
+
+    /// Execute a SQL statement with `params` bound in order, discarding any rows it produces.
+    ///
+    /// Use [params!] to build `params` from plain Rust values.
+    pub fn execute(&mut self, sql: &str, params: &[Value]) -> Result<(), Error> {
+        self.query_columns(sql, params)?;
+        Ok(())
+    }
+
+    /// Map each row of the result with `f`.
+    pub fn query_map<T, F>(&mut self, sql: &str, params: &[Value], f: F) -> Result<Vec<T>, Error>
+    where
+        F: FnMut(&Row) -> Result<T, Error>,
+    {
+        self.query(sql, params)?.iter().map(f).collect()
+    }
+
+    /// Map the first row of the result with `f`, failing with [Error::QueryReturnedNoRows]
+    /// if there is none.
+    pub fn query_row<T, F>(&mut self, sql: &str, params: &[Value], f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Row) -> Result<T, Error>,
+    {
+        let rows = self.query(sql, params)?;
+        f(rows.first().ok_or(Error::QueryReturnedNoRows)?)
+    }
+
+    /// Convert each row of the result into a `T`, see [FromRow].
+    pub fn query_as<T: FromRow>(&mut self, sql: &str, params: &[Value]) -> Result<Vec<T>, Error> {
+        self.query_map(sql, params, T::from_row)
+    }
+
+    /// Deserialize each row of the result into a `T`, with struct fields matched to columns by
+    /// name. See [de].
+    #[cfg(feature = "serde")]
+    pub fn query_deserialize<T: serde::de::DeserializeOwned>(
+        &mut self,
+        sql: &str,
+        params: &[Value],
+    ) -> Result<Vec<T>, Error> {
+        self.query_map(sql, params, de::from_row)
+    }
+
+    /// Run `sql` with `params` bound in order, returning the result encoded as a
+    /// `QueryResponse` from `proto/limbo.proto`.
+    pub fn query_proto(&mut self, sql: &str, params: &[Value]) -> Result<Vec<u8>, Error> {
+        self.with_statement(sql, params, |limbo, store, statement| {
+            Ok(limbo.statement().call_all_proto(store, statement)??)
+        })
+    }
+
+    /// Like [Database::query], but keeps the column names even when there are no rows.
+    pub(crate) fn query_columns(
+        &mut self,
+        sql: &str,
+        params: &[Value],
+    ) -> Result<(Arc<[String]>, Vec<Vec<Value>>), Error> {
+        let result_set = self.with_statement(sql, params, |limbo, store, statement| {
+            Ok(limbo.statement().call_all_with_names(store, statement)??)
+        })?;
+        Ok((result_set.columns.into(), result_set.rows))
+    }
+
+    /// Prepare `sql`, bind `params` in order and run `f` on the statement,
+    /// dropping the statement afterwards whether or not `f` succeeded.
+    fn with_statement<T>(
+        &mut self,
+        sql: &str,
+        params: &[Value],
+        f: impl FnOnce(&Guest, &mut Store<HostState>, ResourceAny) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let limbo = self.bindings.component_wit_limbo_limbo();
+
+        let statement = limbo
+            .database()
+            .call_prepare(&mut self.store, self.db, sql)??;
+        let result = params
+            .iter()
+            .enumerate()
+            .try_for_each(|(i, param)| {
+                Ok::<_, Error>(limbo.statement().call_bind(
+                    &mut self.store,
+                    statement,
+                    i as u32 + 1,
+                    param,
+                )??)
+            })
+            .and_then(|()| f(limbo, &mut self.store, statement));
+        statement.resource_drop(&mut self.store)?;
+
+        result
+    }
+}
@@ -0,0 +1,99 @@
+//! [NativeDatabase], the wit-limbo API on limbo_core linked into the host, with no wasm
+//! runtime in between. Use it through [Connection](crate::Connection) to switch between
+//! native and sandboxed execution, e.g. for faster tests or to measure the overhead.
+//!
+//! Paths behave as in the component: `:memory:`, `kv:<name>` (kept in memory for the life
+//! of the process) or a file relative to the working directory.
+use std::sync::Arc;
+
+use crate::{Connection, DbError, Error, ParseError, Row, SqlPosition, Value};
+
+/// A database opened in-process, see the [module documentation](self).
+pub struct NativeDatabase {
+    db: wit_limbo::NativeDatabase,
+}
+
+impl NativeDatabase {
+    /// Open the database at `path`.
+    pub fn open(path: &str) -> Self {
+        Self {
+            db: wit_limbo::NativeDatabase::open(path),
+        }
+    }
+}
+
+impl Connection for NativeDatabase {
+    fn exec(&mut self, sql: &str) -> Result<(), Error> {
+        self.db.exec(sql).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>, Error> {
+        let statement = self.db.prepare(sql).map_err(db_error)?;
+        for (i, param) in params.iter().enumerate() {
+            statement
+                .bind(i as u32 + 1, to_native(param))
+                .map_err(db_error)?;
+        }
+        let result_set = statement.all_with_names().map_err(db_error)?;
+
+        let columns: Arc<[String]> = result_set.columns.into();
+        Ok(result_set
+            .rows
+            .into_iter()
+            .map(|values| Row {
+                columns: columns.clone(),
+                values: values.into_iter().map(from_native).collect(),
+            })
+            .collect())
+    }
+}
+
+fn db_error(e: wit_limbo::DbError) -> Error {
+    use wit_limbo::DbError as E;
+    Error::Db(match e {
+        E::Parse(e) => DbError::Parse(ParseError {
+            message: e.message,
+            position: e.position.map(|position| SqlPosition {
+                line: position.line,
+                column: position.column,
+                offset: position.offset,
+                token: position.token,
+            }),
+        }),
+        E::Corrupt(message) => DbError::Corrupt(message),
+        E::Io(message) => DbError::Io(message),
+        E::Misuse(message) => DbError::Misuse(message),
+        E::Busy(message) => DbError::Busy(message),
+        E::Closed(message) => DbError::Closed(message),
+        E::Interrupted(message) => DbError::Interrupted(message),
+        E::OutOfMemory(message) => DbError::OutOfMemory(message),
+        E::LimitExceeded(message) => DbError::LimitExceeded(message),
+        E::CannotOpen(message) => DbError::CannotOpen(message),
+        E::Other(message) => DbError::Other(message),
+    })
+}
+
+fn to_native(value: &Value) -> wit_limbo::RecordValue {
+    use wit_limbo::RecordValue as V;
+    match value.clone() {
+        Value::Null => V::Null,
+        Value::Integer(i) => V::Integer(i),
+        Value::Float(f) => V::Float(f),
+        Value::Text(s) => V::Text(s),
+        Value::Blob(b) => V::Blob(b),
+        Value::TextDecimal(s) => V::TextDecimal(s),
+    }
+}
+
+fn from_native(value: wit_limbo::RecordValue) -> Value {
+    use wit_limbo::RecordValue as V;
+    match value {
+        V::Null => Value::Null,
+        V::Integer(i) => Value::Integer(i),
+        V::Float(f) => Value::Float(f),
+        V::Text(s) => Value::Text(s),
+        V::Blob(b) => Value::Blob(b),
+        V::TextDecimal(s) => Value::TextDecimal(s),
+    }
+}
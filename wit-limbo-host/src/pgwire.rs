@@ -0,0 +1,249 @@
+//! PostgreSQL wire protocol frontend, so `psql` and other Postgres clients can talk to a
+//! [Database] running in the component.
+//!
+//! Only the simple query protocol is spoken: no authentication, no TLS and no extended
+//! (prepared statement) protocol. Every value is sent in text format, with the column type
+//! guessed from the first row.
+//!
+//! ```no_run
+//! use std::net::TcpListener;
+//! use wit_limbo_host::{pgwire, Database};
+//!
+//! let engine = wit_limbo_host::engine()?;
+//! let component = wasmtime::component::Component::from_file(&engine, "wit_limbo.wasm")?;
+//! let mut db = Database::open(&engine, &component, "app.db")?;
+//!
+//! // psql -h 127.0.0.1 -p 5432
+//! pgwire::serve(TcpListener::bind("127.0.0.1:5432")?, &mut db)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::{Database, Value};
+
+const PROTOCOL_VERSION_3: i32 = 196_608;
+const SSL_REQUEST: i32 = 80_877_103;
+const GSSENC_REQUEST: i32 = 80_877_104;
+
+/// Type oids from `pg_type`
+const TEXT_OID: i32 = 25;
+const INT8_OID: i32 = 20;
+const FLOAT8_OID: i32 = 701;
+const BYTEA_OID: i32 = 17;
+
+/// Accept connections on `listener` one at a time, running their queries against `db`.
+///
+/// Connections are served sequentially, since the database lives in a single store.
+pub fn serve(listener: TcpListener, db: &mut Database) -> io::Result<()> {
+    for stream in listener.incoming() {
+        if let Err(e) = serve_connection(stream?, db) {
+            eprintln!("pgwire: connection closed: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Speak the protocol on a single connection until the client terminates it.
+pub fn serve_connection(stream: TcpStream, db: &mut Database) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    startup(&mut reader, &mut writer)?;
+
+    loop {
+        let mut tag = [0u8; 1];
+        if reader.read(&mut tag)? == 0 {
+            return Ok(());
+        }
+        let body = read_body(&mut reader)?;
+        match tag[0] {
+            b'Q' => {
+                let sql = String::from_utf8_lossy(body.strip_suffix(&[0]).unwrap_or(&body));
+                simple_query(&mut writer, db, &sql)?;
+            }
+            b'X' => return Ok(()),
+            other => error_response(
+                &mut writer,
+                "0A000",
+                &format!("unsupported message type '{}'", other as char),
+            )?,
+        }
+        ready_for_query(&mut writer)?;
+        writer.flush()?;
+    }
+}
+
+/// Handle the startup packet, declining SSL and GSS encryption, and accept without authentication.
+fn startup(reader: &mut impl Read, writer: &mut impl Write) -> io::Result<()> {
+    loop {
+        let body = read_body(reader)?;
+        let code = body
+            .get(..4)
+            .and_then(|code| code.try_into().ok())
+            .map(i32::from_be_bytes)
+            .ok_or_else(|| invalid("startup packet too short"))?;
+        match code {
+            SSL_REQUEST | GSSENC_REQUEST => {
+                writer.write_all(b"N")?;
+                writer.flush()?;
+            }
+            PROTOCOL_VERSION_3 => break,
+            _ => return Err(invalid(format!("unsupported protocol version {}", code))),
+        }
+    }
+
+    // AuthenticationOk
+    message(writer, b'R', &0i32.to_be_bytes())?;
+    for (name, value) in [
+        ("server_version", "14.0"),
+        ("server_encoding", "UTF8"),
+        ("client_encoding", "UTF8"),
+        ("DateStyle", "ISO, MDY"),
+    ] {
+        let mut body = Vec::new();
+        put_str(&mut body, name);
+        put_str(&mut body, value);
+        message(writer, b'S', &body)?;
+    }
+    ready_for_query(writer)?;
+    writer.flush()
+}
+
+fn simple_query(writer: &mut impl Write, db: &mut Database, sql: &str) -> io::Result<()> {
+    if sql.trim().trim_end_matches(';').trim().is_empty() {
+        return message(writer, b'I', &[]);
+    }
+
+    let (columns, rows) = match db.query_columns(sql, &[]) {
+        Ok(result) => result,
+        Err(e) => return error_response(writer, "XX000", &e.to_string()),
+    };
+
+    if !columns.is_empty() {
+        row_description(writer, &columns, rows.first())?;
+        for row in &rows {
+            data_row(writer, row)?;
+        }
+    }
+
+    let mut body = Vec::new();
+    put_str(&mut body, &command_tag(sql, rows.len()));
+    message(writer, b'C', &body)
+}
+
+fn row_description(
+    writer: &mut impl Write,
+    columns: &[String],
+    first: Option<&Vec<Value>>,
+) -> io::Result<()> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&(columns.len() as i16).to_be_bytes());
+    for (i, name) in columns.iter().enumerate() {
+        let type_oid = match first.and_then(|row| row.get(i)) {
+            Some(Value::Integer(_)) => INT8_OID,
+            Some(Value::Float(_)) => FLOAT8_OID,
+            Some(Value::Blob(_)) => BYTEA_OID,
+            _ => TEXT_OID,
+        };
+        put_str(&mut body, name);
+        body.extend_from_slice(&0i32.to_be_bytes()); // table oid
+        body.extend_from_slice(&0i16.to_be_bytes()); // column attribute number
+        body.extend_from_slice(&type_oid.to_be_bytes());
+        body.extend_from_slice(&(-1i16).to_be_bytes()); // variable size
+        body.extend_from_slice(&(-1i32).to_be_bytes()); // type modifier
+        body.extend_from_slice(&0i16.to_be_bytes()); // text format
+    }
+    message(writer, b'T', &body)
+}
+
+fn data_row(writer: &mut impl Write, row: &[Value]) -> io::Result<()> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&(row.len() as i16).to_be_bytes());
+    for value in row {
+        match text_value(value) {
+            Some(text) => {
+                body.extend_from_slice(&(text.len() as i32).to_be_bytes());
+                body.extend_from_slice(text.as_bytes());
+            }
+            None => body.extend_from_slice(&(-1i32).to_be_bytes()),
+        }
+    }
+    message(writer, b'D', &body)
+}
+
+/// The text format of `value`, or `None` for NULL.
+fn text_value(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Text(s) | Value::TextDecimal(s) => s.clone(),
+        Value::Blob(b) => {
+            // bytea hex format
+            let hex: String = b.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("\\x{}", hex)
+        }
+    })
+}
+
+/// The CommandComplete tag clients expect, e.g. `SELECT 3` or `CREATE TABLE`.
+fn command_tag(sql: &str, rows: usize) -> String {
+    let mut words = sql.split_whitespace().map(str::to_uppercase);
+    match words.next().as_deref() {
+        Some("SELECT") | Some("WITH") | Some("VALUES") | Some("PRAGMA") => {
+            format!("SELECT {}", rows)
+        }
+        Some("INSERT") => format!("INSERT 0 {}", rows),
+        Some(verb @ ("CREATE" | "DROP" | "ALTER")) => match words.next() {
+            Some(object) => format!("{} {}", verb, object),
+            None => verb.to_string(),
+        },
+        Some(verb) => verb.to_string(),
+        None => String::new(),
+    }
+}
+
+fn error_response(writer: &mut impl Write, code: &str, message_text: &str) -> io::Result<()> {
+    let mut body = Vec::new();
+    for (field, value) in [(b'S', "ERROR"), (b'C', code), (b'M', message_text)] {
+        body.push(field);
+        put_str(&mut body, value);
+    }
+    body.push(0);
+    message(writer, b'E', &body)
+}
+
+fn ready_for_query(writer: &mut impl Write) -> io::Result<()> {
+    // idle, not in a transaction block
+    message(writer, b'Z', b"I")
+}
+
+/// Write a tagged message, the length prefix includes itself but not the tag.
+fn message(writer: &mut impl Write, tag: u8, body: &[u8]) -> io::Result<()> {
+    writer.write_all(&[tag])?;
+    writer.write_all(&(body.len() as i32 + 4).to_be_bytes())?;
+    writer.write_all(body)
+}
+
+/// Read a length prefixed message body, after its tag (if any) has been read.
+fn read_body(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = i32::from_be_bytes(len);
+    if len < 4 {
+        return Err(invalid(format!("invalid message length {}", len)));
+    }
+    let mut body = vec![0u8; len as usize - 4];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+fn put_str(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
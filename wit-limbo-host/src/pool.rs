@@ -0,0 +1,133 @@
+//! [DbPool], several instances of the component behind one async API, for hosts such as web
+//! servers that serve many requests at once.
+//!
+//! ```no_run
+//! use wit_limbo_host::{pool::DbPool, Database, HostFs, OpenFlags};
+//!
+//! # async fn serve() -> Result<(), wit_limbo_host::Error> {
+//! let engine = wit_limbo_host::engine()?;
+//! let component = wasmtime::component::Component::from_file(&engine, "wit_limbo.wasm")?;
+//!
+//! let pool = DbPool::new(4, |_| {
+//!     let fs = HostFs::with_root("data");
+//!     Database::open_with_flags(&engine, &component, "app.db", fs, OpenFlags::READ_ONLY)
+//! })?;
+//! let rows = pool.query("SELECT name FROM users", vec![]).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Each instance runs on a thread of its own, taking calls from a queue they share, so a
+//! call waits only until one of them is free, and never blocks the async runtime. Instances
+//! share nothing: `:memory:` and `kv:` databases are separate in each, and as the locks
+//! guarding a database file are kept inside the instance that opened it, more than one
+//! instance must only open a file read-only: [DbPool::new] refuses a pool of more than one
+//! that opens a file any other way. Writes need a pool of one.
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+
+use tokio::sync::oneshot;
+
+use crate::{Database, Error, Row, Value};
+
+/// A call waiting for an instance.
+type Job = Box<dyn FnOnce(&mut Database) + Send>;
+
+/// Instances of the component running calls from a shared queue, see the
+/// [module documentation](self).
+pub struct DbPool {
+    /// The queue, `None` once the pool is dropped, so the threads stop.
+    jobs: Option<Sender<Job>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl DbPool {
+    /// Open `size` instances with `open`, which is passed each one's index. Fails with
+    /// [Error::PoolWritesFile] if `size` is more than one and an instance opens a file other
+    /// than read-only, as the instances would each take the file's locks as if it were theirs
+    /// alone.
+    pub fn new(
+        size: usize,
+        open: impl Fn(usize) -> Result<Database, Error>,
+    ) -> Result<Self, Error> {
+        let dbs = (0..size).map(open).collect::<Result<Vec<_>, _>>()?;
+        if size > 1 && dbs.iter().any(Database::writes_file) {
+            return Err(Error::PoolWritesFile(size));
+        }
+        let (jobs, queue) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        let threads = dbs
+            .into_iter()
+            .map(|db| {
+                let queue = queue.clone();
+                std::thread::spawn(move || work(db, &queue))
+            })
+            .collect();
+        Ok(Self {
+            jobs: Some(jobs),
+            threads,
+        })
+    }
+
+    /// Run `f` on the next free instance.
+    pub async fn call<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Database) -> Result<T, Error> + Send + 'static,
+    ) -> Result<T, Error> {
+        let (reply, result) = oneshot::channel();
+        let job: Job = Box::new(move |db| {
+            // the caller may have stopped waiting
+            let _ = reply.send(f(db));
+        });
+        self.jobs
+            .as_ref()
+            .and_then(|jobs| jobs.send(job).ok())
+            .ok_or(Error::PoolStopped)?;
+        result.await.map_err(|_| Error::PoolStopped)?
+    }
+
+    /// Execute a SQL statement, discarding any rows it produces.
+    pub async fn exec(&self, sql: impl Into<String>) -> Result<(), Error> {
+        let sql = sql.into();
+        self.call(move |db| db.exec(&sql)).await
+    }
+
+    /// Run `sql` with `params` bound in order, and collect the resulting rows.
+    pub async fn query(
+        &self,
+        sql: impl Into<String>,
+        params: Vec<Value>,
+    ) -> Result<Vec<Row>, Error> {
+        let sql = sql.into();
+        self.call(move |db| db.query(&sql, &params)).await
+    }
+
+    /// Execute a SQL statement with `params` bound in order, discarding any rows it produces.
+    pub async fn execute(&self, sql: impl Into<String>, params: Vec<Value>) -> Result<(), Error> {
+        let sql = sql.into();
+        self.call(move |db| db.execute(&sql, &params)).await
+    }
+}
+
+impl Drop for DbPool {
+    /// Closing the queue stops each thread once the calls already queued have run.
+    fn drop(&mut self) {
+        self.jobs.take();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Run the calls from `queue` on `db` until the pool is dropped.
+fn work(mut db: Database, queue: &Mutex<Receiver<Job>>) {
+    loop {
+        // the lock is only held while waiting, so the others can take the next call
+        let job = queue.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        match job {
+            Ok(job) => job(&mut db),
+            Err(_) => return,
+        }
+    }
+}
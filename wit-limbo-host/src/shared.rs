@@ -0,0 +1,91 @@
+//! [SharedDatabase], a [Database] that threads can share, e.g. as the state of an axum server.
+//!
+//! The guest is single threaded, with `Rc` and `RefCell` throughout, and a wasmtime
+//! [Store](wasmtime::Store) runs one call at a time, so every call on a [Database] takes
+//! `&mut self`. A [Database] is `Send`, so it can move to another thread, and [SharedDatabase]
+//! keeps it behind a mutex, so threads holding clones of it take turns calling it.
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{Connection, Database, Error, FromRow, Row, Value};
+
+/// A [Database] shared between threads, which runs one call at a time.
+///
+/// Clones refer to the same database. A call waits for any other thread's call to finish, so
+/// a long query holds up the rest; run independent databases in their own instances.
+#[derive(Clone)]
+pub struct SharedDatabase {
+    inner: Arc<Mutex<Database>>,
+}
+
+impl SharedDatabase {
+    pub fn new(db: Database) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(db)),
+        }
+    }
+
+    /// Run `f` on the database once no other thread is calling it, e.g. to run several
+    /// statements with nothing from other threads in between.
+    pub fn with<T>(&self, f: impl FnOnce(&mut Database) -> T) -> T {
+        // a thread that panicked part way through `f` left the database no worse than an
+        // error would have
+        let mut db = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        f(&mut db)
+    }
+
+    /// Execute a SQL statement, discarding any rows it produces.
+    pub fn exec(&self, sql: &str) -> Result<(), Error> {
+        self.with(|db| db.exec(sql))
+    }
+
+    /// Run `sql` with `params` bound in order, and collect the resulting rows.
+    pub fn query(&self, sql: &str, params: &[Value]) -> Result<Vec<Row>, Error> {
+        self.with(|db| db.query(sql, params))
+    }
+
+    /// Execute a SQL statement with `params` bound in order, discarding any rows it produces.
+    pub fn execute(&self, sql: &str, params: &[Value]) -> Result<(), Error> {
+        self.with(|db| db.execute(sql, params))
+    }
+
+    /// Map the first row of the result with `f`, failing with [Error::QueryReturnedNoRows]
+    /// if there is none.
+    pub fn query_row<T, F>(&self, sql: &str, params: &[Value], f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Row) -> Result<T, Error>,
+    {
+        let rows = self.query(sql, params)?;
+        f(rows.first().ok_or(Error::QueryReturnedNoRows)?)
+    }
+
+    /// Convert each row of the result into a `T`, see [FromRow].
+    pub fn query_as<T: FromRow>(&self, sql: &str, params: &[Value]) -> Result<Vec<T>, Error> {
+        self.query(sql, params)?.iter().map(T::from_row).collect()
+    }
+}
+
+impl From<Database> for SharedDatabase {
+    fn from(db: Database) -> Self {
+        Self::new(db)
+    }
+}
+
+impl Connection for SharedDatabase {
+    fn exec(&mut self, sql: &str) -> Result<(), Error> {
+        SharedDatabase::exec(self, sql)
+    }
+
+    fn query(&mut self, sql: &str, params: &[Value]) -> Result<Vec<Row>, Error> {
+        SharedDatabase::query(self, sql, params)
+    }
+
+    fn execute(&mut self, sql: &str, params: &[Value]) -> Result<(), Error> {
+        SharedDatabase::execute(self, sql, params)
+    }
+}
+
+/// Fails to compile if anything the [Store](wasmtime::Store) holds stops being `Send`.
+fn _assert_send_sync() {
+    fn check<T: Send + Sync>() {}
+    check::<SharedDatabase>();
+}
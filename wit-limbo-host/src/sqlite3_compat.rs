@@ -0,0 +1,340 @@
+//! Functions shaped like the SQLite C API, backed by a [Database] running in the component.
+//!
+//! This eases porting code written against `libsqlite3-sys` (or a thin wrapper over it):
+//! the names, argument order, result codes and type codes match SQLite, with out-parameters
+//! replaced by `&mut Option<_>` and raw pointers by Rust types.
+//!
+//! Rows are fetched from the component when a statement is first stepped, so
+//! [sqlite3_step] walks an already materialized result.
+//!
+//! ```no_run
+//! use wit_limbo_host::sqlite3_compat::*;
+//!
+//! let engine = wit_limbo_host::engine()?;
+//! let component = wasmtime::component::Component::from_file(&engine, "wit_limbo.wasm")?;
+//!
+//! let mut db = None;
+//! assert_eq!(sqlite3_open(&engine, &component, ":memory:", &mut db), SQLITE_OK);
+//! let db = db.unwrap();
+//!
+//! let mut stmt = None;
+//! sqlite3_prepare_v2(&db, "SELECT ? + 1", &mut stmt);
+//! let mut stmt = stmt.unwrap();
+//! sqlite3_bind_int64(&mut stmt, 1, 41);
+//! while sqlite3_step(&mut stmt) == SQLITE_ROW {
+//!     assert_eq!(sqlite3_column_int64(&stmt, 0), 42);
+//! }
+//! sqlite3_finalize(stmt);
+//! sqlite3_close(db);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use wasmtime::component::Component;
+use wasmtime::Engine;
+
+use crate::{Database, DbError, Error, Value};
+
+pub const SQLITE_OK: i32 = 0;
+pub const SQLITE_ERROR: i32 = 1;
+pub const SQLITE_BUSY: i32 = 5;
+pub const SQLITE_NOMEM: i32 = 7;
+pub const SQLITE_INTERRUPT: i32 = 9;
+pub const SQLITE_CANTOPEN: i32 = 14;
+pub const SQLITE_TOOBIG: i32 = 18;
+pub const SQLITE_MISUSE: i32 = 21;
+pub const SQLITE_RANGE: i32 = 25;
+pub const SQLITE_ROW: i32 = 100;
+pub const SQLITE_DONE: i32 = 101;
+
+pub const SQLITE_INTEGER: i32 = 1;
+pub const SQLITE_FLOAT: i32 = 2;
+pub const SQLITE_TEXT: i32 = 3;
+pub const SQLITE_BLOB: i32 = 4;
+pub const SQLITE_NULL: i32 = 5;
+
+struct Connection {
+    db: Database,
+    errmsg: String,
+}
+
+/// A database connection, the `sqlite3*` handle.
+pub struct Sqlite3 {
+    conn: Rc<RefCell<Connection>>,
+}
+
+/// A prepared statement, the `sqlite3_stmt*` handle.
+pub struct Sqlite3Stmt {
+    conn: Rc<RefCell<Connection>>,
+    sql: String,
+    params: Vec<Value>,
+    /// The result, once the statement has been stepped
+    result: Option<(Arc<[String]>, Vec<Vec<Value>>)>,
+    /// Index of the current row in `result`, valid after [sqlite3_step] returns [SQLITE_ROW]
+    row: Option<usize>,
+}
+
+/// The result code for a failed call, recording its message as the connection's [sqlite3_errmsg].
+fn error(conn: &mut Connection, e: Error) -> i32 {
+    conn.errmsg = e.to_string();
+    match e {
+        Error::Db(DbError::Busy(_)) => SQLITE_BUSY,
+        Error::Db(DbError::Closed(_)) => SQLITE_MISUSE,
+        Error::Db(DbError::Interrupted(_)) => SQLITE_INTERRUPT,
+        Error::Db(DbError::OutOfMemory(_)) => SQLITE_NOMEM,
+        Error::Db(DbError::LimitExceeded(_)) => SQLITE_TOOBIG,
+        Error::Db(DbError::CannotOpen(_)) => SQLITE_CANTOPEN,
+        _ => SQLITE_ERROR,
+    }
+}
+
+/// Open the database at `filename` in a new instance of `component`.
+pub fn sqlite3_open(
+    engine: &Engine,
+    component: &Component,
+    filename: &str,
+    pp_db: &mut Option<Sqlite3>,
+) -> i32 {
+    match Database::open(engine, component, filename) {
+        Ok(db) => {
+            *pp_db = Some(Sqlite3 {
+                conn: Rc::new(RefCell::new(Connection {
+                    db,
+                    errmsg: String::new(),
+                })),
+            });
+            SQLITE_OK
+        }
+        Err(_) => {
+            *pp_db = None;
+            SQLITE_ERROR
+        }
+    }
+}
+
+/// Close the connection. Statements still alive keep the instance around until finalized.
+pub fn sqlite3_close(db: Sqlite3) -> i32 {
+    let mut conn = db.conn.borrow_mut();
+    match conn.db.close() {
+        Ok(()) => SQLITE_OK,
+        Err(e) => error(&mut conn, e),
+    }
+}
+
+/// Retry statements that find the database locked for up to `ms` milliseconds before
+/// they fail with [SQLITE_BUSY]. A negative or zero `ms` turns the retries off.
+pub fn sqlite3_busy_timeout(db: &Sqlite3, ms: i32) -> i32 {
+    let mut conn = db.conn.borrow_mut();
+    match conn.db.set_busy_timeout(ms.max(0) as u32) {
+        Ok(()) => SQLITE_OK,
+        Err(e) => error(&mut conn, e),
+    }
+}
+
+/// Fail statements with [SQLITE_NOMEM] once the component's heap grows past `n` bytes,
+/// for every database in the instance, and return the previous limit, as SQLite does. A
+/// negative `n` leaves the limit as it is, also as SQLite does.
+pub fn sqlite3_soft_heap_limit64(db: &Sqlite3, n: i64) -> i64 {
+    let mut conn = db.conn.borrow_mut();
+    let Ok(before) = conn.db.set_soft_heap_limit(n.max(0) as u64) else {
+        return 0;
+    };
+    if n < 0 {
+        let _ = conn.db.set_soft_heap_limit(before);
+    }
+    before as i64
+}
+
+/// The message for the most recent failed call on `db`.
+pub fn sqlite3_errmsg(db: &Sqlite3) -> String {
+    db.conn.borrow().errmsg.clone()
+}
+
+/// The extended result code of the most recent failed call on `db`, e.g. 2067
+/// (`SQLITE_CONSTRAINT_UNIQUE`), or [SQLITE_OK] if none has failed.
+pub fn sqlite3_extended_errcode(db: &Sqlite3) -> i32 {
+    match db.conn.borrow_mut().db.last_error_code() {
+        Ok(code) => code.map_or(SQLITE_OK, |code| code as i32),
+        Err(_) => SQLITE_ERROR,
+    }
+}
+
+/// Run `sql`, discarding any rows it produces.
+pub fn sqlite3_exec(db: &Sqlite3, sql: &str) -> i32 {
+    let mut conn = db.conn.borrow_mut();
+    match conn.db.exec(sql) {
+        Ok(()) => SQLITE_OK,
+        Err(e) => error(&mut conn, e),
+    }
+}
+
+/// Prepare `sql` for stepping. Parameters are bound with the `sqlite3_bind_*` functions.
+pub fn sqlite3_prepare_v2(db: &Sqlite3, sql: &str, pp_stmt: &mut Option<Sqlite3Stmt>) -> i32 {
+    *pp_stmt = Some(Sqlite3Stmt {
+        conn: db.conn.clone(),
+        sql: sql.to_string(),
+        params: vec![],
+        result: None,
+        row: None,
+    });
+    SQLITE_OK
+}
+
+/// Advance to the next row, returning [SQLITE_ROW] or [SQLITE_DONE].
+///
+/// [SQLITE_BUSY] means another connection holds the write lock; reset and step again later.
+pub fn sqlite3_step(stmt: &mut Sqlite3Stmt) -> i32 {
+    if stmt.result.is_none() {
+        let mut conn = stmt.conn.borrow_mut();
+        match conn.db.query_columns(&stmt.sql, &stmt.params) {
+            Ok(result) => stmt.result = Some(result),
+            Err(e) => return error(&mut conn, e),
+        }
+    }
+
+    let rows = stmt.result.as_ref().map_or(0, |(_, rows)| rows.len());
+    let next = stmt.row.map_or(0, |row| row + 1);
+    if next < rows {
+        stmt.row = Some(next);
+        SQLITE_ROW
+    } else {
+        stmt.row = Some(rows);
+        SQLITE_DONE
+    }
+}
+
+/// Rewind the statement so the next [sqlite3_step] runs it again. Bindings are kept.
+pub fn sqlite3_reset(stmt: &mut Sqlite3Stmt) -> i32 {
+    stmt.result = None;
+    stmt.row = None;
+    SQLITE_OK
+}
+
+/// Clear all bindings back to NULL.
+pub fn sqlite3_clear_bindings(stmt: &mut Sqlite3Stmt) -> i32 {
+    stmt.params
+        .iter_mut()
+        .for_each(|param| *param = Value::Null);
+    SQLITE_OK
+}
+
+/// Destroy the statement.
+pub fn sqlite3_finalize(stmt: Sqlite3Stmt) -> i32 {
+    drop(stmt);
+    SQLITE_OK
+}
+
+fn bind(stmt: &mut Sqlite3Stmt, index: i32, value: Value) -> i32 {
+    if index < 1 {
+        return SQLITE_RANGE;
+    }
+    if stmt.result.is_some() {
+        // SQLite requires a reset before rebinding
+        return SQLITE_MISUSE;
+    }
+    let index = index as usize - 1;
+    if stmt.params.len() <= index {
+        stmt.params.resize(index + 1, Value::Null);
+    }
+    stmt.params[index] = value;
+    SQLITE_OK
+}
+
+pub fn sqlite3_bind_null(stmt: &mut Sqlite3Stmt, index: i32) -> i32 {
+    bind(stmt, index, Value::Null)
+}
+
+pub fn sqlite3_bind_int(stmt: &mut Sqlite3Stmt, index: i32, value: i32) -> i32 {
+    bind(stmt, index, Value::Integer(value as i64))
+}
+
+pub fn sqlite3_bind_int64(stmt: &mut Sqlite3Stmt, index: i32, value: i64) -> i32 {
+    bind(stmt, index, Value::Integer(value))
+}
+
+pub fn sqlite3_bind_double(stmt: &mut Sqlite3Stmt, index: i32, value: f64) -> i32 {
+    bind(stmt, index, Value::Float(value))
+}
+
+pub fn sqlite3_bind_text(stmt: &mut Sqlite3Stmt, index: i32, value: &str) -> i32 {
+    bind(stmt, index, Value::Text(value.to_string()))
+}
+
+pub fn sqlite3_bind_blob(stmt: &mut Sqlite3Stmt, index: i32, value: &[u8]) -> i32 {
+    bind(stmt, index, Value::Blob(value.to_vec()))
+}
+
+/// Number of columns in the result, which is only known once the statement has been stepped.
+pub fn sqlite3_column_count(stmt: &Sqlite3Stmt) -> i32 {
+    stmt.result
+        .as_ref()
+        .map_or(0, |(columns, _)| columns.len() as i32)
+}
+
+pub fn sqlite3_column_name(stmt: &Sqlite3Stmt, col: i32) -> Option<&str> {
+    let (columns, _) = stmt.result.as_ref()?;
+    columns.get(usize::try_from(col).ok()?).map(String::as_str)
+}
+
+/// The value in column `col` of the current row, if there is one.
+fn column(stmt: &Sqlite3Stmt, col: i32) -> Option<&Value> {
+    let (_, rows) = stmt.result.as_ref()?;
+    rows.get(stmt.row?)?.get(usize::try_from(col).ok()?)
+}
+
+pub fn sqlite3_column_type(stmt: &Sqlite3Stmt, col: i32) -> i32 {
+    match column(stmt, col) {
+        Some(Value::Integer(_)) => SQLITE_INTEGER,
+        Some(Value::Float(_)) => SQLITE_FLOAT,
+        Some(Value::Text(_)) | Some(Value::TextDecimal(_)) => SQLITE_TEXT,
+        Some(Value::Blob(_)) => SQLITE_BLOB,
+        Some(Value::Null) | None => SQLITE_NULL,
+    }
+}
+
+pub fn sqlite3_column_int64(stmt: &Sqlite3Stmt, col: i32) -> i64 {
+    match column(stmt, col) {
+        Some(Value::Integer(i)) => *i,
+        Some(Value::Float(f)) => *f as i64,
+        Some(Value::Text(s)) | Some(Value::TextDecimal(s)) => s.trim().parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+pub fn sqlite3_column_int(stmt: &Sqlite3Stmt, col: i32) -> i32 {
+    sqlite3_column_int64(stmt, col) as i32
+}
+
+pub fn sqlite3_column_double(stmt: &Sqlite3Stmt, col: i32) -> f64 {
+    match column(stmt, col) {
+        Some(Value::Integer(i)) => *i as f64,
+        Some(Value::Float(f)) => *f,
+        Some(Value::Text(s)) | Some(Value::TextDecimal(s)) => s.trim().parse().unwrap_or(0.0),
+        _ => 0.0,
+    }
+}
+
+/// The value as text, or `None` for NULL (where SQLite would return a null pointer).
+pub fn sqlite3_column_text(stmt: &Sqlite3Stmt, col: i32) -> Option<String> {
+    match column(stmt, col)? {
+        Value::Null => None,
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        Value::Text(s) | Value::TextDecimal(s) => Some(s.clone()),
+        Value::Blob(b) => Some(String::from_utf8_lossy(b).into_owned()),
+    }
+}
+
+/// The value as bytes, or `None` for NULL (where SQLite would return a null pointer).
+pub fn sqlite3_column_blob(stmt: &Sqlite3Stmt, col: i32) -> Option<Vec<u8>> {
+    match column(stmt, col)? {
+        Value::Blob(b) => Some(b.clone()),
+        _ => sqlite3_column_text(stmt, col).map(String::into_bytes),
+    }
+}
+
+pub fn sqlite3_column_bytes(stmt: &Sqlite3Stmt, col: i32) -> i32 {
+    sqlite3_column_blob(stmt, col).map_or(0, |bytes| bytes.len() as i32)
+}
@@ -0,0 +1,240 @@
+//! Conversions between Rust types and [Value], for binding parameters and reading rows
+//! without matching on variants by hand.
+use std::num::{IntErrorKind, ParseIntError};
+use std::str::FromStr;
+
+use crate::{Error, Row, Value};
+
+/// Types that can be bound as a statement parameter, see [params!](crate::params).
+pub trait ToValue {
+    fn to_value(&self) -> Value;
+}
+
+/// Types that can be read out of a column, see [Row::try_get].
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, Error>;
+}
+
+/// Types that can be built from a whole row, see [Database::query_as](crate::Database::query_as).
+///
+/// With the `derive` feature, `#[derive(FromRow)]` reads each field from the column of the same name.
+pub trait FromRow: Sized {
+    fn from_row(row: &Row) -> Result<Self, Error>;
+}
+
+/// Build the parameter slice for [Database::execute](crate::Database::execute) and friends
+/// from any values implementing [ToValue].
+///
+/// ```ignore
+/// db.execute("INSERT INTO users (id, name) VALUES (?, ?)", params![1, "Alice"])?;
+/// ```
+#[macro_export]
+macro_rules! params {
+    () => {
+        &[] as &[$crate::Value]
+    };
+    ($($param:expr),+ $(,)?) => {
+        &[$($crate::ToValue::to_value(&$param)),+] as &[$crate::Value]
+    };
+}
+
+impl ToValue for Value {
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl<T: ToValue + ?Sized> ToValue for &T {
+    fn to_value(&self) -> Value {
+        (**self).to_value()
+    }
+}
+
+impl<T: ToValue> ToValue for Option<T> {
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => value.to_value(),
+            None => Value::Null,
+        }
+    }
+}
+
+macro_rules! integer_to_value {
+    ($($ty:ty),*) => {
+        $(impl ToValue for $ty {
+            fn to_value(&self) -> Value {
+                Value::Integer(*self as i64)
+            }
+        })*
+    };
+}
+
+integer_to_value!(i8, i16, i32, i64, u8, u16, u32, bool);
+
+impl ToValue for u64 {
+    /// Values above `i64::MAX` become [Value::TextDecimal] rather than wrapping.
+    fn to_value(&self) -> Value {
+        match i64::try_from(*self) {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Value::TextDecimal(self.to_string()),
+        }
+    }
+}
+
+impl ToValue for i128 {
+    /// Values outside the `i64` range become [Value::TextDecimal] rather than wrapping.
+    fn to_value(&self) -> Value {
+        match i64::try_from(*self) {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Value::TextDecimal(self.to_string()),
+        }
+    }
+}
+
+impl ToValue for f32 {
+    fn to_value(&self) -> Value {
+        Value::Float(*self as f64)
+    }
+}
+
+impl ToValue for f64 {
+    fn to_value(&self) -> Value {
+        Value::Float(*self)
+    }
+}
+
+impl ToValue for str {
+    fn to_value(&self) -> Value {
+        Value::Text(self.to_string())
+    }
+}
+
+impl ToValue for String {
+    fn to_value(&self) -> Value {
+        Value::Text(self.clone())
+    }
+}
+
+impl ToValue for [u8] {
+    fn to_value(&self) -> Value {
+        Value::Blob(self.to_vec())
+    }
+}
+
+impl ToValue for Vec<u8> {
+    fn to_value(&self) -> Value {
+        Value::Blob(self.clone())
+    }
+}
+
+fn invalid_type(expected: &str, value: &Value) -> Error {
+    Error::InvalidType(format!("expected {}, found {:?}", expected, value))
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        Ok(value.clone())
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Integer(i) => Ok(*i),
+            value => Err(invalid_type("integer", value)),
+        }
+    }
+}
+
+macro_rules! integer_from_value {
+    ($($ty:ty),*) => {
+        $(impl FromValue for $ty {
+            fn from_value(value: &Value) -> Result<Self, Error> {
+                let i = i64::from_value(value)?;
+                <$ty>::try_from(i)
+                    .map_err(|_| Error::InvalidType(format!("{} out of range for {}", i, stringify!($ty))))
+            }
+        })*
+    };
+}
+
+integer_from_value!(i8, i16, i32, u8, u16, u32);
+
+impl FromValue for u64 {
+    /// Reads back what [ToValue] for `u64` binds, including the decimal text a value above
+    /// `i64::MAX` is stored as.
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        wide_integer(value, "u64")
+    }
+}
+
+impl FromValue for i128 {
+    /// Reads back what [ToValue] for `i128` binds, including the decimal text a value outside
+    /// the `i64` range is stored as.
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        wide_integer(value, "i128")
+    }
+}
+
+/// An integer, or text that is one, as `name`, failing if it is out of its range.
+fn wide_integer<T>(value: &Value, name: &str) -> Result<T, Error>
+where
+    T: TryFrom<i64> + FromStr<Err = ParseIntError>,
+{
+    let out_of_range = |value: &dyn std::fmt::Display| {
+        Error::InvalidType(format!("{} out of range for {}", value, name))
+    };
+    match value {
+        Value::Integer(i) => T::try_from(*i).map_err(|_| out_of_range(i)),
+        Value::Text(s) | Value::TextDecimal(s) => {
+            s.parse().map_err(|e: ParseIntError| match e.kind() {
+                IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => out_of_range(s),
+                _ => invalid_type("integer", value),
+            })
+        }
+        value => Err(invalid_type("integer", value)),
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        i64::from_value(value).map(|i| i != 0)
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Float(f) => Ok(*f),
+            Value::Integer(i) => Ok(*i as f64),
+            value => Err(invalid_type("float", value)),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Text(s) | Value::TextDecimal(s) => Ok(s.clone()),
+            value => Err(invalid_type("text", value)),
+        }
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Blob(b) => Ok(b.clone()),
+            value => Err(invalid_type("blob", value)),
+        }
+    }
+}
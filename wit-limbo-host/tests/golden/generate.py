@@ -0,0 +1,51 @@
+"""Regenerate the golden databases with upstream SQLite: python generate.py
+
+Every database has the same schema and rows, in a different on-disk shape.
+"""
+import os
+import sqlite3
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+SCHEMA = """
+CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, email TEXT, score REAL, avatar BLOB);
+CREATE INDEX users_name ON users (name);
+CREATE UNIQUE INDEX users_email ON users (email);
+CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL REFERENCES users (id), body TEXT);
+CREATE VIEW user_posts AS SELECT users.name, posts.body FROM users JOIN posts ON posts.user_id = users.id;
+CREATE TRIGGER users_delete AFTER DELETE ON users BEGIN DELETE FROM posts WHERE user_id = old.id; END;
+"""
+
+# enough rows to need interior pages at the smaller page sizes
+USERS = 1000
+
+
+def generate(name, page_size, journal_mode):
+    path = os.path.join(HERE, name)
+    if os.path.exists(path):
+        os.remove(path)
+    db = sqlite3.connect(path)
+    db.execute(f"PRAGMA page_size = {page_size}")
+    db.execute(f"PRAGMA journal_mode = {journal_mode}")
+    db.executescript(SCHEMA)
+    db.executemany(
+        "INSERT INTO users (name, email, score, avatar) VALUES (?, ?, ?, ?)",
+        [
+            (f"user {i}", f"user{i}@example.com", i / 4, bytes([i % 256]) * (i % 8))
+            for i in range(1, USERS + 1)
+        ],
+    )
+    db.executemany(
+        "INSERT INTO posts (user_id, body) VALUES (?, ?)",
+        [(i % USERS + 1, f"post {i}") for i in range(1, 2 * USERS + 1)],
+    )
+    db.commit()
+    # checkpoints the WAL into the main file, so only the .sqlite file is needed
+    db.close()
+
+
+generate("rollback-4096.sqlite", 4096, "DELETE")
+generate("rollback-512.sqlite", 512, "DELETE")
+generate("rollback-65536.sqlite", 65536, "DELETE")
+generate("wal-4096.sqlite", 4096, "WAL")
+generate("wal-1024.sqlite", 1024, "WAL")
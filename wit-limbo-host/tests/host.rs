@@ -0,0 +1,497 @@
+use std::path::{Path, PathBuf};
+
+use wasmtime::component::Component;
+use wit_limbo_host::{
+    params, Database, DbError, Error, FromValue, HostFs, OpenFlags, SharedDatabase, Value,
+};
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn test_exec_and_query() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let mut db = Database::open(&engine, &component, ":memory:")?;
+    db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);")?;
+    db.exec("INSERT INTO users (name) VALUES ('Alice');")?;
+    db.exec("INSERT INTO users (name) VALUES ('Bob');")?;
+
+    let rows = db.query("SELECT * FROM users WHERE id = ?", &[Value::Integer(2)])?;
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].columns(), ["id", "name"]);
+    assert_eq!(
+        rows[0].get_by_name("name"),
+        Some(&Value::Text("Bob".into()))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_file_database_persists() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    let dir = tempfile::tempdir().unwrap();
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    {
+        let fs = HostFs::with_root(dir.path());
+        let mut db = Database::open_with(&engine, &component, "test.db", fs)?;
+        db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);")?;
+        db.exec("INSERT INTO users (name) VALUES ('Alice');")?;
+    }
+
+    // a fresh instance sees what the first one wrote
+    let fs = HostFs::with_root(dir.path());
+    let mut db = Database::open_with(&engine, &component, "test.db", fs)?;
+    let rows = db.query("SELECT name FROM users", &[])?;
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get(0), Some(&Value::Text("Alice".into())));
+
+    Ok(())
+}
+
+#[test]
+fn test_file_uri_params() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    let dir = tempfile::tempdir().unwrap();
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+    let open = |uri: &str, flags| {
+        let fs = HostFs::with_root(dir.path());
+        Database::open_with_flags(&engine, &component, uri, fs, flags)
+    };
+
+    // `mode=rw` doesn't create a missing database, `mode=rwc` does, whatever the flags say
+    let result = open("file:uri.db?mode=rw", OpenFlags::CREATE);
+    assert!(
+        matches!(result, Err(Error::Db(DbError::CannotOpen(_)))),
+        "{:?}",
+        result.err()
+    );
+    let mut db = open("file:uri.db?mode=rwc", OpenFlags::empty())?;
+    db.exec("CREATE TABLE t (x); INSERT INTO t VALUES (1);")?;
+    drop(db);
+    let mut db = open("file:uri.db?mode=rw", OpenFlags::empty())?;
+    db.exec("INSERT INTO t VALUES (2);")?;
+    drop(db);
+
+    // `mode=ro` reads, but refuses writes
+    let mut db = open("file:uri.db?mode=ro", OpenFlags::CREATE)?;
+    assert_eq!(db.query("SELECT x FROM t", &[])?.len(), 2);
+    let result = db.exec("INSERT INTO t VALUES (3);");
+    assert!(
+        matches!(result, Err(Error::Db(DbError::Misuse(_)))),
+        "{:?}",
+        result
+    );
+    drop(db);
+
+    // a parameter that isn't supported is refused rather than ignored
+    for uri in [
+        "file:uri.db?mode=memory",
+        "file:uri.db?cache=shared",
+        "file:uri.db?mode=ro&immutable=1",
+        "file:uri.db?nolock",
+    ] {
+        let result = open(uri, OpenFlags::empty());
+        assert!(
+            matches!(result, Err(Error::Db(DbError::CannotOpen(_)))),
+            "{}: {:?}",
+            uri,
+            result.err()
+        );
+    }
+    let fs = HostFs::with_root(dir.path());
+    let result = Database::open_with(&engine, &component, "file:uri.db?cache=shared", fs);
+    assert!(
+        matches!(result, Err(Error::Db(DbError::CannotOpen(_)))),
+        "{:?}",
+        result.err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_readahead_scan() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    let dir = tempfile::tempdir().unwrap();
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+    let fs = HostFs::with_root(dir.path());
+    let mut db = Database::open_with(&engine, &component, "scan.db", fs)?;
+    db.exec("CREATE TABLE items (id INTEGER PRIMARY KEY, body TEXT);")?;
+    for i in 0..200 {
+        db.execute(
+            "INSERT INTO items (id, body) VALUES (?, hex(zeroblob(200)))",
+            &[Value::Integer(i)],
+        )?;
+    }
+    db.close()?;
+
+    // a fresh instance reads the pages from the file, many of them read ahead
+    let fs = HostFs::with_root(dir.path());
+    let mut db = Database::open_with(&engine, &component, "scan.db", fs)?;
+    db.set_readahead_pages(8)?;
+    let rows = db.query("SELECT id, length(body) FROM items ORDER BY id", &[])?;
+    assert_eq!(rows.len(), 200);
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(row.get(0), Some(&Value::Integer(i as i64)));
+        assert_eq!(row.get(1), Some(&Value::Integer(400)));
+    }
+
+    // writes after reading ahead are seen
+    db.execute("UPDATE items SET body = 'x' WHERE id = 100", &[])?;
+    let rows = db.query("SELECT body FROM items WHERE id = 100", &[])?;
+    assert_eq!(rows[0].get(0), Some(&Value::Text("x".into())));
+
+    let mut memory = Database::open(&engine, &component, ":memory:")?;
+    assert!(matches!(
+        memory.set_readahead_pages(8),
+        Err(wit_limbo_host::Error::Db(wit_limbo_host::DbError::Misuse(
+            _
+        )))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_coalesce_writes() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    let dir = tempfile::tempdir().unwrap();
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+    let fs = HostFs::with_root(dir.path());
+    let mut db = Database::open_with(&engine, &component, "held.db", fs)?;
+    db.set_coalesce_writes(true)?;
+    db.exec("CREATE TABLE items (id INTEGER PRIMARY KEY, body TEXT);")?;
+    db.exec("BEGIN;")?;
+    for i in 0..200 {
+        db.execute(
+            "INSERT INTO items (id, body) VALUES (?, hex(zeroblob(200)))",
+            &[Value::Integer(i)],
+        )?;
+    }
+    db.exec("COMMIT;")?;
+    // the held writes are read back before and after they are written
+    let rows = db.query("SELECT count(*) FROM items", &[])?;
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(200)));
+    db.exec("PRAGMA wal_checkpoint;")?;
+    db.execute("UPDATE items SET body = 'x' WHERE id = 100", &[])?;
+    db.close()?;
+
+    let fs = HostFs::with_root(dir.path());
+    let mut db = Database::open_with(&engine, &component, "held.db", fs)?;
+    let rows = db.query("SELECT count(*), max(body) FROM items", &[])?;
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(200)));
+    assert_eq!(rows[0].get(1), Some(&Value::Text("x".into())));
+
+    let mut memory = Database::open(&engine, &component, ":memory:")?;
+    assert!(matches!(
+        memory.set_coalesce_writes(true),
+        Err(wit_limbo_host::Error::Db(wit_limbo_host::DbError::Misuse(
+            _
+        )))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_host_fs_submit_and_poll() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("pages"), b"0123456789").unwrap();
+
+    let mut fs = HostFs::with_root(dir.path());
+    let fd = fs.open("pages", false).unwrap();
+    let first = fs.submit(fd, 2, 4);
+    // past the end of the file reads what there is
+    let second = fs.submit(fd, 8, 4);
+    assert_ne!(first, second);
+    assert_eq!(fs.poll(second), Some(Ok(b"89".to_vec())));
+    assert_eq!(fs.poll(first), Some(Ok(b"2345".to_vec())));
+    // each result is returned once
+    assert_eq!(fs.poll(first), None);
+    let bad = fs.submit(fd + 1, 0, 4);
+    assert!(matches!(fs.poll(bad), Some(Err(_))));
+}
+
+#[test]
+fn test_params_and_query_row() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let mut db = Database::open(&engine, &component, ":memory:")?;
+    db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INTEGER);")?;
+    db.execute(
+        "INSERT INTO users (name, age) VALUES (?, ?);",
+        params!["Alice", 30],
+    )?;
+    db.execute(
+        "INSERT INTO users (name, age) VALUES (?, ?);",
+        params!["Bob", None::<i64>],
+    )?;
+
+    let ages = db.query_map("SELECT age FROM users ORDER BY id;", params![], |row| {
+        row.try_get::<Option<u32>>(0)
+    })?;
+    assert_eq!(ages, vec![Some(30), None]);
+
+    let name: String = db.query_row("SELECT name FROM users WHERE id = ?;", params![2], |row| {
+        row.try_get_by_name("name")
+    })?;
+    assert_eq!(name, "Bob");
+
+    assert!(matches!(
+        db.query_row("SELECT name FROM users WHERE id = ?;", params![3], |row| {
+            row.try_get::<String>(0)
+        }),
+        Err(wit_limbo_host::Error::QueryReturnedNoRows)
+    ));
+
+    Ok(())
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn test_wide_integers_round_trip() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let mut db = Database::open(&engine, &component, ":memory:")?;
+    db.exec("CREATE TABLE n (id INTEGER PRIMARY KEY, small INTEGER, wide TEXT);")?;
+    // values outside `i64` are bound as text-decimal, and read back exactly
+    db.execute(
+        "INSERT INTO n (small, wide) VALUES (?, ?);",
+        params![7u64, u64::MAX],
+    )?;
+    db.execute(
+        "INSERT INTO n (small, wide) VALUES (?, ?);",
+        params![-7i128, i128::MIN],
+    )?;
+    let rows = db.query("SELECT small, wide FROM n ORDER BY id;", params![])?;
+    assert_eq!(rows[0].try_get::<u64>(0)?, 7);
+    assert_eq!(rows[0].try_get::<u64>(1)?, u64::MAX);
+    assert_eq!(rows[0].try_get::<i128>(1)?, u64::MAX as i128);
+    assert_eq!(rows[1].try_get::<i128>(0)?, -7);
+    assert_eq!(rows[1].try_get::<i128>(1)?, i128::MIN);
+
+    // out of range for the type asked for
+    for result in [rows[1].try_get::<u64>(0), rows[1].try_get::<u64>(1)] {
+        assert!(matches!(result, Err(Error::InvalidType(_))), "{:?}", result);
+    }
+    let too_wide = Value::Text("18446744073709551616".into());
+    let result = u64::from_value(&too_wide);
+    assert!(
+        matches!(&result, Err(Error::InvalidType(message)) if message.contains("out of range")),
+        "{:?}",
+        result
+    );
+
+    // text that isn't an integer isn't read as one
+    for text in ["abc", "1.5", "", "1e3"] {
+        let result = u64::from_value(&Value::TextDecimal(text.into()));
+        assert!(matches!(result, Err(Error::InvalidType(_))), "{:?}", result);
+    }
+    // nor bound as a decimal
+    let result = db.execute(
+        "INSERT INTO n (wide) VALUES (?);",
+        &[Value::TextDecimal("12abc".into())],
+    );
+    assert!(
+        matches!(result, Err(Error::Db(DbError::Misuse(_)))),
+        "{:?}",
+        result
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_derive_from_row() -> Result<(), wit_limbo_host::Error> {
+    #[derive(Debug, PartialEq, wit_limbo_host::FromRow)]
+    struct User {
+        id: i64,
+        #[limbo(rename = "name")]
+        username: String,
+        age: Option<u32>,
+    }
+
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let mut db = Database::open(&engine, &component, ":memory:")?;
+    db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, age INTEGER);")?;
+    db.execute(
+        "INSERT INTO users (name, age) VALUES (?, ?);",
+        params!["Alice", 30],
+    )?;
+
+    let users: Vec<User> = db.query_as("SELECT * FROM users;", params![])?;
+    assert_eq!(
+        users,
+        vec![User {
+            id: 1,
+            username: "Alice".to_string(),
+            age: Some(30),
+        }]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_query_deserialize() -> Result<(), wit_limbo_host::Error> {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct User {
+        id: i64,
+        name: String,
+        active: bool,
+        nickname: Option<String>,
+    }
+
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let mut db = Database::open(&engine, &component, ":memory:")?;
+    db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, active INTEGER, nickname TEXT);")?;
+    db.execute(
+        "INSERT INTO users (name, active, nickname) VALUES (?, ?, ?);",
+        params!["Alice", true, None::<String>],
+    )?;
+
+    let users: Vec<User> = db.query_deserialize("SELECT * FROM users;", &[])?;
+    assert_eq!(
+        users,
+        vec![User {
+            id: 1,
+            name: "Alice".to_string(),
+            active: true,
+            nickname: None,
+        }]
+    );
+
+    // tuples are read by position
+    let pairs: Vec<(i64, String)> = db.query_deserialize("SELECT id, name FROM users;", &[])?;
+    assert_eq!(pairs, vec![(1, "Alice".to_string())]);
+
+    Ok(())
+}
+
+#[test]
+fn test_shared_database() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let db = SharedDatabase::new(Database::open(&engine, &component, ":memory:")?);
+    db.exec("CREATE TABLE hits (thread INTEGER NOT NULL, n INTEGER NOT NULL);")?;
+
+    // threads holding clones take turns, and every write lands
+    let threads: Vec<_> = (0..8)
+        .map(|thread| {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                for n in 0..10 {
+                    db.execute("INSERT INTO hits VALUES (?, ?);", params![thread, n])?;
+                }
+                Ok::<_, wit_limbo_host::Error>(())
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+    let count: i64 = db.query_row("SELECT count(*) FROM hits;", params![], |row| {
+        row.try_get(0)
+    })?;
+    assert_eq!(count, 80);
+
+    // `with` runs several calls with none from other threads in between
+    let total: i64 = db.with(|db| {
+        db.exec("BEGIN;")?;
+        db.exec("DELETE FROM hits WHERE n >= 5;")?;
+        db.exec("COMMIT;")?;
+        db.query_row("SELECT sum(n) FROM hits;", params![], |row| row.try_get(0))
+    })?;
+    assert_eq!(total, 8 * 10);
+
+    Ok(())
+}
+
+#[test]
+fn test_cached_query() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let mut db = Database::open(&engine, &component, ":memory:")?;
+    db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);")?;
+    db.exec("INSERT INTO users (name) VALUES ('Alice'), ('Bob'), (NULL);")?;
+
+    // the statement is reused with new bindings each time
+    let sql = "SELECT name FROM users WHERE id = ?";
+    for (id, name) in [(1, "Alice"), (2, "Bob"), (1, "Alice")] {
+        let rows = db.cached_query(sql, params![id])?;
+        assert_eq!(rows[0].columns(), ["name"]);
+        assert_eq!(rows[0].get(0), Some(&Value::Text(name.into())));
+    }
+
+    // parameters left out are NULL, as they would be for a freshly prepared statement
+    let sql = "SELECT count(*) FROM users WHERE id = ? OR name IS ?";
+    let rows = db.cached_query(sql, params![1, "Bob"])?;
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(2)));
+    let rows = db.cached_query(sql, params![3])?;
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(1)));
+
+    // a statement that fails isn't kept, and the next call prepares it again
+    let sql = "INSERT INTO users (id, name) VALUES (?, ?)";
+    let result = db.cached_query(sql, params![1, "Carol"]);
+    assert!(
+        matches!(result, Err(wit_limbo_host::Error::Db(_))),
+        "{:?}",
+        result
+    );
+    db.cached_query(sql, params![4, "Carol"])?;
+
+    // with no capacity nothing is kept, and queries still run
+    db.set_statement_cache_capacity(0)?;
+    let rows = db.cached_query("SELECT count(*) FROM users", &[])?;
+    assert_eq!(rows[0].get(0), Some(&Value::Integer(4)));
+    db.close()?;
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::path::{Path, PathBuf};
+
+use wit_limbo_host::{layer_host::LimboInstance, Value};
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn test_layer_exec_and_query() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    let bytes = std::fs::read(wasm_path).unwrap();
+
+    let engine = wasm_component_layer::Engine::new(wasmi_runtime_layer::Engine::default());
+    let mut instance = LimboInstance::new(&engine, &bytes)?;
+
+    let db = instance.open(":memory:")?;
+    instance.exec(
+        &db,
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);",
+    )?;
+    instance.exec(&db, "INSERT INTO users (name) VALUES ('Alice');")?;
+
+    let rows = instance.query(
+        &db,
+        "SELECT * FROM users WHERE id = ?",
+        &[Value::Integer(1)],
+    )?;
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(
+        rows[0].get_by_name("name"),
+        Some(&Value::Text("Alice".into()))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use wasmtime::component::Component;
+use wit_limbo_host::native::NativeDatabase;
+use wit_limbo_host::{params, Connection, Database, DbError, Error};
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+/// The same workload, whichever backend runs it.
+fn workload(db: &mut impl Connection) -> Result<Vec<(i64, String, Option<f64>)>, Error> {
+    db.exec("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL);")?;
+    db.execute(
+        "INSERT INTO users (name, score) VALUES (?, ?);",
+        params!["Alice", 1.5],
+    )?;
+    db.execute(
+        "INSERT INTO users (name, score) VALUES (?, ?);",
+        params!["Bob", None::<f64>],
+    )?;
+
+    db.query_map(
+        "SELECT id, name, score FROM users ORDER BY id;",
+        params![],
+        |row| Ok((row.try_get(0)?, row.try_get(1)?, row.try_get(2)?)),
+    )
+}
+
+#[test]
+fn test_native_and_wasm_agree() -> Result<(), Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+    let mut wasm = Database::open(&engine, &component, ":memory:")?;
+    let mut native = NativeDatabase::open(":memory:");
+
+    let rows = workload(&mut native)?;
+    assert_eq!(
+        rows,
+        [
+            (1, "Alice".to_string(), Some(1.5)),
+            (2, "Bob".to_string(), None)
+        ]
+    );
+    assert_eq!(workload(&mut wasm)?, rows);
+
+    // errors come back the same way too
+    for db in [&mut wasm as &mut dyn Exec, &mut native] {
+        assert!(matches!(
+            db.run("SELEKT 1;"),
+            Err(Error::Db(DbError::Parse(_)))
+        ));
+    }
+
+    Ok(())
+}
+
+/// [Connection] has generic methods, so isn't object safe; this is enough to loop over both.
+trait Exec {
+    fn run(&mut self, sql: &str) -> Result<(), Error>;
+}
+
+impl<C: Connection> Exec for C {
+    fn run(&mut self, sql: &str) -> Result<(), Error> {
+        self.exec(sql)
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+
+use wasmtime::component::Component;
+use wit_limbo_host::{pgwire, Database};
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+/// Read backend messages until ReadyForQuery, returning them as (tag, body) pairs.
+fn read_until_ready(stream: &mut TcpStream) -> Vec<(u8, Vec<u8>)> {
+    let mut messages = vec![];
+    loop {
+        let mut header = [0u8; 5];
+        stream.read_exact(&mut header).unwrap();
+        let len = i32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+        let mut body = vec![0u8; len - 4];
+        stream.read_exact(&mut body).unwrap();
+        messages.push((header[0], body));
+        if header[0] == b'Z' {
+            return messages;
+        }
+    }
+}
+
+fn query(stream: &mut TcpStream, sql: &str) -> Vec<(u8, Vec<u8>)> {
+    let mut msg = vec![b'Q'];
+    msg.extend_from_slice(&(sql.len() as i32 + 5).to_be_bytes());
+    msg.extend_from_slice(sql.as_bytes());
+    msg.push(0);
+    stream.write_all(&msg).unwrap();
+    read_until_ready(stream)
+}
+
+#[test]
+fn test_simple_query_protocol() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+    let mut db = Database::open(&engine, &component, ":memory:")?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = std::thread::spawn(move || {
+        let mut stream = TcpStream::connect(addr).unwrap();
+
+        // StartupMessage: protocol 3.0, user=test
+        let mut params = b"user\0test\0\0".to_vec();
+        let mut startup = ((params.len() + 8) as i32).to_be_bytes().to_vec();
+        startup.extend_from_slice(&196_608i32.to_be_bytes());
+        startup.append(&mut params);
+        stream.write_all(&startup).unwrap();
+
+        let messages = read_until_ready(&mut stream);
+        assert_eq!(messages[0], (b'R', 0i32.to_be_bytes().to_vec()));
+
+        query(
+            &mut stream,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);",
+        );
+        query(&mut stream, "INSERT INTO users (name) VALUES ('Alice');");
+        let messages = query(&mut stream, "SELECT id, name FROM users;");
+
+        let tags: Vec<u8> = messages.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, b"TDCZ");
+        // two columns: 1 and 'Alice'
+        assert_eq!(
+            messages[1].1,
+            [
+                &[0, 2][..],
+                &1i32.to_be_bytes(),
+                b"1",
+                &5i32.to_be_bytes(),
+                b"Alice"
+            ]
+            .concat()
+        );
+        assert_eq!(messages[2].1, b"SELECT 1\0");
+
+        stream.write_all(&[b'X', 0, 0, 0, 4]).unwrap();
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    pgwire::serve_connection(stream, &mut db).unwrap();
+    client.join().unwrap();
+
+    Ok(())
+}
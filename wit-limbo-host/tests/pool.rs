@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use wasmtime::component::Component;
+use wit_limbo_host::pool::DbPool;
+use wit_limbo_host::{Database, Error, HostFs, OpenFlags, Value};
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn test_pool_reads_concurrently() -> Result<(), Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+    let dir = tempfile::tempdir().unwrap();
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+    {
+        let fs = HostFs::with_root(dir.path());
+        let mut db = Database::open_with(&engine, &component, "pool.db", fs)?;
+        db.exec("CREATE TABLE t (n INTEGER PRIMARY KEY);")?;
+        for n in 1..=100 {
+            db.execute("INSERT INTO t VALUES (?);", &[Value::Integer(n)])?;
+        }
+        db.close()?;
+    }
+
+    // instances opening the file read-only each answer their share of the queries
+    let pool = Arc::new(DbPool::new(4, |_| {
+        let fs = HostFs::with_root(dir.path());
+        Database::open_with_flags(&engine, &component, "pool.db", fs, OpenFlags::READ_ONLY)
+    })?);
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let sums = runtime.block_on(async {
+        let queries: Vec<_> = (1..=20)
+            .map(|n| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let sql = "SELECT sum(n) FROM t WHERE n <= ?;";
+                    let rows = pool.query(sql, vec![Value::Integer(n)]).await?;
+                    rows[0].try_get::<i64>(0)
+                })
+            })
+            .collect();
+        let mut sums = vec![];
+        for query in queries {
+            sums.push(query.await.unwrap()?);
+        }
+        Ok::<_, Error>(sums)
+    })?;
+    assert_eq!(
+        sums,
+        (1..=20).map(|n| n * (n + 1) / 2).collect::<Vec<i64>>()
+    );
+
+    // errors come back to the caller, and the instance carries on
+    runtime.block_on(async {
+        let result = pool.exec("INSERT INTO t VALUES (0);").await;
+        assert!(matches!(result, Err(Error::Db(_))), "{:?}", result);
+        let rows = pool.query("SELECT count(*) FROM t;", vec![]).await?;
+        assert_eq!(rows[0].get(0), Some(&Value::Integer(100)));
+        Ok::<_, Error>(())
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn test_pool_of_one_writes() -> Result<(), Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+    let pool = DbPool::new(1, |_| Database::open(&engine, &component, ":memory:"))?;
+
+    // instances that could write the same file would each lock it as if it were theirs alone
+    let dir = tempfile::tempdir().unwrap();
+    for path in ["pool.db", "file:pool.db?mode=rw"] {
+        let result = DbPool::new(2, |_| {
+            let fs = HostFs::with_root(dir.path());
+            Database::open_with(&engine, &component, path, fs)
+        });
+        assert!(
+            matches!(result, Err(Error::PoolWritesFile(2))),
+            "{}: {:?}",
+            path,
+            result.err()
+        );
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let count = runtime.block_on(async {
+        pool.exec("CREATE TABLE t (n INTEGER);").await?;
+        let inserts: Vec<_> = (0..10)
+            .map(|n| pool.execute("INSERT INTO t VALUES (?);", vec![Value::Integer(n)]))
+            .collect();
+        for insert in inserts {
+            insert.await?;
+        }
+        // `call` runs several statements on the instance with nothing in between
+        pool.call(|db| {
+            db.exec("DELETE FROM t WHERE n >= 5;")?;
+            db.query_row("SELECT count(*) FROM t;", &[], |row| row.try_get::<i64>(0))
+        })
+        .await
+    })?;
+    assert_eq!(count, 5);
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+
+use wasmtime::component::Component;
+use wit_limbo_host::sqlite3_compat::*;
+
+/// Utility function to get the workspace dir
+pub fn workspace_dir() -> PathBuf {
+    let output = std::process::Command::new(env!("CARGO"))
+        .arg("locate-project")
+        .arg("--workspace")
+        .arg("--message-format=plain")
+        .output()
+        .unwrap()
+        .stdout;
+    let cargo_path = Path::new(std::str::from_utf8(&output).unwrap().trim());
+    cargo_path.parent().unwrap().to_path_buf()
+}
+
+#[test]
+fn test_prepare_bind_step() -> Result<(), wit_limbo_host::Error> {
+    let wasm_path = workspace_dir().join("target/wasm32-unknown-unknown/release/wit_limbo.wasm");
+
+    let engine = wit_limbo_host::engine()?;
+    let component = Component::from_file(&engine, wasm_path)?;
+
+    let mut db = None;
+    assert_eq!(
+        sqlite3_open(&engine, &component, ":memory:", &mut db),
+        SQLITE_OK
+    );
+    let db = db.unwrap();
+
+    assert_eq!(
+        sqlite3_exec(
+            &db,
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);"
+        ),
+        SQLITE_OK
+    );
+
+    let mut insert = None;
+    sqlite3_prepare_v2(&db, "INSERT INTO users (name) VALUES (?);", &mut insert);
+    let mut insert = insert.unwrap();
+    for name in ["Alice", "Bob"] {
+        assert_eq!(sqlite3_bind_text(&mut insert, 1, name), SQLITE_OK);
+        assert_eq!(sqlite3_step(&mut insert), SQLITE_DONE);
+        sqlite3_reset(&mut insert);
+    }
+    sqlite3_finalize(insert);
+
+    let mut select = None;
+    sqlite3_prepare_v2(&db, "SELECT id, name FROM users ORDER BY id;", &mut select);
+    let mut select = select.unwrap();
+
+    let mut rows = vec![];
+    while sqlite3_step(&mut select) == SQLITE_ROW {
+        assert_eq!(sqlite3_column_type(&select, 0), SQLITE_INTEGER);
+        rows.push((
+            sqlite3_column_int64(&select, 0),
+            sqlite3_column_text(&select, 1).unwrap(),
+        ));
+    }
+    assert_eq!(sqlite3_column_count(&select), 2);
+    assert_eq!(sqlite3_column_name(&select, 1), Some("name"));
+    assert_eq!(rows, vec![(1, "Alice".to_string()), (2, "Bob".to_string())]);
+
+    // a duplicate key fails with SQLITE_ERROR, and its extended code says why
+    assert_eq!(
+        sqlite3_exec(&db, "INSERT INTO users VALUES (1, 'Carol');"),
+        SQLITE_ERROR
+    );
+    assert_eq!(sqlite3_extended_errcode(&db), 2067);
+
+    assert_eq!(sqlite3_finalize(select), SQLITE_OK);
+    assert_eq!(sqlite3_close(db), SQLITE_OK);
+
+    Ok(())
+}
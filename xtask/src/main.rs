@@ -0,0 +1,208 @@
+//! Build tasks that need more than `cargo build`.
+//!
+//! ```sh
+//! cargo xtask build-component [cargo build args, e.g. --features http]
+//! cargo xtask build-component --features wasi-logging
+//! cargo xtask build-component --features fault-injection
+//! cargo xtask build-component --features in-memory
+//! cargo xtask build-small
+//! ```
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+type Error = Box<dyn std::error::Error>;
+
+const TARGET: &str = "wasm32-unknown-unknown";
+
+/// The world in `wit/` the component implements.
+const WORLD: &str = "example";
+
+/// The world a component built with the `wasi-logging` feature implements.
+const WASI_LOGGING_WORLD: &str = "example-wasi-logging";
+
+/// The world a component built with the `fault-injection` feature implements.
+const FAULT_INJECTION_WORLD: &str = "example-fault-injection";
+
+/// The world a component built with the `in-memory` feature implements. It is written to
+/// `wit_limbo_in_memory.wasm`, next to the component with storage rather than over it.
+const IN_MEMORY_WORLD: &str = "example-in-memory";
+
+/// The interface the built component must export.
+const EXPORT: &str = "export component:wit-limbo/limbo@0.2.0";
+
+const USAGE: &str = "usage: cargo xtask <task> [cargo build args]
+
+tasks:
+  build-component  build target/wasm32-unknown-unknown/release/wit_limbo.wasm
+  build-small      build the component with the release-small profile and wasm-opt";
+
+fn main() -> Result<(), Error> {
+    let mut args = std::env::args().skip(1);
+    let task = args.next();
+    let cargo_args: Vec<String> = args.collect();
+
+    match task.as_deref() {
+        Some("build-component") => build_component("release", &cargo_args, false).map(|_| ()),
+        Some("build-small") => build_component("release-small", &cargo_args, true).map(|_| ()),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn workspace_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+/// Run `cmd`, failing if it can't be started or exits unsuccessfully.
+fn run(cmd: &mut Command) -> Result<(), Error> {
+    output(cmd.stdout(std::process::Stdio::inherit())).map(|_| ())
+}
+
+/// Run `cmd` and return what it printed, failing if it can't be started or exits unsuccessfully.
+fn output(cmd: &mut Command) -> Result<String, Error> {
+    eprintln!("$ {:?}", cmd);
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output = cmd
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(|e| format!("could not run {}: {}", program, e))?;
+    if !output.status.success() {
+        return Err(format!("{} failed: {}", program, output.status).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn cargo() -> Command {
+    Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+}
+
+/// Build the component with `profile`, returning the path to it:
+/// `target/wasm32-unknown-unknown/<profile>/wit_limbo.wasm`, where the tests look for it, or
+/// `wit_limbo_in_memory.wasm` for the `in-memory` feature.
+///
+/// 1. build the core module with `cargo build`, which generates the bindings from `wit/`
+/// 2. if `optimize`, shrink it with `wasm-opt`, which only understands core modules
+/// 3. embed the `wit/` world with `wasm-tools component embed`
+/// 4. wrap it as a component with `wasm-tools component new`
+/// 5. validate the component and check it exports the limbo interface
+fn build_component(profile: &str, cargo_args: &[String], optimize: bool) -> Result<PathBuf, Error> {
+    let root = workspace_dir();
+    run(cargo()
+        .current_dir(&root)
+        .args([
+            "build",
+            "--package",
+            "wit-limbo",
+            "--target",
+            TARGET,
+            "--profile",
+            profile,
+        ])
+        .args(cargo_args))?;
+
+    let world = world(cargo_args)?;
+    let out = root.join("target").join(TARGET).join(profile);
+    let wasm = match world {
+        IN_MEMORY_WORLD => out.join("wit_limbo_in_memory.wasm"),
+        _ => out.join("wit_limbo.wasm"),
+    };
+    let core = out.join("wit_limbo.core.wasm");
+    let embedded = out.join("wit_limbo.embedded.wasm");
+
+    // read cargo's own output, the uplifted copy is a hard link we are about to replace
+    std::fs::copy(out.join("deps").join("wit_limbo.wasm"), &core)?;
+    let before = std::fs::metadata(&core)?.len();
+
+    if optimize {
+        // the features rustc enables by default for wasm32-unknown-unknown
+        run(Command::new("wasm-opt")
+            .args([
+                "-Oz",
+                "--strip-debug",
+                "--strip-producers",
+                "--enable-bulk-memory",
+                "--enable-sign-ext",
+                "--enable-mutable-globals",
+                "--enable-nontrapping-float-to-int",
+                "--enable-multivalue",
+                "--enable-reference-types",
+            ])
+            .arg(&core)
+            .arg("-o")
+            .arg(&core))?;
+    }
+
+    run(Command::new("wasm-tools")
+        .args(["component", "embed"])
+        .arg(root.join("wit"))
+        .args(["--world", world])
+        .arg(&core)
+        .arg("-o")
+        .arg(&embedded))?;
+    if wasm.exists() {
+        std::fs::remove_file(&wasm)?;
+    }
+    run(Command::new("wasm-tools")
+        .args(["component", "new"])
+        .arg(&embedded)
+        .arg("-o")
+        .arg(&wasm))?;
+    std::fs::remove_file(&core)?;
+    std::fs::remove_file(&embedded)?;
+
+    run(Command::new("wasm-tools").arg("validate").arg(&wasm))?;
+    let wit = output(
+        Command::new("wasm-tools")
+            .args(["component", "wit"])
+            .arg(&wasm),
+    )?;
+    if !wit.lines().any(|line| line.trim().starts_with(EXPORT)) {
+        return Err(format!("{} does not `{}`:\n{}", wasm.display(), EXPORT, wit).into());
+    }
+
+    let after = std::fs::metadata(&wasm)?.len();
+    println!(
+        "{}: {} bytes (core module was {} bytes)",
+        wasm.strip_prefix(&root)?.display(),
+        after,
+        before
+    );
+    Ok(wasm)
+}
+
+/// The world for a build with `cargo_args`, which imports wasi:logging if the `wasi-logging`
+/// feature is enabled, host-faults if `fault-injection` is, or no storage if `in-memory` is.
+/// No world does more than one of these.
+fn world(cargo_args: &[String]) -> Result<&'static str, Error> {
+    let features = cargo_args
+        .iter()
+        .zip(cargo_args.iter().skip(1))
+        .filter(|(flag, _)| *flag == "--features" || *flag == "-F")
+        .map(|(_, features)| features.as_str())
+        .chain(
+            cargo_args
+                .iter()
+                .filter_map(|arg| arg.strip_prefix("--features=")),
+        );
+    let features: Vec<&str> = features
+        .flat_map(|features| features.split([',', ' ']))
+        .collect();
+    let wasi_logging = features.contains(&"wasi-logging");
+    let fault_injection = features.contains(&"fault-injection");
+    let in_memory = features.contains(&"in-memory");
+    match (wasi_logging, fault_injection, in_memory) {
+        (false, false, false) => Ok(WORLD),
+        (true, false, false) => Ok(WASI_LOGGING_WORLD),
+        (false, true, false) => Ok(FAULT_INJECTION_WORLD),
+        (false, false, true) => Ok(IN_MEMORY_WORLD),
+        _ => Err(
+            "only one of the wasi-logging, fault-injection and in-memory features can be enabled"
+                .into(),
+        ),
+    }
+}